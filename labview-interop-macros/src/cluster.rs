@@ -0,0 +1,146 @@
+//! Implementation of `#[labview_cluster]`.
+
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::spanned::Spanned;
+use syn::{Error, Fields, ItemStruct, Result};
+
+/// The settings from the attribute arguments.
+#[derive(Default)]
+struct ClusterAttr {
+    debug: bool,
+}
+
+impl ClusterAttr {
+    fn parse(attr: TokenStream) -> Result<Self> {
+        let mut settings = Self::default();
+        let parser = syn::meta::parser(|meta| {
+            if meta.path.is_ident("debug") {
+                settings.debug = true;
+                Ok(())
+            } else {
+                Err(meta.error("expected `debug`"))
+            }
+        });
+        syn::parse::Parser::parse2(parser, attr)?;
+        Ok(settings)
+    }
+}
+
+pub fn labview_cluster(attr: TokenStream, item: TokenStream) -> Result<TokenStream> {
+    let settings = ClusterAttr::parse(attr)?;
+    let cluster: ItemStruct = syn::parse2(item)?;
+    let Fields::Named(fields) = &cluster.fields else {
+        return Err(Error::new(
+            cluster.fields.span(),
+            "labview_cluster structs must have named fields",
+        ));
+    };
+
+    // Fields starting with an underscore are padding so get no accessors.
+    let fields: Vec<_> = fields
+        .named
+        .iter()
+        .filter_map(|field| {
+            let name = field.ident.as_ref()?;
+            (!name.to_string().starts_with('_')).then_some((name, &field.ty, &field.vis))
+        })
+        .collect();
+
+    // The `for<'a>` makes each bound a requirement of the caller rather than
+    // an error in the definition, so fields which aren't `Copy` just can't be
+    // read or written by value.
+    let accessors = fields.iter().map(|(name, ty, vis)| {
+        let setter = format_ident!("set_{}", name);
+        let get_doc = format!("Read `{name}`. This is safe on packed 32 bit layouts.");
+        let set_doc = format!("Write `{name}`. This is safe on packed 32 bit layouts.");
+        quote! {
+            #[doc = #get_doc]
+            #vis fn #name(&self) -> #ty
+            where
+                for<'a> #ty: ::std::marker::Copy,
+            {
+                unsafe { ::std::ptr::read_unaligned(::std::ptr::addr_of!(self.#name)) }
+            }
+
+            #[doc = #set_doc]
+            #vis fn #setter(&mut self, value: #ty)
+            where
+                for<'a> #ty: ::std::marker::Copy,
+            {
+                unsafe { ::std::ptr::write_unaligned(::std::ptr::addr_of_mut!(self.#name), value) }
+            }
+        }
+    });
+
+    let name = &cluster.ident;
+    let (impl_generics, type_generics, where_clause) = cluster.generics.split_for_impl();
+
+    let debug = settings.debug.then(|| {
+        let name_string = name.to_string();
+        let types = fields.iter().map(|(_, ty, _)| ty);
+        let debug_fields = fields.iter().map(|(field, _, _)| {
+            let field_string = field.to_string();
+            quote! { .field(#field_string, &self.#field()) }
+        });
+        let mut predicates: Vec<TokenStream> = where_clause
+            .map(|clause| clause.predicates.iter().map(|p| quote! { #p }).collect())
+            .unwrap_or_default();
+        predicates.extend(
+            types.map(|ty| quote! { for<'a> #ty: ::std::marker::Copy + ::std::fmt::Debug }),
+        );
+        quote! {
+            impl #impl_generics ::std::fmt::Debug for #name #type_generics
+            where
+                #(#predicates,)*
+            {
+                fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+                    f.debug_struct(#name_string)
+                        #(#debug_fields)*
+                        .finish()
+                }
+            }
+        }
+    });
+
+    Ok(quote! {
+        #[repr(C)]
+        #[cfg_attr(target_pointer_width = "32", repr(packed))]
+        #cluster
+
+        impl #impl_generics #name #type_generics #where_clause {
+            #(#accessors)*
+        }
+
+        #debug
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rejects_tuple_structs() {
+        let result = labview_cluster(TokenStream::new(), quote! { struct Point(f64, f64); });
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_rejects_unknown_setting() {
+        let result = labview_cluster(quote! { display }, quote! { struct Point { x: f64 } });
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_padding_has_no_accessors() {
+        let output = labview_cluster(
+            TokenStream::new(),
+            quote! { struct Point { x: f64, _pad: u32 } },
+        )
+        .unwrap()
+        .to_string();
+        assert!(output.contains("fn set_x"));
+        assert!(!output.contains("fn _pad"));
+    }
+}
@@ -0,0 +1,82 @@
+//! Implementation of `#[derive(DeepCopy)]`.
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{Data, DeriveInput, Error, Fields, Result};
+
+use crate::deep_dispose::cluster_members;
+
+pub fn derive_deep_copy(input: TokenStream) -> Result<TokenStream> {
+    let input: DeriveInput = syn::parse2(input)?;
+    let Data::Struct(data) = &input.data else {
+        return Err(Error::new_spanned(
+            &input.ident,
+            "DeepCopy can only be derived for structs",
+        ));
+    };
+    let members = cluster_members(&data.fields);
+
+    // Fields are read and written unaligned as they may be packed. Padding is
+    // copied as it is and left alone when copying into an existing value.
+    let read = |value: TokenStream, member: &TokenStream| {
+        quote! { ::std::ptr::read_unaligned(::std::ptr::addr_of!(#value.#member)) }
+    };
+    let copied_fields = members.iter().map(|(member, padding)| {
+        let field = read(quote! { self }, member);
+        if *padding {
+            quote! { #member: #field }
+        } else {
+            quote! { #member: ::labview_interop::memory::DeepCopy::deep_copy(&#field)? }
+        }
+    });
+    let copy = match &data.fields {
+        Fields::Unit => quote! { Self },
+        _ => quote! { Self { #(#copied_fields,)* } },
+    };
+    let copy_from = members
+        .iter()
+        .filter(|(_, padding)| !padding)
+        .map(|(member, _)| {
+            let target = read(quote! { self }, member);
+            let source = read(quote! { source }, member);
+            quote! {
+                let mut target = #target;
+                ::labview_interop::memory::DeepCopy::deep_copy_from(&mut target, &#source)?;
+                ::std::ptr::write_unaligned(::std::ptr::addr_of_mut!(self.#member), target);
+            }
+        });
+
+    let name = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::labview_interop::memory::DeepCopy for #name #type_generics #where_clause {
+            unsafe fn deep_copy(&self) -> ::labview_interop::errors::Result<Self> {
+                Ok(#copy)
+            }
+
+            unsafe fn deep_copy_from(&mut self, source: &Self) -> ::labview_interop::errors::Result<()> {
+                #(#copy_from)*
+                Ok(())
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rejects_enums() {
+        let result = derive_deep_copy(quote! { enum Mode { A, B } });
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_tuple_struct_uses_indexes() {
+        let output = derive_deep_copy(quote! { struct Pair(f64, f64); })
+            .unwrap()
+            .to_string();
+        assert!(output.contains("self . 1"));
+    }
+}
@@ -0,0 +1,80 @@
+//! Implementation of `#[derive(DeepDispose)]`.
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{Data, DeriveInput, Error, Fields, Index, Result};
+
+pub fn derive_deep_dispose(input: TokenStream) -> Result<TokenStream> {
+    let input: DeriveInput = syn::parse2(input)?;
+    let Data::Struct(data) = &input.data else {
+        return Err(Error::new_spanned(
+            &input.ident,
+            "DeepDispose can only be derived for structs",
+        ));
+    };
+
+    // Fields are moved out rather than borrowed as they may be packed.
+    let members: Vec<TokenStream> = cluster_members(&data.fields)
+        .into_iter()
+        .filter_map(|(member, padding)| (!padding).then_some(member))
+        .collect();
+
+    let name = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::labview_interop::memory::DeepDispose for #name #type_generics #where_clause {
+            unsafe fn deep_dispose(self) -> ::labview_interop::errors::Result<()> {
+                #(::labview_interop::memory::DeepDispose::deep_dispose(self.#members)?;)*
+                Ok(())
+            }
+        }
+
+        // So handles to the cluster, and arrays of them, dispose its fields.
+        impl #impl_generics ::labview_interop::memory::DisposeContents for #name #type_generics #where_clause {
+            unsafe fn dispose_contents(&mut self) -> ::labview_interop::errors::Result<()> {
+                ::labview_interop::memory::DeepDispose::deep_dispose(::std::ptr::read(self))
+            }
+        }
+    })
+}
+
+/// The members of a cluster and whether each is padding, which is a named
+/// field starting with an underscore.
+pub(crate) fn cluster_members(fields: &Fields) -> Vec<(TokenStream, bool)> {
+    match fields {
+        Fields::Named(fields) => fields
+            .named
+            .iter()
+            .filter_map(|field| field.ident.as_ref())
+            .map(|name| (quote! { #name }, name.to_string().starts_with('_')))
+            .collect(),
+        Fields::Unnamed(fields) => (0..fields.unnamed.len())
+            .map(|index| {
+                let index = Index::from(index);
+                (quote! { #index }, false)
+            })
+            .collect(),
+        Fields::Unit => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rejects_enums() {
+        let result = derive_deep_dispose(quote! { enum Mode { A, B } });
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_padding_is_skipped() {
+        let output = derive_deep_dispose(quote! { struct Point { x: f64, _pad: u32 } })
+            .unwrap()
+            .to_string();
+        assert!(output.contains("self . x"));
+        assert!(!output.contains("_pad"));
+        assert!(output.contains("DisposeContents for Point"));
+    }
+}
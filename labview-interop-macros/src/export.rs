@@ -0,0 +1,182 @@
+//! Implementation of `#[labview_export]`.
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::spanned::Spanned;
+use syn::{
+    Error, FnArg, GenericArgument, Ident, ItemFn, Pat, PathArguments, Result, ReturnType, Type,
+};
+
+/// How the return value is passed back to LabVIEW.
+enum ReturnKind {
+    /// No return value so no output parameter.
+    Unit,
+    /// `Result<(), E>` so only the error cluster is written.
+    UnitResult(Box<Type>),
+    /// A value written to an output parameter.
+    Value(Box<Type>),
+}
+
+fn is_unit(ty: &Type) -> bool {
+    matches!(ty, Type::Tuple(tuple) if tuple.elems.is_empty())
+}
+
+fn return_kind(output: &ReturnType) -> ReturnKind {
+    let ReturnType::Type(_, ty) = output else {
+        return ReturnKind::Unit;
+    };
+    if is_unit(ty) {
+        return ReturnKind::Unit;
+    }
+    if let Type::Path(path) = ty.as_ref() {
+        let last = path.path.segments.last();
+        if let Some(PathArguments::AngleBracketed(args)) = last
+            .filter(|segment| segment.ident == "Result")
+            .map(|segment| &segment.arguments)
+        {
+            if let [GenericArgument::Type(ok), GenericArgument::Type(error)] =
+                args.args.iter().collect::<Vec<_>>()[..]
+            {
+                if is_unit(ok) {
+                    return ReturnKind::UnitResult(Box::new(error.clone()));
+                }
+            }
+        }
+    }
+    ReturnKind::Value(ty.clone())
+}
+
+/// The name and type of each argument.
+fn arguments(function: &ItemFn) -> Result<Vec<(Ident, Type)>> {
+    function
+        .sig
+        .inputs
+        .iter()
+        .map(|input| match input {
+            FnArg::Typed(typed) => match typed.pat.as_ref() {
+                Pat::Ident(pat) if pat.subpat.is_none() => {
+                    Ok((pat.ident.clone(), typed.ty.as_ref().clone()))
+                }
+                pat => Err(Error::new(
+                    pat.span(),
+                    "labview_export arguments must be simple names",
+                )),
+            },
+            FnArg::Receiver(receiver) => Err(Error::new(
+                receiver.span(),
+                "labview_export can't be used on methods",
+            )),
+        })
+        .collect()
+}
+
+fn check_signature(function: &ItemFn) -> Result<()> {
+    let sig = &function.sig;
+    if !sig.generics.params.is_empty() {
+        return Err(Error::new(
+            sig.generics.span(),
+            "labview_export functions can't be generic",
+        ));
+    }
+    if let Some(asyncness) = sig.asyncness {
+        return Err(Error::new(
+            asyncness.span(),
+            "labview_export functions can't be async",
+        ));
+    }
+    if let Some(unsafety) = sig.unsafety {
+        return Err(Error::new(
+            unsafety.span(),
+            "labview_export functions can't be unsafe",
+        ));
+    }
+    if let Some(variadic) = &sig.variadic {
+        return Err(Error::new(
+            variadic.span(),
+            "labview_export functions can't be variadic",
+        ));
+    }
+    Ok(())
+}
+
+pub fn labview_export(attr: TokenStream, item: TokenStream) -> Result<TokenStream> {
+    if !attr.is_empty() {
+        return Err(Error::new(
+            attr.span(),
+            "labview_export doesn't take any arguments",
+        ));
+    }
+    let function: ItemFn = syn::parse2(item)?;
+    check_signature(&function)?;
+
+    let ItemFn {
+        attrs,
+        vis,
+        sig,
+        block,
+    } = &function;
+    let name = &sig.ident;
+    let (names, types): (Vec<_>, Vec<_>) = arguments(&function)?.into_iter().unzip();
+
+    // `lv_export!` matches a unit result by its tokens so it is written in
+    // that form whatever path was used for `Result`.
+    let output = match return_kind(&sig.output) {
+        ReturnKind::Unit => quote! {},
+        ReturnKind::UnitResult(error) => quote! { -> Result<(), #error> },
+        ReturnKind::Value(ty) => quote! { -> #ty },
+    };
+
+    // Expand through `lv_export!` so both forms generate the same export.
+    Ok(quote! {
+        ::labview_interop::lv_export! {
+            #(#attrs)*
+            #vis fn #name(#(#names: #types),*) #output #block
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kind(tokens: TokenStream) -> ReturnKind {
+        let function: ItemFn = syn::parse2(tokens).unwrap();
+        return_kind(&function.sig.output)
+    }
+
+    #[test]
+    fn test_return_kind() {
+        assert!(matches!(kind(quote! { fn a() {} }), ReturnKind::Unit));
+        assert!(matches!(
+            kind(quote! { fn a() -> Result<(), E> {} }),
+            ReturnKind::UnitResult(_)
+        ));
+        assert!(matches!(
+            kind(quote! { fn a() -> Result<f64, E> {} }),
+            ReturnKind::Value(_)
+        ));
+        assert!(matches!(
+            kind(quote! { fn a() -> String {} }),
+            ReturnKind::Value(_)
+        ));
+    }
+
+    #[test]
+    fn test_expands_through_lv_export() {
+        let output = labview_export(
+            TokenStream::new(),
+            quote! { pub fn reset(force: bool) -> std::result::Result<(), Error> {} },
+        )
+        .unwrap()
+        .to_string();
+        assert!(output.starts_with(":: labview_interop :: lv_export !"));
+        assert!(output.contains("pub fn reset"));
+        assert!(output.contains("-> Result < () , Error >"));
+    }
+
+    #[test]
+    fn test_rejects_generics() {
+        let result = labview_export(TokenStream::new(), quote! { fn a<T>(value: T) {} });
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,254 @@
+//! Procedural macros for `labview-interop`.
+//!
+//! These are re-exported from `labview-interop` with the `macros` feature so
+//! depend on that rather than this crate directly.
+
+mod cluster;
+mod deep_copy;
+mod deep_dispose;
+mod export;
+mod lv_data;
+mod to_lv_error;
+
+use proc_macro::TokenStream;
+
+/// Export a function written with Rust types to LabVIEW.
+///
+/// The function is wrapped in a `#[no_mangle] extern "C"` export of the same
+/// name which takes the LabVIEW type for each argument, an output for the
+/// return value and an error cluster, and returns the status code. Errors are
+/// written to the error cluster as with `with_lverrorhandling!`.
+///
+/// This is the attribute form of `lv_export!`, which it expands to, so it
+/// supports the same types and generates the same export. See the
+/// `labview_interop::export` module for the parameter types to configure in
+/// the Call Library node. Functions returning nothing or `Result<(), E>` have no
+/// output parameter. The export has the visibility written on the function.
+///
+/// # Example
+/// ```
+/// use labview_interop::errors::LVInteropError;
+/// use labview_interop::labview_export;
+///
+/// /// Scale the values by the gain.
+/// #[labview_export]
+/// pub fn scale(values: &[f64], gain: f64) -> Result<Vec<f64>, LVInteropError> {
+///     if !gain.is_finite() {
+///         return Err(LVInteropError::ValidationFailed("gain must be finite".into()));
+///     }
+///     Ok(values.iter().map(|value| value * gain).collect())
+/// }
+/// ```
+///
+/// This generates an export with the signature:
+/// ```
+/// use labview_interop::errors::MgErr;
+/// use labview_interop::memory::UPtr;
+/// use labview_interop::types::{ErrorClusterPtr, LVArrayHandle};
+///
+/// #[no_mangle]
+/// pub extern "C" fn scale(
+///     values: LVArrayHandle<1, f64>,
+///     gain: f64,
+///     output: UPtr<LVArrayHandle<1, f64>>,
+///     error_cluster: ErrorClusterPtr,
+/// ) -> MgErr {
+///     # MgErr::NO_ERROR
+///     // ...
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn labview_export(attr: TokenStream, item: TokenStream) -> TokenStream {
+    export::labview_export(attr.into(), item.into())
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Declare a struct matching a LabVIEW cluster.
+///
+/// This applies the same layout as `labview_layout!`, which is packed on 32 bit
+/// targets, and adds a getter and a `set_` setter for each field. These read
+/// and write unaligned so they are safe on either layout, where references to
+/// packed fields are not. They take and return the field by value so can only
+/// be called for fields which are `Copy`, which includes the handle types.
+///
+/// Fields starting with an underscore are treated as padding and get no
+/// accessors. Each accessor has the visibility of its field.
+///
+/// Add `debug` to also implement `Debug` through the getters, which requires
+/// every field to be `Copy` and `Debug`.
+///
+/// This is an attribute rather than a derive because a derive can't change the
+/// layout of the struct.
+///
+/// # Example
+/// ```
+/// use labview_interop::labview_cluster;
+/// use labview_interop::types::LVBool;
+///
+/// #[labview_cluster(debug)]
+/// pub struct Setpoint {
+///     pub channel: u16,
+///     pub value: f64,
+///     pub enabled: LVBool,
+/// }
+///
+/// let mut setpoint = Setpoint {
+///     channel: 3,
+///     value: 1.5,
+///     enabled: true.into(),
+/// };
+/// setpoint.set_value(setpoint.value() * 2.0);
+/// assert_eq!(setpoint.value(), 3.0);
+/// assert_eq!(setpoint.channel(), 3);
+/// ```
+#[proc_macro_attribute]
+pub fn labview_cluster(attr: TokenStream, item: TokenStream) -> TokenStream {
+    cluster::labview_cluster(attr.into(), item.into())
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Derive `ToLvError` for an error type implementing `Display`.
+///
+/// The description is the `Display` output. The code and source are set with
+/// `#[lv_error(...)]` on the type, which applies to every variant, and on
+/// individual variants:
+///
+/// * `code = 5001` sets the error code. The default is 42.
+/// * `source = "MyLib"` sets the source. The default is empty.
+/// * `warning` reports the variant as a warning rather than an error.
+///
+/// This conflicts with the blanket implementation from the `std-error` feature.
+///
+/// # Example
+/// ```
+/// use labview_interop::types::ToLvError;
+///
+/// #[derive(Debug, thiserror::Error, ToLvError)]
+/// #[lv_error(source = "Motion")]
+/// enum MotionError {
+///     #[error("Axis {0} is not homed.")]
+///     #[lv_error(code = 5001)]
+///     NotHomed(u8),
+///     #[error("Move to {position} is past the soft limit.")]
+///     #[lv_error(code = 5002, warning)]
+///     SoftLimit { position: f64 },
+/// }
+///
+/// let error = MotionError::NotHomed(2);
+/// assert_eq!(i32::from(error.code()), 5001);
+/// assert_eq!(error.source(), "Motion");
+/// assert_eq!(error.description(), "Axis 2 is not homed.");
+/// assert!(!MotionError::SoftLimit { position: 10.0 }.is_error());
+/// ```
+#[proc_macro_derive(ToLvError, attributes(lv_error))]
+pub fn derive_to_lv_error(input: TokenStream) -> TokenStream {
+    to_lv_error::derive_to_lv_error(input.into())
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Derive `DeepDispose` for a cluster, disposing each field in turn.
+///
+/// Fields starting with an underscore are treated as padding and skipped. See
+/// `labview_interop::memory::DeepDispose` for details. `DisposeContents` is
+/// implemented too, so an owned handle to the cluster disposes its fields.
+///
+/// # Example
+/// ```
+/// use labview_interop::labview_layout;
+/// use labview_interop::memory::{DeepDispose, UHandle};
+/// use labview_interop::types::{LStrHandle, LStrOwned, LVArrayHandle};
+///
+/// labview_layout!(
+///     #[derive(DeepDispose)]
+///     pub struct Channel {
+///         name: LStrHandle,
+///         samples: LVArrayHandle<1, f64>,
+///         gain: f64,
+///     }
+/// );
+///
+/// let channel = Channel {
+///     name: LStrOwned::from_data(b"ai0").unwrap().into_raw(),
+///     samples: UHandle(std::ptr::null_mut()),
+///     gain: 1.0,
+/// };
+/// unsafe { channel.deep_dispose() }.unwrap();
+/// ```
+#[proc_macro_derive(DeepDispose)]
+pub fn derive_deep_dispose(input: TokenStream) -> TokenStream {
+    deep_dispose::derive_deep_dispose(input.into())
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Derive `DeepCopy` for a cluster, copying each field in turn.
+///
+/// Fields starting with an underscore are treated as padding and copied as
+/// they are. The cluster must also implement `DeepDispose`. See
+/// `labview_interop::memory::DeepCopy` for details.
+///
+/// # Example
+/// ```
+/// use labview_interop::labview_layout;
+/// use labview_interop::memory::{DeepCopy, DeepDispose};
+/// use labview_interop::types::{LStrHandle, LStrOwned};
+///
+/// labview_layout!(
+///     #[derive(DeepCopy, DeepDispose)]
+///     pub struct Channel {
+///         name: LStrHandle,
+///         gain: f64,
+///     }
+/// );
+///
+/// let channel = Channel {
+///     name: LStrOwned::from_data(b"ai0").unwrap().into_raw(),
+///     gain: 1.0,
+/// };
+/// let snapshot = unsafe { channel.deep_copy() }.unwrap();
+/// unsafe { channel.deep_dispose() }.unwrap();
+/// assert_eq!(snapshot.name.as_bytes().unwrap(), b"ai0");
+/// unsafe { snapshot.deep_dispose() }.unwrap();
+/// ```
+#[proc_macro_derive(DeepCopy)]
+pub fn derive_deep_copy(input: TokenStream) -> TokenStream {
+    deep_copy::derive_deep_copy(input.into())
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Derive `LvData` for a cluster which LabVIEW holds in place.
+///
+/// Every field must be held in place too, such as numerics, `LVBool` and
+/// `LVTime`, and the cluster must implement `Clone`. Fields starting with an
+/// underscore are treated as padding so are left out of the flattened data
+/// and set to their default when unflattened. See
+/// `labview_interop::types::data::LvData` for details.
+///
+/// # Example
+/// ```
+/// use labview_interop::labview_layout;
+/// use labview_interop::types::data::{flatten, unflatten, LvData};
+/// use labview_interop::types::LVBool;
+///
+/// labview_layout!(
+///     #[derive(Clone, Copy, Debug, PartialEq, LvData)]
+///     pub struct Setpoint {
+///         channel: u16,
+///         value: f64,
+///         enabled: LVBool,
+///     }
+/// );
+///
+/// let setpoint = Setpoint { channel: 2, value: 1.5, enabled: true.into() };
+/// assert_eq!(unflatten::<Setpoint>(&flatten(&setpoint).unwrap()).unwrap(), setpoint);
+/// ```
+#[proc_macro_derive(LvData)]
+pub fn derive_lv_data(input: TokenStream) -> TokenStream {
+    lv_data::derive_lv_data(input.into())
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
@@ -0,0 +1,95 @@
+//! Implementation of `#[derive(LvData)]`.
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{Data, DeriveInput, Error, Result};
+
+use crate::deep_dispose::cluster_members;
+
+pub fn derive_lv_data(input: TokenStream) -> Result<TokenStream> {
+    let input: DeriveInput = syn::parse2(input)?;
+    let Data::Struct(data) = &input.data else {
+        return Err(Error::new_spanned(
+            &input.ident,
+            "LvData can only be derived for structs",
+        ));
+    };
+
+    // Padding isn't part of the LabVIEW cluster so is left out of the
+    // descriptor and flattened data.
+    let (members, types): (Vec<_>, Vec<_>) = cluster_members(&data.fields)
+        .into_iter()
+        .zip(&data.fields)
+        .filter(|((_, padding), _)| !padding)
+        .map(|((member, _), field)| (member, &field.ty))
+        .unzip();
+    let padding = cluster_members(&data.fields)
+        .into_iter()
+        .filter_map(|(member, padding)| padding.then_some(member));
+    let unflatten = match &data.fields {
+        syn::Fields::Unit => quote! { Self },
+        _ => quote! {
+            Self {
+                #(#members: <#types as ::labview_interop::types::data::LvData>::unflatten(input)?,)*
+                #(#padding: ::std::default::Default::default(),)*
+            }
+        },
+    };
+
+    let name = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::labview_interop::types::data::LvData for #name #type_generics #where_clause {
+            type Repr = Self;
+
+            fn type_descriptor() -> ::std::vec::Vec<u8> {
+                // The cluster is held in place so each field must be too.
+                fn in_place<T: ::labview_interop::types::data::LvData<Repr = T>>() {}
+                #(in_place::<#types>();)*
+                ::labview_interop::types::data::cluster_descriptor(&[
+                    #(<#types as ::labview_interop::types::data::LvData>::type_descriptor(),)*
+                ])
+            }
+
+            fn from_lv(repr: &Self::Repr) -> ::labview_interop::errors::Result<Self> {
+                Ok(::std::clone::Clone::clone(repr))
+            }
+
+            fn to_lv(&self, repr: &mut Self::Repr) -> ::labview_interop::errors::Result<()> {
+                *repr = ::std::clone::Clone::clone(self);
+                Ok(())
+            }
+
+            fn flatten(&self, output: &mut ::std::vec::Vec<u8>) -> ::labview_interop::errors::Result<()> {
+                // Fields are copied out as they may be packed.
+                #(::labview_interop::types::data::LvData::flatten(&{ self.#members }, output)?;)*
+                Ok(())
+            }
+
+            fn unflatten(input: &mut &[u8]) -> ::labview_interop::errors::Result<Self> {
+                Ok(#unflatten)
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rejects_enums() {
+        let result = derive_lv_data(quote! { enum Mode { A, B } });
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_padding_is_skipped() {
+        let output = derive_lv_data(quote! { struct Point { x: f64, _pad: u32, y: f64 } })
+            .unwrap()
+            .to_string();
+        assert!(output.contains("self . y"));
+        assert!(!output.contains("self . _pad"));
+        assert!(output.contains("_pad : :: std :: default :: Default :: default ()"));
+    }
+}
@@ -0,0 +1,153 @@
+//! Implementation of `#[derive(ToLvError)]`.
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{Attribute, Data, DeriveInput, Error, Expr, LitStr, Result};
+
+/// The settings from `#[lv_error(...)]` attributes.
+#[derive(Default, Clone)]
+struct LvErrorAttr {
+    code: Option<Expr>,
+    source: Option<LitStr>,
+    warning: bool,
+}
+
+impl LvErrorAttr {
+    fn parse(attrs: &[Attribute]) -> Result<Self> {
+        let mut settings = Self::default();
+        for attr in attrs.iter().filter(|attr| attr.path().is_ident("lv_error")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("code") {
+                    settings.code = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("source") {
+                    settings.source = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("warning") {
+                    settings.warning = true;
+                } else {
+                    return Err(meta.error("expected `code`, `source` or `warning`"));
+                }
+                Ok(())
+            })?;
+        }
+        Ok(settings)
+    }
+
+    /// Use the type level settings where the variant doesn't set them.
+    fn or(self, default: &LvErrorAttr) -> Self {
+        Self {
+            code: self.code.or_else(|| default.code.clone()),
+            source: self.source.or_else(|| default.source.clone()),
+            warning: self.warning || default.warning,
+        }
+    }
+
+    fn code(&self) -> TokenStream {
+        match &self.code {
+            Some(code) => quote! { ::labview_interop::errors::MgErr::from(#code) },
+            None => quote! { ::labview_interop::errors::MgErr::from(42) },
+        }
+    }
+
+    fn source(&self) -> TokenStream {
+        match &self.source {
+            Some(source) => quote! { #source },
+            None => quote! { "" },
+        }
+    }
+}
+
+pub fn derive_to_lv_error(input: TokenStream) -> Result<TokenStream> {
+    let input: DeriveInput = syn::parse2(input)?;
+    let defaults = LvErrorAttr::parse(&input.attrs)?;
+
+    // Each arm of the generated matches with the settings for it.
+    let arms: Vec<(TokenStream, LvErrorAttr)> = match &input.data {
+        Data::Enum(data) => data
+            .variants
+            .iter()
+            .map(|variant| {
+                let name = &variant.ident;
+                let settings = LvErrorAttr::parse(&variant.attrs)?.or(&defaults);
+                Ok((quote! { Self::#name { .. } }, settings))
+            })
+            .collect::<Result<_>>()?,
+        Data::Struct(_) => vec![(quote! { _ }, defaults)],
+        Data::Union(_) => {
+            return Err(Error::new_spanned(
+                &input.ident,
+                "ToLvError can't be derived for unions",
+            ))
+        }
+    };
+
+    let patterns: Vec<_> = arms.iter().map(|(pattern, _)| pattern).collect();
+    let codes = arms.iter().map(|(_, settings)| settings.code());
+    let sources = arms.iter().map(|(_, settings)| settings.source());
+    let is_errors = arms.iter().map(|(_, settings)| !settings.warning);
+
+    let name = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::labview_interop::types::ToLvError for #name #type_generics #where_clause {
+            fn code(&self) -> ::labview_interop::errors::MgErr {
+                match self {
+                    #(#patterns => #codes,)*
+                }
+            }
+
+            fn is_error(&self) -> bool {
+                match self {
+                    #(#patterns => #is_errors,)*
+                }
+            }
+
+            fn source(&self) -> ::std::borrow::Cow<'_, str> {
+                match self {
+                    #(#patterns => #sources.into(),)*
+                }
+            }
+
+            fn description(&self) -> ::std::borrow::Cow<'_, str> {
+                self.to_string().into()
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rejects_unknown_setting() {
+        let result = derive_to_lv_error(quote! {
+            enum Error {
+                #[lv_error(level = 3)]
+                Failed,
+            }
+        });
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_variant_settings_override_type() {
+        let attrs: DeriveInput = syn::parse2(quote! {
+            #[lv_error(code = 5000, source = "Lib")]
+            struct Error;
+        })
+        .unwrap();
+        let variant: syn::Variant = syn::parse2(quote! {
+            #[lv_error(code = 5001, warning)]
+            Failed
+        })
+        .unwrap();
+        let defaults = LvErrorAttr::parse(&attrs.attrs).unwrap();
+        let settings = LvErrorAttr::parse(&variant.attrs).unwrap().or(&defaults);
+        assert_eq!(
+            settings.code().to_string(),
+            quote! { ::labview_interop::errors::MgErr::from(5001) }.to_string()
+        );
+        assert_eq!(settings.source.unwrap().value(), "Lib");
+        assert!(settings.warning);
+    }
+}
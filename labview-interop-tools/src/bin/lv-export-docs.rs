@@ -0,0 +1,76 @@
+//! Extract the doc comments of exported functions for LabVIEW Context Help.
+//!
+//! ```text
+//! lv-export-docs [--nodes <nodes.tsv>] <source files or folders>... > exports.tsv
+//! ```
+//!
+//! Folders are searched for `.rs` files. With `--nodes` the descriptions are
+//! listed by the path of each VI in the node configuration file rather than by
+//! export name. See [`docs`] for the output format.
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use labview_interop_tools::docs;
+use labview_interop_tools::exports::collect_sources;
+use labview_interop_tools::nodes;
+
+fn main() -> ExitCode {
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    let nodes_path = match args.first().map(String::as_str) {
+        Some("--nodes") if args.len() > 1 => {
+            let nodes_path = args.remove(1);
+            args.remove(0);
+            Some(nodes_path)
+        }
+        _ => None,
+    };
+    let paths: Vec<PathBuf> = args.into_iter().map(PathBuf::from).collect();
+    if paths.is_empty() || paths[0].starts_with("--") {
+        eprintln!("usage: lv-export-docs [--nodes <nodes.tsv>] <source files or folders>...");
+        return ExitCode::from(2);
+    }
+    let nodes = match nodes_path {
+        Some(nodes_path) => {
+            let parsed = std::fs::read_to_string(&nodes_path)
+                .map_err(|error| error.to_string())
+                .and_then(|text| nodes::parse_nodes(&text).map_err(|error| error.to_string()));
+            match parsed {
+                Ok(nodes) => Some(nodes),
+                Err(error) => {
+                    eprintln!("failed to read {nodes_path}: {error}");
+                    return ExitCode::from(2);
+                }
+            }
+        }
+        None => None,
+    };
+
+    let mut sources = Vec::new();
+    for path in &paths {
+        if let Err(error) = collect_sources(path, &mut sources) {
+            eprintln!("failed to read {}: {error}", path.display());
+            return ExitCode::from(2);
+        }
+    }
+
+    let mut exports = Vec::new();
+    for source in &sources {
+        let parsed = std::fs::read_to_string(source)
+            .map_err(|error| error.to_string())
+            .and_then(|text| docs::export_docs(&text).map_err(|error| error.to_string()));
+        match parsed {
+            Ok(docs) => exports.extend(docs),
+            Err(error) => {
+                eprintln!("failed to read {}: {error}", source.display());
+                return ExitCode::from(2);
+            }
+        }
+    }
+
+    match nodes {
+        Some(nodes) => print!("{}", docs::to_vi_tsv(&nodes, &exports)),
+        None => print!("{}", docs::to_tsv(&exports)),
+    }
+    ExitCode::SUCCESS
+}
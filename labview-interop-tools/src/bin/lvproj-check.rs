@@ -0,0 +1,111 @@
+//! Check a LabVIEW project or library refers to a single, existing copy of a
+//! shared library, and optionally that its Call Library nodes match the exports.
+//!
+//! ```text
+//! lvproj-check <project.lvproj|library.lvlib> <library name> [<nodes.tsv> <source files or folders>...]
+//! ```
+//!
+//! The node configurations are read from a file written by a scripting VI,
+//! see [`nodes`](labview_interop_tools::nodes) for the format, and checked
+//! against the exports found in the Rust sources.
+//!
+//! Exits with a non-zero status if any problems are found.
+
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use labview_interop_tools::exports::{collect_sources, exports, Export};
+use labview_interop_tools::nodes::{check_nodes, parse_nodes};
+use labview_interop_tools::project::{check_references, find_library_references};
+
+const USAGE: &str = "usage: lvproj-check <project.lvproj|library.lvlib> <library name> [<nodes.tsv> <source files or folders>...]";
+
+fn read_exports(paths: &[String]) -> Result<Vec<Export>, String> {
+    let mut sources = Vec::new();
+    for path in paths.iter().map(PathBuf::from) {
+        collect_sources(&path, &mut sources)
+            .map_err(|error| format!("failed to read {}: {error}", path.display()))?;
+    }
+    let mut found = Vec::new();
+    for source in &sources {
+        let text = std::fs::read_to_string(source)
+            .map_err(|error| format!("failed to read {}: {error}", source.display()))?;
+        let source_exports = exports(&text)
+            .map_err(|error| format!("failed to parse {}: {error}", source.display()))?;
+        found.extend(source_exports);
+    }
+    Ok(found)
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let (project_path, library_name, node_args) = match args.as_slice() {
+        [project_path, library_name] => (project_path, library_name, None),
+        [project_path, library_name, nodes_path, sources @ ..] if !sources.is_empty() => {
+            (project_path, library_name, Some((nodes_path, sources)))
+        }
+        _ => {
+            eprintln!("{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+
+    let project_path = Path::new(project_path);
+    let xml = match std::fs::read_to_string(project_path) {
+        Ok(xml) => xml,
+        Err(error) => {
+            eprintln!("failed to read {}: {error}", project_path.display());
+            return ExitCode::from(2);
+        }
+    };
+    // LabVIEW writes a byte order mark at the start of the file.
+    let xml = xml.trim_start_matches('\u{feff}');
+
+    let references = match find_library_references(xml, library_name) {
+        Ok(references) => references,
+        Err(error) => {
+            eprintln!("failed to parse {}: {error}", project_path.display());
+            return ExitCode::from(2);
+        }
+    };
+    for reference in &references {
+        println!("found {} at {}", reference.name, reference.url);
+    }
+
+    let findings = check_references(&references, project_path, Path::exists);
+    for finding in &findings {
+        println!("error: {finding}");
+    }
+    let mut failed = !findings.is_empty();
+
+    if let Some((nodes_path, sources)) = node_args {
+        let nodes = match std::fs::read_to_string(nodes_path) {
+            Ok(text) => parse_nodes(&text).map_err(|error| format!("{nodes_path}: {error}")),
+            Err(error) => Err(format!("failed to read {nodes_path}: {error}")),
+        };
+        let checked = nodes.and_then(|nodes| Ok((nodes, read_exports(sources)?)));
+        let (nodes, exports) = match checked {
+            Ok(checked) => checked,
+            Err(error) => {
+                eprintln!("{error}");
+                return ExitCode::from(2);
+            }
+        };
+        println!(
+            "checking {} nodes against {} exports",
+            nodes.len(),
+            exports.len()
+        );
+        let findings = check_nodes(&nodes, &exports);
+        for finding in &findings {
+            println!("error: {finding}");
+        }
+        failed |= !findings.is_empty();
+    }
+
+    if failed {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
@@ -0,0 +1,175 @@
+//! Extraction of documentation for exported functions.
+//!
+//! The doc comments of every export found by [`exports`](crate::exports),
+//! including `lv_export!` and `#[labview_export]` functions, are collected so
+//! they can be shown in LabVIEW's Context Help. The output is a tab separated
+//! file with the export name and its description on each line, with line
+//! breaks in the description escaped as `\n`.
+//!
+//! Given the Call Library node configurations described in
+//! [`nodes`](crate::nodes), the first column is the path of each VI calling
+//! the export instead. The descriptions are stored in the binary VI files so
+//! they are set from LabVIEW: a scripting VI reads the file with Read
+//! Delimited Spreadsheet and for each line opens the VI, sets its Description
+//! property and saves it.
+
+use std::fmt::Write;
+
+use crate::exports::{exports, Export};
+use crate::nodes::CallLibraryNode;
+
+/// The documentation for one exported function.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExportDoc {
+    /// The exported symbol name.
+    pub name: String,
+    /// The parameter names in order.
+    pub parameters: Vec<String>,
+    /// The doc comment text.
+    pub description: String,
+}
+
+impl ExportDoc {
+    /// The description for the VI, with the parameters listed after the doc text.
+    pub fn vi_description(&self) -> String {
+        let mut description = self.description.clone();
+        if !self.parameters.is_empty() {
+            if !description.is_empty() {
+                description.push_str("\n\n");
+            }
+            description.push_str("Parameters: ");
+            description.push_str(&self.parameters.join(", "));
+        }
+        description
+    }
+
+    /// The description escaped for one field of the tab separated output.
+    fn escaped_description(&self) -> String {
+        self.vi_description()
+            .replace('\\', "\\\\")
+            .replace('\t', " ")
+            .replace('\n', "\\n")
+    }
+}
+
+impl From<Export> for ExportDoc {
+    fn from(export: Export) -> Self {
+        Self {
+            name: export.name,
+            parameters: export
+                .parameters
+                .into_iter()
+                .map(|parameter| parameter.name)
+                .collect(),
+            description: export.description,
+        }
+    }
+}
+
+/// Collect the documentation of the exported functions in a source file.
+pub fn export_docs(source: &str) -> syn::Result<Vec<ExportDoc>> {
+    Ok(exports(source)?.into_iter().map(ExportDoc::from).collect())
+}
+
+/// Write the docs as tab separated lines of name and description.
+pub fn to_tsv(docs: &[ExportDoc]) -> String {
+    let mut output = String::new();
+    for doc in docs {
+        let _ = writeln!(output, "{}\t{}", doc.name, doc.escaped_description());
+    }
+    output
+}
+
+/// Write the docs as tab separated lines of VI path and description, for each
+/// node calling a documented export.
+pub fn to_vi_tsv(nodes: &[CallLibraryNode], docs: &[ExportDoc]) -> String {
+    let mut output = String::new();
+    for node in nodes {
+        if let Some(doc) = docs.iter().find(|doc| doc.name == node.function) {
+            let _ = writeln!(output, "{}\t{}", node.vi, doc.escaped_description());
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nodes::parse_nodes;
+
+    const SOURCE: &str = r#"
+/// Add two numbers.
+///
+/// Returns the sum.
+#[no_mangle]
+pub extern "C" fn add(a: f64, b: f64) -> f64 {
+    a + b
+}
+
+/// Not exported.
+pub fn helper() {}
+
+mod inner {
+    #[no_mangle]
+    pub extern "C" fn undocumented() {}
+}
+
+lv_export! {
+    /// Scale a value.
+    fn scale(value: f64, factor: f64) -> f64 {
+        value * factor
+    }
+}
+"#;
+
+    #[test]
+    fn test_export_docs() {
+        let docs = export_docs(SOURCE).unwrap();
+        assert_eq!(
+            docs,
+            vec![
+                ExportDoc {
+                    name: "add".to_string(),
+                    parameters: vec!["a".to_string(), "b".to_string()],
+                    description: "Add two numbers.\n\nReturns the sum.".to_string(),
+                },
+                ExportDoc {
+                    name: "undocumented".to_string(),
+                    parameters: vec![],
+                    description: String::new(),
+                },
+                ExportDoc {
+                    name: "scale".to_string(),
+                    parameters: vec![
+                        "value".to_string(),
+                        "factor".to_string(),
+                        "output".to_string(),
+                        "error_cluster".to_string(),
+                    ],
+                    description: "Scale a value.".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_to_tsv() {
+        let docs = export_docs(SOURCE).unwrap();
+        assert_eq!(
+            to_tsv(&docs[..2]),
+            "add\tAdd two numbers.\\n\\nReturns the sum.\\n\\nParameters: a, b\nundocumented\t\n"
+        );
+    }
+
+    #[test]
+    fn test_to_vi_tsv() {
+        let docs = export_docs(SOURCE).unwrap();
+        let nodes =
+            parse_nodes("Scale.vi\tscale\tvalue, value, pointer, pointer\nOld.vi\tremoved\t\n")
+                .unwrap();
+        assert_eq!(
+            to_vi_tsv(&nodes, &docs),
+            "Scale.vi\tScale a value.\\n\\nParameters: value, factor, output, error_cluster\n"
+        );
+    }
+}
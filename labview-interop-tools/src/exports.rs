@@ -0,0 +1,394 @@
+//! Reading the exported functions and their parameters from Rust sources.
+//!
+//! Exports are found in three forms:
+//!
+//! * `#[no_mangle] extern "C"` functions, where the parameter types are the
+//!   types LabVIEW passes.
+//! * [`lv_export!`] invocations and `#[labview_export]` functions, where the
+//!   parameters are Rust types and the generated export adds an output for the
+//!   return value and an error cluster.
+//!
+//! The pass mode of each parameter is worked out from the type name as this
+//! only has the source, so type aliases for handles or pointers aren't
+//! recognised unless they end in `Handle` or `Ptr`.
+//!
+//! [`lv_export!`]: https://docs.rs/labview-interop/latest/labview_interop/macro.lv_export.html
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use syn::{
+    Attribute, Expr, FnArg, GenericArgument, Item, ItemFn, Lit, Meta, Pat, PathArguments,
+    ReturnType, Type,
+};
+
+/// How a Call Library node passes a parameter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PassMode {
+    /// The value itself, such as a numeric passed by value.
+    Value,
+    /// A pointer to the value.
+    Pointer,
+    /// A string or array handle.
+    Handle,
+    /// A pointer to a string or array handle.
+    HandlePointer,
+}
+
+impl fmt::Display for PassMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            PassMode::Value => "value",
+            PassMode::Pointer => "pointer",
+            PassMode::Handle => "handle",
+            PassMode::HandlePointer => "handle pointer",
+        })
+    }
+}
+
+impl FromStr for PassMode {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text.trim().to_ascii_lowercase().as_str() {
+            "value" => Ok(PassMode::Value),
+            "pointer" => Ok(PassMode::Pointer),
+            "handle" => Ok(PassMode::Handle),
+            "handle pointer" => Ok(PassMode::HandlePointer),
+            other => Err(format!("unknown pass mode \"{other}\"")),
+        }
+    }
+}
+
+/// A parameter of an exported function.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Parameter {
+    pub name: String,
+    pub mode: PassMode,
+}
+
+/// An exported function.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Export {
+    /// The exported symbol name.
+    pub name: String,
+    /// The parameters of the export in order.
+    pub parameters: Vec<Parameter>,
+    /// The doc comment text.
+    pub description: String,
+}
+
+/// Join the `#[doc]` attributes into the doc comment text.
+fn doc_text(attributes: &[Attribute]) -> String {
+    let lines: Vec<String> = attributes
+        .iter()
+        .filter(|attribute| attribute.path().is_ident("doc"))
+        .filter_map(|attribute| match &attribute.meta {
+            Meta::NameValue(name_value) => match &name_value.value {
+                Expr::Lit(expr) => match &expr.lit {
+                    Lit::Str(text) => Some(text.value()),
+                    _ => None,
+                },
+                _ => None,
+            },
+            _ => None,
+        })
+        .map(|line| line.strip_prefix(' ').unwrap_or(&line).to_string())
+        .collect();
+    lines.join("\n").trim().to_string()
+}
+
+fn is_no_mangle(attributes: &[Attribute]) -> bool {
+    attributes.iter().any(|attribute| match &attribute.meta {
+        Meta::Path(path) => path.is_ident("no_mangle"),
+        // Rust 2024 writes this as #[unsafe(no_mangle)].
+        Meta::List(list) => list.path.is_ident("unsafe") && list.tokens.to_string() == "no_mangle",
+        _ => false,
+    })
+}
+
+fn is_labview_export(attributes: &[Attribute]) -> bool {
+    attributes.iter().any(|attribute| {
+        attribute
+            .path()
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == "labview_export")
+    })
+}
+
+/// The name of the last path segment of a type, if it is a path.
+fn type_name(ty: &Type) -> Option<String> {
+    match ty {
+        Type::Path(path) => path
+            .path
+            .segments
+            .last()
+            .map(|segment| segment.ident.to_string()),
+        Type::Group(group) => type_name(&group.elem),
+        Type::Paren(paren) => type_name(&paren.elem),
+        _ => None,
+    }
+}
+
+fn is_handle_type(ty: &Type) -> bool {
+    type_name(ty).is_some_and(|name| name.ends_with("Handle"))
+}
+
+/// The pass mode of an `extern "C"` parameter type.
+fn raw_mode(ty: &Type) -> PassMode {
+    let target = match ty {
+        Type::Ptr(pointer) => &pointer.elem,
+        Type::Reference(reference) => &reference.elem,
+        Type::Group(group) => return raw_mode(&group.elem),
+        Type::Paren(paren) => return raw_mode(&paren.elem),
+        ty if is_handle_type(ty) => return PassMode::Handle,
+        ty if type_name(ty).is_some_and(|name| name.ends_with("Ptr")) => return PassMode::Pointer,
+        _ => return PassMode::Value,
+    };
+    if is_handle_type(target) {
+        PassMode::HandlePointer
+    } else {
+        PassMode::Pointer
+    }
+}
+
+/// The pass mode of an argument to an `lv_export!` function, following `FromLvArg`.
+fn argument_mode(ty: &Type) -> PassMode {
+    match ty {
+        Type::Reference(reference) => match reference.elem.as_ref() {
+            // &str and &[T] are passed as handles.
+            Type::Slice(_) => PassMode::Handle,
+            elem if type_name(elem).as_deref() == Some("str") => PassMode::Handle,
+            _ => PassMode::Pointer,
+        },
+        Type::Group(group) => argument_mode(&group.elem),
+        Type::Paren(paren) => argument_mode(&paren.elem),
+        ty => match type_name(ty).as_deref() {
+            Some("String" | "Vec") => PassMode::Handle,
+            Some("LVTime") => PassMode::Pointer,
+            _ => raw_mode(ty),
+        },
+    }
+}
+
+/// The first generic type argument of a path type such as `Result<T, E>`.
+fn first_type_argument(ty: &Type) -> Option<&Type> {
+    let Type::Path(path) = ty else {
+        return None;
+    };
+    let PathArguments::AngleBracketed(arguments) = &path.path.segments.last()?.arguments else {
+        return None;
+    };
+    match arguments.args.first()? {
+        GenericArgument::Type(ty) => Some(ty),
+        _ => None,
+    }
+}
+
+/// The pass mode of the output for an `lv_export!` return type, following
+/// `IntoLvReturn`, or `None` if there is no output parameter.
+fn output_mode(ty: &Type) -> Option<PassMode> {
+    if matches!(ty, Type::Tuple(tuple) if tuple.elems.is_empty()) {
+        return None;
+    }
+    match type_name(ty).as_deref() {
+        Some("Result") => first_type_argument(ty).and_then(output_mode),
+        // Strings and arrays are written through a pointer to the handle.
+        Some("String" | "Vec") => Some(PassMode::HandlePointer),
+        _ => Some(PassMode::Pointer),
+    }
+}
+
+/// The name and type of each typed parameter.
+fn typed_parameters(function: &ItemFn) -> impl Iterator<Item = (String, &Type)> {
+    function.sig.inputs.iter().filter_map(|input| match input {
+        FnArg::Typed(typed) => {
+            let name = match typed.pat.as_ref() {
+                Pat::Ident(ident) => ident.ident.to_string(),
+                _ => "_".to_string(),
+            };
+            Some((name, typed.ty.as_ref()))
+        }
+        FnArg::Receiver(_) => None,
+    })
+}
+
+fn raw_export(function: &ItemFn) -> Export {
+    Export {
+        name: function.sig.ident.to_string(),
+        parameters: typed_parameters(function)
+            .map(|(name, ty)| Parameter {
+                name,
+                mode: raw_mode(ty),
+            })
+            .collect(),
+        description: doc_text(&function.attrs),
+    }
+}
+
+/// The export generated by `lv_export!` or `#[labview_export]`.
+fn generated_export(function: &ItemFn) -> Export {
+    let mut parameters: Vec<Parameter> = typed_parameters(function)
+        .map(|(name, ty)| Parameter {
+            name,
+            mode: argument_mode(ty),
+        })
+        .collect();
+    if let ReturnType::Type(_, ty) = &function.sig.output {
+        if let Some(mode) = output_mode(ty) {
+            parameters.push(Parameter {
+                name: "output".to_string(),
+                mode,
+            });
+        }
+    }
+    parameters.push(Parameter {
+        name: "error_cluster".to_string(),
+        mode: PassMode::Pointer,
+    });
+    Export {
+        name: function.sig.ident.to_string(),
+        parameters,
+        description: doc_text(&function.attrs),
+    }
+}
+
+/// Find the exported functions in a source file.
+pub fn exports(source: &str) -> syn::Result<Vec<Export>> {
+    let file = syn::parse_file(source)?;
+    let mut exports = Vec::new();
+    collect_items(&file.items, &mut exports)?;
+    Ok(exports)
+}
+
+fn collect_items(items: &[Item], exports: &mut Vec<Export>) -> syn::Result<()> {
+    for item in items {
+        match item {
+            Item::Fn(function) if is_labview_export(&function.attrs) => {
+                exports.push(generated_export(function));
+            }
+            Item::Fn(function) if function.sig.abi.is_some() && is_no_mangle(&function.attrs) => {
+                exports.push(raw_export(function));
+            }
+            Item::Macro(item)
+                if item
+                    .mac
+                    .path
+                    .segments
+                    .last()
+                    .is_some_and(|segment| segment.ident == "lv_export") =>
+            {
+                let function: ItemFn = syn::parse2(item.mac.tokens.clone())?;
+                exports.push(generated_export(&function));
+            }
+            Item::Mod(module) => {
+                if let Some((_, items)) = &module.content {
+                    collect_items(items, exports)?;
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Add the `.rs` files in `path` to `sources`, searching folders recursively
+/// in name order.
+pub fn collect_sources(path: &Path, sources: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    if path.is_dir() {
+        let mut entries = std::fs::read_dir(path)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<std::io::Result<Vec<_>>>()?;
+        entries.sort();
+        for entry in entries {
+            collect_sources(&entry, sources)?;
+        }
+    } else if path.extension().is_some_and(|extension| extension == "rs") {
+        sources.push(path.to_path_buf());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = r#"
+#[no_mangle]
+pub extern "C" fn raw(
+    value: f64,
+    output: *mut f64,
+    text: LStrHandle,
+    resized: *mut LStrHandle,
+    error_cluster: ErrorClusterPtr,
+) -> MgErr {
+    MgErr::NO_ERROR
+}
+
+lv_export! {
+    /// The mean of the values.
+    fn mean(values: &[f64]) -> Result<f64, LVInteropError> {
+        Ok(0.0)
+    }
+}
+
+mod inner {
+    #[labview_export]
+    fn greeting(name: &str, shout: bool, time: LVTime) -> String {
+        String::new()
+    }
+
+    #[labview_export]
+    fn reset() -> Result<(), LVInteropError> {
+        Ok(())
+    }
+}
+
+pub fn helper() {}
+"#;
+
+    fn modes(export: &Export) -> Vec<PassMode> {
+        export
+            .parameters
+            .iter()
+            .map(|parameter| parameter.mode)
+            .collect()
+    }
+
+    #[test]
+    fn test_exports() {
+        use PassMode::*;
+
+        let exports = exports(SOURCE).unwrap();
+        let names: Vec<&str> = exports.iter().map(|export| export.name.as_str()).collect();
+        assert_eq!(names, ["raw", "mean", "greeting", "reset"]);
+        assert_eq!(
+            modes(&exports[0]),
+            [Value, Pointer, Handle, HandlePointer, Pointer]
+        );
+        assert_eq!(modes(&exports[1]), [Handle, Pointer, Pointer]);
+        assert_eq!(
+            modes(&exports[2]),
+            [Handle, Value, Pointer, HandlePointer, Pointer]
+        );
+        assert_eq!(exports[2].parameters[3].name, "output");
+        assert_eq!(modes(&exports[3]), [Pointer]);
+        assert_eq!(exports[1].description, "The mean of the values.");
+    }
+
+    #[test]
+    fn test_pass_mode_round_trip() {
+        for mode in [
+            PassMode::Value,
+            PassMode::Pointer,
+            PassMode::Handle,
+            PassMode::HandlePointer,
+        ] {
+            assert_eq!(mode.to_string().parse::<PassMode>(), Ok(mode));
+        }
+        assert!("Handle Pointer".parse::<PassMode>().is_ok());
+        assert!("reference".parse::<PassMode>().is_err());
+    }
+}
@@ -0,0 +1,11 @@
+//! Development tools for libraries built with labview-interop.
+//!
+//! * `lvproj-check` checks a LabVIEW project refers to a single, existing copy
+//!   of the library and that the Call Library nodes match the exports.
+//! * `lv-export-docs` extracts the doc comments of the exports for LabVIEW
+//!   Context Help.
+
+pub mod docs;
+pub mod exports;
+pub mod nodes;
+pub mod project;
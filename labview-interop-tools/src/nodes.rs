@@ -0,0 +1,238 @@
+//! Checking Call Library Function node configurations against the exports.
+//!
+//! The node configuration is stored in the binary VI files, so it is read from
+//! a tab separated file which a scripting VI can write with Write Delimited
+//! Spreadsheet. Each line has the VI path, the function name and the pass mode
+//! of each parameter in order, separated by commas. With the tabs shown as
+//! spaces:
+//!
+//! ```text
+//! Mean.vi     mean     handle, pointer, pointer
+//! Reset.vi    reset    pointer
+//! ```
+//!
+//! The pass modes are `value`, `pointer`, `handle` and `handle pointer` as in
+//! [`PassMode`]. The return value isn't listed. Empty lines and lines starting
+//! with `#` are ignored.
+
+use std::fmt;
+
+use crate::exports::{Export, PassMode};
+
+/// The configuration of a Call Library Function node.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CallLibraryNode {
+    /// The VI containing the node.
+    pub vi: String,
+    /// The function the node calls.
+    pub function: String,
+    /// The pass mode of each parameter in order.
+    pub parameters: Vec<PassMode>,
+}
+
+/// An error reading the node configuration file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// The line number, starting from 1.
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Read the node configurations from the tab separated text.
+pub fn parse_nodes(text: &str) -> Result<Vec<CallLibraryNode>, ParseError> {
+    let mut nodes = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let error = |message: String| ParseError {
+            line: index + 1,
+            message,
+        };
+        let line = line.trim_end_matches('\r');
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = line.split('\t').collect();
+        let [vi, function, parameters] = fields.as_slice() else {
+            return Err(error(format!("expected 3 fields, found {}", fields.len())));
+        };
+        let parameters = parameters
+            .split(',')
+            .filter(|mode| !mode.trim().is_empty())
+            .map(str::parse)
+            .collect::<Result<_, _>>()
+            .map_err(error)?;
+        nodes.push(CallLibraryNode {
+            vi: vi.to_string(),
+            function: function.trim().to_string(),
+            parameters,
+        });
+    }
+    Ok(nodes)
+}
+
+/// A mismatch between a node and the exports.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NodeFinding {
+    /// The node calls a function the library doesn't export.
+    UnknownFunction { vi: String, function: String },
+    /// The node has a different number of parameters to the export.
+    ParameterCount {
+        vi: String,
+        function: String,
+        expected: usize,
+        found: usize,
+    },
+    /// A parameter is passed differently to how the export takes it.
+    PassMode {
+        vi: String,
+        function: String,
+        parameter: String,
+        expected: PassMode,
+        found: PassMode,
+    },
+}
+
+impl fmt::Display for NodeFinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NodeFinding::UnknownFunction { vi, function } => {
+                write!(f, "{vi}: the library doesn't export {function}")
+            }
+            NodeFinding::ParameterCount {
+                vi,
+                function,
+                expected,
+                found,
+            } => write!(
+                f,
+                "{vi}: {function} takes {expected} parameters but the node has {found}"
+            ),
+            NodeFinding::PassMode {
+                vi,
+                function,
+                parameter,
+                expected,
+                found,
+            } => write!(
+                f,
+                "{vi}: {function} takes {parameter} by {expected} but the node passes it by {found}"
+            ),
+        }
+    }
+}
+
+/// Check each node calls an export with matching parameters.
+pub fn check_nodes(nodes: &[CallLibraryNode], exports: &[Export]) -> Vec<NodeFinding> {
+    let mut findings = Vec::new();
+    for node in nodes {
+        let Some(export) = exports.iter().find(|export| export.name == node.function) else {
+            findings.push(NodeFinding::UnknownFunction {
+                vi: node.vi.clone(),
+                function: node.function.clone(),
+            });
+            continue;
+        };
+        if export.parameters.len() != node.parameters.len() {
+            findings.push(NodeFinding::ParameterCount {
+                vi: node.vi.clone(),
+                function: node.function.clone(),
+                expected: export.parameters.len(),
+                found: node.parameters.len(),
+            });
+            continue;
+        }
+        for (parameter, &found) in export.parameters.iter().zip(&node.parameters) {
+            if parameter.mode != found {
+                findings.push(NodeFinding::PassMode {
+                    vi: node.vi.clone(),
+                    function: node.function.clone(),
+                    parameter: parameter.name.clone(),
+                    expected: parameter.mode,
+                    found,
+                });
+            }
+        }
+    }
+    findings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exports::exports;
+
+    const SOURCE: &str = r#"
+lv_export! {
+    fn mean(values: &[f64]) -> Result<f64, LVInteropError> {
+        Ok(0.0)
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn scale(value: f64, output: *mut f64) -> MgErr {
+    MgErr::NO_ERROR
+}
+"#;
+
+    const NODES: &str = "# VI\tfunction\tparameters
+Mean.vi\tmean\thandle, pointer, pointer
+Mean Pointer.vi\tmean\thandle pointer, pointer, pointer
+
+Scale.vi\tscale\tvalue
+Old.vi\tremoved\t\r
+";
+
+    #[test]
+    fn test_parse_nodes() {
+        let nodes = parse_nodes(NODES).unwrap();
+        assert_eq!(nodes.len(), 4);
+        assert_eq!(
+            nodes[0],
+            CallLibraryNode {
+                vi: "Mean.vi".to_string(),
+                function: "mean".to_string(),
+                parameters: vec![PassMode::Handle, PassMode::Pointer, PassMode::Pointer],
+            }
+        );
+        assert!(nodes[3].parameters.is_empty());
+
+        let error = parse_nodes("A.vi\tmean\n").unwrap_err();
+        assert_eq!(error.line, 1);
+        assert_eq!(parse_nodes("\nA.vi\tmean\tref\n").unwrap_err().line, 2);
+    }
+
+    #[test]
+    fn test_check_nodes() {
+        let exports = exports(SOURCE).unwrap();
+        let nodes = parse_nodes(NODES).unwrap();
+        assert_eq!(
+            check_nodes(&nodes, &exports),
+            vec![
+                NodeFinding::PassMode {
+                    vi: "Mean Pointer.vi".to_string(),
+                    function: "mean".to_string(),
+                    parameter: "values".to_string(),
+                    expected: PassMode::Handle,
+                    found: PassMode::HandlePointer,
+                },
+                NodeFinding::ParameterCount {
+                    vi: "Scale.vi".to_string(),
+                    function: "scale".to_string(),
+                    expected: 2,
+                    found: 1,
+                },
+                NodeFinding::UnknownFunction {
+                    vi: "Old.vi".to_string(),
+                    function: "removed".to_string(),
+                },
+            ]
+        );
+    }
+}
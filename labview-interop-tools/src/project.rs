@@ -0,0 +1,232 @@
+//! Reading library references from LabVIEW project and library files.
+//!
+//! Both `.lvproj` and `.lvlib` files are XML with nested `Item` elements. A
+//! shared library appears as an item named after the file, usually under
+//! Dependencies, with a URL relative to the project file itself, so
+//! `../my_lib.dll` is in the same folder as the project.
+//!
+//! The Call Library Function node configuration (function names, parameters
+//! and pass modes) is stored in the binary VI files rather than the project.
+//! See [`nodes`](crate::nodes) for checking it against the exports.
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// A project item referring to the shared library.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LibraryReference {
+    /// The item name, normally the library file name.
+    pub name: String,
+    /// The URL as stored in the project.
+    pub url: String,
+}
+
+impl LibraryReference {
+    /// Resolve the URL to a file path using the path of the project file.
+    ///
+    /// Returns `None` for symbolic paths such as `<vilib>` which depend on the
+    /// LabVIEW installation.
+    pub fn resolve(&self, project_path: &Path) -> Option<PathBuf> {
+        let url = self.url.as_str();
+        if url.starts_with("/<") {
+            return None;
+        }
+        // Absolute Windows paths are stored as /C/folder/file.dll.
+        if let Some(rest) = url.strip_prefix('/') {
+            let mut parts = rest.splitn(2, '/');
+            if let (Some(drive), Some(path)) = (parts.next(), parts.next()) {
+                if drive.len() == 1 && drive.chars().all(|c| c.is_ascii_alphabetic()) {
+                    return Some(PathBuf::from(format!("{drive}:/{path}")));
+                }
+            }
+            return Some(PathBuf::from(url));
+        }
+        let mut path = project_path.to_path_buf();
+        for segment in url.split('/') {
+            match segment {
+                "" | "." => {}
+                ".." => {
+                    path.pop();
+                }
+                segment => path.push(segment),
+            }
+        }
+        Some(path)
+    }
+}
+
+/// Check whether an item name refers to the library, ignoring case and extension.
+fn is_library_item(item_name: &str, library_name: &str) -> bool {
+    let stem = |name: &str| {
+        let name = name.to_ascii_lowercase();
+        match name.rsplit_once('.') {
+            Some((stem, "dll" | "so" | "dylib" | "framework")) => stem.to_string(),
+            _ => name,
+        }
+    };
+    stem(item_name) == stem(library_name)
+}
+
+/// Find every item in the project or library XML which refers to the library.
+pub fn find_library_references(
+    xml: &str,
+    library_name: &str,
+) -> Result<Vec<LibraryReference>, roxmltree::Error> {
+    let document = roxmltree::Document::parse(xml)?;
+    let references = document
+        .descendants()
+        .filter(|node| node.has_tag_name("Item"))
+        .filter_map(|node| {
+            let name = node.attribute("Name")?;
+            let url = node.attribute("URL")?;
+            is_library_item(name, library_name).then(|| LibraryReference {
+                name: name.to_string(),
+                url: url.to_string(),
+            })
+        })
+        .collect();
+    Ok(references)
+}
+
+/// A problem found with the library references.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Finding {
+    /// The project doesn't refer to the library at all.
+    NotReferenced,
+    /// A referenced library file doesn't exist.
+    Missing(PathBuf),
+    /// The project refers to more than one copy of the library so VIs may load different builds.
+    MultipleCopies(Vec<PathBuf>),
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Finding::NotReferenced => write!(f, "the library is not referenced by the project"),
+            Finding::Missing(path) => write!(f, "referenced library not found: {}", path.display()),
+            Finding::MultipleCopies(paths) => {
+                write!(f, "the project references multiple copies of the library:")?;
+                for path in paths {
+                    write!(f, "\n  {}", path.display())?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Check the references resolve to a single existing copy of the library.
+///
+/// `exists` is used to check the files so the checks can be run without a file system.
+pub fn check_references(
+    references: &[LibraryReference],
+    project_path: &Path,
+    exists: impl Fn(&Path) -> bool,
+) -> Vec<Finding> {
+    if references.is_empty() {
+        return vec![Finding::NotReferenced];
+    }
+
+    let mut paths: Vec<PathBuf> = references
+        .iter()
+        .filter_map(|reference| reference.resolve(project_path))
+        .collect();
+    paths.sort();
+    paths.dedup();
+
+    let mut findings: Vec<Finding> = paths
+        .iter()
+        .filter(|path| !exists(path))
+        .map(|path| Finding::Missing(path.clone()))
+        .collect();
+    if paths.len() > 1 {
+        findings.push(Finding::MultipleCopies(paths));
+    }
+    findings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROJECT: &str = r#"<?xml version='1.0' encoding='UTF-8'?>
+<Project Type="Project" LVVersion="20008000">
+    <Item Name="My Computer" Type="My Computer">
+        <Item Name="Test.vi" Type="VI" URL="../Test.vi"/>
+        <Item Name="Dependencies" Type="Dependencies">
+            <Item Name="vi.lib" Type="Folder">
+                <Item Name="Error Cluster From Error Code.vi" Type="VI" URL="/&lt;vilib&gt;/Utility/error.llb/Error Cluster From Error Code.vi"/>
+            </Item>
+            <Item Name="my_lib.dll" Type="Document" URL="../target/release/my_lib.dll"/>
+            <Item Name="My_Lib.DLL" Type="Document" URL="/C/builds/my_lib.dll"/>
+        </Item>
+    </Item>
+</Project>"#;
+
+    #[test]
+    fn test_find_library_references() {
+        let references = find_library_references(PROJECT, "my_lib").unwrap();
+        assert_eq!(
+            references,
+            vec![
+                LibraryReference {
+                    name: "my_lib.dll".to_string(),
+                    url: "../target/release/my_lib.dll".to_string()
+                },
+                LibraryReference {
+                    name: "My_Lib.DLL".to_string(),
+                    url: "/C/builds/my_lib.dll".to_string()
+                },
+            ]
+        );
+        assert!(find_library_references(PROJECT, "other.dll")
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_resolve_urls() {
+        let project_path = Path::new("/projects/test/test.lvproj");
+        let reference = |url: &str| LibraryReference {
+            name: "my_lib.dll".to_string(),
+            url: url.to_string(),
+        };
+        assert_eq!(
+            reference("../my_lib.dll").resolve(project_path),
+            Some(PathBuf::from("/projects/test/my_lib.dll"))
+        );
+        assert_eq!(
+            reference("../../target/release/my_lib.dll").resolve(project_path),
+            Some(PathBuf::from("/projects/target/release/my_lib.dll"))
+        );
+        assert_eq!(
+            reference("/C/builds/my_lib.dll").resolve(project_path),
+            Some(PathBuf::from("C:/builds/my_lib.dll"))
+        );
+        assert_eq!(reference("/<vilib>/my_lib.dll").resolve(project_path), None);
+    }
+
+    #[test]
+    fn test_check_references() {
+        let project_path = Path::new("/projects/test/test.lvproj");
+        let references = find_library_references(PROJECT, "my_lib.dll").unwrap();
+        let findings = check_references(&references, project_path, |path| {
+            path.starts_with("/projects")
+        });
+        assert_eq!(
+            findings,
+            vec![
+                Finding::Missing(PathBuf::from("C:/builds/my_lib.dll")),
+                Finding::MultipleCopies(vec![
+                    PathBuf::from("/projects/test/target/release/my_lib.dll"),
+                    PathBuf::from("C:/builds/my_lib.dll"),
+                ]),
+            ]
+        );
+
+        assert_eq!(
+            check_references(&[], project_path, |_| true),
+            vec![Finding::NotReferenced]
+        );
+    }
+}
@@ -0,0 +1,256 @@
+//! Detection of stale copies of a library during development.
+//!
+//! LabVIEW keeps a shared library loaded until every VI referencing it is
+//! closed, so after a rebuild it is easy to end up with VIs calling an old copy
+//! of the library while others call the new one. The symptoms of this are
+//! usually confusing.
+//!
+//! The build stamp gives each build an ID which is compiled in. When a library
+//! is loaded it records its ID in a process wide registry and exports check
+//! their compiled ID against it, returning a clear error if a newer build has
+//! since been loaded.
+//!
+//! On Windows the registry is the process environment, which is shared by every
+//! copy of the library and which the Win32 API locks. Changing the environment
+//! isn't thread safe on other platforms so there the registry is only seen by
+//! one copy of the library and stale copies aren't detected.
+//!
+//! # Usage
+//!
+//! In the library's `build.rs` (with this crate as a build dependency), generate
+//! a new ID for every build:
+//!
+//! ```no_run
+//! // build.rs
+//! labview_interop::build_stamp::emit_build_id();
+//! ```
+//!
+//! Then declare the stamp in the library. Exports defined with
+//! [`lv_export!`](crate::lv_export) or `#[labview_export]` check it before
+//! running and other exports can call the generated `check_build_stamp()`:
+//!
+//! ```
+//! use labview_interop::errors::MgErr;
+//!
+//! labview_interop::lv_build_stamp!();
+//!
+//! #[no_mangle]
+//! pub extern "C" fn my_export() -> MgErr {
+//!     if let Err(error) = check_build_stamp() {
+//!         return error.into();
+//!     }
+//!     MgErr::NO_ERROR
+//! }
+//! ```
+//!
+//! Without the build script the package version is used as the ID.
+
+use std::sync::OnceLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::errors::{LVInteropError, Result};
+
+/// The compile time environment variable holding the build ID.
+pub const BUILD_ID_ENV: &str = "LABVIEW_INTEROP_BUILD_ID";
+
+/// Generate a unique build ID for the crate being built.
+///
+/// Call this from a build script. It emits the ID as a compile time
+/// environment variable which [`lv_build_stamp!`](crate::lv_build_stamp) reads.
+/// As it doesn't emit any `rerun-if` directives cargo reruns it whenever the
+/// package changes.
+pub fn emit_build_id() {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_nanos())
+        .unwrap_or_default();
+    println!("cargo:rustc-env={BUILD_ID_ENV}={nanos}");
+}
+
+/// The name of the registry entry for `library`, which is a valid environment
+/// variable name.
+fn registry_key(library: &str) -> String {
+    let library: String = library
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("LABVIEW_INTEROP_LOADED_{library}")
+}
+
+/// The registry of the latest loaded builds in the process environment.
+#[cfg(windows)]
+mod registry {
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn SetEnvironmentVariableW(name: *const u16, value: *const u16) -> i32;
+    }
+
+    fn wide(text: &str) -> Vec<u16> {
+        text.encode_utf16().chain(Some(0)).collect()
+    }
+
+    // `std::env::set_var` is also safe on Windows but it is an unsafe function
+    // from edition 2024, so the Win32 call it makes is used directly.
+    pub fn set(key: &str, value: &str) {
+        let (key, value) = (wide(key), wide(value));
+        unsafe { SetEnvironmentVariableW(key.as_ptr(), value.as_ptr()) };
+    }
+
+    pub fn get(key: &str) -> Option<String> {
+        std::env::var(key).ok()
+    }
+}
+
+/// The registry of the latest loaded builds, only shared within this copy of the library.
+#[cfg(not(windows))]
+mod registry {
+    use std::collections::BTreeMap;
+    use std::sync::{Mutex, MutexGuard};
+
+    static REGISTRY: Mutex<BTreeMap<String, String>> = Mutex::new(BTreeMap::new());
+
+    fn lock() -> MutexGuard<'static, BTreeMap<String, String>> {
+        REGISTRY
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    pub fn set(key: &str, value: &str) {
+        lock().insert(key.to_string(), value.to_string());
+    }
+
+    pub fn get(key: &str) -> Option<String> {
+        lock().get(key).cloned()
+    }
+}
+
+/// Record `build_id` as the latest loaded build of `library`.
+pub fn register_build(library: &str, build_id: &str) {
+    registry::set(&registry_key(library), build_id);
+}
+
+/// Check that `build_id` is the latest loaded build of `library`.
+///
+/// # Errors
+///
+/// * [`LVInteropError::StaleLibrary`] if a different build has been loaded since.
+pub fn check_build(library: &str, build_id: &str) -> Result<()> {
+    match registry::get(&registry_key(library)) {
+        Some(loaded) if loaded != build_id => Err(LVInteropError::StaleLibrary {
+            loaded,
+            expected: build_id.to_string(),
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// The library name and build ID declared by [`lv_build_stamp!`](crate::lv_build_stamp).
+static BUILD_STAMP: OnceLock<(&'static str, &'static str)> = OnceLock::new();
+
+/// Set the build stamp of this library and register it as the latest loaded build.
+///
+/// This is called by [`lv_build_stamp!`](crate::lv_build_stamp) when the
+/// library is loaded. Only the first stamp set is used.
+pub fn set_build_stamp(library: &'static str, build_id: &'static str) {
+    if BUILD_STAMP.set((library, build_id)).is_ok() {
+        register_build(library, build_id);
+    }
+}
+
+/// Check the build stamp of this library, if it has one, is the latest loaded build.
+///
+/// This is what the exports from [`lv_export!`](crate::lv_export) check before running.
+///
+/// # Errors
+///
+/// * [`LVInteropError::StaleLibrary`] if a different build has been loaded since.
+pub fn check_build_stamp() -> Result<()> {
+    check_stamp(BUILD_STAMP.get().copied())
+}
+
+/// Check a stamp, where no stamp always passes.
+fn check_stamp(stamp: Option<(&str, &str)>) -> Result<()> {
+    match stamp {
+        Some((library, build_id)) => check_build(library, build_id),
+        None => Ok(()),
+    }
+}
+
+/// Declare the build stamp for this library.
+///
+/// This defines a `BUILD_ID` constant, registers it when the library is loaded
+/// and defines `check_build_stamp()` for exports which don't check it already.
+///
+/// See the [`build_stamp`](crate::build_stamp) module for details.
+#[macro_export]
+macro_rules! lv_build_stamp {
+    () => {
+        /// The ID of this build of the library.
+        pub const BUILD_ID: &str = match option_env!("LABVIEW_INTEROP_BUILD_ID") {
+            Some(id) => id,
+            None => env!("CARGO_PKG_VERSION"),
+        };
+
+        #[$crate::ctor::ctor]
+        fn register_build_stamp() {
+            $crate::build_stamp::set_build_stamp(env!("CARGO_PKG_NAME"), BUILD_ID);
+        }
+
+        /// Check that this is the latest build of the library to be loaded.
+        pub fn check_build_stamp() -> $crate::errors::Result<()> {
+            $crate::build_stamp::check_build_stamp()
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unregistered_library_passes() {
+        assert!(check_build("build-stamp-unregistered", "1").is_ok());
+    }
+
+    #[test]
+    fn test_newer_build_makes_old_stale() {
+        register_build("build-stamp-test", "1");
+        assert!(check_build("build-stamp-test", "1").is_ok());
+
+        register_build("build-stamp-test", "2");
+        assert!(matches!(
+            check_build("build-stamp-test", "1"),
+            Err(LVInteropError::StaleLibrary { .. })
+        ));
+        assert!(check_build("build-stamp-test", "2").is_ok());
+    }
+
+    #[test]
+    fn test_build_stamp() {
+        // The test binary has no stamp so every check passes.
+        assert!(check_build_stamp().is_ok());
+        assert!(check_stamp(None).is_ok());
+
+        // The global stamp can only be set once so a local one is checked.
+        let stamp = ("build-stamp-static", "1");
+        register_build(stamp.0, stamp.1);
+        assert!(check_stamp(Some(stamp)).is_ok());
+
+        register_build("build-stamp-static", "2");
+        assert!(check_stamp(Some(stamp)).is_err());
+    }
+
+    #[test]
+    fn test_registry_key_is_valid_variable_name() {
+        assert_eq!(
+            registry_key("my-lib.v2"),
+            "LABVIEW_INTEROP_LOADED_MY_LIB_V2"
+        );
+    }
+}
@@ -0,0 +1,211 @@
+//! Cleanup procedures run by LabVIEW when a VI goes idle or is aborted.
+//!
+//! LabVIEW's `RTSetCleanupProc` registers a function to call when the VI
+//! calling into the library stops, including when it is aborted from the
+//! toolbar. [`on_cleanup`] registers a Rust closure this way so background
+//! threads, handles and registry entries started by a VI can be torn down
+//! even if it never reaches its close function.
+//!
+//! The cleanup is registered for the VI making the current call so this must
+//! be called from an export rather than a background thread.
+//!
+//! # Example
+//! ```
+//! use labview_interop::cleanup::{on_cleanup, CleanupMode};
+//! use labview_interop::errors::MgErr;
+//! use std::sync::atomic::{AtomicBool, Ordering};
+//! use std::sync::Arc;
+//!
+//! #[no_mangle]
+//! pub extern "C" fn start_worker() -> MgErr {
+//!     let stop = Arc::new(AtomicBool::new(false));
+//!     let worker_stop = stop.clone();
+//!     std::thread::spawn(move || {
+//!         while !worker_stop.load(Ordering::Relaxed) {
+//!             // Do some background work.
+//!         }
+//!     });
+//!     // Stop the worker if the VI is aborted.
+//!     match on_cleanup(CleanupMode::OnIdle, move || stop.store(true, Ordering::Relaxed)) {
+//!         Ok(_) => MgErr::NO_ERROR,
+//!         Err(error) => error.into(),
+//!     }
+//! }
+//! ```
+
+/// When LabVIEW should run a cleanup.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(i32)]
+pub enum CleanupMode {
+    /// When LabVIEW exits.
+    OnExit = 1,
+    /// When the top level VI goes idle, including when it is aborted.
+    OnIdle = 2,
+    /// After the top level VI is reset.
+    AfterReset = 3,
+    /// When the VI goes idle, unless it is the top level VI.
+    OnIdleIfNotTop = 4,
+    /// After the VI is reset, unless it is the top level VI.
+    AfterResetIfNotTop = 5,
+}
+
+#[cfg(feature = "link")]
+pub use registration::{on_cleanup, CleanupRegistration};
+
+/// The registrations with LabVIEW, which need the LabVIEW API.
+#[cfg(feature = "link")]
+mod registration {
+    use std::collections::BTreeMap;
+    use std::ffi::c_void;
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Mutex, MutexGuard};
+
+    use super::CleanupMode;
+    use crate::errors::Result;
+
+    /// Removes a registration in `RTSetCleanupProc`.
+    const CLEAN_REMOVE: i32 = 0;
+
+    type Cleanup = Box<dyn FnOnce() + Send>;
+
+    /// A cleanup registered with LabVIEW, which can be cancelled if the resource
+    /// is released normally first.
+    ///
+    /// Dropping this leaves the cleanup registered.
+    //
+    // LabVIEW identifies a registration by the procedure and a data pointer. The
+    // data pointer is a key into a table of closures rather than a real pointer
+    // so whichever of LabVIEW running the cleanup and Rust cancelling it takes the
+    // closure from the table first owns it.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct CleanupRegistration(usize);
+
+    impl CleanupRegistration {
+        /// Cancel the cleanup without running it. This does nothing if it has already run.
+        pub fn cancel(self) {
+            // Take the closure out before dropping it, as dropping what it
+            // captured may cancel other cleanups.
+            let cleanup = lock().remove(&self.0);
+            if cleanup.is_some() {
+                if let Ok(api) = crate::labview::cleanup_api() {
+                    let _ = unsafe {
+                        api.set_cleanup_proc(run_cleanup, self.data_pointer(), CLEAN_REMOVE)
+                    };
+                }
+            }
+        }
+
+        /// Whether the cleanup is still waiting to run.
+        pub fn is_pending(&self) -> bool {
+            lock().contains_key(&self.0)
+        }
+
+        fn data_pointer(self) -> *mut c_void {
+            std::ptr::without_provenance_mut(self.0)
+        }
+    }
+
+    static CLEANUPS: Mutex<BTreeMap<usize, Cleanup>> = Mutex::new(BTreeMap::new());
+    static NEXT_KEY: AtomicUsize = AtomicUsize::new(1);
+
+    fn lock() -> MutexGuard<'static, BTreeMap<usize, Cleanup>> {
+        CLEANUPS
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Called by LabVIEW to run a cleanup.
+    extern "C" fn run_cleanup(data: *mut c_void) -> i32 {
+        // The lock must be released before the closure runs, as it may register
+        // or cancel other cleanups.
+        let cleanup = lock().remove(&data.addr());
+        if let Some(cleanup) = cleanup {
+            // Never unwind into LabVIEW.
+            let _ = catch_unwind(AssertUnwindSafe(cleanup));
+        }
+        0
+    }
+
+    /// Register `cleanup` to run when the calling VI reaches the point given by `mode`.
+    ///
+    /// The closure runs at most once, on a LabVIEW thread. A panic in it is caught.
+    ///
+    /// # Errors
+    ///
+    /// * [`LVInteropError::NoLabviewApi`](crate::errors::LVInteropError::NoLabviewApi)
+    ///   outside LabVIEW, where there is no VI to go idle.
+    /// * The error from LabVIEW if the registration fails.
+    pub fn on_cleanup(
+        mode: CleanupMode,
+        cleanup: impl FnOnce() + Send + 'static,
+    ) -> Result<CleanupRegistration> {
+        let api = crate::labview::cleanup_api()?;
+        let registration = CleanupRegistration(NEXT_KEY.fetch_add(1, Ordering::Relaxed));
+        lock().insert(registration.0, Box::new(cleanup));
+        let err =
+            unsafe { api.set_cleanup_proc(run_cleanup, registration.data_pointer(), mode as i32) };
+        if let Err(error) = err.to_result(()) {
+            let cleanup = lock().remove(&registration.0);
+            drop(cleanup);
+            return Err(error);
+        }
+        Ok(registration)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use std::sync::atomic::AtomicBool;
+        use std::sync::Arc;
+
+        #[test]
+        fn test_cleanup_runs_once() {
+            let ran = Arc::new(AtomicBool::new(false));
+            let registration = CleanupRegistration(NEXT_KEY.fetch_add(1, Ordering::Relaxed));
+            let flag = ran.clone();
+            lock().insert(
+                registration.0,
+                Box::new(move || flag.store(true, Ordering::Relaxed)),
+            );
+            assert!(registration.is_pending());
+
+            assert_eq!(run_cleanup(registration.data_pointer()), 0);
+            assert!(ran.load(Ordering::Relaxed));
+            assert!(!registration.is_pending());
+            // Cancelling or running again finds nothing.
+            registration.cancel();
+            assert_eq!(run_cleanup(registration.data_pointer()), 0);
+        }
+
+        #[test]
+        fn test_cleanup_can_cancel_cleanups() {
+            let other = CleanupRegistration(NEXT_KEY.fetch_add(1, Ordering::Relaxed));
+            lock().insert(other.0, Box::new(|| ()));
+            let registration = CleanupRegistration(NEXT_KEY.fetch_add(1, Ordering::Relaxed));
+            let cancelled = Arc::new(AtomicBool::new(false));
+            let flag = cancelled.clone();
+            lock().insert(
+                registration.0,
+                Box::new(move || {
+                    // Deadlocks if the table is still locked.
+                    assert!(other.is_pending());
+                    other.cancel();
+                    let next = CleanupRegistration(NEXT_KEY.fetch_add(1, Ordering::Relaxed));
+                    lock().insert(next.0, Box::new(|| ()));
+                    next.cancel();
+                    flag.store(true, Ordering::Relaxed);
+                }),
+            );
+
+            assert_eq!(run_cleanup(registration.data_pointer()), 0);
+            assert!(cancelled.load(Ordering::Relaxed));
+            assert!(!other.is_pending());
+        }
+
+        #[test]
+        fn test_needs_labview() {
+            assert!(on_cleanup(CleanupMode::OnIdle, || ()).is_err());
+        }
+    }
+}
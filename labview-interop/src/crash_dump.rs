@@ -0,0 +1,529 @@
+//! An optional crash handler which writes a minidump when code in this
+//! library faults inside the LabVIEW process.
+//!
+//! Access violations in a library normally take down LabVIEW with little
+//! information about the cause. With the handler installed a minidump and a
+//! short summary of the interop state are written to the chosen directory
+//! before LabVIEW's own crash handling runs. The dump can be opened in WinDbg
+//! or Visual Studio with the library's PDB.
+//!
+//! Only faults at addresses inside this library trigger a dump so exceptions
+//! which LabVIEW raises and handles itself are ignored. At most one dump is
+//! written per load of the library, named with the process ID and the time the
+//! handler was installed.
+//!
+//! The faulting thread may hold any lock, including the heap's, so the
+//! exception handler itself only uses atomics. The dump is written by a
+//! watchdog thread started when the handler is installed, as Microsoft
+//! recommends for `MiniDumpWriteDump`, with the paths built in advance. State
+//! whose lock is held at the time of the fault is reported as unknown.
+//!
+//! This is only available on Windows with the `crash-dump` feature.
+//!
+//! # Example
+//! ```no_run
+//! use labview_interop::crash_dump::install_crash_handler;
+//!
+//! install_crash_handler(r"C:\ProgramData\MyLibrary\Crashes").unwrap();
+//! ```
+
+use std::ffi::{c_char, c_void, OsStr};
+use std::fmt::Write;
+use std::os::windows::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicI32, AtomicPtr, AtomicU32, AtomicUsize, Ordering};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use windows_sys::Win32::Foundation::{
+    CloseHandle, EXCEPTION_ACCESS_VIOLATION, EXCEPTION_ILLEGAL_INSTRUCTION,
+    EXCEPTION_INT_DIVIDE_BY_ZERO, EXCEPTION_IN_PAGE_ERROR, EXCEPTION_PRIV_INSTRUCTION,
+    EXCEPTION_STACK_OVERFLOW, GENERIC_WRITE, HANDLE, HMODULE, INVALID_HANDLE_VALUE, NTSTATUS,
+};
+use windows_sys::Win32::Storage::FileSystem::{
+    CreateFileW, WriteFile, CREATE_ALWAYS, FILE_ATTRIBUTE_NORMAL,
+};
+use windows_sys::Win32::System::Diagnostics::Debug::{
+    AddVectoredExceptionHandler, MiniDumpWithDataSegs, MiniDumpWithThreadInfo,
+    MiniDumpWithUnloadedModules, MiniDumpWriteDump, RemoveVectoredExceptionHandler,
+    EXCEPTION_CONTINUE_SEARCH, EXCEPTION_POINTERS, MINIDUMP_EXCEPTION_INFORMATION,
+};
+use windows_sys::Win32::System::LibraryLoader::{
+    GetModuleHandleExW, GET_MODULE_HANDLE_EX_FLAG_FROM_ADDRESS,
+    GET_MODULE_HANDLE_EX_FLAG_UNCHANGED_REFCOUNT,
+};
+use windows_sys::Win32::System::ProcessStatus::{K32GetModuleInformation, MODULEINFO};
+use windows_sys::Win32::System::Threading::{
+    CreateEventW, GetCurrentProcess, GetCurrentProcessId, GetCurrentThreadId, SetEvent,
+    WaitForSingleObject, INFINITE,
+};
+
+use crate::errors::{LVInteropError, MgErr, Result};
+use crate::validation::ValidationLevel;
+
+/// Exceptions which indicate a fault rather than a deliberate exception.
+const FATAL_EXCEPTIONS: [NTSTATUS; 6] = [
+    EXCEPTION_ACCESS_VIOLATION,
+    EXCEPTION_ILLEGAL_INSTRUCTION,
+    EXCEPTION_INT_DIVIDE_BY_ZERO,
+    EXCEPTION_IN_PAGE_ERROR,
+    EXCEPTION_PRIV_INSTRUCTION,
+    EXCEPTION_STACK_OVERFLOW,
+];
+
+/// How long the faulting thread waits for the watchdog to write the dump.
+const DUMP_TIMEOUT_MS: u32 = 60_000;
+
+/// How long unloading waits for the watchdog to stop.
+const WATCHDOG_STOP_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// The paths to write a dump to, as null terminated wide strings.
+struct DumpFiles {
+    dump: Vec<u16>,
+    summary: Vec<u16>,
+}
+
+static HANDLER: AtomicPtr<c_void> = AtomicPtr::new(std::ptr::null_mut());
+static DUMP_WRITTEN: AtomicBool = AtomicBool::new(false);
+static MODULE_START: AtomicUsize = AtomicUsize::new(0);
+static MODULE_END: AtomicUsize = AtomicUsize::new(0);
+/// Leaked when replaced, as the watchdog may be reading it.
+static DUMP_FILES: AtomicPtr<DumpFiles> = AtomicPtr::new(std::ptr::null_mut());
+
+// The fault, passed from the faulting thread to the watchdog.
+static FAULT_POINTERS: AtomicPtr<EXCEPTION_POINTERS> = AtomicPtr::new(std::ptr::null_mut());
+static FAULT_THREAD: AtomicU32 = AtomicU32::new(0);
+static FAULT_CODE: AtomicI32 = AtomicI32::new(0);
+static FAULT_ADDRESS: AtomicUsize = AtomicUsize::new(0);
+
+/// Set by the handler to wake the watchdog.
+static DUMP_REQUESTED: AtomicPtr<c_void> = AtomicPtr::new(std::ptr::null_mut());
+/// Set by the watchdog once the dump is written.
+static DUMP_DONE: AtomicPtr<c_void> = AtomicPtr::new(std::ptr::null_mut());
+static WATCHDOG_RUNNING: AtomicBool = AtomicBool::new(false);
+static WATCHDOG_STOP: AtomicBool = AtomicBool::new(false);
+
+/// Install the crash handler, writing dumps into `directory`.
+///
+/// The directory is created if it doesn't exist. Calling this again changes
+/// the directory.
+///
+/// # Errors
+///
+/// * [`LVInteropError::CrashHandler`] if the directory, watchdog thread or
+///   handler can't be created.
+pub fn install_crash_handler(directory: impl Into<PathBuf>) -> Result<()> {
+    let directory = directory.into();
+    std::fs::create_dir_all(&directory).map_err(|error| {
+        LVInteropError::CrashHandler(format!("{}: {error}", directory.display()))
+    })?;
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default();
+    let name = format!("crash-{}-{timestamp}", std::process::id());
+    let files = Box::new(DumpFiles {
+        dump: wide_path(&directory.join(format!("{name}.dmp"))),
+        summary: wide_path(&directory.join(format!("{name}.txt"))),
+    });
+    // The previous paths are leaked in case the watchdog is using them.
+    DUMP_FILES.store(Box::into_raw(files), Ordering::Release);
+
+    start_watchdog()?;
+    if HANDLER.load(Ordering::Acquire).is_null() {
+        find_module_range()?;
+        let handler = unsafe { AddVectoredExceptionHandler(1, Some(crash_handler)) };
+        if handler.is_null() {
+            return Err(LVInteropError::CrashHandler(
+                "the exception handler could not be registered".to_string(),
+            ));
+        }
+        if HANDLER
+            .compare_exchange(
+                std::ptr::null_mut(),
+                handler,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            )
+            .is_err()
+        {
+            // Another thread installed it first.
+            unsafe { RemoveVectoredExceptionHandler(handler) };
+        }
+    }
+    Ok(())
+}
+
+/// Whether the crash handler is installed.
+pub fn is_crash_handler_installed() -> bool {
+    !HANDLER.load(Ordering::Acquire).is_null()
+}
+
+/// Remove the crash handler and stop the watchdog thread.
+///
+/// This is called automatically when the library is unloaded.
+pub fn uninstall_crash_handler() {
+    let handler = HANDLER.swap(std::ptr::null_mut(), Ordering::AcqRel);
+    if !handler.is_null() {
+        unsafe { RemoveVectoredExceptionHandler(handler) };
+    }
+    stop_watchdog();
+}
+
+/// Install the crash handler from LabVIEW.
+///
+/// Configure the parameter as a "C String Pointer".
+///
+/// # Safety
+///
+/// * `directory` must be null or point to a null terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn interop_install_crash_handler(directory: *const c_char) -> MgErr {
+    let result = crate::error_codes::read_c_string(directory).and_then(install_crash_handler);
+    result.into()
+}
+
+/// The handler must not outlive the code it points to.
+#[ctor::dtor]
+unsafe fn remove_crash_handler_on_unload() {
+    uninstall_crash_handler();
+}
+
+fn wide_path(path: &Path) -> Vec<u16> {
+    OsStr::new(path)
+        .encode_wide()
+        .chain(std::iter::once(0))
+        .collect()
+}
+
+/// Record the address range of this library so only our faults are captured.
+fn find_module_range() -> Result<()> {
+    let mut module: HMODULE = std::ptr::null_mut();
+    let mut info = MODULEINFO {
+        lpBaseOfDll: std::ptr::null_mut(),
+        SizeOfImage: 0,
+        EntryPoint: std::ptr::null_mut(),
+    };
+    let found = unsafe {
+        GetModuleHandleExW(
+            GET_MODULE_HANDLE_EX_FLAG_FROM_ADDRESS | GET_MODULE_HANDLE_EX_FLAG_UNCHANGED_REFCOUNT,
+            find_module_range as *const () as *const u16,
+            &mut module,
+        ) != 0
+            && K32GetModuleInformation(
+                GetCurrentProcess(),
+                module,
+                &mut info,
+                std::mem::size_of::<MODULEINFO>() as u32,
+            ) != 0
+    };
+    if !found {
+        return Err(LVInteropError::CrashHandler(
+            "the library module could not be found".to_string(),
+        ));
+    }
+    let start = info.lpBaseOfDll.addr();
+    MODULE_START.store(start, Ordering::Release);
+    MODULE_END.store(start + info.SizeOfImage as usize, Ordering::Release);
+    Ok(())
+}
+
+/// Create an auto reset event, once.
+fn create_event(event: &AtomicPtr<c_void>) -> Result<HANDLE> {
+    let existing = event.load(Ordering::Acquire);
+    if !existing.is_null() {
+        return Ok(existing);
+    }
+    let created = unsafe { CreateEventW(std::ptr::null(), 0, 0, std::ptr::null()) };
+    if created.is_null() {
+        return Err(LVInteropError::CrashHandler(
+            "the watchdog event could not be created".to_string(),
+        ));
+    }
+    match event.compare_exchange(
+        std::ptr::null_mut(),
+        created,
+        Ordering::AcqRel,
+        Ordering::Acquire,
+    ) {
+        Ok(_) => Ok(created),
+        Err(existing) => {
+            unsafe { CloseHandle(created) };
+            Ok(existing)
+        }
+    }
+}
+
+/// Start the thread which writes the dump, if it isn't running.
+fn start_watchdog() -> Result<()> {
+    create_event(&DUMP_REQUESTED)?;
+    create_event(&DUMP_DONE)?;
+    WATCHDOG_STOP.store(false, Ordering::Release);
+    if WATCHDOG_RUNNING.swap(true, Ordering::AcqRel) {
+        return Ok(());
+    }
+    let spawned = std::thread::Builder::new()
+        .name("labview-interop-crash-watchdog".to_string())
+        .spawn(watchdog);
+    if let Err(error) = spawned {
+        WATCHDOG_RUNNING.store(false, Ordering::Release);
+        return Err(LVInteropError::CrashHandler(format!(
+            "the watchdog thread could not be started: {error}"
+        )));
+    }
+    Ok(())
+}
+
+/// Ask the watchdog to stop and wait briefly for it.
+///
+/// It isn't joined, as joining while the library unloads can deadlock on
+/// Windows. Waiting on the flag is enough to know it is no longer running our code.
+fn stop_watchdog() {
+    let requested = DUMP_REQUESTED.load(Ordering::Acquire);
+    if requested.is_null() || !WATCHDOG_RUNNING.load(Ordering::Acquire) {
+        return;
+    }
+    WATCHDOG_STOP.store(true, Ordering::Release);
+    unsafe { SetEvent(requested) };
+    let start = Instant::now();
+    while WATCHDOG_RUNNING.load(Ordering::Acquire) && start.elapsed() < WATCHDOG_STOP_TIMEOUT {
+        std::thread::sleep(Duration::from_millis(1));
+    }
+}
+
+fn watchdog() {
+    let requested = DUMP_REQUESTED.load(Ordering::Acquire);
+    loop {
+        unsafe { WaitForSingleObject(requested, INFINITE) };
+        if WATCHDOG_STOP.load(Ordering::Acquire) {
+            break;
+        }
+        unsafe { write_crash_files() };
+        let done = DUMP_DONE.load(Ordering::Acquire);
+        unsafe { SetEvent(done) };
+    }
+    WATCHDOG_RUNNING.store(false, Ordering::Release);
+}
+
+unsafe extern "system" fn crash_handler(info: *mut EXCEPTION_POINTERS) -> i32 {
+    let Some(record) = info.as_ref().and_then(|info| info.ExceptionRecord.as_ref()) else {
+        return EXCEPTION_CONTINUE_SEARCH;
+    };
+    let address = record.ExceptionAddress.addr();
+    let in_library = (MODULE_START.load(Ordering::Acquire)..MODULE_END.load(Ordering::Acquire))
+        .contains(&address);
+    if FATAL_EXCEPTIONS.contains(&record.ExceptionCode)
+        && in_library
+        && !DUMP_WRITTEN.swap(true, Ordering::AcqRel)
+    {
+        // Only atomics and waiting on the watchdog here, as the fault may have
+        // happened with any lock held.
+        FAULT_POINTERS.store(info, Ordering::Release);
+        FAULT_THREAD.store(GetCurrentThreadId(), Ordering::Release);
+        FAULT_CODE.store(record.ExceptionCode, Ordering::Release);
+        FAULT_ADDRESS.store(address, Ordering::Release);
+        let requested = DUMP_REQUESTED.load(Ordering::Acquire);
+        let done = DUMP_DONE.load(Ordering::Acquire);
+        if WATCHDOG_RUNNING.load(Ordering::Acquire) && !requested.is_null() && !done.is_null() {
+            SetEvent(requested);
+            WaitForSingleObject(done, DUMP_TIMEOUT_MS);
+        }
+    }
+    // Always let LabVIEW's own handling continue.
+    EXCEPTION_CONTINUE_SEARCH
+}
+
+/// Write the dump and summary from the watchdog. Failures are ignored as there
+/// is nothing useful to do.
+///
+/// Nothing here allocates, in case the faulting thread holds the heap lock.
+unsafe fn write_crash_files() {
+    let Some(files) = DUMP_FILES.load(Ordering::Acquire).as_ref() else {
+        return;
+    };
+    write_minidump(&files.dump);
+
+    let report = CrashReport {
+        code: FAULT_CODE.load(Ordering::Acquire),
+        address: FAULT_ADDRESS.load(Ordering::Acquire),
+        module_start: MODULE_START.load(Ordering::Acquire),
+        thread: FAULT_THREAD.load(Ordering::Acquire),
+        background_threads: crate::scope::scope().try_running(),
+        deferred_calls: crate::deferred::try_pending(),
+        validation_level: crate::validation::validation_level(),
+    };
+    let mut summary = FixedBuffer::new();
+    // Anything past the end of the buffer is dropped.
+    let _ = report.write(&mut summary);
+    if let Some(file) = create_file(&files.summary) {
+        let mut written = 0;
+        WriteFile(
+            file,
+            summary.as_bytes().as_ptr(),
+            summary.as_bytes().len() as u32,
+            &mut written,
+            std::ptr::null_mut(),
+        );
+        CloseHandle(file);
+    }
+}
+
+unsafe fn create_file(path: &[u16]) -> Option<HANDLE> {
+    let file = CreateFileW(
+        path.as_ptr(),
+        GENERIC_WRITE,
+        0,
+        std::ptr::null(),
+        CREATE_ALWAYS,
+        FILE_ATTRIBUTE_NORMAL,
+        std::ptr::null_mut(),
+    );
+    (file != INVALID_HANDLE_VALUE).then_some(file)
+}
+
+unsafe fn write_minidump(path: &[u16]) {
+    let Some(file) = create_file(path) else {
+        return;
+    };
+    let exception = MINIDUMP_EXCEPTION_INFORMATION {
+        ThreadId: FAULT_THREAD.load(Ordering::Acquire),
+        ExceptionPointers: FAULT_POINTERS.load(Ordering::Acquire),
+        ClientPointers: 0,
+    };
+    MiniDumpWriteDump(
+        GetCurrentProcess(),
+        GetCurrentProcessId(),
+        file,
+        MiniDumpWithDataSegs | MiniDumpWithThreadInfo | MiniDumpWithUnloadedModules,
+        &exception,
+        std::ptr::null(),
+        std::ptr::null(),
+    );
+    CloseHandle(file);
+}
+
+/// The fault and the state of the interop subsystems.
+struct CrashReport {
+    code: NTSTATUS,
+    address: usize,
+    module_start: usize,
+    thread: u32,
+    /// `None` if the count's lock was held.
+    background_threads: Option<usize>,
+    deferred_calls: Option<usize>,
+    validation_level: ValidationLevel,
+}
+
+impl CrashReport {
+    /// Write a readable summary.
+    fn write(&self, summary: &mut impl Write) -> std::fmt::Result {
+        writeln!(summary, "Exception code: {:#010X}", self.code as u32)?;
+        writeln!(
+            summary,
+            "Address: {:#X} (library offset {:#X})",
+            self.address,
+            self.address.wrapping_sub(self.module_start)
+        )?;
+        writeln!(summary, "Thread: {}", self.thread)?;
+        writeln!(
+            summary,
+            "labview-interop version: {}",
+            env!("CARGO_PKG_VERSION")
+        )?;
+        write!(summary, "Background threads running: ")?;
+        write_count(summary, self.background_threads)?;
+        write!(summary, "Deferred calls pending: ")?;
+        write_count(summary, self.deferred_calls)?;
+        writeln!(summary, "Validation level: {:?}", self.validation_level)
+    }
+}
+
+fn write_count(summary: &mut impl Write, count: Option<usize>) -> std::fmt::Result {
+    match count {
+        Some(count) => writeln!(summary, "{count}"),
+        None => writeln!(summary, "unknown"),
+    }
+}
+
+/// A text buffer which never allocates, failing once it is full.
+struct FixedBuffer {
+    bytes: [u8; 1024],
+    len: usize,
+}
+
+impl FixedBuffer {
+    fn new() -> Self {
+        Self {
+            bytes: [0; 1024],
+            len: 0,
+        }
+    }
+
+    fn as_bytes(&self) -> &[u8] {
+        &self.bytes[..self.len]
+    }
+}
+
+impl Write for FixedBuffer {
+    fn write_str(&mut self, text: &str) -> std::fmt::Result {
+        let end = self.len + text.len();
+        if end > self.bytes.len() {
+            return Err(std::fmt::Error);
+        }
+        self.bytes[self.len..end].copy_from_slice(text.as_bytes());
+        self.len = end;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_text() {
+        let report = CrashReport {
+            code: EXCEPTION_ACCESS_VIOLATION,
+            address: 0x1800_1234,
+            module_start: 0x1800_0000,
+            thread: 42,
+            background_threads: Some(2),
+            deferred_calls: None,
+            validation_level: ValidationLevel::Basic,
+        };
+        let mut summary = FixedBuffer::new();
+        report.write(&mut summary).unwrap();
+        let text = std::str::from_utf8(summary.as_bytes()).unwrap();
+        assert!(text.starts_with("Exception code: 0xC0000005\n"));
+        assert!(text.contains("Address: 0x18001234 (library offset 0x1234)\n"));
+        assert!(text.contains("Thread: 42\n"));
+        assert!(text.contains("Background threads running: 2\n"));
+        assert!(text.contains("Deferred calls pending: unknown\n"));
+        assert!(text.contains("Validation level: Basic\n"));
+    }
+
+    #[test]
+    fn test_fixed_buffer_is_bounded() {
+        let mut buffer = FixedBuffer::new();
+        assert!(buffer.write_str(&"x".repeat(1000)).is_ok());
+        assert!(buffer.write_str(&"x".repeat(100)).is_err());
+        assert_eq!(buffer.as_bytes().len(), 1000);
+    }
+
+    #[test]
+    fn test_install_and_uninstall() {
+        let directory = std::env::temp_dir().join("labview-interop-crash-dump-test");
+        install_crash_handler(&directory).unwrap();
+        assert!(directory.is_dir());
+        assert!(is_crash_handler_installed());
+        assert!(WATCHDOG_RUNNING.load(Ordering::Acquire));
+
+        // Installing again keeps the one handler and watchdog.
+        install_crash_handler(&directory).unwrap();
+        assert!(is_crash_handler_installed());
+
+        uninstall_crash_handler();
+        assert!(!is_crash_handler_installed());
+        assert!(!WATCHDOG_RUNNING.load(Ordering::Acquire));
+        let _ = std::fs::remove_dir(&directory);
+    }
+}
@@ -0,0 +1,112 @@
+//! A queue of work for background threads to hand to LabVIEW calling threads.
+//!
+//! Some operations must run on a thread that LabVIEW has called into, for
+//! example memory manager calls that are unsafe while LabVIEW is aborting a VI.
+//! Background threads can [`defer`] these and they will be run at the start of
+//! the next export using [`with_lverrorhandling!`](crate::with_lverrorhandling),
+//! by the `interop_run_deferred` export or by an explicit call to [`run_deferred`].
+//!
+//! # Example
+//! ```
+//! use labview_interop::deferred::{defer, run_deferred};
+//!
+//! std::thread::spawn(|| {
+//!     defer(|| println!("Running on a LabVIEW thread"));
+//! })
+//! .join()
+//! .unwrap();
+//!
+//! // At the top of the next export.
+//! assert_eq!(run_deferred(), 1);
+//! ```
+
+use std::collections::VecDeque;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::{Mutex, MutexGuard};
+
+use crate::errors::MgErr;
+
+type Deferred = Box<dyn FnOnce() + Send>;
+
+static QUEUE: Mutex<VecDeque<Deferred>> = Mutex::new(VecDeque::new());
+
+fn lock_queue() -> MutexGuard<'static, VecDeque<Deferred>> {
+    QUEUE
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Queue `work` to run on the next thread that calls into the library.
+pub fn defer<F: FnOnce() + Send + 'static>(work: F) {
+    lock_queue().push_back(Box::new(work));
+}
+
+/// The number of deferred calls waiting to run.
+pub fn pending() -> usize {
+    lock_queue().len()
+}
+
+/// The number of deferred calls without blocking, or `None` if the queue is locked.
+#[cfg(all(feature = "crash-dump", target_os = "windows"))]
+pub(crate) fn try_pending() -> Option<usize> {
+    QUEUE.try_lock().ok().map(|queue| queue.len())
+}
+
+/// Run everything deferred so far on the calling thread.
+///
+/// Work deferred while this runs waits for the next call. A panic in deferred
+/// work is caught so it can't unwind into LabVIEW and the remaining work still
+/// runs. Returns the number of calls which ran.
+pub fn run_deferred() -> usize {
+    let work: Vec<Deferred> = lock_queue().drain(..).collect();
+    let count = work.len();
+    for work in work {
+        let _ = catch_unwind(AssertUnwindSafe(work));
+    }
+    count
+}
+
+/// Run the deferred work from LabVIEW, for example from a timed loop.
+#[no_mangle]
+pub extern "C" fn interop_run_deferred() -> MgErr {
+    run_deferred();
+    MgErr::NO_ERROR
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    // The queue is global so this is a single test to avoid other tests draining it.
+    #[test]
+    fn test_deferred_work_runs_once_in_order_despite_panics() {
+        let order = Arc::new(Mutex::new(Vec::new()));
+        for index in 0..3 {
+            let order = order.clone();
+            defer(move || order.lock().unwrap().push(index));
+            if index == 1 {
+                defer(|| panic!("deferred panic"));
+            }
+        }
+        assert_eq!(pending(), 4);
+
+        assert_eq!(run_deferred(), 4);
+        assert_eq!(run_deferred(), 0);
+        assert_eq!(*order.lock().unwrap(), vec![0, 1, 2]);
+
+        let ran = Arc::new(AtomicUsize::new(0));
+        let inner = ran.clone();
+        defer(move || {
+            let inner = inner.clone();
+            defer(move || {
+                inner.fetch_add(1, Ordering::SeqCst);
+            });
+        });
+        run_deferred();
+        assert_eq!(ran.load(Ordering::SeqCst), 0);
+        run_deferred();
+        assert_eq!(ran.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,607 @@
+//! Support for the user defined error codes of a library.
+//!
+//! # Assigned Codes
+//!
+//! [`lv_error_codes!`](crate::lv_error_codes) declares an error enum whose codes
+//! are assigned in order from a base code. Every declared table is registered
+//! when the library loads and the `interop_error_table` export returns all of the
+//! codes and descriptions so LabVIEW can build its own error dialogs and tests.
+//!
+//! # Localized Messages
+//!
+//! Message tables can be registered for each language and the language
+//! selected at runtime, either from Rust with [`set_language`] or from LabVIEW
+//! with the `interop_set_language` export. When an error is written to a
+//! cluster the message for its code in the current language replaces the
+//! description, falling back to [`DEFAULT_LANGUAGE`] and then to the error's
+//! own description.
+//!
+//! Tables can be compiled in with [`register_messages`] or loaded from text
+//! files with [`load_messages`] so translations can be updated without
+//! rebuilding the library. The file format is one `code = message` per line
+//! with `#` comments:
+//!
+//! ```text
+//! # German messages
+//! 5001 = Kanal ist deaktiviert.
+//! 5002 = Abtastrate zu hoch.
+//! ```
+//!
+//! # Error Code Files
+//!
+//! LabVIEW reads custom error descriptions from `*-errors.txt` files in
+//! `user.lib/errors` or `<project>/errors`. [`error_file`] and
+//! [`write_error_file`] generate one from the declared codes so the Rust and
+//! LabVIEW definitions can't drift apart. To generate it from a build script,
+//! declare the codes in their own file and include it from both the library
+//! and `build.rs`, with `labview-interop` as a build dependency:
+//!
+//! ```ignore
+//! // build.rs
+//! include!("src/device_errors.rs");
+//!
+//! fn main() {
+//!     let out = std::env::var("OUT_DIR").unwrap();
+//!     labview_interop::error_codes::write_error_file(
+//!         std::path::Path::new(&out).join("device-errors.txt"),
+//!         "Device driver errors",
+//!         &DeviceError::table(),
+//!     )
+//!     .unwrap();
+//! }
+//! ```
+//!
+//! # Example
+//! ```
+//! use labview_interop::error_codes::{localized_message, register_messages, set_language};
+//!
+//! register_messages("en", &[(5001, "Channel is disabled.")]);
+//! register_messages("de", &[(5001, "Kanal ist deaktiviert.")]);
+//!
+//! set_language("de");
+//! assert_eq!(localized_message(5001).as_deref(), Some("Kanal ist deaktiviert."));
+//! # set_language("en");
+//! ```
+
+use std::collections::HashMap;
+#[cfg(feature = "std-error")]
+use std::error::Error;
+use std::ffi::{c_char, CStr};
+use std::path::Path;
+use std::sync::{OnceLock, RwLock};
+
+use crate::errors::{LVInteropError, MgErr};
+#[cfg(feature = "link")]
+use crate::types::{LStrHandle, LVArrayHandle};
+
+/// The language used when a message isn't available in the current language.
+pub const DEFAULT_LANGUAGE: &str = "en";
+
+#[derive(Default)]
+struct MessageTables {
+    language: Option<String>,
+    tables: HashMap<String, HashMap<i32, String>>,
+}
+
+fn message_tables() -> &'static RwLock<MessageTables> {
+    static TABLES: OnceLock<RwLock<MessageTables>> = OnceLock::new();
+    TABLES.get_or_init(Default::default)
+}
+
+/// The codes and descriptions declared with [`lv_error_codes!`](crate::lv_error_codes).
+static ERROR_TABLE: RwLock<Vec<(i32, &'static str)>> = RwLock::new(Vec::new());
+
+/// Register codes and descriptions to be returned by `interop_error_table`.
+///
+/// This is called automatically for enums declared with [`lv_error_codes!`](crate::lv_error_codes).
+pub fn register_error_table(entries: &[(i32, &'static str)]) {
+    let mut table = ERROR_TABLE
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    for &(code, description) in entries {
+        match table.binary_search_by_key(&code, |(code, _)| *code) {
+            Ok(index) => table[index].1 = description,
+            Err(index) => table.insert(index, (code, description)),
+        }
+    }
+}
+
+/// All registered codes and descriptions, ordered by code.
+pub fn error_table() -> Vec<(i32, &'static str)> {
+    ERROR_TABLE
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .clone()
+}
+
+/// Generate a LabVIEW error code file for the codes and descriptions.
+///
+/// The `comment` is shown in LabVIEW's error code file editor.
+///
+/// # Example
+/// ```
+/// use labview_interop::error_codes::error_file;
+///
+/// let file = error_file("Device driver errors", &[(5000, "The device is not connected.")]);
+/// assert!(file.contains("<nierror code=\"5000\">\nThe device is not connected.\n</nierror>"));
+/// ```
+pub fn error_file(comment: &str, entries: &[(i32, &str)]) -> String {
+    let mut file = String::from("<?xml version=\"1.0\"?>\n<nidocument>\n<nicomment>\n");
+    file.push_str(&escape_xml(comment));
+    file.push_str("\n</nicomment>\n");
+    for (code, description) in entries {
+        file.push_str(&format!(
+            "<nierror code=\"{code}\">\n{}\n</nierror>\n",
+            escape_xml(description)
+        ));
+    }
+    file.push_str("</nidocument>\n");
+    file
+}
+
+/// Write a LabVIEW error code file for the codes and descriptions.
+///
+/// LabVIEW only loads files whose name ends in `-errors.txt`.
+pub fn write_error_file(
+    path: impl AsRef<Path>,
+    comment: &str,
+    entries: &[(i32, &str)],
+) -> Result<(), LVInteropError> {
+    std::fs::write(path, error_file(comment, entries))?;
+    Ok(())
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Declare an error enum with codes assigned from a base code.
+///
+/// Codes are assigned in declaration order so new variants must be added at
+/// the end to keep existing codes stable. Every code must be in one of
+/// LabVIEW's [user defined ranges](crate::errors::USER_DEFINED_RANGES) or
+/// the declaration fails to compile. The enum implements
+/// [`ToLvError`](crate::types::ToLvError) and [`std::error::Error`] and its
+/// table is registered for the `interop_error_table` export.
+///
+/// # Example
+/// ```
+/// use labview_interop::lv_error_codes;
+///
+/// lv_error_codes! {
+///     /// Errors from the device driver.
+///     pub enum DeviceError: base = 5000 {
+///         NotConnected => "The device is not connected.",
+///         Timeout => "The device did not respond in time.",
+///     }
+/// }
+///
+/// assert_eq!(DeviceError::Timeout.error_code(), 5001);
+/// assert_eq!(DeviceError::Timeout.to_string(), "The device did not respond in time.");
+/// ```
+#[macro_export]
+macro_rules! lv_error_codes {
+    (
+        $(#[$meta:meta])*
+        $vis:vis enum $name:ident: base = $base:literal {
+            $(
+                $(#[$variant_meta:meta])*
+                $variant:ident => $description:literal
+            ),+ $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        $vis enum $name {
+            $($(#[$variant_meta])* $variant),+
+        }
+
+        impl $name {
+            /// The code of the first variant.
+            pub const BASE: i32 = $base;
+            /// Every variant in code order.
+            pub const ALL: &'static [Self] = &[$(Self::$variant),+];
+
+            /// The code assigned to this error.
+            pub const fn error_code(self) -> i32 {
+                Self::BASE + self as i32
+            }
+
+            /// The description of this error.
+            pub const fn error_description(self) -> &'static str {
+                match self {
+                    $(Self::$variant => $description),+
+                }
+            }
+
+            /// The short name of this error, which is the variant name.
+            pub const fn error_name(self) -> &'static str {
+                match self {
+                    $(Self::$variant => stringify!($variant)),+
+                }
+            }
+
+            /// Every code and description, for [`error_file`]($crate::error_codes::error_file).
+            pub fn table() -> ::std::vec::Vec<(i32, &'static str)> {
+                Self::ALL
+                    .iter()
+                    .map(|error| (error.error_code(), error.error_description()))
+                    .collect()
+            }
+        }
+
+        impl ::std::fmt::Display for $name {
+            fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+                f.write_str(self.error_description())
+            }
+        }
+
+        impl ::std::error::Error for $name {}
+
+        $crate::__lv_error_codes_to_lv_error!($name);
+
+        const _: () = assert!(
+            $crate::errors::is_user_defined_code($name::BASE)
+                && $crate::errors::is_user_defined_code(
+                    $name::BASE + $name::ALL.len() as i32 - 1
+                ),
+            "error codes must be in a user defined range",
+        );
+
+        const _: () = {
+            #[$crate::ctor::ctor]
+            fn register_error_table() {
+                $crate::error_codes::register_error_table(&[
+                    $(($name::$variant.error_code(), $description)),+
+                ]);
+            }
+        };
+    };
+}
+
+/// Implement `ToLvError` for an enum from [`lv_error_codes!`](crate::lv_error_codes).
+#[cfg(not(feature = "std-error"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __lv_error_codes_to_lv_error {
+    ($name:ident) => {
+        impl $crate::types::ToLvError for $name {
+            fn code(&self) -> $crate::errors::MgErr {
+                self.error_code().into()
+            }
+
+            fn description(&self) -> ::std::borrow::Cow<'_, str> {
+                self.error_description().into()
+            }
+        }
+    };
+}
+
+/// With the blanket implementation the enum's codes are registered instead.
+#[cfg(feature = "std-error")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __lv_error_codes_to_lv_error {
+    ($name:ident) => {
+        const _: () = {
+            #[$crate::ctor::ctor]
+            fn register_error_type() {
+                $crate::error_codes::register_error_type::<$name>(|error| {
+                    error.error_code().into()
+                });
+            }
+        };
+    };
+}
+
+/// Looks up the code of a registered error type.
+#[cfg(feature = "std-error")]
+type CodeLookup = Box<dyn Fn(&(dyn Error + 'static)) -> Option<MgErr> + Send + Sync>;
+
+/// The error types registered with [`register_error_type`].
+#[cfg(feature = "std-error")]
+static ERROR_TYPES: RwLock<Vec<CodeLookup>> = RwLock::new(Vec::new());
+
+/// Register how to get the code of an error type.
+///
+/// With the `std-error` feature every standard error implements
+/// [`ToLvError`](crate::types::ToLvError) with a code of 42 by default. Registering a
+/// type gives it a specific code, which is also used when it is the source of
+/// another error. Enums from [`lv_error_codes!`](crate::lv_error_codes) are
+/// registered automatically.
+#[cfg(feature = "std-error")]
+pub fn register_error_type<E: Error + 'static>(code: fn(&E) -> MgErr) {
+    ERROR_TYPES
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .push(Box::new(move |error| error.downcast_ref::<E>().map(code)));
+}
+
+/// The code of the first registered error type in the source chain of `error`.
+#[cfg(feature = "std-error")]
+pub fn registered_code(error: &(dyn Error + 'static)) -> Option<MgErr> {
+    let lookups = ERROR_TYPES
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    let mut next = Some(error);
+    while let Some(error) = next {
+        if let Some(code) = lookups.iter().find_map(|lookup| lookup(error)) {
+            return Some(code);
+        }
+        next = error.source();
+    }
+    None
+}
+
+/// Add messages for `language`, replacing any already registered for the same codes.
+pub fn register_messages<S: AsRef<str>>(language: &str, messages: &[(i32, S)]) {
+    let mut tables = message_tables()
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    let table = tables.tables.entry(language.to_string()).or_default();
+    for (code, message) in messages {
+        table.insert(*code, message.as_ref().to_string());
+    }
+}
+
+/// Load messages for `language` from a message file.
+///
+/// See the [module documentation](self) for the file format.
+///
+/// # Errors
+///
+/// * The file can't be read or a line isn't in the `code = message` format.
+pub fn load_messages(language: &str, path: impl AsRef<Path>) -> Result<(), LVInteropError> {
+    let contents = std::fs::read_to_string(path.as_ref()).map_err(|error| {
+        LVInteropError::InvalidMessageTable(format!("{}: {error}", path.as_ref().display()))
+    })?;
+    let messages = parse_messages(&contents)?;
+    register_messages(language, &messages);
+    Ok(())
+}
+
+/// Parse the contents of a message file.
+fn parse_messages(contents: &str) -> Result<Vec<(i32, String)>, LVInteropError> {
+    contents
+        .lines()
+        .enumerate()
+        .map(|(number, line)| (number + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(number, line)| {
+            line.split_once('=')
+                .and_then(|(code, message)| {
+                    let code = code.trim().parse().ok()?;
+                    Some((code, message.trim().to_string()))
+                })
+                .ok_or_else(|| {
+                    LVInteropError::InvalidMessageTable(format!("line {number}: `{line}`"))
+                })
+        })
+        .collect()
+}
+
+/// Select the language for error messages.
+pub fn set_language(language: &str) {
+    message_tables()
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .language = Some(language.to_string());
+}
+
+/// The currently selected language for error messages.
+pub fn language() -> String {
+    message_tables()
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .language
+        .clone()
+        .unwrap_or_else(|| DEFAULT_LANGUAGE.to_string())
+}
+
+/// The message for `code` in the current language, if one is registered.
+pub fn localized_message(code: impl Into<MgErr>) -> Option<String> {
+    let code = i32::from(code.into());
+    let tables = message_tables()
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    let language = tables.language.as_deref().unwrap_or(DEFAULT_LANGUAGE);
+    [language, DEFAULT_LANGUAGE]
+        .iter()
+        .find_map(|language| tables.tables.get(*language)?.get(&code))
+        .cloned()
+}
+
+/// Read a C string from LabVIEW.
+///
+/// # Safety
+///
+/// * `value` must be null or point to a null terminated string.
+pub(crate) unsafe fn read_c_string(value: *const c_char) -> Result<String, LVInteropError> {
+    if value.is_null() {
+        return Err(LVInteropError::InvalidHandle);
+    }
+    Ok(CStr::from_ptr(value).to_string_lossy().into_owned())
+}
+
+/// Select the language for error messages from LabVIEW.
+///
+/// Configure the parameter as a "C String Pointer".
+///
+/// # Safety
+///
+/// * `language` must be null or point to a null terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn interop_set_language(language: *const c_char) -> MgErr {
+    match read_c_string(language) {
+        Ok(language) => {
+            set_language(&language);
+            MgErr::NO_ERROR
+        }
+        Err(error) => error.into(),
+    }
+}
+
+/// Load a message file for a language from LabVIEW.
+///
+/// Configure both parameters as "C String Pointer".
+///
+/// # Safety
+///
+/// * `language` and `path` must be null or point to null terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn interop_load_messages(
+    language: *const c_char,
+    path: *const c_char,
+) -> MgErr {
+    let result = read_c_string(language).and_then(|language| {
+        let path = read_c_string(path)?;
+        load_messages(&language, path)
+    });
+    result.into()
+}
+
+/// Get every registered error code and its description from LabVIEW.
+///
+/// The codes array is resized to hold every code and the descriptions are
+/// written to the string one per line in the same order. Descriptions are in
+/// the current language where a translation is available.
+#[cfg(feature = "link")]
+#[no_mangle]
+pub extern "C" fn interop_error_table(
+    mut codes: LVArrayHandle<1, i32>,
+    mut descriptions: LStrHandle,
+) -> MgErr {
+    let table = error_table();
+    let result = (|| {
+        let count =
+            i32::try_from(table.len()).map_err(|_| LVInteropError::ArrayDimensionsOutOfRange)?;
+        codes.resize_array([count].into())?;
+        let code_values = unsafe { codes.as_ref_mut()? };
+        let mut text = String::new();
+        for (index, (code, description)) in table.iter().enumerate() {
+            // Written element by element as slices aren't available on 32 bit.
+            unsafe { code_values.set_value_unchecked(index, *code) };
+            let localized = localized_message(*code);
+            text.push_str(localized.as_deref().unwrap_or(description));
+            text.push('\n');
+        }
+        descriptions.set_str(text.trim_end_matches('\n'))
+    })();
+    result.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    crate::lv_error_codes! {
+        enum TestError: base = 7100 {
+            First => "First error",
+            Second => "Second error",
+        }
+    }
+
+    #[test]
+    fn test_codes_assigned_from_base() {
+        assert_eq!(TestError::First.error_code(), 7100);
+        assert_eq!(TestError::Second.error_code(), 7101);
+        assert_eq!(TestError::ALL, &[TestError::First, TestError::Second]);
+    }
+
+    #[test]
+    fn test_error_file() {
+        assert_eq!(TestError::Second.error_name(), "Second");
+        let file = error_file("Test & errors", &TestError::table());
+        assert_eq!(
+            file,
+            "<?xml version=\"1.0\"?>\n<nidocument>\n<nicomment>\nTest &amp; errors\n</nicomment>\n\
+             <nierror code=\"7100\">\nFirst error\n</nierror>\n\
+             <nierror code=\"7101\">\nSecond error\n</nierror>\n</nidocument>\n"
+        );
+    }
+
+    #[test]
+    fn test_error_table_registered_at_load() {
+        let table = error_table();
+        assert!(table.contains(&(7101, "Second error")));
+        assert!(table.windows(2).all(|pair| pair[0].0 < pair[1].0));
+    }
+
+    #[cfg(feature = "link")]
+    #[test]
+    fn test_error_table_export() {
+        use crate::memory::UHandle;
+        use crate::types::{LStrOwned, LVArrayOwned};
+
+        let codes = LVArrayOwned::<1, i32>::from_slice(&[]).unwrap();
+        let descriptions = LStrOwned::from_data(b"").unwrap();
+        assert_eq!(
+            interop_error_table(UHandle(codes.0), UHandle(descriptions.0)),
+            MgErr::NO_ERROR
+        );
+
+        let codes = unsafe { codes.as_ref() }.unwrap();
+        let index = (0..codes.element_count())
+            .find(|&index| unsafe { codes.get_value_unchecked(index) } == 7101)
+            .unwrap();
+        let text = descriptions.to_rust_string();
+        assert_eq!(text.lines().nth(index), Some("Second error"));
+    }
+
+    #[cfg(feature = "std-error")]
+    #[test]
+    fn test_registered_code_found_in_source_chain() {
+        #[derive(Debug)]
+        struct Wrapper(TestError);
+
+        impl std::fmt::Display for Wrapper {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.write_str("Wrapper")
+            }
+        }
+
+        impl Error for Wrapper {
+            fn source(&self) -> Option<&(dyn Error + 'static)> {
+                Some(&self.0)
+            }
+        }
+
+        let error = Wrapper(TestError::Second);
+        assert_eq!(registered_code(&error), Some(MgErr::from(7101)));
+        assert_eq!(registered_code(&std::fmt::Error), None);
+    }
+
+    #[test]
+    fn test_parse_messages() {
+        let messages = parse_messages("# comment\n\n5001 = First\n 5002=Second = more \n").unwrap();
+        assert_eq!(
+            messages,
+            vec![
+                (5001, "First".to_string()),
+                (5002, "Second = more".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_messages_invalid_line() {
+        assert!(matches!(
+            parse_messages("5001 = First\nnot a message"),
+            Err(LVInteropError::InvalidMessageTable(_))
+        ));
+    }
+
+    #[test]
+    fn test_falls_back_to_default_language() {
+        register_messages(DEFAULT_LANGUAGE, &[(-9001, "fallback")]);
+        register_messages("xx", &[(-9002, "translated")]);
+        set_language("xx");
+        assert_eq!(localized_message(-9001).as_deref(), Some("fallback"));
+        assert_eq!(localized_message(-9002).as_deref(), Some("translated"));
+        assert_eq!(localized_message(-9003), None);
+        set_language(DEFAULT_LANGUAGE);
+    }
+}
@@ -13,9 +13,35 @@ impl From<i32> for MgErr {
     }
 }
 
+impl From<MgErr> for i32 {
+    fn from(value: MgErr) -> i32 {
+        value.0
+    }
+}
+
+/// The ranges of codes LabVIEW reserves for users, inclusive.
+///
+/// NI and other vendors don't use these so libraries can define their own
+/// errors in them without colliding with LabVIEW's.
+pub const USER_DEFINED_RANGES: [(i32, i32); 3] = [(-8999, -8000), (5000, 9999), (500_000, 599_999)];
+
+/// True if the code is in one of LabVIEW's [user defined ranges](USER_DEFINED_RANGES).
+pub const fn is_user_defined_code(code: i32) -> bool {
+    let mut index = 0;
+    while index < USER_DEFINED_RANGES.len() {
+        let (start, end) = USER_DEFINED_RANGES[index];
+        if code >= start && code <= end {
+            return true;
+        }
+        index += 1;
+    }
+    false
+}
+
 impl MgErr {
     pub const NO_ERROR: MgErr = MgErr(0);
     pub const INTEROP_ERROR: MgErr = MgErr(-1);
+    pub const ARGUMENT_ERROR: MgErr = MgErr(1);
     pub const MEMORY_FULL: MgErr = MgErr(2);
     pub fn to_result<T>(self, success_value: T) -> Result<T> {
         if self.0 != 0 {
@@ -25,11 +51,203 @@ impl MgErr {
         }
     }
 
+    /// Create a code in one of LabVIEW's [user defined ranges](USER_DEFINED_RANGES).
+    ///
+    /// # Panics
+    ///
+    /// If the code isn't user defined. In a `const` this is a compile error.
+    ///
+    /// # Example
+    /// ```
+    /// use labview_interop::errors::MgErr;
+    ///
+    /// const NOT_HOMED: MgErr = MgErr::user_defined(5001);
+    /// assert!(NOT_HOMED.is_user_defined());
+    /// ```
+    ///
+    /// ```compile_fail
+    /// use labview_interop::errors::MgErr;
+    ///
+    /// // 42 is reserved by LabVIEW.
+    /// const NOT_HOMED: MgErr = MgErr::user_defined(42);
+    /// ```
+    pub const fn user_defined(code: i32) -> MgErr {
+        assert!(
+            is_user_defined_code(code),
+            "the code is not in a user defined range"
+        );
+        MgErr(code)
+    }
+
+    /// Create a code in one of LabVIEW's [user defined ranges](USER_DEFINED_RANGES),
+    /// returning an error if it isn't.
+    pub fn try_user_defined(code: i32) -> Result<MgErr> {
+        if is_user_defined_code(code) {
+            Ok(MgErr(code))
+        } else {
+            Err(LVInteropError::NotUserDefinedCode(code))
+        }
+    }
+
+    /// True if the code is in one of LabVIEW's [user defined ranges](USER_DEFINED_RANGES).
+    pub const fn is_user_defined(&self) -> bool {
+        is_user_defined_code(self.0)
+    }
+
+    /// The manager error for this code, for matching on.
+    pub fn kind(&self) -> MgError {
+        MgError::from(*self)
+    }
+
     fn get_description(&self) -> &'static str {
-        match self.0 {
-            0 => "No Error",
-            2 => "Memory Full",
-            _ => "No Description for Code",
+        self.kind()
+            .description()
+            .unwrap_or("No Description for Code")
+    }
+}
+
+macro_rules! mg_errors {
+    ($($variant:ident = $code:literal => $description:literal,)+) => {
+        /// The LabVIEW manager error codes.
+        ///
+        /// These are the codes the memory, file and other managers return
+        /// through [`MgErr`]. Convert an [`MgErr`] with [`MgErr::kind`] or `into()`
+        /// to match on them. Codes outside the manager range are [`MgError::Other`].
+        ///
+        /// # Example
+        /// ```
+        /// use labview_interop::errors::{MgErr, MgError};
+        ///
+        /// let error = MgErr::from(7);
+        /// assert_eq!(error.kind(), MgError::FileNotFound);
+        /// assert_eq!(error.kind().to_string(), "File not found.");
+        /// assert_eq!(MgError::from(MgErr::from(5001)), MgError::Other(5001));
+        /// ```
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        #[non_exhaustive]
+        pub enum MgError {
+            $(
+                #[doc = $description]
+                $variant,
+            )+
+            /// A code which isn't a manager error.
+            Other(i32),
+        }
+
+        impl MgError {
+            /// The numeric code.
+            pub fn code(&self) -> i32 {
+                match self {
+                    $(MgError::$variant => $code,)+
+                    MgError::Other(code) => *code,
+                }
+            }
+
+            /// The LabVIEW description of the code, if it is a manager error.
+            pub fn description(&self) -> Option<&'static str> {
+                match self {
+                    $(MgError::$variant => Some($description),)+
+                    MgError::Other(_) => None,
+                }
+            }
+        }
+
+        impl From<MgErr> for MgError {
+            fn from(value: MgErr) -> Self {
+                match value.0 {
+                    $($code => MgError::$variant,)+
+                    code => MgError::Other(code),
+                }
+            }
+        }
+    };
+}
+
+mg_errors!(
+    NoError = 0 => "No error.",
+    ArgumentError = 1 => "An input parameter is invalid.",
+    MemoryFull = 2 => "Memory is full.",
+    MemoryZone = 3 => "Memory zone error.",
+    EndOfFile = 4 => "End of file encountered.",
+    FileAlreadyOpen = 5 => "File already open.",
+    FileIo = 6 => "Generic file I/O error.",
+    FileNotFound = 7 => "File not found.",
+    FilePermission = 8 => "File permission error.",
+    DiskFull = 9 => "Disk full.",
+    DuplicatePath = 10 => "Duplicate path.",
+    TooManyFilesOpen = 11 => "Too many files open.",
+    NotEnabled = 12 => "Some system capacity necessary for operation is not enabled.",
+    NotAResourceFile = 13 => "File is not a resource file.",
+    CannotAddResource = 14 => "Cannot add resource.",
+    ResourceNotFound = 15 => "Resource not found.",
+    ImageNotFound = 16 => "Image not found.",
+    ImageMemory = 17 => "Image memory error.",
+    PenDoesNotExist = 18 => "Pen does not exist.",
+    ConfigTypeInvalid = 19 => "Config type invalid.",
+    ConfigTokenNotFound = 20 => "Config token not found.",
+    ConfigParse = 21 => "Config parse error.",
+    ConfigMemory = 22 => "Config memory error.",
+    BadExternalCodeFormat = 23 => "Bad external code format.",
+    ExternalSubroutineNotSupported = 24 => "External subroutine not supported.",
+    ExternalCodeNotPresent = 25 => "External code not present.",
+    NullWindow = 26 => "Null window.",
+    DestroyWindow = 27 => "Destroy window error.",
+    NullMenu = 28 => "Null menu.",
+    PrintAborted = 29 => "Print aborted.",
+    BadPrintRecord = 30 => "Bad print record.",
+    PrintDriver = 31 => "Print driver error.",
+    PrintWindows = 32 => "Windows error during printing.",
+    PrintMemory = 33 => "Memory error during printing.",
+    PrintDialog = 34 => "Print dialog error.",
+    PrintGeneric = 35 => "Generic print error.",
+    InvalidDeviceRefnum = 36 => "Invalid device refnum.",
+    DeviceNotFound = 37 => "Device not found.",
+    DeviceParameter = 38 => "Device parameter error.",
+    DeviceUnit = 39 => "Device unit error.",
+    CannotOpenDevice = 40 => "Cannot open device.",
+    DeviceCallAborted = 41 => "Device call aborted.",
+    Generic = 42 => "Generic error.",
+    Cancelled = 43 => "Operation cancelled by user.",
+    ObjectIdTooLow = 44 => "Object ID too low.",
+    ObjectIdTooHigh = 45 => "Object ID too high.",
+    ObjectNotInHeap = 46 => "Object not in heap.",
+    UnknownHeap = 47 => "Unknown heap.",
+    UnknownObjectInvalidDefProc = 48 => "Unknown object (invalid DefProc).",
+    UnknownObjectDefProcNotInTable = 49 => "Unknown object (DefProc not in table).",
+    MessageOutOfRange = 50 => "Message out of range.",
+    NullMethod = 51 => "Null method.",
+    UnknownMessage = 52 => "Unknown message.",
+    NotSupported = 53 => "Manager call not supported.",
+    NetworkBadAddress = 54 => "The network address is ill-formed.",
+    NetworkInProgress = 55 => "The network operation is in progress.",
+    NetworkTimeout = 56 => "The network operation exceeded the user-specified or system time limit.",
+    NetworkBusy = 57 => "The network connection is busy.",
+    NetworkNotSupported = 58 => "The network function is not supported by the system.",
+    NetworkDown = 59 => "The network is down, unreachable, or has been reset.",
+    NetworkAddressInUse = 60 => "The specified port or network address is currently in use.",
+    NetworkOutOfMemory = 61 => "The system could not allocate the necessary memory.",
+    NetworkConnectionAborted = 62 => "The system caused the network connection to be aborted.",
+    NetworkConnectionRefused = 63 => "The network connection was refused by the server.",
+    NetworkNotConnected = 64 => "The network connection is not yet established.",
+    NetworkAlreadyConnected = 65 => "The network connection is already established.",
+    NetworkConnectionClosed = 66 => "The network connection was closed by the peer.",
+    InterapplicationManagerInit = 67 => "Interapplication Manager initialization error.",
+    BadOccurrence = 68 => "Bad occurrence.",
+    WaitOnUnboundHandler = 69 => "Wait on unbound notifier handler.",
+    OccurrenceQueueOverflow = 70 => "Occurrence queue overflow.",
+);
+
+impl From<MgError> for MgErr {
+    fn from(value: MgError) -> Self {
+        MgErr(value.code())
+    }
+}
+
+impl Display for MgError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.description() {
+            Some(description) => write!(f, "{description}"),
+            None => write!(f, "Error {}.", self.code()),
         }
     }
 }
@@ -60,10 +278,105 @@ pub enum LVInteropError {
         "Array dimensions don't match. You may require the link feature to enable auto-resizing."
     )]
     ArrayDimensionMismatch,
+    #[error("Parameter `{parameter}` has dimensions {actual:?} but {expected:?} are required.")]
+    UnexpectedArrayDimensions {
+        parameter: String,
+        expected: Vec<usize>,
+        actual: Vec<usize>,
+    },
+    #[error("Background work did not stop before the timeout.")]
+    ShutdownTimeout,
+    #[error("Stale library loaded: build {expected} was called but build {loaded} has been loaded since. Restart LabVIEW to unload the stale library.")]
+    StaleLibrary { loaded: String, expected: String },
+    #[error("Invalid error message table: {0}")]
+    InvalidMessageTable(String),
+    #[error("The string `{0}` contains a null character so can't be passed to C.")]
+    NulInString(String),
+    #[error("The TDM C DLL (nilibddc) could not be loaded. Check the TDM C API is installed.")]
+    TdmsUnavailable,
+    #[error("TDMS error {0}")]
+    TdmsError(i32),
+    #[error("{0} is not a validation level. Use 0 (off), 1 (basic) or 2 (paranoid).")]
+    InvalidValidationLevel(i32),
+    #[error("Data from LabVIEW failed validation: {0}")]
+    ValidationFailed(String),
+    #[error("Crash handler could not be installed: {0}")]
+    CrashHandler(String),
+    #[error(transparent)]
+    Format(#[from] crate::format::FormatError),
+    #[error("Refnum {0} is not valid. It may have been closed already.")]
+    InvalidRefnum(u32),
+    #[error("{value} is out of range for {target}{}.", element_index(index))]
+    CoercionOutOfRange {
+        value: String,
+        target: &'static str,
+        index: Option<usize>,
+    },
+    #[error("NaN can't be converted to {target}{}.", element_index(index))]
+    CoercionNaN {
+        target: &'static str,
+        index: Option<usize>,
+    },
+    #[error("Invalid call capture: {0}")]
+    InvalidCapture(String),
+    #[error("Flattened data is corrupt or truncated: {0}")]
+    InvalidFlattenedData(String),
+    #[error(transparent)]
+    Io(std::io::Error),
+    #[error("Rust code panicked: {0}")]
+    Panic(String),
+    #[error("The path {0:?} can't be converted to a LabVIEW path.")]
+    InvalidPath(std::path::PathBuf),
+    #[error("The string is {0} bytes long but at most {1} can be stored.")]
+    StringTooLong(usize, usize),
+    #[error("{0} is not in a user defined error code range.")]
+    NotUserDefinedCode(i32),
+    #[error("The string is not valid {0}.")]
+    InvalidStringEncoding(&'static str),
+    #[error("A required pointer is null. Check the terminal is wired.")]
+    NullPointer,
+    #[error("The value can't be flattened: {0}")]
+    FlattenFailed(String),
+    #[error("Invalid JSON: {0}")]
+    InvalidJson(String),
+    #[error("The variant holds {actual} but {expected} was requested.")]
+    VariantTypeMismatch { expected: String, actual: String },
+    #[error("A {0} can't be read or written in place as its structure is internal to LabVIEW.")]
+    OpaqueData(&'static str),
+}
+
+fn element_index(index: &Option<usize>) -> String {
+    match index {
+        Some(index) => format!(" at index {index}"),
+        None => String::new(),
+    }
 }
 
 pub type Result<T> = std::result::Result<T, LVInteropError>;
 
+/// Our errors pass through the I/O traits so they are unwrapped again here.
+impl From<std::io::Error> for LVInteropError {
+    fn from(error: std::io::Error) -> Self {
+        if error
+            .get_ref()
+            .is_some_and(|inner| inner.is::<LVInteropError>())
+        {
+            let inner = error.into_inner().expect("checked above");
+            return *inner.downcast::<LVInteropError>().expect("checked above");
+        }
+        LVInteropError::Io(error)
+    }
+}
+
+impl From<LVInteropError> for std::io::Error {
+    fn from(error: LVInteropError) -> Self {
+        match error {
+            LVInteropError::Io(error) => error,
+            error => std::io::Error::other(error),
+        }
+    }
+}
+
 impl From<LVInteropError> for MgErr {
     fn from(value: LVInteropError) -> Self {
         match value {
@@ -72,6 +385,40 @@ impl From<LVInteropError> for MgErr {
             LVInteropError::NoLabviewApi => MgErr(-2),
             LVInteropError::ArrayDimensionsOutOfRange => MgErr(-3),
             LVInteropError::ArrayDimensionMismatch => MgErr(-3),
+            LVInteropError::UnexpectedArrayDimensions { .. } => MgErr(-3),
+            LVInteropError::ShutdownTimeout => MgErr(-4),
+            LVInteropError::StaleLibrary { .. } => MgErr(-5),
+            LVInteropError::InvalidMessageTable(_) => MgErr(-6),
+            LVInteropError::NulInString(_) => MgErr(-7),
+            LVInteropError::TdmsUnavailable => MgErr(-8),
+            // The DLL uses LabVIEW's TDMS error codes.
+            LVInteropError::TdmsError(code) => MgErr(code),
+            LVInteropError::InvalidValidationLevel(_) => MgErr(-9),
+            LVInteropError::ValidationFailed(_) => MgErr(-10),
+            LVInteropError::CrashHandler(_) => MgErr(-11),
+            // These use LabVIEW's codes to match Format Into String and Scan From String.
+            LVInteropError::Format(error) => MgErr(error.code()),
+            // LabVIEW's invalid reference error.
+            LVInteropError::InvalidRefnum(_) => MgErr(1556),
+            LVInteropError::CoercionOutOfRange { .. } => MgErr(-12),
+            LVInteropError::CoercionNaN { .. } => MgErr(-13),
+            LVInteropError::InvalidCapture(_) => MgErr(-14),
+            // LabVIEW's error for corrupt data in Unflatten From String.
+            LVInteropError::InvalidFlattenedData(_) => MgErr(116),
+            // LabVIEW's generic file I/O error.
+            LVInteropError::Io(_) => MgErr(6),
+            LVInteropError::Panic(_) => MgErr(-15),
+            LVInteropError::InvalidPath(_) => MgErr(-16),
+            LVInteropError::StringTooLong(..) => MgErr(-17),
+            LVInteropError::NotUserDefinedCode(_) => MgErr(-18),
+            LVInteropError::InvalidStringEncoding(_) => MgErr(-19),
+            // LabVIEW's argument error, as for any other invalid input.
+            LVInteropError::NullPointer => MgErr::ARGUMENT_ERROR,
+            LVInteropError::FlattenFailed(_) => MgErr(-20),
+            LVInteropError::InvalidJson(_) => MgErr(-21),
+            // LabVIEW's error from Variant To Data for incompatible types.
+            LVInteropError::VariantTypeMismatch { .. } => MgErr(91),
+            LVInteropError::OpaqueData(_) => MgErr(-22),
         }
     }
 }
@@ -84,3 +431,20 @@ impl<T> From<Result<T>> for MgErr {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_user_defined_ranges() {
+        for code in [-8999, -8000, 5000, 9999, 500_000, 599_999] {
+            assert!(MgErr::try_user_defined(code).is_ok(), "{code}");
+        }
+        for code in [-9000, -7999, 42, 4999, 10_000, 600_000] {
+            assert!(
+                matches!(MgErr::try_user_defined(code), Err(LVInteropError::NotUserDefinedCode(c)) if c == code)
+            );
+        }
+    }
+}
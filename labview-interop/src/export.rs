@@ -0,0 +1,496 @@
+//! Exports written with natural Rust types.
+//!
+//! [`lv_export!`](crate::lv_export) wraps a function taking and returning
+//! ordinary Rust types in an `extern "C"` export. The export takes the LabVIEW
+//! types for each argument, followed by an output for the return value and an
+//! error cluster. Arguments are converted with [`FromLvArg`], the return value
+//! is written with [`IntoLvReturn`] and errors go to the error cluster as with
+//! [`with_lverrorhandling!`](crate::with_lverrorhandling). If the library has a
+//! [build stamp](crate::build_stamp) it is checked before the function runs.
+//!
+//! With the `macros` feature the `#[labview_export]` attribute does the same
+//! for a plain function definition.
+//!
+//! | Rust type | Call Library node parameter |
+//! |-----------|-----------------------------|
+//! | Numerics | Numeric, pass by value (output: pointer to value) |
+//! | `bool` | Unsigned 8 bit integer (output: pointer to value) |
+//! | [`LVTime`] | Adapt to type, pointer (input and output) |
+//! | `&str`, `String` | String, string handle (output: pointer to string handle) |
+//! | `&[T]`, `Vec<T>` | Array, array handle, handles by value (output: pointers to handles) |
+//! | `Result<T, E>` | As `T`, with `E` written to the error cluster |
+//!
+//! Arrays are only supported on 64 bit, where they can be borrowed as slices.
+//! A `&str` argument must be valid UTF-8. Use `String` to accept text in
+//! LabVIEW's encoding.
+//!
+//! String and array outputs are passed as a pointer to the handle, so a
+//! handle allocated for an empty output is passed back to LabVIEW.
+//!
+//! The export has the visibility written on the function.
+//!
+//! # Example
+//! ```
+//! use labview_interop::errors::LVInteropError;
+//! use labview_interop::lv_export;
+//!
+//! lv_export! {
+//!     /// The mean of the values.
+//!     pub fn mean(values: &[f64]) -> Result<f64, LVInteropError> {
+//!         if values.is_empty() {
+//!             return Err(LVInteropError::ArrayDimensionMismatch);
+//!         }
+//!         Ok(values.iter().sum::<f64>() / values.len() as f64)
+//!     }
+//! }
+//!
+//! lv_export! {
+//!     pub fn greeting(name: &str, shout: bool) -> String {
+//!         let greeting = format!("Hello {name}");
+//!         if shout { greeting.to_uppercase() } else { greeting }
+//!     }
+//! }
+//! ```
+//!
+//! The generated exports are equivalent to:
+//! ```
+//! use labview_interop::errors::MgErr;
+//! use labview_interop::memory::UPtr;
+//! use labview_interop::types::{ErrorClusterPtr, LStrHandle, LVArrayHandle, LVBool};
+//!
+//! #[no_mangle]
+//! pub extern "C" fn mean(values: LVArrayHandle<1, f64>, output: *mut f64, error_cluster: ErrorClusterPtr) -> MgErr {
+//!     # MgErr::NO_ERROR
+//!     // ...
+//! }
+//!
+//! #[no_mangle]
+//! pub extern "C" fn greeting(
+//!     name: LStrHandle,
+//!     shout: LVBool,
+//!     output: UPtr<LStrHandle>,
+//!     error_cluster: ErrorClusterPtr,
+//! ) -> MgErr {
+//!     # MgErr::NO_ERROR
+//!     // ...
+//! }
+//! ```
+
+use crate::errors::{LVInteropError, MgErr, Result};
+#[cfg(feature = "link")]
+use crate::memory::UPtr;
+use crate::types::string::LV_ENCODING;
+#[cfg(target_pointer_width = "64")]
+use crate::types::LVArrayHandle;
+use crate::types::{ErrorEntry, LStrHandle, LVBool, LVTime, ToLvError};
+
+/// A type which can be an argument of an [`lv_export!`](crate::lv_export) function.
+pub trait FromLvArg: Sized {
+    /// The type LabVIEW passes.
+    type Lv;
+
+    /// Convert the argument from LabVIEW.
+    ///
+    /// # Safety
+    ///
+    /// Borrowed data must stay valid for the lifetime of the result. This holds
+    /// for arguments while LabVIEW is calling the export.
+    unsafe fn from_lv_arg(arg: Self::Lv) -> Result<Self>;
+}
+
+/// A type which can be returned from an [`lv_export!`](crate::lv_export) function.
+pub trait IntoLvReturn {
+    /// The output parameter LabVIEW passes for the value.
+    type Output;
+
+    /// Write the value into the output.
+    fn into_lv_return(self, output: Self::Output) -> std::result::Result<(), ErrorEntry>;
+}
+
+fn entry(error: LVInteropError) -> ErrorEntry {
+    ErrorEntry::from_error(&error)
+}
+
+/// Write through an output pointer, which must not be null.
+fn write_output<T>(output: *mut T, value: T) -> std::result::Result<(), ErrorEntry> {
+    match unsafe { output.as_mut() } {
+        Some(output) => {
+            *output = value;
+            Ok(())
+        }
+        None => Err(entry(LVInteropError::InvalidHandle)),
+    }
+}
+
+macro_rules! export_by_value {
+    ($($type:ty),+) => {
+        $(
+            impl FromLvArg for $type {
+                type Lv = $type;
+
+                unsafe fn from_lv_arg(arg: Self::Lv) -> Result<Self> {
+                    Ok(arg)
+                }
+            }
+
+            impl IntoLvReturn for $type {
+                type Output = *mut $type;
+
+                fn into_lv_return(self, output: Self::Output) -> std::result::Result<(), ErrorEntry> {
+                    write_output(output, self)
+                }
+            }
+        )+
+    };
+}
+
+export_by_value!(i8, i16, i32, i64, u8, u16, u32, u64, f32, f64);
+
+impl FromLvArg for bool {
+    type Lv = LVBool;
+
+    unsafe fn from_lv_arg(arg: Self::Lv) -> Result<Self> {
+        Ok(arg.into())
+    }
+}
+
+impl IntoLvReturn for bool {
+    type Output = *mut LVBool;
+
+    fn into_lv_return(self, output: Self::Output) -> std::result::Result<(), ErrorEntry> {
+        write_output(output, self.into())
+    }
+}
+
+impl FromLvArg for LVTime {
+    type Lv = *const LVTime;
+
+    unsafe fn from_lv_arg(arg: Self::Lv) -> Result<Self> {
+        arg.as_ref().copied().ok_or(LVInteropError::InvalidHandle)
+    }
+}
+
+impl IntoLvReturn for LVTime {
+    type Output = *mut LVTime;
+
+    fn into_lv_return(self, output: Self::Output) -> std::result::Result<(), ErrorEntry> {
+        write_output(output, self)
+    }
+}
+
+/// The bytes of a string, treating a null handle as empty.
+unsafe fn string_bytes<'a>(arg: LStrHandle) -> Result<&'a [u8]> {
+    match arg.0.as_ref().and_then(|pointer| pointer.as_ref()) {
+        Some(string) => {
+            arg.validate()?;
+            Ok(string.as_slice())
+        }
+        None => Ok(&[]),
+    }
+}
+
+impl FromLvArg for &str {
+    type Lv = LStrHandle;
+
+    unsafe fn from_lv_arg(arg: Self::Lv) -> Result<Self> {
+        std::str::from_utf8(string_bytes(arg)?).map_err(|error| {
+            LVInteropError::ValidationFailed(format!("string argument isn't UTF-8: {error}"))
+        })
+    }
+}
+
+impl FromLvArg for String {
+    type Lv = LStrHandle;
+
+    unsafe fn from_lv_arg(arg: Self::Lv) -> Result<Self> {
+        let bytes = string_bytes(arg)?;
+        Ok(LV_ENCODING
+            .decode_without_bom_handling(bytes)
+            .0
+            .into_owned())
+    }
+}
+
+#[cfg(target_pointer_width = "64")]
+impl<T: Copy> FromLvArg for &[T] {
+    type Lv = LVArrayHandle<1, T>;
+
+    /// A null handle is an empty slice.
+    unsafe fn from_lv_arg(arg: Self::Lv) -> Result<Self> {
+        arg.validate()?;
+        match arg.0.as_ref().and_then(|pointer| pointer.as_ref()) {
+            Some(array) => Ok(array.data_as_slice()),
+            None => Ok(&[]),
+        }
+    }
+}
+
+#[cfg(target_pointer_width = "64")]
+impl<T: Copy> FromLvArg for Vec<T> {
+    type Lv = LVArrayHandle<1, T>;
+
+    unsafe fn from_lv_arg(arg: Self::Lv) -> Result<Self> {
+        <&[T]>::from_lv_arg(arg).map(<[T]>::to_vec)
+    }
+}
+
+/// Strings are returned in LabVIEW's encoding.
+#[cfg(feature = "link")]
+impl IntoLvReturn for String {
+    type Output = UPtr<LStrHandle>;
+
+    /// A null handle is allocated and passed back through the pointer.
+    fn into_lv_return(self, output: Self::Output) -> std::result::Result<(), ErrorEntry> {
+        let output = unsafe { output.as_ref_mut() }.map_err(entry)?;
+        if output.is_null() {
+            let string = crate::types::LStrOwned::try_from(self.as_str()).map_err(entry)?;
+            *output = string.into_lv_owned();
+            return Ok(());
+        }
+        output.set_str(&self).map_err(entry)
+    }
+}
+
+#[cfg(all(feature = "link", target_pointer_width = "64"))]
+impl<T> IntoLvReturn for Vec<T>
+where
+    T: Copy + crate::types::array::NumericArrayResizable,
+{
+    type Output = UPtr<LVArrayHandle<1, T>>;
+
+    /// A null handle is allocated and passed back through the pointer.
+    fn into_lv_return(self, output: Self::Output) -> std::result::Result<(), ErrorEntry> {
+        let output = unsafe { output.as_ref_mut() }.map_err(entry)?;
+        let dims = crate::types::array::LVArrayDims::try_from(&[self.len()]).map_err(entry)?;
+        output.resize_array(dims).map_err(entry)?;
+        let array = unsafe { output.as_ref_mut() }.map_err(entry)?;
+        array.data_as_slice_mut().copy_from_slice(&self);
+        Ok(())
+    }
+}
+
+impl<T: IntoLvReturn, E: ToLvError> IntoLvReturn for std::result::Result<T, E> {
+    type Output = T::Output;
+
+    fn into_lv_return(self, output: Self::Output) -> std::result::Result<(), ErrorEntry> {
+        match self {
+            Ok(value) => value.into_lv_return(output),
+            Err(error) => Err(ErrorEntry::from_error(&error)),
+        }
+    }
+}
+
+/// Convert an argument for [`lv_export!`](crate::lv_export).
+///
+/// # Safety
+///
+/// See [`FromLvArg::from_lv_arg`].
+#[doc(hidden)]
+pub unsafe fn arg<T: FromLvArg>(arg: T::Lv) -> std::result::Result<T, ErrorEntry> {
+    T::from_lv_arg(arg).map_err(entry)
+}
+
+/// Check the [build stamp](crate::build_stamp) for [`lv_export!`](crate::lv_export).
+#[doc(hidden)]
+pub fn check_build_stamp() -> std::result::Result<(), ErrorEntry> {
+    crate::build_stamp::check_build_stamp().map_err(entry)
+}
+
+/// Run an export generated by the crate which has no error cluster, such as
+/// those from [`lv_queue_exports!`](crate::lv_queue_exports), so a panic
+/// returns its status code rather than unwinding into LabVIEW.
+#[doc(hidden)]
+pub fn catch_panic_status(body: impl FnOnce() -> MgErr) -> MgErr {
+    crate::types::lv_errors::catch_panic(body).unwrap_or_else(|panic| panic.code)
+}
+
+/// Convert the errors of a function returning `Result<(), E>` for [`lv_export!`](crate::lv_export).
+#[doc(hidden)]
+pub fn unit_result<E: ToLvError>(
+    result: std::result::Result<(), E>,
+) -> std::result::Result<(), ErrorEntry> {
+    result.map_err(|error| ErrorEntry::from_error(&error))
+}
+
+/// Define an export from a function using Rust types.
+///
+/// See the [module documentation](crate::export) for the supported types and
+/// the generated signature. Functions returning nothing or `Result<(), E>`
+/// have no output parameter.
+#[macro_export]
+macro_rules! lv_export {
+    (
+        $(#[$meta:meta])*
+        $vis:vis fn $name:ident($($arg:ident : $type:ty),* $(,)?) $body:block
+    ) => {
+        $(#[$meta])*
+        #[no_mangle]
+        $vis extern "C" fn $name(
+            $($arg: <$type as $crate::export::FromLvArg>::Lv,)*
+            error_cluster: $crate::types::ErrorClusterPtr,
+        ) -> $crate::errors::MgErr {
+            fn body($($arg: $type),*) $body
+            $crate::with_lverrorhandling!(error_cluster, {
+                (|| {
+                    $crate::export::check_build_stamp()?;
+                    $(let $arg = unsafe { $crate::export::arg::<$type>($arg) }?;)*
+                    body($($arg),*);
+                    Ok::<(), $crate::types::ErrorEntry>(())
+                })()
+            })
+        }
+    };
+    (
+        $(#[$meta:meta])*
+        $vis:vis fn $name:ident($($arg:ident : $type:ty),* $(,)?) -> Result<(), $error:ty> $body:block
+    ) => {
+        $(#[$meta])*
+        #[no_mangle]
+        $vis extern "C" fn $name(
+            $($arg: <$type as $crate::export::FromLvArg>::Lv,)*
+            error_cluster: $crate::types::ErrorClusterPtr,
+        ) -> $crate::errors::MgErr {
+            fn body($($arg: $type),*) -> ::std::result::Result<(), $error> $body
+            $crate::with_lverrorhandling!(error_cluster, {
+                (|| {
+                    $crate::export::check_build_stamp()?;
+                    $(let $arg = unsafe { $crate::export::arg::<$type>($arg) }?;)*
+                    $crate::export::unit_result(body($($arg),*))
+                })()
+            })
+        }
+    };
+    (
+        $(#[$meta:meta])*
+        $vis:vis fn $name:ident($($arg:ident : $type:ty),* $(,)?) -> $return:ty $body:block
+    ) => {
+        $(#[$meta])*
+        #[no_mangle]
+        $vis extern "C" fn $name(
+            $($arg: <$type as $crate::export::FromLvArg>::Lv,)*
+            output: <$return as $crate::export::IntoLvReturn>::Output,
+            error_cluster: $crate::types::ErrorClusterPtr,
+        ) -> $crate::errors::MgErr {
+            fn body($($arg: $type),*) -> $return $body
+            $crate::with_lverrorhandling!(error_cluster, {
+                (|| {
+                    $crate::export::check_build_stamp()?;
+                    $(let $arg = unsafe { $crate::export::arg::<$type>($arg) }?;)*
+                    $crate::export::IntoLvReturn::into_lv_return(body($($arg),*), output)
+                })()
+            })
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::UHandle;
+
+    #[test]
+    fn test_result_return_maps_error() {
+        let mut output = 0.0;
+        assert!(Ok::<f64, LVInteropError>(2.0)
+            .into_lv_return(&mut output)
+            .is_ok());
+        assert_eq!(output, 2.0);
+
+        let error = Err::<f64, _>(LVInteropError::ArrayDimensionMismatch)
+            .into_lv_return(&mut output)
+            .unwrap_err();
+        assert_eq!(error.code, LVInteropError::ArrayDimensionMismatch.code());
+        assert!(5u8.into_lv_return(std::ptr::null_mut()).is_err());
+    }
+
+    #[cfg(all(feature = "link", target_pointer_width = "64"))]
+    mod generated {
+        use crate::errors::LVInteropError;
+
+        crate::lv_export! {
+            pub(crate) fn test_export_describe(value: f64) -> Result<String, LVInteropError> {
+                if value < 0.0 {
+                    return Err(LVInteropError::ArrayDimensionMismatch);
+                }
+                Ok(format!("{value}"))
+            }
+        }
+
+        crate::lv_export! {
+            pub(super) fn test_export_doubled(values: &[f64]) -> Result<Vec<f64>, LVInteropError> {
+                Ok(values.iter().map(|value| value * 2.0).collect())
+            }
+        }
+    }
+
+    #[cfg(all(feature = "link", target_pointer_width = "64"))]
+    #[test]
+    fn test_lv_export_handle_outputs() {
+        use crate::types::lv_errors::ErrorCluster;
+        use crate::types::{ErrorClusterPtr, LStrOwned, LVArrayOwned};
+        use generated::{test_export_describe, test_export_doubled};
+
+        let mut cluster = ErrorCluster::with_empty_source();
+        let error_cluster =
+            |cluster: &mut ErrorCluster| unsafe { ErrorClusterPtr::from_raw(cluster) };
+
+        // A null handle is allocated and passed back through the pointer.
+        let mut text: LStrHandle = UHandle(std::ptr::null_mut());
+        let status = test_export_describe(
+            2.5,
+            unsafe { UPtr::from_raw(&mut text) },
+            error_cluster(&mut cluster),
+        );
+        assert_eq!(status, MgErr::NO_ERROR);
+        let text = unsafe { LStrOwned::from_raw(text) };
+        assert_eq!(text.to_rust_string(), "2.5");
+
+        let values = LVArrayOwned::<1, f64>::from_slice(&[1.0, 2.0]).unwrap();
+        let mut doubled: LVArrayHandle<1, f64> = UHandle(std::ptr::null_mut());
+        let status = test_export_doubled(
+            UHandle(values.0),
+            unsafe { UPtr::from_raw(&mut doubled) },
+            error_cluster(&mut cluster),
+        );
+        assert_eq!(status, MgErr::NO_ERROR);
+        let doubled = unsafe { LVArrayOwned::from_raw(doubled) };
+        assert_eq!(doubled.as_slice(), [2.0, 4.0]);
+
+        // Errors from the function and a null output pointer go to the cluster.
+        let mut unused: LStrHandle = UHandle(std::ptr::null_mut());
+        let status = test_export_describe(
+            -1.0,
+            unsafe { UPtr::from_raw(&mut unused) },
+            error_cluster(&mut cluster),
+        );
+        assert_eq!(status, LVInteropError::ArrayDimensionMismatch.code());
+        assert!(cluster.is_error());
+        assert!(unused.0.is_null());
+        drop(cluster.take_source());
+
+        cluster = ErrorCluster::with_empty_source();
+        let status = test_export_describe(
+            1.0,
+            unsafe { UPtr::from_raw(std::ptr::null_mut()) },
+            error_cluster(&mut cluster),
+        );
+        assert_eq!(status, LVInteropError::InvalidHandle.code());
+        assert_eq!(cluster.code(), LVInteropError::InvalidHandle.code());
+        drop(cluster.take_source());
+    }
+
+    #[test]
+    fn test_catch_panic_status() {
+        assert_eq!(catch_panic_status(|| MgErr::NO_ERROR), MgErr::NO_ERROR);
+        let status = catch_panic_status(|| panic!("export failed"));
+        assert_eq!(status, MgErr::from(LVInteropError::Panic(String::new())));
+    }
+
+    #[test]
+    fn test_null_handles_are_empty() {
+        let values = unsafe { <&[f64]>::from_lv_arg(UHandle(std::ptr::null_mut())) }.unwrap();
+        assert!(values.is_empty());
+        let text = unsafe { <&str>::from_lv_arg(UHandle(std::ptr::null_mut())) }.unwrap();
+        assert_eq!(text, "");
+    }
+}
@@ -0,0 +1,221 @@
+//! File I/O through LabVIEW's file manager.
+//!
+//! [`LVFile`] wraps the `FM*` functions so Rust code reads and writes files
+//! the same way the file functions on the diagram do. A file opened in Rust
+//! can be turned into a file refnum with [`LVFile::into_refnum`] and handed
+//! back to LabVIEW, where Close File closes it. A refnum from the diagram can
+//! be used from Rust with [`LVFile::from_refnum`].
+//!
+//! [`LVFile`] implements [`Read`], [`Write`] and [`Seek`] so it works with
+//! anything built on the standard I/O traits.
+//!
+//! # Example
+//! ```
+//! use std::io::Write;
+//! use labview_interop::errors::{MgErr, Result};
+//! use labview_interop::file::{DenyMode, FileRefNum, LVFile, OpenMode};
+//! use labview_interop::types::LVPathHandle;
+//!
+//! fn write_header(path: LVPathHandle) -> Result<FileRefNum> {
+//!     let mut file = LVFile::open(path, OpenMode::WriteTruncate, DenyMode::WriteOnly)?;
+//!     file.write_all(b"time,value\n")?;
+//!     file.into_refnum(path)
+//! }
+//!
+//! #[no_mangle]
+//! pub extern "C" fn create_log(path: LVPathHandle, refnum: *mut FileRefNum) -> MgErr {
+//!     match write_header(path) {
+//!         Ok(file) => {
+//!             unsafe { *refnum = file };
+//!             MgErr::NO_ERROR
+//!         }
+//!         Err(err) => err.into(),
+//!     }
+//! }
+//! ```
+
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+use crate::errors::{LVInteropError, Result};
+use crate::labview::{file_api, FileDescriptor};
+use crate::memory::MagicCookie;
+use crate::types::LVPathHandle;
+
+/// A LabVIEW file refnum.
+///
+/// From LabVIEW you can set the terminal to be `adapt to type` and `handles by value`.
+pub type FileRefNum = MagicCookie;
+
+/// The file manager returns this when a read reaches the end of the file.
+const END_OF_FILE: i32 = 4;
+
+/// The access requested when opening a file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(i32)]
+pub enum OpenMode {
+    Read = 0,
+    Write = 1,
+    ReadWrite = 2,
+    /// Open for writing and truncate the file to zero length.
+    WriteTruncate = 3,
+}
+
+/// The access denied to others while the file is open.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(i32)]
+pub enum DenyMode {
+    ReadWrite = 0,
+    WriteOnly = 1,
+    Neither = 2,
+}
+
+/// The file manager seek mode and offset for a [`SeekFrom`].
+fn seek_args(position: SeekFrom) -> (i64, i32) {
+    match position {
+        SeekFrom::Start(offset) => (offset as i64, 1),
+        SeekFrom::End(offset) => (offset, 2),
+        SeekFrom::Current(offset) => (offset, 3),
+    }
+}
+
+/// A file opened through the LabVIEW file manager.
+///
+/// Files opened with [`LVFile::open`] are closed when dropped. Files from
+/// [`LVFile::from_refnum`] belong to the refnum and are left open.
+#[derive(Debug)]
+pub struct LVFile {
+    fd: FileDescriptor,
+    owned: bool,
+}
+
+/// Safety: the file manager functions can be called from any thread.
+unsafe impl Send for LVFile {}
+
+impl LVFile {
+    /// Open the file at `path`. This doesn't create the file.
+    pub fn open(path: LVPathHandle, mode: OpenMode, deny: DenyMode) -> Result<Self> {
+        let api = file_api()?;
+        if path.is_null() {
+            return Err(LVInteropError::InvalidHandle);
+        }
+        let mut fd = std::ptr::null_mut();
+        unsafe { api.open(&mut fd, path.as_raw(), mode as i32, deny as i32) }.to_result(())?;
+        Ok(Self { fd, owned: true })
+    }
+
+    /// Use the file behind a file refnum from LabVIEW.
+    ///
+    /// The file is left open when this is dropped.
+    pub fn from_refnum(refnum: FileRefNum) -> Result<Self> {
+        let mut fd = std::ptr::null_mut();
+        unsafe { file_api()?.refnum_to_fd(refnum, &mut fd) }.to_result(())?;
+        Ok(Self { fd, owned: false })
+    }
+
+    /// Create a file refnum for this file which can be returned to LabVIEW.
+    ///
+    /// The refnum then owns the file and it is closed with Close File on the
+    /// diagram. `path` should be the path the file was opened with. If the
+    /// refnum can't be created the file is closed.
+    pub fn into_refnum(mut self, path: LVPathHandle) -> Result<FileRefNum> {
+        let mut refnum = std::mem::MaybeUninit::<FileRefNum>::uninit();
+        unsafe { file_api()?.new_refnum(path.as_raw(), self.fd, refnum.as_mut_ptr()) }
+            .to_result(())?;
+        self.owned = false;
+        Ok(unsafe { refnum.assume_init() })
+    }
+
+    /// Close the file, reporting any error.
+    ///
+    /// This does nothing for a file from [`LVFile::from_refnum`].
+    pub fn close(mut self) -> Result<()> {
+        self.close_owned()
+    }
+
+    fn close_owned(&mut self) -> Result<()> {
+        if !self.owned {
+            return Ok(());
+        }
+        self.owned = false;
+        unsafe { file_api()?.close(self.fd) }.to_result(())
+    }
+
+    /// The current position in the file.
+    pub fn position(&self) -> Result<u64> {
+        let mut offset = 0i64;
+        unsafe { file_api()?.tell(self.fd, &mut offset) }.to_result(())?;
+        Ok(offset as u64)
+    }
+}
+
+impl Read for LVFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let api = file_api()?;
+        let count = buf.len().min(i32::MAX as usize) as i32;
+        let mut read = 0i32;
+        let err = unsafe { api.read(self.fd, count, &mut read, buf.as_mut_ptr()) };
+        match i32::from(err) {
+            0 | END_OF_FILE => Ok(read as usize),
+            _ => Err(LVInteropError::from(err).into()),
+        }
+    }
+}
+
+impl Write for LVFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let api = file_api()?;
+        let count = buf.len().min(i32::MAX as usize) as i32;
+        let mut written = 0i32;
+        unsafe { api.write(self.fd, count, &mut written, buf.as_ptr()) }
+            .to_result(written as usize)
+            .map_err(Into::into)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Seek for LVFile {
+    fn seek(&mut self, position: SeekFrom) -> io::Result<u64> {
+        let api = file_api()?;
+        let (offset, mode) = seek_args(position);
+        unsafe { api.seek(self.fd, offset, mode) }.to_result(())?;
+        Ok(self.position()?)
+    }
+}
+
+impl Drop for LVFile {
+    fn drop(&mut self) {
+        // Nothing can be done about a failure while dropping.
+        let _ = self.close_owned();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seek_args() {
+        assert_eq!(seek_args(SeekFrom::Start(10)), (10, 1));
+        assert_eq!(seek_args(SeekFrom::End(-4)), (-4, 2));
+        assert_eq!(seek_args(SeekFrom::Current(3)), (3, 3));
+    }
+
+    #[test]
+    fn test_io_error_round_trip() {
+        let error: io::Error = LVInteropError::InvalidRefnum(3).into();
+        assert!(matches!(
+            LVInteropError::from(error),
+            LVInteropError::InvalidRefnum(3)
+        ));
+    }
+
+    #[test]
+    fn test_open_without_labview() {
+        let path = unsafe { LVPathHandle::from_raw(std::ptr::null_mut()) };
+        let result = LVFile::open(path, OpenMode::Read, DenyMode::Neither);
+        assert!(matches!(result, Err(LVInteropError::NoLabviewApi)));
+    }
+}
@@ -0,0 +1,975 @@
+//! LabVIEW compatible Format Into String and Scan From String.
+//!
+//! These follow LabVIEW's format specifier rules so strings built in Rust match
+//! those produced by the surrounding VIs, and strings from VIs can be parsed in
+//! Rust.
+//!
+//! A specifier is `%[flags][width][.precision|_significant digits]conversion`.
+//!
+//! * Flags: `-` left justify, `+` always show the sign, `0` pad with zeros,
+//!   `#` remove trailing zeros and `^` use engineering notation with `%e`.
+//! * Conversions: `d` decimal, `x` hexadecimal, `o` octal, `b` binary,
+//!   `f` fractional, `e` scientific, `g` fractional or scientific depending on
+//!   the exponent and `s` string. `%%` is a literal percent.
+//! * `%,;` uses a comma as the decimal separator for the following specifiers
+//!   and `%.;` or `%;` switches back to a point.
+//! * `%<...>T` formats an [`LVTime`] in local time using the [time codes](time)
+//!   between the brackets and `%^<...>T` formats it in UTC. `%T` is the same
+//!   as `%<%x %X>T`.
+//!
+//! As in LabVIEW hexadecimal digits and exponents are upper case, exponents
+//! have no leading zeros (`1.5E+3`) and negative integers are formatted in
+//! hexadecimal, octal and binary using the two's complement of their type.
+//!
+//! # Example
+//! ```
+//! use labview_interop::{lv_format, lv_scan};
+//!
+//! let text = lv_format!("%s: %.3f V (%05d)", "Channel", 1.23456, 42).unwrap();
+//! assert_eq!(text, "Channel: 1.235 V (00042)");
+//!
+//! let (name, value) = lv_scan!("Voltage=2,5", "%[^=]=%,;%f" => String, f64).unwrap();
+//! assert_eq!(name, "Voltage");
+//! assert_eq!(value, 2.5);
+//! ```
+//!
+//! Character sets (`%[...]`) are only supported for scanning.
+
+pub mod time;
+
+use thiserror::Error;
+
+use crate::types::LVTime;
+
+/// Errors formatting or scanning, using LabVIEW's error codes.
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum FormatError {
+    #[error("Format specifier type mismatch: {0}")]
+    TypeMismatch(String),
+    #[error("Unknown format specifier: {0}")]
+    UnknownSpecifier(String),
+    #[error("Too few format specifiers for the inputs.")]
+    TooFewSpecifiers,
+    #[error("Too many format specifiers for the inputs.")]
+    TooManySpecifiers,
+    #[error("Scan failed. The input string does not contain data in the expected format: {0}")]
+    ScanFailed(String),
+}
+
+impl FormatError {
+    /// The LabVIEW error code matching the error.
+    pub fn code(&self) -> i32 {
+        match self {
+            FormatError::TypeMismatch(_) => 81,
+            FormatError::UnknownSpecifier(_) => 82,
+            FormatError::TooFewSpecifiers => 83,
+            FormatError::TooManySpecifiers => 84,
+            FormatError::ScanFailed(_) => 85,
+        }
+    }
+}
+
+/// A value to format.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FormatArg {
+    /// A signed integer with the number of bits in its type.
+    Int {
+        value: i64,
+        bits: u32,
+    },
+    /// An unsigned integer.
+    UInt(u64),
+    Float(f64),
+    Str(String),
+    /// Booleans are formatted as `TRUE` or `FALSE` with `%s`.
+    Bool(bool),
+    Time(LVTime),
+}
+
+macro_rules! format_arg_int {
+    ($($int:ty),+) => {
+        $(
+            impl From<$int> for FormatArg {
+                fn from(value: $int) -> Self {
+                    FormatArg::Int { value: value as i64, bits: <$int>::BITS }
+                }
+            }
+        )+
+    };
+}
+
+macro_rules! format_arg_uint {
+    ($($int:ty),+) => {
+        $(
+            impl From<$int> for FormatArg {
+                fn from(value: $int) -> Self {
+                    FormatArg::UInt(value as u64)
+                }
+            }
+        )+
+    };
+}
+
+format_arg_int!(i8, i16, i32, i64, isize);
+format_arg_uint!(u8, u16, u32, u64, usize);
+
+impl From<f32> for FormatArg {
+    fn from(value: f32) -> Self {
+        FormatArg::Float(value as f64)
+    }
+}
+
+impl From<f64> for FormatArg {
+    fn from(value: f64) -> Self {
+        FormatArg::Float(value)
+    }
+}
+
+impl From<&str> for FormatArg {
+    fn from(value: &str) -> Self {
+        FormatArg::Str(value.to_string())
+    }
+}
+
+impl From<String> for FormatArg {
+    fn from(value: String) -> Self {
+        FormatArg::Str(value)
+    }
+}
+
+impl From<&String> for FormatArg {
+    fn from(value: &String) -> Self {
+        FormatArg::Str(value.clone())
+    }
+}
+
+impl From<bool> for FormatArg {
+    fn from(value: bool) -> Self {
+        FormatArg::Bool(value)
+    }
+}
+
+impl From<LVTime> for FormatArg {
+    fn from(value: LVTime) -> Self {
+        FormatArg::Time(value)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Precision {
+    /// Digits after the decimal point.
+    Digits(usize),
+    /// Significant digits.
+    Significant(usize),
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+struct Spec {
+    left: bool,
+    plus: bool,
+    zero: bool,
+    strip_zeros: bool,
+    engineering: bool,
+    width: Option<usize>,
+    precision: Option<Precision>,
+    conversion: char,
+    /// The characters accepted by a `%[...]` scan set and whether it is negated.
+    set: Option<(bool, Vec<char>)>,
+    /// The time codes for `%<...>T`.
+    time_format: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Literal(char),
+    Spec(Spec),
+    DecimalSeparator(char),
+}
+
+fn parse_number(chars: &[char], index: &mut usize) -> Option<usize> {
+    let start = *index;
+    while *index < chars.len() && chars[*index].is_ascii_digit() {
+        *index += 1;
+    }
+    chars[start..*index].iter().collect::<String>().parse().ok()
+}
+
+fn parse_format(format: &str) -> Result<Vec<Token>, FormatError> {
+    let chars: Vec<char> = format.chars().collect();
+    let mut tokens = Vec::new();
+    let mut index = 0;
+    while index < chars.len() {
+        let start = index;
+        let c = chars[index];
+        index += 1;
+        if c != '%' {
+            tokens.push(Token::Literal(c));
+            continue;
+        }
+        let unknown = || FormatError::UnknownSpecifier(chars[start..].iter().collect());
+
+        match chars.get(index..index + 2) {
+            Some([',', ';']) => {
+                tokens.push(Token::DecimalSeparator(','));
+                index += 2;
+                continue;
+            }
+            Some(['.', ';']) => {
+                tokens.push(Token::DecimalSeparator('.'));
+                index += 2;
+                continue;
+            }
+            _ => {}
+        }
+        match chars.get(index) {
+            Some('%') => {
+                tokens.push(Token::Literal('%'));
+                index += 1;
+                continue;
+            }
+            Some(';') => {
+                tokens.push(Token::DecimalSeparator('.'));
+                index += 1;
+                continue;
+            }
+            _ => {}
+        }
+
+        let mut spec = Spec::default();
+        while let Some(flag) = chars.get(index) {
+            match flag {
+                '-' => spec.left = true,
+                '+' => spec.plus = true,
+                '0' => spec.zero = true,
+                '#' => spec.strip_zeros = true,
+                '^' => spec.engineering = true,
+                _ => break,
+            }
+            index += 1;
+        }
+        spec.width = parse_number(&chars, &mut index);
+        match chars.get(index) {
+            Some('.') => {
+                index += 1;
+                spec.precision = Some(Precision::Digits(
+                    parse_number(&chars, &mut index).unwrap_or(0),
+                ));
+            }
+            Some('_') => {
+                index += 1;
+                let digits = parse_number(&chars, &mut index).ok_or_else(unknown)?;
+                spec.precision = Some(Precision::Significant(digits));
+            }
+            _ => {}
+        }
+        if chars.get(index) == Some(&'<') {
+            let end = chars[index..]
+                .iter()
+                .position(|c| *c == '>')
+                .ok_or_else(unknown)?;
+            spec.time_format = Some(chars[index + 1..index + end].iter().collect());
+            index += end + 1;
+            if chars.get(index) != Some(&'T') {
+                return Err(unknown());
+            }
+        }
+        spec.conversion = *chars.get(index).ok_or_else(unknown)?;
+        index += 1;
+        match spec.conversion {
+            'd' | 'x' | 'X' | 'o' | 'b' | 'f' | 'F' | 'e' | 'E' | 'g' | 'G' | 's' => {}
+            'T' => {
+                if spec.time_format.is_none() {
+                    spec.time_format = Some("%x %X".to_string());
+                }
+            }
+            '[' => {
+                let negated = chars.get(index) == Some(&'^');
+                if negated {
+                    index += 1;
+                }
+                let mut set = Vec::new();
+                // A leading ] is part of the set.
+                if chars.get(index) == Some(&']') {
+                    set.push(']');
+                    index += 1;
+                }
+                loop {
+                    match chars.get(index) {
+                        Some(']') => break,
+                        Some('-')
+                            if !set.is_empty()
+                                && chars.get(index + 1).is_some_and(|c| *c != ']') =>
+                        {
+                            let from = *set.last().unwrap();
+                            let to = chars[index + 1];
+                            set.extend((from..=to).skip(1));
+                            index += 2;
+                        }
+                        Some(c) => {
+                            set.push(*c);
+                            index += 1;
+                        }
+                        None => return Err(unknown()),
+                    }
+                }
+                index += 1;
+                spec.set = Some((negated, set));
+            }
+            _ => return Err(unknown()),
+        }
+        tokens.push(Token::Spec(spec));
+    }
+    Ok(tokens)
+}
+
+/// Round half to even as LabVIEW does when converting floats to integers.
+fn round_to_integer(value: f64) -> i128 {
+    value.round_ties_even() as i128
+}
+
+fn format_radix(value: u64, conversion: char) -> String {
+    match conversion {
+        'x' | 'X' => format!("{value:X}"),
+        'o' => format!("{value:o}"),
+        'b' => format!("{value:b}"),
+        _ => value.to_string(),
+    }
+}
+
+/// Remove trailing zeros after the decimal point, and the point if nothing is left.
+fn strip_trailing_zeros(mantissa: &str) -> String {
+    if !mantissa.contains('.') {
+        return mantissa.to_string();
+    }
+    mantissa
+        .trim_end_matches('0')
+        .trim_end_matches('.')
+        .to_string()
+}
+
+/// Digits after the point to show `significant` digits of a value with the exponent.
+fn significant_to_digits(significant: usize, exponent: i32) -> usize {
+    (significant as i32 - 1 - exponent).max(0) as usize
+}
+
+fn decimal_exponent(value: f64) -> i32 {
+    if value == 0.0 {
+        0
+    } else {
+        value.abs().log10().floor() as i32
+    }
+}
+
+fn format_fractional(value: f64, spec: &Spec) -> String {
+    let digits = match spec.precision {
+        None => 6,
+        Some(Precision::Digits(digits)) => digits,
+        Some(Precision::Significant(significant)) => {
+            significant_to_digits(significant, decimal_exponent(value))
+        }
+    };
+    let text = format!("{:.*}", digits, value.abs());
+    if spec.strip_zeros {
+        strip_trailing_zeros(&text)
+    } else {
+        text
+    }
+}
+
+fn format_scientific(value: f64, spec: &Spec) -> String {
+    let value = value.abs();
+    let mut exponent = decimal_exponent(value);
+    if spec.engineering {
+        exponent -= exponent.rem_euclid(3);
+    }
+    let digits_for = |exponent: i32| match spec.precision {
+        None => 6,
+        Some(Precision::Digits(digits)) => digits,
+        Some(Precision::Significant(significant)) => {
+            significant_to_digits(significant, decimal_exponent(value) - exponent)
+        }
+    };
+    let mut mantissa = value / 10f64.powi(exponent);
+    let mut digits = digits_for(exponent);
+    // Rounding can carry into another digit, for example 9.9999 to 10.000.
+    let limit = if spec.engineering { 1000.0 } else { 10.0 };
+    if format!("{:.*}", digits, mantissa)
+        .parse::<f64>()
+        .unwrap_or(0.0)
+        >= limit
+    {
+        exponent += if spec.engineering { 3 } else { 1 };
+        mantissa = value / 10f64.powi(exponent);
+        digits = digits_for(exponent);
+    }
+    let mut text = format!("{:.*}", digits, mantissa);
+    if spec.strip_zeros {
+        text = strip_trailing_zeros(&text);
+    }
+    let sign = if exponent < 0 { '-' } else { '+' };
+    format!("{text}E{sign}{}", exponent.abs())
+}
+
+/// Format a float without the sign.
+fn format_float(value: f64, spec: &Spec) -> String {
+    if value.is_nan() {
+        return "NaN".to_string();
+    }
+    if value.is_infinite() {
+        return "Inf".to_string();
+    }
+    match spec.conversion {
+        'f' | 'F' => format_fractional(value, spec),
+        'e' | 'E' => format_scientific(value, spec),
+        _ => {
+            let exponent = decimal_exponent(value);
+            let limit = match spec.precision {
+                Some(Precision::Digits(digits)) | Some(Precision::Significant(digits)) => {
+                    digits.max(1) as i32
+                }
+                None => 6,
+            };
+            if exponent < -4 || exponent >= limit {
+                format_scientific(value, spec)
+            } else {
+                format_fractional(value, spec)
+            }
+        }
+    }
+}
+
+/// Apply the width, sign and padding to formatted digits.
+fn pad(spec: &Spec, negative: bool, digits: String, numeric: bool) -> String {
+    let sign = if negative {
+        "-"
+    } else if spec.plus && numeric {
+        "+"
+    } else {
+        ""
+    };
+    let length = sign.chars().count() + digits.chars().count();
+    let padding = spec.width.unwrap_or(0).saturating_sub(length);
+    if spec.left {
+        format!("{sign}{digits}{}", " ".repeat(padding))
+    } else if spec.zero && numeric {
+        format!("{sign}{}{digits}", "0".repeat(padding))
+    } else {
+        format!("{}{sign}{digits}", " ".repeat(padding))
+    }
+}
+
+fn format_spec(spec: &Spec, arg: &FormatArg, separator: char) -> Result<String, FormatError> {
+    let mismatch = || FormatError::TypeMismatch(format!("%{} with {:?}", spec.conversion, arg));
+    let text = match (spec.conversion, arg) {
+        ('T', FormatArg::Time(time)) => {
+            let format = spec.time_format.as_deref().unwrap_or_default();
+            let text = time::format_time(*time, format, time_offset(spec, *time))?;
+            pad(spec, false, text, false)
+        }
+        ('T', _) | (_, FormatArg::Time(_)) => return Err(mismatch()),
+        ('s', FormatArg::Str(value)) => {
+            let value: String = match spec.precision {
+                Some(Precision::Digits(max)) => value.chars().take(max).collect(),
+                _ => value.clone(),
+            };
+            pad(spec, false, value, false)
+        }
+        ('s', FormatArg::Bool(value)) => pad(
+            spec,
+            false,
+            if *value { "TRUE" } else { "FALSE" }.to_string(),
+            false,
+        ),
+        ('[', _) => {
+            return Err(FormatError::UnknownSpecifier(
+                "character sets are only supported for scanning".to_string(),
+            ))
+        }
+        ('s', _) | (_, FormatArg::Str(_)) | (_, FormatArg::Bool(_)) => return Err(mismatch()),
+        ('d' | 'x' | 'X' | 'o' | 'b', _) => {
+            let (value, bits) = match arg {
+                FormatArg::Int { value, bits } => (*value as i128, *bits),
+                FormatArg::UInt(value) => (*value as i128, u64::BITS),
+                FormatArg::Float(value) if value.is_finite() => {
+                    (round_to_integer(*value), i64::BITS)
+                }
+                FormatArg::Float(value) => {
+                    let digits = format_float(*value, spec);
+                    return Ok(pad(
+                        spec,
+                        value.is_sign_negative() && !value.is_nan(),
+                        digits,
+                        true,
+                    ));
+                }
+                _ => unreachable!(),
+            };
+            if spec.conversion == 'd' {
+                pad(spec, value < 0, value.unsigned_abs().to_string(), true)
+            } else {
+                // Negative values show the two's complement of their type.
+                let mask = if bits >= 64 {
+                    u64::MAX
+                } else {
+                    (1u64 << bits) - 1
+                };
+                pad(
+                    spec,
+                    false,
+                    format_radix(value as u64 & mask, spec.conversion),
+                    true,
+                )
+            }
+        }
+        (_, arg) => {
+            let value = match arg {
+                FormatArg::Int { value, .. } => *value as f64,
+                FormatArg::UInt(value) => *value as f64,
+                FormatArg::Float(value) => *value,
+                _ => unreachable!(),
+            };
+            let digits = format_float(value, spec).replace('.', &separator.to_string());
+            pad(
+                spec,
+                value.is_sign_negative() && !value.is_nan(),
+                digits,
+                true,
+            )
+        }
+    };
+    Ok(text)
+}
+
+/// The UTC offset for a time specifier. The `^` flag selects UTC.
+fn time_offset(spec: &Spec, time: LVTime) -> i32 {
+    if spec.engineering {
+        0
+    } else {
+        time::local_utc_offset(time)
+    }
+}
+
+/// Format the values using a LabVIEW format string, like Format Into String.
+///
+/// See [`lv_format!`](crate::lv_format) to pass the values directly.
+pub fn format_into_string(format: &str, args: &[FormatArg]) -> Result<String, FormatError> {
+    let mut output = String::new();
+    let mut args = args.iter();
+    let mut separator = '.';
+    for token in parse_format(format)? {
+        match token {
+            Token::Literal(c) => output.push(c),
+            Token::DecimalSeparator(c) => separator = c,
+            Token::Spec(spec) => {
+                let arg = args.next().ok_or(FormatError::TooManySpecifiers)?;
+                output.push_str(&format_spec(&spec, arg, separator)?);
+            }
+        }
+    }
+    if args.next().is_some() {
+        return Err(FormatError::TooFewSpecifiers);
+    }
+    Ok(output)
+}
+
+/// Format values into a string using LabVIEW's format specifiers.
+///
+/// This returns a `Result` with a [`FormatError`](crate::format::FormatError).
+/// See the [`format`](crate::format) module for the supported specifiers.
+#[macro_export]
+macro_rules! lv_format {
+    ($format:expr $(, $arg:expr)* $(,)?) => {
+        $crate::format::format_into_string(
+            $format,
+            &[$($crate::format::FormatArg::from($arg)),*],
+        )
+    };
+}
+
+/// A value read by [`scan_from_string`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScanValue {
+    Int(i64),
+    Float(f64),
+    Str(String),
+    Time(LVTime),
+}
+
+/// The result of [`scan_from_string`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scanned<'a> {
+    /// The values for each specifier.
+    pub values: Vec<ScanValue>,
+    /// The input following the last match.
+    pub remaining: &'a str,
+}
+
+fn skip_whitespace(input: &str) -> &str {
+    input.trim_start()
+}
+
+/// Split off the longest prefix, up to the width, whose characters match.
+fn take_while(
+    input: &str,
+    width: Option<usize>,
+    mut matches: impl FnMut(usize, char) -> bool,
+) -> (&str, &str) {
+    let limit = width.unwrap_or(usize::MAX);
+    let end = input
+        .char_indices()
+        .enumerate()
+        .find(|(count, (_, c))| *count >= limit || !matches(*count, *c))
+        .map(|(_, (index, _))| index)
+        .unwrap_or(input.len());
+    input.split_at(end)
+}
+
+fn scan_integer<'a>(input: &'a str, spec: &Spec) -> Result<(ScanValue, &'a str), FormatError> {
+    let radix = match spec.conversion {
+        'x' | 'X' => 16,
+        'o' => 8,
+        'b' => 2,
+        _ => 10,
+    };
+    let (text, rest) = take_while(input, spec.width, |count, c| {
+        (count == 0 && (c == '-' || c == '+')) || c.is_digit(radix)
+    });
+    let value = i128::from_str_radix(text, radix)
+        .ok()
+        .and_then(|value| i64::try_from(value).ok())
+        .ok_or_else(|| FormatError::ScanFailed(format!("expected an integer at `{input}`")))?;
+    Ok((ScanValue::Int(value), rest))
+}
+
+fn scan_float<'a>(
+    input: &'a str,
+    spec: &Spec,
+    separator: char,
+) -> Result<(ScanValue, &'a str), FormatError> {
+    let failed = || FormatError::ScanFailed(format!("expected a number at `{input}`"));
+    let (sign, unsigned) = match input.strip_prefix('-') {
+        Some(rest) => (-1.0, rest),
+        None => (1.0, input.strip_prefix('+').unwrap_or(input)),
+    };
+    for (name, value) in [("Inf", f64::INFINITY), ("NaN", f64::NAN)] {
+        if unsigned.len() >= name.len() && unsigned[..name.len()].eq_ignore_ascii_case(name) {
+            return Ok((ScanValue::Float(sign * value), &unsigned[name.len()..]));
+        }
+    }
+
+    let mut seen_separator = false;
+    let mut seen_exponent = false;
+    let mut previous = ' ';
+    let (text, _) = take_while(input, spec.width, |count, c| {
+        let accept = match c {
+            '0'..='9' => true,
+            '-' | '+' => count == 0 || previous == 'e' || previous == 'E',
+            'e' | 'E' if !seen_exponent && previous.is_ascii_digit() => {
+                seen_exponent = true;
+                true
+            }
+            c if c == separator && !seen_separator && !seen_exponent => {
+                seen_separator = true;
+                true
+            }
+            _ => false,
+        };
+        previous = c;
+        accept
+    });
+    // An exponent marker without digits isn't part of the number.
+    let text = text.trim_end_matches(['e', 'E', '-', '+']);
+    let rest = &input[text.len()..];
+    let value: f64 = text.replace(separator, ".").parse().map_err(|_| failed())?;
+    Ok((ScanValue::Float(value), rest))
+}
+
+/// Scan values from the input using a LabVIEW format string, like Scan From String.
+///
+/// Whitespace in the format matches any amount of whitespace in the input and
+/// numeric and `%s` specifiers skip leading whitespace. See
+/// [`lv_scan!`](crate::lv_scan) to convert the values to Rust types.
+pub fn scan_from_string<'a>(input: &'a str, format: &str) -> Result<Scanned<'a>, FormatError> {
+    let mut values = Vec::new();
+    let mut remaining = input;
+    let mut separator = '.';
+    for token in parse_format(format)? {
+        match token {
+            Token::DecimalSeparator(c) => separator = c,
+            Token::Literal(c) if c.is_whitespace() => remaining = skip_whitespace(remaining),
+            Token::Literal(c) => {
+                remaining = remaining.strip_prefix(c).ok_or_else(|| {
+                    FormatError::ScanFailed(format!("expected `{c}` at `{remaining}`"))
+                })?;
+            }
+            Token::Spec(spec) => {
+                let (value, rest) = match spec.conversion {
+                    'd' | 'x' | 'X' | 'o' | 'b' => scan_integer(skip_whitespace(remaining), &spec)?,
+                    's' => {
+                        let (text, rest) =
+                            take_while(skip_whitespace(remaining), spec.width, |_, c| {
+                                !c.is_whitespace()
+                            });
+                        (ScanValue::Str(text.to_string()), rest)
+                    }
+                    '[' => {
+                        let (negated, set) = spec
+                            .set
+                            .as_ref()
+                            .expect("scan sets are parsed with their set");
+                        let (text, rest) =
+                            take_while(remaining, spec.width, |_, c| set.contains(&c) != *negated);
+                        if text.is_empty() {
+                            return Err(FormatError::ScanFailed(format!(
+                                "no characters in the set at `{remaining}`"
+                            )));
+                        }
+                        (ScanValue::Str(text.to_string()), rest)
+                    }
+                    'T' => {
+                        let format = spec.time_format.as_deref().unwrap_or_default();
+                        let (time, rest) = time::scan_time(remaining, format, 0)?;
+                        // Shift local times by the offset at that time.
+                        let offset = time_offset(&spec, time) as i64;
+                        let (seconds, fractions) = time.to_parts();
+                        (
+                            ScanValue::Time(LVTime::from_parts(seconds - offset, fractions)),
+                            rest,
+                        )
+                    }
+                    _ => scan_float(skip_whitespace(remaining), &spec, separator)?,
+                };
+                values.push(value);
+                remaining = rest;
+            }
+        }
+    }
+    Ok(Scanned { values, remaining })
+}
+
+/// Types which can be read from a [`ScanValue`].
+pub trait FromScanValue: Sized {
+    fn from_scan_value(value: ScanValue) -> Result<Self, FormatError>;
+}
+
+fn scan_mismatch(value: &ScanValue, target: &str) -> FormatError {
+    FormatError::TypeMismatch(format!("can't read {value:?} as {target}"))
+}
+
+macro_rules! from_scan_value_int {
+    ($($int:ty),+) => {
+        $(
+            impl FromScanValue for $int {
+                fn from_scan_value(value: ScanValue) -> Result<Self, FormatError> {
+                    match value {
+                        ScanValue::Int(int) => {
+                            <$int>::try_from(int).map_err(|_| scan_mismatch(&value, stringify!($int)))
+                        }
+                        _ => Err(scan_mismatch(&value, stringify!($int))),
+                    }
+                }
+            }
+        )+
+    };
+}
+
+from_scan_value_int!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+
+impl FromScanValue for f64 {
+    fn from_scan_value(value: ScanValue) -> Result<Self, FormatError> {
+        match value {
+            ScanValue::Int(int) => Ok(int as f64),
+            ScanValue::Float(float) => Ok(float),
+            ScanValue::Str(_) | ScanValue::Time(_) => Err(scan_mismatch(&value, "f64")),
+        }
+    }
+}
+
+impl FromScanValue for f32 {
+    fn from_scan_value(value: ScanValue) -> Result<Self, FormatError> {
+        f64::from_scan_value(value).map(|value| value as f32)
+    }
+}
+
+impl FromScanValue for LVTime {
+    fn from_scan_value(value: ScanValue) -> Result<Self, FormatError> {
+        match value {
+            ScanValue::Time(time) => Ok(time),
+            _ => Err(scan_mismatch(&value, "LVTime")),
+        }
+    }
+}
+
+impl FromScanValue for String {
+    fn from_scan_value(value: ScanValue) -> Result<Self, FormatError> {
+        match value {
+            ScanValue::Str(text) => Ok(text),
+            _ => Err(scan_mismatch(&value, "String")),
+        }
+    }
+}
+
+#[doc(hidden)]
+pub fn next_scan_value<T: FromScanValue>(
+    values: &mut impl Iterator<Item = ScanValue>,
+) -> Result<T, FormatError> {
+    T::from_scan_value(values.next().ok_or(FormatError::TooFewSpecifiers)?)
+}
+
+/// Scan values from a string using LabVIEW's format specifiers, returning them
+/// as a tuple of the given types.
+///
+/// This returns a `Result` with a [`FormatError`](crate::format::FormatError).
+/// See [`scan_from_string`](crate::format::scan_from_string) to get the remaining input.
+///
+/// # Example
+/// ```
+/// use labview_interop::lv_scan;
+///
+/// let (count, unit) = lv_scan!("12 samples", "%d %s" => u32, String).unwrap();
+/// assert_eq!(count, 12);
+/// assert_eq!(unit, "samples");
+/// ```
+#[macro_export]
+macro_rules! lv_scan {
+    ($input:expr, $format:expr => $($type:ty),+ $(,)?) => {
+        $crate::format::scan_from_string($input, $format).and_then(|scanned| {
+            let mut values = scanned.values.into_iter();
+            let result = ($($crate::format::next_scan_value::<$type>(&mut values)?,)+);
+            if values.next().is_some() {
+                return Err($crate::format::FormatError::TooManySpecifiers);
+            }
+            Ok(result)
+        })
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn format(format: &str, arg: impl Into<FormatArg>) -> String {
+        format_into_string(format, &[arg.into()]).unwrap()
+    }
+
+    #[test]
+    fn test_format_integers() {
+        assert_eq!(format("%d", 42), "42");
+        assert_eq!(format("%5d", -42), "  -42");
+        assert_eq!(format("%-5d|", 42), "42   |");
+        assert_eq!(format("%05d", -42), "-0042");
+        assert_eq!(format("%+d", 42), "+42");
+        assert_eq!(format("%x", 255u8), "FF");
+        assert_eq!(format("%x", -1i16), "FFFF");
+        assert_eq!(format("%o", 8), "10");
+        assert_eq!(format("%08b", 5), "00000101");
+        // Floats are rounded half to even.
+        assert_eq!(format("%d", 2.5), "2");
+        assert_eq!(format("%d", 3.5), "4");
+    }
+
+    #[test]
+    fn test_format_floats() {
+        assert_eq!(format("%f", 1.5), "1.500000");
+        assert_eq!(format("%.2f", -1.005e3), "-1005.00");
+        assert_eq!(format("%_3f", 123.456), "123");
+        assert_eq!(format("%_2f", 0.012345), "0.012");
+        assert_eq!(format("%#f", 1.5), "1.5");
+        assert_eq!(format("%.3e", 12345.0), "1.234E+4");
+        assert_eq!(format("%.2e", 0.00123), "1.23E-3");
+        assert_eq!(format("%.1e", 9.99), "1.0E+1");
+        assert_eq!(format("%^.2e", 12345.0), "12.35E+3");
+        assert_eq!(format("%g", 1.5), "1.500000");
+        assert_eq!(format("%g", 1.5e9), "1.500000E+9");
+        assert_eq!(format("%f", f64::NAN), "NaN");
+        assert_eq!(format("%6f", f64::NEG_INFINITY), "  -Inf");
+        assert_eq!(format("%d", 3), "3");
+        assert_eq!(format("%.1f", 3), "3.0");
+    }
+
+    #[test]
+    fn test_format_strings_and_separators() {
+        assert_eq!(format("%s", "text"), "text");
+        assert_eq!(format("%6s|", "ab"), "    ab|");
+        assert_eq!(format("%-6s|", "ab"), "ab    |");
+        assert_eq!(format("%.2s", "abcdef"), "ab");
+        assert_eq!(format("%s", true), "TRUE");
+        assert_eq!(
+            format_into_string("%,;%.2f %.;%.2f", &[1.5.into(), 1.5.into()]).unwrap(),
+            "1,50 1.50"
+        );
+    }
+
+    #[test]
+    fn test_format_errors() {
+        assert_eq!(
+            format_into_string("%d", &["text".into()])
+                .unwrap_err()
+                .code(),
+            81
+        );
+        assert_eq!(
+            format_into_string("%k", &[1.into()]).unwrap_err().code(),
+            82
+        );
+        assert_eq!(
+            format_into_string("%d", &[1.into(), 2.into()]),
+            Err(FormatError::TooFewSpecifiers)
+        );
+        assert_eq!(
+            format_into_string("%d %d", &[1.into()]),
+            Err(FormatError::TooManySpecifiers)
+        );
+        assert_eq!(format_into_string("100%%", &[]).unwrap(), "100%");
+    }
+
+    #[test]
+    fn test_scan() {
+        let scanned = scan_from_string("  12, -3.5e2 volts rest", "%d, %f %s").unwrap();
+        assert_eq!(
+            scanned.values,
+            vec![
+                ScanValue::Int(12),
+                ScanValue::Float(-350.0),
+                ScanValue::Str("volts".to_string())
+            ]
+        );
+        assert_eq!(scanned.remaining, " rest");
+
+        let (hex, value) = lv_scan!("FF 1,25", "%x %,;%f" => u8, f64).unwrap();
+        assert_eq!(hex, 255);
+        assert_eq!(value, 1.25);
+
+        let (digits, rest) = lv_scan!("12345", "%3d%d" => i32, i32).unwrap();
+        assert_eq!((digits, rest), (123, 45));
+
+        let (key, value) = lv_scan!("a-b=c", "%[a-z-]=%s" => String, String).unwrap();
+        assert_eq!((key.as_str(), value.as_str()), ("a-b", "c"));
+    }
+
+    #[test]
+    fn test_time_specifiers() {
+        let time = LVTime::from_parts(3758974472, 0x8000_0000_0000_0000);
+        assert_eq!(
+            lv_format!("at %^<%H:%M:%S%2u>T.", time).unwrap(),
+            "at 15:34:32.50."
+        );
+        assert_eq!(lv_format!("%d", time).unwrap_err().code(), 81);
+
+        let (scanned, count) =
+            lv_scan!("2023-02-11T15:34:32 5", "%^<%Y-%m-%dT%H:%M:%S>T %d" => LVTime, i32).unwrap();
+        assert_eq!(scanned, LVTime::from_parts(3758974472, 0));
+        assert_eq!(count, 5);
+    }
+
+    #[test]
+    fn test_scan_errors() {
+        assert_eq!(scan_from_string("abc", "%d").unwrap_err().code(), 85);
+        assert_eq!(scan_from_string("1;2", "%d,%d").unwrap_err().code(), 85);
+        assert_eq!(lv_scan!("300", "%d" => u8).unwrap_err().code(), 81);
+        assert_eq!(
+            lv_scan!("1 2", "%d %d" => i32),
+            Err(FormatError::TooManySpecifiers)
+        );
+    }
+}
@@ -0,0 +1,504 @@
+//! LabVIEW time format codes for [`LVTime`].
+//!
+//! These are the codes used inside `%<...>T` specifiers, for example
+//! `%<%Y-%m-%d %H:%M:%S%3u>T`.
+//!
+//! | Code | Meaning |
+//! |------|---------|
+//! | `%a` | Abbreviated weekday name (`Mon`) |
+//! | `%b` | Abbreviated month name (`Jan`) |
+//! | `%c` | Date and time, the same as `%x %X` |
+//! | `%d` | Day of the month (`01`-`31`) |
+//! | `%H` | Hour in 24 hour format (`00`-`23`) |
+//! | `%I` | Hour in 12 hour format (`01`-`12`) |
+//! | `%j` | Day of the year (`001`-`366`) |
+//! | `%m` | Month (`01`-`12`) |
+//! | `%M` | Minute (`00`-`59`) |
+//! | `%p` | `AM` or `PM` |
+//! | `%S` | Second (`00`-`59`) |
+//! | `%<digit>u` | Fractional seconds with the given digits including the point (`.123`) |
+//! | `%w` | Weekday with Sunday as 0 |
+//! | `%x` | Date, `%m/%d/%Y` |
+//! | `%X` | Time, `%I:%M:%S %p` |
+//! | `%y` | Year without the century (`00`-`99`) |
+//! | `%Y` | Year with the century |
+//! | `%z` | Offset from UTC (`+01:00:00`) |
+//! | `%Z` | Time zone, `UTC` or the offset |
+//! | `%%` | A percent sign |
+//!
+//! LabVIEW's `%x`, `%X` and `%c` follow the system locale. These use the
+//! English (United States) formats.
+
+use super::FormatError;
+use crate::types::timestamp::{LVTime, UNIX_EPOCH_IN_LV_SECONDS};
+
+const SECONDS_PER_DAY: i64 = 86_400;
+const WEEKDAYS: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// Days since 1970-01-01 for a date in the proleptic Gregorian calendar.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = month as i64;
+    let day_of_year =
+        (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// The date for a number of days since 1970-01-01.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * shifted_month + 2) / 5 + 1) as u32;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+fn is_leap_year(year: i64) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+/// A timestamp broken down into calendar fields.
+struct DateTime {
+    year: i64,
+    month: u32,
+    day: u32,
+    hour: u32,
+    minute: u32,
+    second: u32,
+    /// Fractions of a second in units of 2^-64.
+    fractions: u64,
+    weekday: u32,
+    day_of_year: u32,
+}
+
+impl DateTime {
+    fn from_lv_time(time: LVTime, utc_offset: i32) -> Self {
+        let (seconds, fractions) = time.to_parts();
+        let seconds = seconds - UNIX_EPOCH_IN_LV_SECONDS as i64 + utc_offset as i64;
+        let days = seconds.div_euclid(SECONDS_PER_DAY);
+        let second_of_day = seconds.rem_euclid(SECONDS_PER_DAY) as u32;
+        let (year, month, day) = civil_from_days(days);
+        DateTime {
+            year,
+            month,
+            day,
+            hour: second_of_day / 3600,
+            minute: second_of_day / 60 % 60,
+            second: second_of_day % 60,
+            fractions,
+            // 1970-01-01 was a Thursday.
+            weekday: (days + 4).rem_euclid(7) as u32,
+            day_of_year: (days - days_from_civil(year, 1, 1)) as u32 + 1,
+        }
+    }
+}
+
+fn format_offset(utc_offset: i32) -> String {
+    let sign = if utc_offset < 0 { '-' } else { '+' };
+    let offset = utc_offset.unsigned_abs();
+    format!(
+        "{sign}{:02}:{:02}:{:02}",
+        offset / 3600,
+        offset / 60 % 60,
+        offset % 60
+    )
+}
+
+/// Format the time using LabVIEW time format codes, as used inside `%<...>T`.
+///
+/// `utc_offset` is the offset of the time zone to display in seconds, so 0
+/// gives UTC.
+pub fn format_time(time: LVTime, format: &str, utc_offset: i32) -> Result<String, FormatError> {
+    let date = DateTime::from_lv_time(time, utc_offset);
+    let mut output = String::new();
+    let mut chars = format.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            output.push(c);
+            continue;
+        }
+        let mut digits = None;
+        if let Some(digit) = chars.peek().and_then(|c| c.to_digit(10)) {
+            digits = Some(digit);
+            chars.next();
+        }
+        let code = chars
+            .next()
+            .ok_or_else(|| FormatError::UnknownSpecifier(format!("{format} ends with %")))?;
+        let hour_12 = match date.hour % 12 {
+            0 => 12,
+            hour => hour,
+        };
+        let am_pm = if date.hour < 12 { "AM" } else { "PM" };
+        let text = match (code, digits) {
+            ('u', digits) => {
+                let digits = digits.unwrap_or(3);
+                let scale = 10u128.pow(digits);
+                let value = (date.fractions as u128 * scale) >> 64;
+                if digits == 0 {
+                    String::new()
+                } else {
+                    format!(".{value:0width$}", width = digits as usize)
+                }
+            }
+            (_, Some(_)) => {
+                return Err(FormatError::UnknownSpecifier(format!(
+                    "%{}{code} in {format}",
+                    digits.unwrap_or_default()
+                )))
+            }
+            ('a', _) => WEEKDAYS[date.weekday as usize].to_string(),
+            ('b', _) => MONTHS[date.month as usize - 1].to_string(),
+            ('c', _) => format!(
+                "{:02}/{:02}/{:04} {hour_12:02}:{:02}:{:02} {am_pm}",
+                date.month, date.day, date.year, date.minute, date.second
+            ),
+            ('d', _) => format!("{:02}", date.day),
+            ('H', _) => format!("{:02}", date.hour),
+            ('I', _) => format!("{hour_12:02}"),
+            ('j', _) => format!("{:03}", date.day_of_year),
+            ('m', _) => format!("{:02}", date.month),
+            ('M', _) => format!("{:02}", date.minute),
+            ('p', _) => am_pm.to_string(),
+            ('S', _) => format!("{:02}", date.second),
+            ('w', _) => date.weekday.to_string(),
+            ('x', _) => format!("{:02}/{:02}/{:04}", date.month, date.day, date.year),
+            ('X', _) => format!("{hour_12:02}:{:02}:{:02} {am_pm}", date.minute, date.second),
+            ('y', _) => format!("{:02}", date.year.rem_euclid(100)),
+            ('Y', _) => date.year.to_string(),
+            ('z', _) => format_offset(utc_offset),
+            ('Z', _) if utc_offset == 0 => "UTC".to_string(),
+            ('Z', _) => format_offset(utc_offset),
+            ('%', _) => "%".to_string(),
+            (code, _) => {
+                return Err(FormatError::UnknownSpecifier(format!(
+                    "%{code} in {format}"
+                )))
+            }
+        };
+        output.push_str(&text);
+    }
+    Ok(output)
+}
+
+/// Fields read while scanning.
+#[derive(Default)]
+struct ScannedFields {
+    year: Option<i64>,
+    month: Option<u32>,
+    day: Option<u32>,
+    day_of_year: Option<u32>,
+    hour: u32,
+    minute: u32,
+    second: u32,
+    pm: Option<bool>,
+    fractions: u64,
+}
+
+fn scan_failed(input: &str, expected: &str) -> FormatError {
+    FormatError::ScanFailed(format!("expected {expected} at `{input}`"))
+}
+
+/// Read an unsigned number of up to `max_digits` digits.
+fn scan_number<'a>(
+    input: &'a str,
+    max_digits: usize,
+    expected: &str,
+) -> Result<(u32, &'a str), FormatError> {
+    let input = input.trim_start();
+    let end = input
+        .char_indices()
+        .take(max_digits)
+        .take_while(|(_, c)| c.is_ascii_digit())
+        .last()
+        .map(|(index, c)| index + c.len_utf8())
+        .ok_or_else(|| scan_failed(input, expected))?;
+    let value = input[..end]
+        .parse()
+        .map_err(|_| scan_failed(input, expected))?;
+    Ok((value, &input[end..]))
+}
+
+/// Read one of the names, ignoring case, returning its index.
+fn scan_name<'a>(
+    input: &'a str,
+    names: &[&str],
+    expected: &str,
+) -> Result<(usize, &'a str), FormatError> {
+    let input = input.trim_start();
+    names
+        .iter()
+        .position(|name| {
+            input
+                .get(..name.len())
+                .is_some_and(|prefix| prefix.eq_ignore_ascii_case(name))
+        })
+        .map(|index| (index, &input[names[index].len()..]))
+        .ok_or_else(|| scan_failed(input, expected))
+}
+
+fn scan_fields<'a>(
+    mut input: &'a str,
+    format: &str,
+    fields: &mut ScannedFields,
+) -> Result<&'a str, FormatError> {
+    let mut chars = format.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c.is_whitespace() {
+            input = input.trim_start();
+            continue;
+        }
+        if c != '%' {
+            input = input
+                .strip_prefix(c)
+                .ok_or_else(|| scan_failed(input, &format!("`{c}`")))?;
+            continue;
+        }
+        let mut digits = None;
+        if let Some(digit) = chars.peek().and_then(|c| c.to_digit(10)) {
+            digits = Some(digit);
+            chars.next();
+        }
+        let code = chars
+            .next()
+            .ok_or_else(|| FormatError::UnknownSpecifier(format!("{format} ends with %")))?;
+        input = match code {
+            'u' => {
+                let Some(rest) = input.strip_prefix('.') else {
+                    // The fractional seconds are optional.
+                    continue;
+                };
+                let max_digits = digits.map(|digits| digits as usize).unwrap_or(usize::MAX);
+                let end = rest
+                    .char_indices()
+                    .take(max_digits)
+                    .take_while(|(_, c)| c.is_ascii_digit())
+                    .last()
+                    .map(|(index, _)| index + 1)
+                    .unwrap_or(0);
+                let fraction: f64 = format!("0.{}", &rest[..end]).parse().unwrap_or(0.0);
+                fields.fractions = (fraction * u64::MAX as f64) as u64;
+                &rest[end..]
+            }
+            'a' => scan_name(input, &WEEKDAYS, "a weekday")?.1,
+            'b' => {
+                let (month, rest) = scan_name(input, &MONTHS, "a month")?;
+                fields.month = Some(month as u32 + 1);
+                rest
+            }
+            'c' => scan_fields(input, "%x %X", fields)?,
+            'd' => {
+                let (day, rest) = scan_number(input, 2, "a day")?;
+                fields.day = Some(day);
+                rest
+            }
+            'H' | 'I' => {
+                let (hour, rest) = scan_number(input, 2, "an hour")?;
+                fields.hour = hour;
+                rest
+            }
+            'j' => {
+                let (day, rest) = scan_number(input, 3, "a day of the year")?;
+                fields.day_of_year = Some(day);
+                rest
+            }
+            'm' => {
+                let (month, rest) = scan_number(input, 2, "a month")?;
+                fields.month = Some(month);
+                rest
+            }
+            'M' => {
+                let (minute, rest) = scan_number(input, 2, "a minute")?;
+                fields.minute = minute;
+                rest
+            }
+            'p' => {
+                let (index, rest) = scan_name(input, &["AM", "PM"], "AM or PM")?;
+                fields.pm = Some(index == 1);
+                rest
+            }
+            'S' => {
+                let (second, rest) = scan_number(input, 2, "a second")?;
+                fields.second = second;
+                rest
+            }
+            'w' => scan_number(input, 1, "a weekday")?.1,
+            'x' => scan_fields(input, "%m/%d/%Y", fields)?,
+            'X' => scan_fields(input, "%I:%M:%S %p", fields)?,
+            'y' => {
+                let (year, rest) = scan_number(input, 2, "a year")?;
+                // Follow LabVIEW in treating 00-37 as 2000-2037.
+                fields.year = Some(if year < 38 { 2000 } else { 1900 } + year as i64);
+                rest
+            }
+            'Y' => {
+                let (year, rest) = scan_number(input, 4, "a year")?;
+                fields.year = Some(year as i64);
+                rest
+            }
+            '%' => input
+                .strip_prefix('%')
+                .ok_or_else(|| scan_failed(input, "`%`"))?,
+            code => {
+                return Err(FormatError::UnknownSpecifier(format!(
+                    "%{code} in {format}"
+                )))
+            }
+        };
+    }
+    Ok(input)
+}
+
+/// Scan a time using LabVIEW time format codes, as used inside `%<...>T`.
+///
+/// `utc_offset` is the offset in seconds of the time zone the input is in.
+/// Missing date fields default to 1904-01-01 as in LabVIEW. Returns the time
+/// and the remaining input.
+pub fn scan_time<'a>(
+    input: &'a str,
+    format: &str,
+    utc_offset: i32,
+) -> Result<(LVTime, &'a str), FormatError> {
+    let mut fields = ScannedFields::default();
+    let remaining = scan_fields(input, format, &mut fields)?;
+
+    let year = fields.year.unwrap_or(1904);
+    let days = match (fields.day_of_year, fields.month, fields.day) {
+        (Some(day_of_year), None, None) => {
+            let days_in_year = if is_leap_year(year) { 366 } else { 365 };
+            if !(1..=days_in_year).contains(&day_of_year) {
+                return Err(scan_failed(input, "a valid day of the year"));
+            }
+            days_from_civil(year, 1, 1) + day_of_year as i64 - 1
+        }
+        (_, month, day) => {
+            let (month, day) = (month.unwrap_or(1), day.unwrap_or(1));
+            if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+                return Err(scan_failed(input, "a valid date"));
+            }
+            days_from_civil(year, month, day)
+        }
+    };
+    let hour = match fields.pm {
+        Some(pm) => fields.hour % 12 + if pm { 12 } else { 0 },
+        None => fields.hour,
+    };
+    if hour > 23 || fields.minute > 59 || fields.second > 60 {
+        return Err(scan_failed(input, "a valid time"));
+    }
+
+    let seconds = days * SECONDS_PER_DAY
+        + hour as i64 * 3600
+        + fields.minute as i64 * 60
+        + fields.second as i64
+        - utc_offset as i64
+        + UNIX_EPOCH_IN_LV_SECONDS as i64;
+    Ok((LVTime::from_parts(seconds, fields.fractions), remaining))
+}
+
+/// The offset of the local time zone from UTC in seconds at the given time.
+///
+/// This needs the `chrono` feature to find the time zone, otherwise local
+/// times are shown in UTC.
+pub fn local_utc_offset(time: LVTime) -> i32 {
+    #[cfg(feature = "chrono")]
+    {
+        use ::chrono::{Local, Offset, TimeZone};
+        let unix_seconds = time.seconds() - UNIX_EPOCH_IN_LV_SECONDS as i64;
+        Local
+            .timestamp_opt(unix_seconds, 0)
+            .single()
+            .map(|local| local.offset().fix().local_minus_utc())
+            .unwrap_or(0)
+    }
+    #[cfg(not(feature = "chrono"))]
+    {
+        let _ = time;
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 2023-02-11 15:34:32.5 UTC, a Saturday.
+    fn time() -> LVTime {
+        LVTime::from_parts(3758974472, 0x8000_0000_0000_0000)
+    }
+
+    #[test]
+    fn test_civil_round_trip() {
+        for days in [-719_468, -25_567, -1, 0, 1, 11_016, 19_399, 2_932_896] {
+            let (year, month, day) = civil_from_days(days);
+            assert_eq!(days_from_civil(year, month, day), days);
+        }
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(-24_107), (1904, 1, 1));
+    }
+
+    #[test]
+    fn test_format_time() {
+        assert_eq!(
+            format_time(time(), "%Y-%m-%d %H:%M:%S%3u", 0).unwrap(),
+            "2023-02-11 15:34:32.500"
+        );
+        assert_eq!(
+            format_time(time(), "%a %b %d %I:%M %p, day %j, %y", 0).unwrap(),
+            "Sat Feb 11 03:34 PM, day 042, 23"
+        );
+        assert_eq!(
+            format_time(time(), "%c", 0).unwrap(),
+            "02/11/2023 03:34:32 PM"
+        );
+        assert_eq!(
+            format_time(time(), "%H:%M %z %Z", 3600 + 1800).unwrap(),
+            "17:04 +01:30:00 +01:30:00"
+        );
+        assert_eq!(format_time(time(), "%Z", 0).unwrap(), "UTC");
+        assert_eq!(
+            format_time(LVTime::from_parts(0, 0), "%x %X", 0).unwrap(),
+            "01/01/1904 12:00:00 AM"
+        );
+        assert_eq!(format_time(time(), "%q", 0).unwrap_err().code(), 82);
+    }
+
+    #[test]
+    fn test_scan_time() {
+        let (scanned, rest) =
+            scan_time("2023-02-11 15:34:32.5 tail", "%Y-%m-%d %H:%M:%S%u", 0).unwrap();
+        assert_eq!(scanned.seconds(), time().seconds());
+        assert!((scanned.sub_seconds() - 0.5).abs() < 1e-9);
+        assert_eq!(rest, " tail");
+
+        let (scanned, _) = scan_time("02/11/2023 04:34:32 PM", "%c", 3600).unwrap();
+        assert_eq!(scanned.seconds(), time().seconds());
+
+        let (scanned, _) = scan_time("Feb 11 23 15:34:32", "%b %d %y %H:%M:%S", 0).unwrap();
+        assert_eq!(scanned.seconds(), time().seconds());
+
+        let (scanned, _) = scan_time("2023 042", "%Y %j", 0).unwrap();
+        assert_eq!(
+            scanned.seconds(),
+            time().seconds() - (15 * 3600 + 34 * 60 + 32)
+        );
+
+        assert_eq!(scan_time("13/40/2023", "%x", 0).unwrap_err().code(), 85);
+    }
+}
@@ -0,0 +1,350 @@
+//! Support for the instance data pointer of the Call Library Function node.
+//!
+//! LabVIEW provides a pointer sized slot for each instance of a Call Library
+//! node. Add a parameter of type "Instance Data Pointer" to the node and
+//! LabVIEW passes a pointer to that slot, which lets each call site keep its
+//! own state without sharing (and locking) a global between parallel loops.
+//!
+//! The same slot is passed to the reserve, unreserve and abort callbacks
+//! configured on the callbacks tab of the node:
+//!
+//! * Reserve is called when the VI is reserved to run, before the first call.
+//! * Unreserve is called when the VI stops being reserved, such as when it is
+//!   unloaded, and must release the state.
+//! * Abort is called from another thread when the VI is aborted, which may be
+//!   while a call is still running.
+//!
+//! [`InstanceCache`] keeps a Rust value in the slot and
+//! [`lv_instance_cache_exports!`](crate::lv_instance_cache_exports) generates
+//! the callbacks for it.
+
+use std::ffi::c_void;
+use std::marker::PhantomData;
+use std::ptr::{addr_of, addr_of_mut};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::errors::{LVInteropError, Result};
+
+/// The value LabVIEW stores for each instance of a Call Library node.
+pub type InstanceDataPtr = *mut c_void;
+
+/// A lazily created value stored in the instance data of a Call Library node.
+///
+/// Each call site gets its own `T`, created with [`Default`] on first use, which
+/// makes it a good place for parsers, scratch buffers and other state that is
+/// expensive to create on every call.
+///
+/// From LabVIEW this is the "Instance Data Pointer" parameter type. The value must
+/// be released in the unreserve callback, which can be generated with
+/// [`lv_instance_cache_exports!`](crate::lv_instance_cache_exports).
+///
+/// Alongside the value the cache keeps an abort flag, set by the abort callback
+/// while a call may be running. Long running calls can check it through
+/// [`InstanceCache::get_with_abort`] to return early. The flag is cleared when
+/// the VI is reserved again.
+///
+/// # Example
+/// ```
+/// use labview_interop::errors::MgErr;
+/// use labview_interop::instance::InstanceCache;
+/// use labview_interop::lv_instance_cache_exports;
+///
+/// #[derive(Default)]
+/// pub struct Scratch {
+///     buffer: Vec<f64>,
+/// }
+///
+/// #[no_mangle]
+/// pub extern "C" fn process(mut cache: InstanceCache<Scratch>, value: f64) -> MgErr {
+///     match cache.get() {
+///         Ok(scratch) => {
+///             scratch.buffer.push(value);
+///             MgErr::NO_ERROR
+///         }
+///         Err(error) => error.into(),
+///     }
+/// }
+///
+/// lv_instance_cache_exports!(Scratch, unreserve = process_unreserve);
+/// ```
+#[repr(transparent)]
+pub struct InstanceCache<T> {
+    slot: *mut InstanceDataPtr,
+    _marker: PhantomData<T>,
+}
+
+/// What the instance data pointer points to.
+///
+/// The abort callback only touches `aborted`, so it never aliases a reference
+/// to the value held by a running call.
+struct InstanceData<T> {
+    aborted: AtomicBool,
+    value: T,
+}
+
+/// Whether the VI running a call has been aborted, from [`InstanceCache::get_with_abort`].
+#[derive(Debug, Clone, Copy)]
+pub struct AbortSignal<'a>(&'a AtomicBool);
+
+impl AbortSignal<'_> {
+    /// True once the abort callback has been called for this call site.
+    pub fn is_aborted(&self) -> bool {
+        self.0.load(Ordering::Acquire)
+    }
+}
+
+impl<T> InstanceCache<T> {
+    /// Create the cache from a pointer to the instance data slot.
+    ///
+    /// # Safety
+    ///
+    /// * The slot must be null or point to valid instance data.
+    /// * The instance data must be null or have been set by an `InstanceCache<T>` of the same `T`.
+    pub unsafe fn from_raw(slot: *mut InstanceDataPtr) -> Self {
+        Self {
+            slot,
+            _marker: PhantomData,
+        }
+    }
+
+    /// The instance data, if it has been created.
+    fn data(&self) -> Option<*mut InstanceData<T>> {
+        let data = unsafe { self.slot.as_ref() }?;
+        (!data.is_null()).then_some(*data as *mut InstanceData<T>)
+    }
+
+    /// True if the value has been created for this call site.
+    pub fn is_initialized(&self) -> bool {
+        self.data().is_some()
+    }
+
+    /// Get the value for this call site if it has already been created.
+    pub fn get_if_initialized(&mut self) -> Option<&mut T> {
+        // Safety: The slot only ever holds instance data created by `get`.
+        self.data()
+            .map(|data| unsafe { &mut *addr_of_mut!((*data).value) })
+    }
+
+    /// Mark the call site as aborted.
+    ///
+    /// This is safe to call from the abort callback while another thread is
+    /// using the value, as it only sets the abort flag. It does nothing if the
+    /// value hasn't been created.
+    pub fn abort(&self) {
+        if let Some(data) = self.data() {
+            // Safety: Only the atomic flag is accessed, not the value.
+            unsafe { &*addr_of!((*data).aborted) }.store(true, Ordering::Release);
+        }
+    }
+
+    /// True if the call site has been aborted since it was last reserved.
+    pub fn is_aborted(&self) -> bool {
+        self.data()
+            .is_some_and(|data| unsafe { &*addr_of!((*data).aborted) }.load(Ordering::Acquire))
+    }
+
+    /// Drop the value stored for this call site, if any.
+    ///
+    /// This should be called from the unreserve callback of the node so the value
+    /// is disposed of when the VI is unloaded.
+    ///
+    /// # Safety
+    ///
+    /// * No references from [`InstanceCache::get`] can still be held.
+    pub unsafe fn release(&mut self) {
+        if let Some(data) = self.slot.as_mut() {
+            if !data.is_null() {
+                drop(Box::from_raw(*data as *mut InstanceData<T>));
+                *data = std::ptr::null_mut();
+            }
+        }
+    }
+}
+
+impl<T: Default> InstanceCache<T> {
+    /// Get the value for this call site, creating it on first use.
+    ///
+    /// # Errors
+    ///
+    /// * This will error if LabVIEW didn't provide the instance data pointer.
+    pub fn get(&mut self) -> Result<&mut T> {
+        self.get_with_abort().map(|(value, _)| value)
+    }
+
+    /// Get the value for this call site along with its abort signal.
+    ///
+    /// # Errors
+    ///
+    /// * This will error if LabVIEW didn't provide the instance data pointer.
+    pub fn get_with_abort(&mut self) -> Result<(&mut T, AbortSignal<'_>)> {
+        let data = unsafe { self.slot.as_mut() }.ok_or(LVInteropError::InvalidHandle)?;
+        if data.is_null() {
+            let created = InstanceData {
+                aborted: AtomicBool::new(false),
+                value: T::default(),
+            };
+            *data = Box::into_raw(Box::new(created)) as InstanceDataPtr;
+        }
+        let data = *data as *mut InstanceData<T>;
+        // Safety: The slot only ever holds instance data created above and the
+        // value and flag are borrowed separately.
+        unsafe {
+            Ok((
+                &mut *addr_of_mut!((*data).value),
+                AbortSignal(&*addr_of!((*data).aborted)),
+            ))
+        }
+    }
+
+    /// Prepare the call site to run, creating the value and clearing the abort flag.
+    ///
+    /// This should be called from the reserve callback of the node.
+    ///
+    /// # Errors
+    ///
+    /// * This will error if LabVIEW didn't provide the instance data pointer.
+    pub fn reserve(&mut self) -> Result<()> {
+        let (_, abort) = self.get_with_abort()?;
+        abort.0.store(false, Ordering::Release);
+        Ok(())
+    }
+}
+
+/// Generate the callback exports for an [`InstanceCache`].
+///
+/// Configure the generated functions as the callbacks of every Call Library
+/// node which uses the cache. The unreserve callback is required so the value
+/// is dropped when the VI is unloaded. The reserve and abort callbacks are
+/// optional and go together, creating the value before the first call and
+/// setting the abort flag when the VI is aborted.
+///
+/// # Example
+/// ```
+/// use labview_interop::errors::MgErr;
+/// use labview_interop::instance::InstanceCache;
+/// use labview_interop::lv_instance_cache_exports;
+///
+/// #[derive(Default)]
+/// pub struct Sweep {
+///     points: Vec<f64>,
+/// }
+///
+/// #[no_mangle]
+/// pub extern "C" fn run_sweep(mut cache: InstanceCache<Sweep>, steps: u32) -> MgErr {
+///     let Ok((sweep, abort)) = cache.get_with_abort() else {
+///         return MgErr::ARGUMENT_ERROR;
+///     };
+///     for step in 0..steps {
+///         if abort.is_aborted() {
+///             break;
+///         }
+///         sweep.points.push(step as f64);
+///     }
+///     MgErr::NO_ERROR
+/// }
+///
+/// lv_instance_cache_exports!(
+///     Sweep,
+///     reserve = sweep_reserve,
+///     unreserve = sweep_unreserve,
+///     abort = sweep_abort,
+/// );
+/// ```
+#[macro_export]
+macro_rules! lv_instance_cache_exports {
+    ($state:ty, reserve = $reserve:ident, unreserve = $unreserve:ident, abort = $abort:ident $(,)?) => {
+        #[no_mangle]
+        pub extern "C" fn $reserve(
+            mut cache: $crate::instance::InstanceCache<$state>,
+        ) -> $crate::errors::MgErr {
+            $crate::export::catch_panic_status(|| cache.reserve().into())
+        }
+
+        #[no_mangle]
+        pub extern "C" fn $abort(
+            cache: $crate::instance::InstanceCache<$state>,
+        ) -> $crate::errors::MgErr {
+            $crate::export::catch_panic_status(|| {
+                cache.abort();
+                $crate::errors::MgErr::NO_ERROR
+            })
+        }
+
+        $crate::lv_instance_cache_exports!($state, unreserve = $unreserve);
+    };
+    ($state:ty, unreserve = $unreserve:ident $(,)?) => {
+        #[no_mangle]
+        pub extern "C" fn $unreserve(
+            mut cache: $crate::instance::InstanceCache<$state>,
+        ) -> $crate::errors::MgErr {
+            $crate::export::catch_panic_status(|| {
+                unsafe { cache.release() };
+                $crate::errors::MgErr::NO_ERROR
+            })
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static DROPPED: AtomicUsize = AtomicUsize::new(0);
+
+    #[derive(Default)]
+    struct Counter(u32);
+
+    impl Drop for Counter {
+        fn drop(&mut self) {
+            DROPPED.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn test_cache_created_once_and_released() {
+        let mut slot: InstanceDataPtr = std::ptr::null_mut();
+        let mut cache = unsafe { InstanceCache::<Counter>::from_raw(&mut slot) };
+        assert!(!cache.is_initialized());
+
+        cache.get().unwrap().0 += 1;
+        cache.get().unwrap().0 += 1;
+        assert_eq!(cache.get().unwrap().0, 2);
+        assert!(cache.is_initialized());
+
+        unsafe { cache.release() };
+        assert!(slot.is_null());
+        assert_eq!(DROPPED.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_abort_flag_cleared_on_reserve() {
+        let mut slot: InstanceDataPtr = std::ptr::null_mut();
+        let slot = addr_of_mut!(slot);
+        let mut cache = unsafe { InstanceCache::<Vec<u8>>::from_raw(slot) };
+        // Nothing to abort before the value exists.
+        cache.abort();
+        assert!(cache.get_if_initialized().is_none());
+
+        cache.reserve().unwrap();
+        let (value, abort) = cache.get_with_abort().unwrap();
+        value.push(1);
+        assert!(!abort.is_aborted());
+
+        // The abort callback gets its own view of the same slot.
+        let aborter = unsafe { InstanceCache::<Vec<u8>>::from_raw(slot) };
+        aborter.abort();
+        assert!(cache.is_aborted());
+
+        cache.reserve().unwrap();
+        assert!(!cache.is_aborted());
+        assert_eq!(cache.get_if_initialized(), Some(&mut vec![1]));
+        unsafe { cache.release() };
+    }
+
+    #[test]
+    fn test_null_slot_errors() {
+        let mut cache = unsafe { InstanceCache::<Counter>::from_raw(std::ptr::null_mut()) };
+        assert!(matches!(cache.get(), Err(LVInteropError::InvalidHandle)));
+    }
+}
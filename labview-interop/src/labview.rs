@@ -2,20 +2,25 @@
 //! abstracting the exact linking methods from the rest of the modules.
 //!
 
+mod fallback;
+
 use std::ffi::c_void;
 
 use ctor::ctor;
 use dlopen2::wrapper::{Container, WrapperApi};
 
+use std::sync::atomic::{AtomicPtr, Ordering};
+use std::sync::OnceLock;
+
 use crate::{
     errors::{LVInteropError, MgErr, Result},
-    memory::MagicCookie,
+    memory::{HandleValue, MagicCookie, MemoryManager},
 };
 
 /// Represents as UHandle passed by value. Can't use the generic
 /// version from the memory module else since the functions
 /// aren't generic.
-pub(crate) type UHandleValue = usize;
+pub(crate) type UHandleValue = HandleValue;
 
 #[ctor]
 static SYNC_API: Option<Container<SyncApi>> = unsafe { Container::load_self().ok() };
@@ -25,10 +30,145 @@ pub fn sync_api() -> Result<&'static Container<SyncApi>> {
 }
 
 #[ctor]
-static MEMORY_API: Option<Container<MemoryApi>> = unsafe { Container::load_self().ok() };
+static CLEANUP_API: Option<Container<CleanupApi>> = unsafe { Container::load_self().ok() };
+
+pub fn cleanup_api() -> Result<&'static Container<CleanupApi>> {
+    CLEANUP_API.as_ref().ok_or(LVInteropError::NoLabviewApi)
+}
+
+#[ctor]
+static FILE_API: Option<Container<FileApi>> = unsafe { Container::load_self().ok() };
 
-pub fn memory_api() -> Result<&'static Container<MemoryApi>> {
-    MEMORY_API.as_ref().ok_or(LVInteropError::NoLabviewApi)
+pub fn file_api() -> Result<&'static Container<FileApi>> {
+    FILE_API.as_ref().ok_or(LVInteropError::NoLabviewApi)
+}
+
+#[ctor]
+static VARIANT_API: Option<Container<VariantApi>> = unsafe { Container::load_self().ok() };
+
+pub fn variant_api() -> Result<&'static Container<VariantApi>> {
+    VARIANT_API.as_ref().ok_or(LVInteropError::NoLabviewApi)
+}
+
+/// A memory manager installed in place of [`MEMORY_API`], or null for the default.
+static MEMORY_MANAGER: AtomicPtr<&'static dyn MemoryManager> = AtomicPtr::new(std::ptr::null_mut());
+
+static MEMORY_API: OnceLock<MemoryApi> = OnceLock::new();
+
+/// The default memory manager functions, resolved on first use.
+///
+/// When the library isn't loaded by LabVIEW this is a pure Rust implementation
+/// so handles can still be used from other hosts and in tests.
+#[inline]
+pub fn default_memory_api() -> &'static MemoryApi {
+    MEMORY_API.get_or_init(MemoryApi::resolve)
+}
+
+/// Get the memory manager functions in use.
+#[inline]
+pub fn memory_api() -> Result<ActiveMemoryApi> {
+    let manager = MEMORY_MANAGER.load(Ordering::Acquire);
+    if manager.is_null() {
+        Ok(ActiveMemoryApi::Default(default_memory_api()))
+    } else {
+        // Safety: Only set from a leaked box in `set_memory_api`.
+        Ok(ActiveMemoryApi::Custom(unsafe { *manager }))
+    }
+}
+
+/// The memory manager in use.
+///
+/// Unless another manager has been installed this calls the default functions
+/// directly through their pointers, rather than through the `dyn MemoryManager`
+/// vtable first, as it is on the path of every handle operation.
+#[derive(Clone, Copy)]
+pub enum ActiveMemoryApi {
+    Default(&'static MemoryApi),
+    Custom(&'static dyn MemoryManager),
+}
+
+/// Call the function pointer directly for the default manager.
+macro_rules! dispatch {
+    ($api:expr, $function:ident($($arg:expr),*)) => {
+        match $api {
+            ActiveMemoryApi::Default(api) => (api.$function)($($arg),*),
+            ActiveMemoryApi::Custom(manager) => manager.$function($($arg),*),
+        }
+    };
+}
+
+impl ActiveMemoryApi {
+    /// The manager as a trait object, for the public API.
+    pub fn as_dyn(self) -> &'static dyn MemoryManager {
+        match self {
+            ActiveMemoryApi::Default(api) => api,
+            ActiveMemoryApi::Custom(manager) => manager,
+        }
+    }
+
+    pub fn is_labview(self) -> bool {
+        match self {
+            ActiveMemoryApi::Default(api) => api.labview,
+            ActiveMemoryApi::Custom(manager) => manager.is_labview(),
+        }
+    }
+
+    #[inline]
+    pub unsafe fn new_handle(self, size: usize) -> UHandleValue {
+        dispatch!(self, new_handle(size))
+    }
+
+    #[inline]
+    pub unsafe fn set_handle_size(self, handle: UHandleValue, size: usize) -> MgErr {
+        dispatch!(self, set_handle_size(handle, size))
+    }
+
+    #[inline]
+    pub unsafe fn get_handle_size(self, handle: UHandleValue) -> i32 {
+        dispatch!(self, get_handle_size(handle))
+    }
+
+    #[inline]
+    pub unsafe fn dispose_handle(self, handle: UHandleValue) -> MgErr {
+        dispatch!(self, dispose_handle(handle))
+    }
+
+    #[inline]
+    pub unsafe fn check_handle(self, handle: UHandleValue) -> MgErr {
+        dispatch!(self, check_handle(handle))
+    }
+
+    #[inline]
+    pub unsafe fn check_ptr(self, ptr: *mut c_void) -> MgErr {
+        dispatch!(self, check_ptr(ptr))
+    }
+
+    #[inline]
+    pub unsafe fn move_block(self, source: *const c_void, destination: *mut c_void, size: usize) {
+        dispatch!(self, move_block(source, destination, size))
+    }
+
+    #[inline]
+    pub unsafe fn numeric_array_resize(
+        self,
+        type_code: i32,
+        number_of_dims: i32,
+        handle_ptr: *mut UHandleValue,
+        total_new_size: usize,
+    ) -> MgErr {
+        dispatch!(
+            self,
+            numeric_array_resize(type_code, number_of_dims, handle_ptr, total_new_size)
+        )
+    }
+}
+
+/// Use `manager` for all memory manager calls from now on.
+pub(crate) fn set_memory_api(manager: &'static dyn MemoryManager) {
+    // Leaked as readers may still hold the previous one. This only happens
+    // when the manager is replaced so the leak is tiny.
+    let manager = Box::into_raw(Box::new(manager));
+    MEMORY_MANAGER.store(manager, Ordering::Release);
 }
 
 #[derive(WrapperApi)]
@@ -39,10 +179,99 @@ pub struct SyncApi {
     occur: unsafe extern "C" fn(occurance: MagicCookie) -> MgErr,
 }
 
+/// A cleanup procedure for `RTSetCleanupProc`.
+pub type CleanupProc = extern "C" fn(data: *mut c_void) -> i32;
+
 #[derive(WrapperApi)]
-pub struct MemoryApi {
+pub struct CleanupApi {
+    #[dlopen2_name = "RTSetCleanupProc"]
+    set_cleanup_proc:
+        unsafe extern "C" fn(proc: CleanupProc, data: *mut c_void, mode: i32) -> MgErr,
+}
+
+/// A file descriptor from the file manager.
+pub(crate) type FileDescriptor = *mut c_void;
+
+#[derive(WrapperApi)]
+pub struct FileApi {
+    #[dlopen2_name = "FMOpen"]
+    open: unsafe extern "C" fn(
+        fd: *mut FileDescriptor,
+        path: *mut c_void,
+        open_mode: i32,
+        deny_mode: i32,
+    ) -> MgErr,
+    #[dlopen2_name = "FMClose"]
+    close: unsafe extern "C" fn(fd: FileDescriptor) -> MgErr,
+    #[dlopen2_name = "FMRead"]
+    read: unsafe extern "C" fn(
+        fd: FileDescriptor,
+        in_count: i32,
+        out_count: *mut i32,
+        buffer: *mut u8,
+    ) -> MgErr,
+    #[dlopen2_name = "FMWrite"]
+    write: unsafe extern "C" fn(
+        fd: FileDescriptor,
+        in_count: i32,
+        out_count: *mut i32,
+        buffer: *const u8,
+    ) -> MgErr,
+    #[dlopen2_name = "FMSeek64"]
+    seek: unsafe extern "C" fn(fd: FileDescriptor, offset: i64, mode: i32) -> MgErr,
+    #[dlopen2_name = "FMTell64"]
+    tell: unsafe extern "C" fn(fd: FileDescriptor, offset: *mut i64) -> MgErr,
+    #[dlopen2_name = "FNewRefNum"]
+    new_refnum: unsafe extern "C" fn(
+        path: *mut c_void,
+        fd: FileDescriptor,
+        refnum: *mut MagicCookie,
+    ) -> MgErr,
+    #[dlopen2_name = "FRefNumToFD"]
+    refnum_to_fd: unsafe extern "C" fn(refnum: MagicCookie, fd: *mut FileDescriptor) -> MgErr,
+    #[dlopen2_name = "FTextToPath"]
+    text_to_path:
+        unsafe extern "C" fn(text: *const u8, length: i32, path: *mut *mut c_void) -> MgErr,
+    #[dlopen2_name = "FPathToAZString"]
+    path_to_string: unsafe extern "C" fn(path: *mut c_void, text: *mut UHandleValue) -> MgErr,
+    #[dlopen2_name = "FDisposePath"]
+    dispose_path: unsafe extern "C" fn(path: *mut c_void) -> MgErr,
+}
+
+/// The runtime exports behind Variant To Flattened String and Flattened
+/// String To Variant. They aren't in the documented C interface.
+#[derive(WrapperApi)]
+pub struct VariantApi {
+    #[dlopen2_name = "LvVariantFlattenExp"]
+    flatten: unsafe extern "C" fn(
+        variant: *mut c_void,
+        type_string: *mut UHandleValue,
+        data: *mut UHandleValue,
+    ) -> MgErr,
+    #[dlopen2_name = "LvVariantUnFlattenExp"]
+    unflatten: unsafe extern "C" fn(
+        type_string: UHandleValue,
+        data: UHandleValue,
+        variant: *mut UHandleValue,
+    ) -> MgErr,
+}
+
+#[derive(WrapperApi)]
+pub struct LabviewMemoryApi {
+    #[dlopen2_name = "DSNewHandle"]
+    new_handle: unsafe extern "C" fn(size: usize) -> UHandleValue,
     #[dlopen2_name = "DSSetHandleSize"]
     set_handle_size: unsafe extern "C" fn(handle: UHandleValue, size: usize) -> MgErr,
+    #[dlopen2_name = "DSGetHandleSize"]
+    get_handle_size: unsafe extern "C" fn(handle: UHandleValue) -> i32,
+    #[dlopen2_name = "DSDisposeHandle"]
+    dispose_handle: unsafe extern "C" fn(handle: UHandleValue) -> MgErr,
+    #[dlopen2_name = "DSCheckHandle"]
+    check_handle: unsafe extern "C" fn(handle: UHandleValue) -> MgErr,
+    #[dlopen2_name = "DSCheckPtr"]
+    check_ptr: unsafe extern "C" fn(ptr: *mut c_void) -> MgErr,
+    #[dlopen2_name = "MoveBlock"]
+    move_block: unsafe extern "C" fn(source: *const c_void, destination: *mut c_void, size: usize),
     #[dlopen2_name = "NumericArrayResize"]
     numeric_array_resize: unsafe extern "C" fn(
         type_code: i32,
@@ -51,3 +280,103 @@ pub struct MemoryApi {
         total_new_size: usize,
     ) -> MgErr,
 }
+
+/// The default memory manager, from LabVIEW if it is loaded.
+///
+/// The functions are looked up once and kept as plain function pointers so
+/// each call is a direct call through the pointer.
+pub struct MemoryApi {
+    labview: bool,
+    new_handle: unsafe extern "C" fn(size: usize) -> UHandleValue,
+    set_handle_size: unsafe extern "C" fn(handle: UHandleValue, size: usize) -> MgErr,
+    get_handle_size: unsafe extern "C" fn(handle: UHandleValue) -> i32,
+    dispose_handle: unsafe extern "C" fn(handle: UHandleValue) -> MgErr,
+    check_handle: unsafe extern "C" fn(handle: UHandleValue) -> MgErr,
+    check_ptr: unsafe extern "C" fn(ptr: *mut c_void) -> MgErr,
+    move_block: unsafe extern "C" fn(source: *const c_void, destination: *mut c_void, size: usize),
+    numeric_array_resize: unsafe extern "C" fn(
+        type_code: i32,
+        number_of_dims: i32,
+        handle_ptr: *mut UHandleValue,
+        total_new_size: usize,
+    ) -> MgErr,
+}
+
+impl MemoryApi {
+    /// Look up LabVIEW's functions, or use the pure Rust implementation without LabVIEW.
+    fn resolve() -> Self {
+        match unsafe { Container::<LabviewMemoryApi>::load_self() } {
+            Ok(api) => {
+                let functions = Self {
+                    labview: true,
+                    new_handle: api.new_handle,
+                    set_handle_size: api.set_handle_size,
+                    get_handle_size: api.get_handle_size,
+                    dispose_handle: api.dispose_handle,
+                    check_handle: api.check_handle,
+                    check_ptr: api.check_ptr,
+                    move_block: api.move_block,
+                    numeric_array_resize: api.numeric_array_resize,
+                };
+                // Keep the library open for as long as the pointers are used.
+                std::mem::forget(api);
+                functions
+            }
+            Err(_) => Self {
+                labview: false,
+                new_handle: fallback::new_handle,
+                set_handle_size: fallback::set_handle_size,
+                get_handle_size: fallback::get_handle_size,
+                dispose_handle: fallback::dispose_handle,
+                check_handle: fallback::check_handle,
+                check_ptr: fallback::check_ptr,
+                move_block: fallback::move_block,
+                numeric_array_resize: fallback::numeric_array_resize,
+            },
+        }
+    }
+}
+
+unsafe impl MemoryManager for MemoryApi {
+    fn is_labview(&self) -> bool {
+        self.labview
+    }
+
+    unsafe fn new_handle(&self, size: usize) -> UHandleValue {
+        (self.new_handle)(size)
+    }
+
+    unsafe fn set_handle_size(&self, handle: UHandleValue, size: usize) -> MgErr {
+        (self.set_handle_size)(handle, size)
+    }
+
+    unsafe fn get_handle_size(&self, handle: UHandleValue) -> i32 {
+        (self.get_handle_size)(handle)
+    }
+
+    unsafe fn dispose_handle(&self, handle: UHandleValue) -> MgErr {
+        (self.dispose_handle)(handle)
+    }
+
+    unsafe fn check_handle(&self, handle: UHandleValue) -> MgErr {
+        (self.check_handle)(handle)
+    }
+
+    unsafe fn move_block(&self, source: *const c_void, destination: *mut c_void, size: usize) {
+        (self.move_block)(source, destination, size)
+    }
+
+    unsafe fn check_ptr(&self, ptr: *mut c_void) -> MgErr {
+        (self.check_ptr)(ptr)
+    }
+
+    unsafe fn numeric_array_resize(
+        &self,
+        type_code: i32,
+        number_of_dims: i32,
+        handle_ptr: *mut UHandleValue,
+        total_new_size: usize,
+    ) -> MgErr {
+        (self.numeric_array_resize)(type_code, number_of_dims, handle_ptr, total_new_size)
+    }
+}
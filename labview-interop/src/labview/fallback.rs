@@ -0,0 +1,270 @@
+//! A pure Rust implementation of the memory manager functions.
+//!
+//! This is used when the library isn't loaded by LabVIEW (for example from
+//! TestStand, Python or unit tests) so handles can still be created and resized.
+//! Handles from this implementation must never be passed to LabVIEW, which
+//! can't happen when LabVIEW isn't loaded.
+//!
+//! A handle points to a master pointer which points to the data. The data
+//! block has a header holding the size so it can be reallocated and freed.
+
+use std::alloc::Layout;
+use std::ffi::c_void;
+
+use crate::errors::MgErr;
+use crate::memory::HandleValue;
+
+/// Space before the data holding its size. This keeps the data 16 byte aligned.
+const HEADER_SIZE: usize = 16;
+const BLOCK_ALIGN: usize = 16;
+
+/// The location a handle points to.
+///
+/// The handle types in this crate read the master pointer as a fat pointer for
+/// their unsized data, so the word after the data pointer is kept as the block
+/// size to bound any slice metadata read from it.
+#[repr(C)]
+struct MasterPointer {
+    data: *mut u8,
+    metadata: usize,
+}
+
+fn block_layout(size: usize) -> Option<Layout> {
+    Layout::from_size_align(HEADER_SIZE.checked_add(size)?, BLOCK_ALIGN).ok()
+}
+
+/// Allocate a zeroed block and return the data pointer.
+fn allocate_block(size: usize) -> Option<*mut u8> {
+    let layout = block_layout(size)?;
+    // Safety: the layout always includes the header so is never zero sized.
+    let block = unsafe { std::alloc::alloc_zeroed(layout) };
+    if block.is_null() {
+        return None;
+    }
+    unsafe {
+        block.cast::<usize>().write(size);
+        Some(block.add(HEADER_SIZE))
+    }
+}
+
+/// The block start and size for a data pointer.
+///
+/// # Safety
+///
+/// The pointer must come from [`allocate_block`].
+unsafe fn block_of(data: *mut u8) -> (*mut u8, usize) {
+    let block = data.sub(HEADER_SIZE);
+    (block, block.cast::<usize>().read())
+}
+
+/// Get the master pointer of a handle, if it isn't null.
+///
+/// # Safety
+///
+/// A non-null handle must come from this module.
+unsafe fn master_pointer<'a>(handle: HandleValue) -> Option<&'a mut MasterPointer> {
+    handle.as_ptr().cast::<MasterPointer>().as_mut()
+}
+
+/// Equivalent of `DSNewHandle`, allocating a new zeroed handle. Returns a
+/// null handle if the allocation fails.
+pub(super) unsafe extern "C" fn new_handle(size: usize) -> HandleValue {
+    let Some(data) = allocate_block(size) else {
+        return HandleValue::from_ptr(std::ptr::null_mut());
+    };
+    let master = Box::new(MasterPointer {
+        data,
+        metadata: size,
+    });
+    HandleValue::from_ptr(Box::into_raw(master).cast::<c_void>())
+}
+
+/// Equivalent of `DSSetHandleSize`.
+///
+/// # Safety
+///
+/// The handle must come from this module.
+pub(super) unsafe extern "C" fn set_handle_size(handle: HandleValue, size: usize) -> MgErr {
+    let Some(master) = master_pointer(handle) else {
+        return MgErr::ARGUMENT_ERROR;
+    };
+    let (block, old_size) = block_of(master.data);
+    let (Some(old_layout), Some(new_layout)) = (block_layout(old_size), block_layout(size)) else {
+        return MgErr::MEMORY_FULL;
+    };
+    let block = std::alloc::realloc(block, old_layout, new_layout.size());
+    if block.is_null() {
+        return MgErr::MEMORY_FULL;
+    }
+    block.cast::<usize>().write(size);
+    master.data = block.add(HEADER_SIZE);
+    master.metadata = size;
+    MgErr::NO_ERROR
+}
+
+/// Equivalent of `DSGetHandleSize`. Returns -1 for a null handle.
+///
+/// # Safety
+///
+/// The handle must come from this module.
+pub(super) unsafe extern "C" fn get_handle_size(handle: HandleValue) -> i32 {
+    match master_pointer(handle) {
+        Some(master) => block_of(master.data).1.try_into().unwrap_or(i32::MAX),
+        None => -1,
+    }
+}
+
+/// Equivalent of `DSDisposeHandle`.
+///
+/// # Safety
+///
+/// The handle must come from this module and not be used again.
+pub(super) unsafe extern "C" fn dispose_handle(handle: HandleValue) -> MgErr {
+    let Some(master) = master_pointer(handle) else {
+        return MgErr::ARGUMENT_ERROR;
+    };
+    let (block, size) = block_of(master.data);
+    if let Some(layout) = block_layout(size) {
+        std::alloc::dealloc(block, layout);
+    }
+    drop(Box::from_raw(master as *mut MasterPointer));
+    MgErr::NO_ERROR
+}
+
+/// Equivalent of `DSCheckHandle`.
+///
+/// Handles from other sources can't be told apart here so this only checks
+/// the handle and its data pointer aren't null.
+///
+/// # Safety
+///
+/// A non-null handle must point to a readable master pointer.
+pub(super) unsafe extern "C" fn check_handle(handle: HandleValue) -> MgErr {
+    match master_pointer(handle) {
+        Some(master) if !master.data.is_null() => MgErr::NO_ERROR,
+        _ => MgErr::ARGUMENT_ERROR,
+    }
+}
+
+/// Equivalent of `DSCheckPtr`. This only checks the pointer isn't null.
+pub(super) unsafe extern "C" fn check_ptr(ptr: *mut c_void) -> MgErr {
+    if ptr.is_null() {
+        MgErr::ARGUMENT_ERROR
+    } else {
+        MgErr::NO_ERROR
+    }
+}
+
+/// Equivalent of `MoveBlock`. The blocks may overlap.
+///
+/// # Safety
+///
+/// Both pointers must be valid for `size` bytes.
+pub(super) unsafe extern "C" fn move_block(
+    source: *const c_void,
+    destination: *mut c_void,
+    size: usize,
+) {
+    std::ptr::copy(source.cast::<u8>(), destination.cast::<u8>(), size)
+}
+
+/// The size of the elements for a numeric type code.
+fn element_size(type_code: i32) -> Option<usize> {
+    match type_code {
+        0x01 | 0x05 => Some(1),
+        0x02 | 0x06 => Some(2),
+        0x03 | 0x07 | 0x09 => Some(4),
+        0x04 | 0x08 | 0x0A | 0x0C => Some(8),
+        0x0D => Some(16),
+        _ => None,
+    }
+}
+
+/// Equivalent of `NumericArrayResize`.
+///
+/// Like LabVIEW this allocates a new handle if the handle is null. The
+/// dimensions are not updated.
+///
+/// # Safety
+///
+/// The handle must be null or come from this module.
+pub(super) unsafe extern "C" fn numeric_array_resize(
+    type_code: i32,
+    number_of_dims: i32,
+    handle_ptr: *mut HandleValue,
+    total_new_size: usize,
+) -> MgErr {
+    let (Some(element_size), Ok(dims), Some(handle)) = (
+        element_size(type_code),
+        usize::try_from(number_of_dims),
+        handle_ptr.as_mut(),
+    ) else {
+        return MgErr::ARGUMENT_ERROR;
+    };
+    // The data is aligned to the element size, up to 8 bytes.
+    let header = (dims * std::mem::size_of::<i32>()).next_multiple_of(element_size.min(8));
+    let Some(size) = total_new_size
+        .checked_mul(element_size)
+        .and_then(|data| data.checked_add(header))
+    else {
+        return MgErr::MEMORY_FULL;
+    };
+
+    if handle.as_ptr().is_null() {
+        let new_handle = new_handle(size);
+        if new_handle.as_ptr().is_null() {
+            return MgErr::MEMORY_FULL;
+        }
+        *handle = new_handle;
+        MgErr::NO_ERROR
+    } else {
+        set_handle_size(*handle, size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    unsafe fn data(handle: HandleValue) -> *mut u8 {
+        master_pointer(handle).unwrap().data
+    }
+
+    #[test]
+    fn test_handle_resize_keeps_data() {
+        unsafe {
+            let mut handle = HandleValue::from_ptr(std::ptr::null_mut());
+            assert_eq!(
+                numeric_array_resize(0x0A, 1, &mut handle, 3),
+                MgErr::NO_ERROR
+            );
+            // 4 bytes of dimensions padded to 8 then 3 doubles.
+            assert_eq!(get_handle_size(handle), 32);
+            assert_eq!(data(handle).addr() % 8, 0);
+            std::slice::from_raw_parts_mut(data(handle), 32).fill(7);
+
+            assert_eq!(set_handle_size(handle, 1024), MgErr::NO_ERROR);
+            assert_eq!(get_handle_size(handle), 1024);
+            assert!(std::slice::from_raw_parts(data(handle), 32)
+                .iter()
+                .all(|byte| *byte == 7));
+
+            assert_eq!(dispose_handle(handle), MgErr::NO_ERROR);
+        }
+    }
+
+    #[test]
+    fn test_invalid_arguments() {
+        unsafe {
+            let null = HandleValue::from_ptr(std::ptr::null_mut());
+            assert_eq!(set_handle_size(null, 8), MgErr::ARGUMENT_ERROR);
+            assert_eq!(get_handle_size(null), -1);
+            let mut handle = null;
+            assert_eq!(
+                numeric_array_resize(0x50, 1, &mut handle, 3),
+                MgErr::ARGUMENT_ERROR
+            );
+            assert!(handle.as_ptr().is_null());
+        }
+    }
+}
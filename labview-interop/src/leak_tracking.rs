@@ -0,0 +1,161 @@
+//! Tracking of the handles this library allocates, to find leaks.
+//!
+//! With the `leak-tracking` feature every handle allocated through the crate,
+//! such as owned strings, arrays and paths, is recorded with an ID and the
+//! backtrace of its allocation. The record is removed when the handle is
+//! disposed or handed to LabVIEW, so anything left is a handle Rust still owns.
+//!
+//! Long running systems can check the count with [`live_handle_count`] or call
+//! the `interop_leak_report` export from LabVIEW to get a report of every live
+//! handle. The backtraces need debug info in the library to be useful.
+//!
+//! Capturing a backtrace on every allocation is slow so this is intended for
+//! test and debug builds.
+//!
+//! # Example
+//! ```
+//! use labview_interop::leak_tracking::{leak_report, live_handle_count};
+//! use labview_interop::types::LStrOwned;
+//!
+//! let before = live_handle_count();
+//! let text = LStrOwned::from_data(b"hello").unwrap();
+//! assert_eq!(live_handle_count(), before + 1);
+//! println!("{}", leak_report());
+//! drop(text);
+//! assert_eq!(live_handle_count(), before);
+//! ```
+
+use std::backtrace::Backtrace;
+use std::collections::BTreeMap;
+use std::ffi::c_void;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, MutexGuard};
+
+use crate::errors::{LVInteropError, MgErr};
+use crate::types::LStrHandle;
+
+/// A handle allocated by the crate which hasn't been disposed.
+#[derive(Debug)]
+pub struct LiveHandle {
+    /// A sequential ID in the order of allocation.
+    pub id: u64,
+    /// The type which allocated the handle.
+    pub kind: &'static str,
+    /// Where the handle was allocated.
+    pub backtrace: String,
+}
+
+struct Allocation {
+    id: u64,
+    kind: &'static str,
+    backtrace: Backtrace,
+}
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+/// The live allocations keyed by the handle address.
+static LIVE: Mutex<BTreeMap<usize, Allocation>> = Mutex::new(BTreeMap::new());
+
+fn live() -> MutexGuard<'static, BTreeMap<usize, Allocation>> {
+    LIVE.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Record a handle the crate now owns.
+pub(crate) fn track(handle: *mut c_void, kind: &'static str) {
+    if handle.is_null() {
+        return;
+    }
+    let allocation = Allocation {
+        id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+        kind,
+        backtrace: Backtrace::force_capture(),
+    };
+    live().insert(handle as usize, allocation);
+}
+
+/// Remove a handle which has been disposed or given to LabVIEW.
+pub(crate) fn untrack(handle: *mut c_void) {
+    live().remove(&(handle as usize));
+}
+
+/// The allocation ID of a tracked handle. Addresses are reused so tests
+/// compare the ID rather than only checking the address is tracked.
+#[cfg(test)]
+pub(crate) fn tracked_id(handle: *mut c_void) -> Option<u64> {
+    live()
+        .get(&(handle as usize))
+        .map(|allocation| allocation.id)
+}
+
+/// The number of handles owned by Rust which haven't been disposed.
+pub fn live_handle_count() -> usize {
+    live().len()
+}
+
+/// Every handle owned by Rust which hasn't been disposed, oldest first.
+pub fn live_handles() -> Vec<LiveHandle> {
+    let mut handles: Vec<_> = live()
+        .values()
+        .map(|allocation| LiveHandle {
+            id: allocation.id,
+            kind: allocation.kind,
+            backtrace: allocation.backtrace.to_string(),
+        })
+        .collect();
+    handles.sort_by_key(|handle| handle.id);
+    handles
+}
+
+/// A text report of the live handles and where they were allocated.
+pub fn leak_report() -> String {
+    format_report(&live_handles())
+}
+
+fn format_report(handles: &[LiveHandle]) -> String {
+    let mut report = format!("{} live handles\n", handles.len());
+    for handle in handles {
+        let _ = write!(
+            report,
+            "\n#{} {}\n{}\n",
+            handle.id, handle.kind, handle.backtrace
+        );
+    }
+    report
+}
+
+/// Get the live handle report from LabVIEW.
+///
+/// The number of live handles is written to `count` and the report from
+/// [`leak_report`] to `report`. Configure `count` as a pointer to value and
+/// `report` as a string handle.
+///
+/// # Safety
+///
+/// * `count` must be null or point to an `i32`.
+#[no_mangle]
+pub unsafe extern "C" fn interop_leak_report(count: *mut i32, mut report: LStrHandle) -> MgErr {
+    let Some(count) = count.as_mut() else {
+        return LVInteropError::InvalidHandle.into();
+    };
+    let handles = live_handles();
+    *count = handles.len().try_into().unwrap_or(i32::MAX);
+    report.set_str(&format_report(&handles)).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tracks_until_untracked() {
+        let mut value = 0u8;
+        let handle: *mut c_void = std::ptr::addr_of_mut!(value).cast();
+        track(handle, "Test");
+        let live = live_handles();
+        let entry = live.iter().find(|live| live.kind == "Test").unwrap();
+        assert!(leak_report().contains(&format!("#{} Test", entry.id)));
+
+        untrack(handle);
+        assert!(!live_handles().iter().any(|live| live.kind == "Test"));
+    }
+}
@@ -2,10 +2,49 @@
 //! that are used for interfacing with LabVIEW, primarily
 //! calling Rust as a shared library from LabVIEW.
 
+pub mod build_stamp;
+pub mod cleanup;
+#[cfg(all(feature = "crash-dump", target_os = "windows"))]
+pub mod crash_dump;
+pub mod deferred;
+pub mod error_codes;
 pub mod errors;
+#[cfg(target_pointer_width = "64")]
+pub mod export;
+#[cfg(feature = "link")]
+pub mod file;
+pub mod format;
+pub mod instance;
 #[cfg(feature = "link")]
 mod labview;
+#[cfg(feature = "leak-tracking")]
+pub mod leak_tracking;
+pub mod lifecycle;
+#[cfg(feature = "log")]
+pub mod logging;
 pub mod memory;
+#[cfg(all(feature = "plotters", target_pointer_width = "64"))]
+pub mod plot;
+pub mod record;
+pub mod registry;
+#[cfg(feature = "tokio")]
+pub mod runtime;
+pub mod scope;
 #[cfg(feature = "sync")]
 pub mod sync;
+#[cfg(feature = "tdms")]
+pub mod tdms;
+#[cfg(feature = "tracing")]
+pub mod telemetry;
 pub mod types;
+pub mod validation;
+
+#[doc(hidden)]
+pub use ctor;
+/// The encoding library, for the `_with_encoding` string methods.
+pub use encoding_rs;
+#[cfg(feature = "macros")]
+pub use labview_interop_macros::labview_cluster;
+#[cfg(all(feature = "macros", target_pointer_width = "64"))]
+pub use labview_interop_macros::labview_export;
+pub use lifecycle::{on_load, on_unload};
@@ -0,0 +1,366 @@
+//! The lifecycle module connects the library's open and close exports
+//! to the subsystems in this crate which hold global state.
+//!
+//! Most libraries should use [`lv_lifecycle_exports!`](crate::lv_lifecycle_exports)
+//! to generate the init and cleanup exports which LabVIEW users expect to
+//! call from their open and close VIs.
+//!
+//! Global resources which should live as long as the library itself, such as
+//! loggers and registries, can instead be set up and torn down by hooks which
+//! run when LabVIEW loads and unloads the library, without any calls from the
+//! VIs. Register them with [`on_load`] and [`on_unload`], usually from
+//! [`lv_lifecycle_hooks!`](crate::lv_lifecycle_hooks).
+//!
+//! Load hooks run in the order they are registered and unload hooks in the
+//! reverse order, so something set up first is torn down last. Background work
+//! in the [`scope`] is stopped after the unload hooks.
+//!
+//! The hooks run from the platform's library constructors and destructors, so
+//! on Windows they run while the loader lock is held. They must not take locks
+//! another thread may hold while it loads a library, or wait on threads they
+//! spawn, which can't start until the loader lock is released.
+
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+
+use crate::deferred::run_deferred;
+use crate::errors::{LVInteropError, Result};
+use crate::scope::{scope, stop_scope_on_unload};
+
+/// How long cleanup waits for background work to stop.
+pub const CLEANUP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Prepare the crate's subsystems for use.
+///
+/// This is called by the generated init export before the library's own
+/// initialisation.
+pub fn library_init() -> Result<()> {
+    // A previous cleanup may have timed out and left the scope cancelled.
+    if scope().is_cancelled() && !scope().shutdown(CLEANUP_TIMEOUT) {
+        return Err(LVInteropError::ShutdownTimeout);
+    }
+    Ok(())
+}
+
+/// Tear down the crate's subsystems.
+///
+/// This is called by the generated cleanup export after the library's own cleanup.
+pub fn library_cleanup() -> Result<()> {
+    let stopped = scope().shutdown(CLEANUP_TIMEOUT);
+    // Tokio panics if the runtime is shut down from inside a runtime context.
+    #[cfg(feature = "tokio")]
+    let runtime_stopped = catch_unwind(|| crate::runtime::shutdown(CLEANUP_TIMEOUT));
+    // Run anything the background work deferred before it stopped.
+    run_deferred();
+    #[cfg(feature = "tokio")]
+    if runtime_stopped.is_err() {
+        return Err(LVInteropError::Panic("runtime shutdown panicked".into()));
+    }
+    if stopped {
+        Ok(())
+    } else {
+        Err(LVInteropError::ShutdownTimeout)
+    }
+}
+
+/// The cleanup which tears down the crate's subsystems if the VI which called
+/// the init export stops without calling the cleanup export.
+#[cfg(feature = "link")]
+static ABORT_CLEANUP: Mutex<Option<crate::cleanup::CleanupRegistration>> = Mutex::new(None);
+
+#[cfg(feature = "link")]
+fn lock_abort_cleanup() -> MutexGuard<'static, Option<crate::cleanup::CleanupRegistration>> {
+    ABORT_CLEANUP
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Run [`library_cleanup`] when the calling VI goes idle, such as when it is
+/// aborted, unless [`cancel_cleanup_on_abort`] is called first.
+#[cfg(feature = "link")]
+fn cleanup_on_abort() {
+    let registration = crate::cleanup::on_cleanup(crate::cleanup::CleanupMode::OnIdle, || {
+        lock_abort_cleanup().take();
+        let _ = library_cleanup();
+    });
+    // Outside LabVIEW there is no VI to abort.
+    if let Ok(registration) = registration {
+        let previous = lock_abort_cleanup().replace(registration);
+        if let Some(previous) = previous {
+            previous.cancel();
+        }
+    }
+}
+
+#[cfg(feature = "link")]
+fn cancel_cleanup_on_abort() {
+    let registration = lock_abort_cleanup().take();
+    if let Some(registration) = registration {
+        registration.cancel();
+    }
+}
+
+type Hook = Box<dyn FnOnce() + Send>;
+
+/// Set once the library has finished loading.
+static LOADED: AtomicBool = AtomicBool::new(false);
+static LOAD_HOOKS: Mutex<Vec<Hook>> = Mutex::new(Vec::new());
+static UNLOAD_HOOKS: Mutex<Vec<Hook>> = Mutex::new(Vec::new());
+
+fn lock_hooks(hooks: &'static Mutex<Vec<Hook>>) -> MutexGuard<'static, Vec<Hook>> {
+    hooks
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Run a hook without letting a panic unwind out of a library constructor or destructor.
+fn run_hook(hook: Hook) {
+    let _ = catch_unwind(AssertUnwindSafe(hook));
+}
+
+/// Run `hook` when the library is loaded.
+///
+/// Hooks run from the library constructors, in the order they were
+/// registered. Hooks registered by constructors which run before the crate's
+/// own are held until it runs, and the rest run as they are registered, so
+/// every hook still runs inside a constructor while the library is loading.
+/// Anything registered after loading runs straight away.
+///
+/// On Windows the loader lock is held for all of this. A hook must not take
+/// locks which another thread may hold while loading a library, or spawn a
+/// thread and wait for it, as a new thread can't start until loading has
+/// finished and the wait deadlocks. Spawning a thread which is left to run
+/// is fine.
+pub fn on_load(hook: impl FnOnce() + Send + 'static) {
+    let mut hooks = lock_hooks(&LOAD_HOOKS);
+    if LOADED.load(Ordering::Acquire) {
+        drop(hooks);
+        run_hook(Box::new(hook));
+    } else {
+        hooks.push(Box::new(hook));
+    }
+}
+
+/// Run `hook` when the library is unloaded.
+///
+/// Unload hooks run in the reverse order they were registered, before the
+/// background work in the [`scope`] is stopped.
+pub fn on_unload(hook: impl FnOnce() + Send + 'static) {
+    lock_hooks(&UNLOAD_HOOKS).push(Box::new(hook));
+}
+
+fn run_load_hooks() {
+    let hooks = {
+        let mut hooks = lock_hooks(&LOAD_HOOKS);
+        LOADED.store(true, Ordering::Release);
+        std::mem::take(&mut *hooks)
+    };
+    hooks.into_iter().for_each(run_hook);
+}
+
+fn run_unload_hooks() {
+    let hooks = std::mem::take(&mut *lock_hooks(&UNLOAD_HOOKS));
+    hooks.into_iter().rev().for_each(run_hook);
+}
+
+/// Constructors may run in any order so the load hooks registered by other
+/// constructors before this one run here, and the rest run as they register.
+#[ctor::ctor]
+fn library_loaded() {
+    run_load_hooks();
+}
+
+#[ctor::dtor]
+fn library_unloading() {
+    run_unload_hooks();
+    stop_scope_on_unload();
+}
+
+/// Register functions to run when the library is loaded and unloaded.
+///
+/// Either hook can be left out. See the [`lifecycle`](crate::lifecycle) module
+/// for when they run.
+///
+/// # Example
+/// ```
+/// use labview_interop::lv_lifecycle_hooks;
+///
+/// fn start_logging() {
+///     // Install a logger...
+/// }
+///
+/// fn flush_logs() {
+///     // Flush the logger...
+/// }
+///
+/// lv_lifecycle_hooks!(on_load = start_logging, on_unload = flush_logs);
+/// ```
+#[macro_export]
+macro_rules! lv_lifecycle_hooks {
+    (on_load = $load:path, on_unload = $unload:path $(,)?) => {
+        $crate::lv_lifecycle_hooks!(on_load = $load);
+        $crate::lv_lifecycle_hooks!(on_unload = $unload);
+    };
+    (on_load = $load:path $(,)?) => {
+        const _: () = {
+            #[$crate::ctor::ctor]
+            fn register_load_hook() {
+                $crate::lifecycle::on_load($load);
+            }
+        };
+    };
+    (on_unload = $unload:path $(,)?) => {
+        const _: () = {
+            #[$crate::ctor::ctor]
+            fn register_unload_hook() {
+                $crate::lifecycle::on_unload($unload);
+            }
+        };
+    };
+}
+
+mod exports {
+    use super::*;
+    use crate::errors::MgErr;
+    use crate::types::lv_errors::{catch_panic, report_error};
+    use crate::types::{ErrorClusterPtr, ToLvError};
+
+    /// Implementation of the generated init export.
+    ///
+    /// Initialisation is skipped if an error is wired into the cluster.
+    #[doc(hidden)]
+    pub fn run_init<E: ToLvError>(
+        error_cluster: ErrorClusterPtr,
+        init: impl FnOnce() -> std::result::Result<(), E>,
+    ) -> MgErr {
+        match unsafe { error_cluster.as_ref() } {
+            Ok(cluster) if cluster.is_error() => return cluster.code(),
+            Ok(_) => {}
+            Err(error) => return error.into(),
+        }
+
+        if let Err(error) = library_init() {
+            return report_error(error_cluster, &error);
+        }
+        match catch_panic(init) {
+            Ok(Ok(())) => {
+                #[cfg(feature = "link")]
+                cleanup_on_abort();
+                MgErr::NO_ERROR
+            }
+            Ok(Err(error)) => report_error(error_cluster, &error),
+            Err(panic) => report_error(error_cluster, &panic),
+        }
+    }
+
+    /// Implementation of the generated cleanup export.
+    ///
+    /// Like LabVIEW close functions this always runs, even with an incoming error,
+    /// but it won't overwrite an incoming error and returns its code.
+    #[doc(hidden)]
+    pub fn run_cleanup<E: ToLvError>(
+        error_cluster: ErrorClusterPtr,
+        cleanup: impl FnOnce() -> std::result::Result<(), E>,
+    ) -> MgErr {
+        let incoming_error = unsafe { error_cluster.as_ref() }
+            .ok()
+            .filter(|cluster| cluster.is_error())
+            .map(|cluster| cluster.code());
+        #[cfg(feature = "link")]
+        cancel_cleanup_on_abort();
+        let user_result = catch_panic(cleanup);
+        let library_result = library_cleanup();
+
+        if let Some(code) = incoming_error {
+            return code;
+        }
+        match (user_result, library_result) {
+            (Err(panic), _) => report_error(error_cluster, &panic),
+            (Ok(Err(error)), _) => report_error(error_cluster, &error),
+            (Ok(Ok(())), Err(error)) => report_error(error_cluster, &error),
+            (Ok(Ok(())), Ok(())) => MgErr::NO_ERROR,
+        }
+    }
+}
+
+#[doc(hidden)]
+pub use exports::{run_cleanup, run_init};
+
+/// Generate the init and cleanup exports for the library.
+///
+/// Each export takes an error cluster (as "Handles By Value") and returns
+/// the error code. The provided functions return a `Result` with any error
+/// type implementing [`ToLvError`](crate::types::ToLvError).
+///
+/// * The init export sets up the crate's subsystems then calls the init function.
+///   It does nothing if an error is wired in.
+/// * The cleanup export calls the cleanup function then stops background work
+///   started through [`scope`], and the async runtime with
+///   the `tokio` feature. It always runs, and returns the code of an error
+///   wired in rather than reporting its own.
+/// * If the VI which called the init export goes idle without calling the
+///   cleanup export, such as when it is aborted, the background work and
+///   runtime are stopped then. The cleanup function isn't called in that case.
+///
+/// # Example
+/// ```
+/// use labview_interop::lv_lifecycle_exports;
+/// use labview_interop::errors::LVInteropError;
+///
+/// fn open_devices() -> Result<(), LVInteropError> {
+///     Ok(())
+/// }
+///
+/// fn close_devices() -> Result<(), LVInteropError> {
+///     Ok(())
+/// }
+///
+/// lv_lifecycle_exports!(
+///     init = mylib_init => open_devices,
+///     cleanup = mylib_cleanup => close_devices,
+/// );
+/// ```
+#[macro_export]
+macro_rules! lv_lifecycle_exports {
+    (init = $init_export:ident => $init:path, cleanup = $cleanup_export:ident => $cleanup:path $(,)?) => {
+        #[no_mangle]
+        pub extern "C" fn $init_export(
+            error_cluster: $crate::types::ErrorClusterPtr,
+        ) -> $crate::errors::MgErr {
+            $crate::lifecycle::run_init(error_cluster, $init)
+        }
+
+        #[no_mangle]
+        pub extern "C" fn $cleanup_export(
+            error_cluster: $crate::types::ErrorClusterPtr,
+        ) -> $crate::errors::MgErr {
+            $crate::lifecycle::run_cleanup(error_cluster, $cleanup)
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_hooks_run_in_order() {
+        let order = Arc::new(Mutex::new(Vec::new()));
+
+        // The library has already loaded so this runs straight away.
+        let load_order = order.clone();
+        on_load(move || load_order.lock().unwrap().push("load"));
+        assert_eq!(*order.lock().unwrap(), vec!["load"]);
+
+        for name in ["first", "second"] {
+            let order = order.clone();
+            on_unload(move || order.lock().unwrap().push(name));
+        }
+        on_unload(|| panic!("unload panic"));
+        run_unload_hooks();
+        assert_eq!(*order.lock().unwrap(), vec!["load", "second", "first"]);
+    }
+}
@@ -0,0 +1,230 @@
+//! A [`log`] backend which posts log records to a LabVIEW user event.
+//!
+//! LabVIEW registers a user event once with the `interop_log_register` export
+//! and from then on every `log::info!`, `log::error!` and so on from Rust is
+//! posted to it as a [`LogRecord`] cluster. The event can be handled in an
+//! event structure anywhere on the diagram to display or save the messages.
+//!
+//! Configure the event data as a cluster of, in order:
+//!
+//! 1. `timestamp`: a timestamp.
+//! 2. `level`: a U16 enum of Error, Warn, Info, Debug and Trace.
+//! 3. `target`: a string, usually the Rust module path.
+//! 4. `message`: a string.
+//!
+//! Records logged before an event is registered, or above the level it was
+//! registered with, are dropped. Logging can be called from any thread.
+//!
+//! # Example
+//! ```
+//! use log::info;
+//!
+//! // Normally called from LabVIEW through `interop_log_register`.
+//! labview_interop::logging::init().unwrap();
+//!
+//! info!("Connected to {}", "COM1");
+//! ```
+
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use log::{Level, LevelFilter, Log, Metadata, Record, SetLoggerError};
+
+use crate::errors::{MgErr, Result};
+use crate::labview_layout;
+use crate::memory::DeepDispose;
+use crate::sync::LVUserEvent;
+use crate::types::{LStrHandle, LStrOwned, LVTime};
+
+labview_layout!(
+    /// The event data posted for each log record.
+    pub struct LogRecord {
+        pub timestamp: LVTime,
+        /// The level as a zero based enum: Error, Warn, Info, Debug, Trace.
+        pub level: u16,
+        pub target: LStrHandle,
+        pub message: LStrHandle,
+    }
+);
+
+impl LogRecord {
+    /// Build the cluster for a record, allocating its strings.
+    pub fn new(record: &Record) -> Result<Self> {
+        let target = LStrOwned::try_from(record.target())?;
+        let message = LStrOwned::try_from(record.args().to_string())?;
+        Ok(Self {
+            timestamp: SystemTime::now().into(),
+            level: level_index(record.level()),
+            target: target.into_raw(),
+            message: message.into_raw(),
+        })
+    }
+}
+
+impl DeepDispose for LogRecord {
+    unsafe fn deep_dispose(self) -> Result<()> {
+        let LogRecord {
+            target, message, ..
+        } = self;
+        target.deep_dispose()?;
+        message.deep_dispose()
+    }
+}
+
+/// The zero based position of the level in the LabVIEW enum.
+fn level_index(level: Level) -> u16 {
+    level as u16 - 1
+}
+
+/// The level filter for a zero based LabVIEW enum value, where anything above
+/// Trace is treated as Trace and anything negative turns logging off.
+fn level_filter(index: i32) -> LevelFilter {
+    match index {
+        ..=-1 => LevelFilter::Off,
+        0 => LevelFilter::Error,
+        1 => LevelFilter::Warn,
+        2 => LevelFilter::Info,
+        3 => LevelFilter::Debug,
+        _ => LevelFilter::Trace,
+    }
+}
+
+/// The logger posting to the registered user event.
+pub struct LabviewLogger {
+    event: Mutex<Option<LVUserEvent<LogRecord>>>,
+}
+
+static LOGGER: LabviewLogger = LabviewLogger {
+    event: Mutex::new(None),
+};
+
+impl LabviewLogger {
+    fn event(&self) -> Option<LVUserEvent<LogRecord>> {
+        *self
+            .event
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn set_event(&self, event: Option<LVUserEvent<LogRecord>>) {
+        *self
+            .event
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = event;
+    }
+}
+
+impl Log for LabviewLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level() && self.event().is_some()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let Some(event) = self.event() else {
+            return;
+        };
+        // There is nowhere to report a failure to log.
+        if let Ok(data) = LogRecord::new(record) {
+            let _ = unsafe { event.post_and_dispose(data) };
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// Install the LabVIEW logger as the global logger.
+///
+/// This is done by `interop_log_register` so only needs calling directly to
+/// install the logger before LabVIEW registers an event, such as from
+/// [`on_load`](crate::lifecycle::on_load).
+///
+/// # Errors
+///
+/// * If another logger has already been installed.
+pub fn init() -> std::result::Result<(), SetLoggerError> {
+    log::set_logger(&LOGGER)
+}
+
+/// Post log records to `event` from now on, up to `max_level`.
+///
+/// This installs the logger if it hasn't been already.
+pub fn register(event: LVUserEvent<LogRecord>, max_level: LevelFilter) {
+    // Already installed if this fails, or another logger is in use which
+    // can't be replaced either way.
+    let _ = init();
+    LOGGER.set_event(Some(event));
+    log::set_max_level(max_level);
+}
+
+/// Stop posting log records.
+pub fn unregister() {
+    LOGGER.set_event(None);
+}
+
+/// Register a user event for the log records from LabVIEW.
+///
+/// `max_level` is the most verbose level to post, as a zero based enum of
+/// Error, Warn, Info, Debug and Trace. Configure `event` as adapt to type with
+/// handles by value.
+///
+/// # Safety
+///
+/// * `event` must be null or point to a user event refnum of [`LogRecord`].
+#[no_mangle]
+pub unsafe extern "C" fn interop_log_register(
+    event: *const LVUserEvent<LogRecord>,
+    max_level: i32,
+) -> MgErr {
+    let Some(event) = event.as_ref() else {
+        return MgErr::ARGUMENT_ERROR;
+    };
+    register(*event, level_filter(max_level));
+    MgErr::NO_ERROR
+}
+
+/// Stop posting log records from LabVIEW, such as before destroying the user event.
+#[no_mangle]
+pub extern "C" fn interop_log_unregister() -> MgErr {
+    unregister();
+    MgErr::NO_ERROR
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_levels_match_enum() {
+        assert_eq!(level_index(Level::Error), 0);
+        assert_eq!(level_index(Level::Trace), 4);
+        assert_eq!(level_filter(-1), LevelFilter::Off);
+        assert_eq!(level_filter(2), LevelFilter::Info);
+        assert_eq!(level_filter(10), LevelFilter::Trace);
+    }
+
+    #[test]
+    fn test_record_cluster() {
+        let cluster = LogRecord::new(
+            &Record::builder()
+                .level(Level::Warn)
+                .target("labview::test")
+                .args(format_args!("{} volts", 5))
+                .build(),
+        )
+        .unwrap();
+        let LogRecord {
+            level,
+            target,
+            message,
+            ..
+        } = cluster;
+        assert_eq!(level, 1);
+        assert_eq!(target.as_bytes().unwrap(), b"labview::test");
+        assert_eq!(message.as_bytes().unwrap(), b"5 volts");
+        unsafe { target.deep_dispose() }.unwrap();
+        unsafe { message.deep_dispose() }.unwrap();
+    }
+}
@@ -1,9 +1,37 @@
 //! The memory module handles the LabVIEW memory manager
 //! functions and types.
 //!
-//! todo: get to reference without panics.
+//! The [`Deref`](std::ops::Deref) implementations on the pointer and handle
+//! types panic if they are null. Enable the `strict-null` feature to remove them
+//! so every access must go through the `Result` returning methods.
+//!
+//! # Threads
+//!
+//! The LabVIEW memory manager functions, such as `DSNewHandle`,
+//! `DSSetHandleSize`, `DSDisposeHandle` and `NumericArrayResize`, are thread
+//! safe and can be called from any thread, as can `PostLVUserEvent` and
+//! `Occur`. What isn't safe is two threads using the same handle at once, or
+//! using a handle LabVIEW still owns.
+//!
+//! * Handles passed in to an export belong to LabVIEW and may be reused or
+//!   disposed as soon as the call returns. Take a copy with
+//!   [`OwnedUHandle::deep_copy_of`] to use the data on another thread.
+//! * [`OwnedUHandle`] is `Send` as nothing else can reach the handle. It isn't
+//!   `Sync` as the handle can be copied out of a shared reference and written.
+//! * [`SendableHandle`] moves a raw [`UHandle`] to another thread where the
+//!   caller knows it stays valid, such as a handle allocated in Rust which is
+//!   handed back to LabVIEW once the thread is done.
+//! * Refnums, including [`LVUserEvent`](crate::sync::LVUserEvent) and
+//!   [`LVOccurrence`](crate::sync::LVOccurrence), are plain values LabVIEW
+//!   checks on use so they are `Send` and `Sync`.
+#[cfg(not(feature = "strict-null"))]
 use std::ops::{Deref, DerefMut};
 
+use std::ffi::c_void;
+use std::ptr::NonNull;
+
+#[cfg(feature = "link")]
+use crate::errors::MgErr;
 use crate::errors::{LVInteropError, Result};
 
 /// A pointer from LabVIEW for the data.
@@ -12,6 +40,20 @@ use crate::errors::{LVInteropError, Result};
 pub struct UPtr<T: ?Sized>(*mut T);
 
 impl<T: ?Sized> UPtr<T> {
+    /// Wrap a raw pointer.
+    ///
+    /// # Safety
+    ///
+    /// The pointer must be null or valid for the uses made of it.
+    pub unsafe fn from_raw(ptr: *mut T) -> Self {
+        Self(ptr)
+    }
+
+    /// The raw pointer.
+    pub fn as_ptr(&self) -> *mut T {
+        self.0
+    }
+
     /// Get a reference to the internal type. Errors if the pointer is null.
     ///
     /// # Safety
@@ -36,6 +78,7 @@ impl<T: ?Sized> UPtr<T> {
     /// * It must be “dereferenceable” in the sense defined in the module documentation.
     /// * The pointer must point to an initialized instance of T.
     /// * You must enforce Rust’s aliasing rules, since the returned lifetime 'a is arbitrarily chosen and does not necessarily reflect the actual lifetime of the data. In particular, while this reference exists, the memory the pointer points to must not get accessed (read or written) through any other pointer.
+    #[allow(clippy::mut_from_ref)]
     pub unsafe fn as_ref_mut(&self) -> Result<&mut T> {
         self.0.as_mut().ok_or(LVInteropError::InvalidHandle)
     }
@@ -45,8 +88,22 @@ impl<T: ?Sized> UPtr<T> {
         let inner_ref = unsafe { self.as_ref() };
         inner_ref.is_ok()
     }
+
+    /// Ask the memory manager whether this is a valid pointer, using `DSCheckPtr`.
+    ///
+    /// Only pointers allocated by the memory manager pass. Pointers LabVIEW
+    /// passes to data in place, such as an error cluster, are not allocations
+    /// so this isn't applied automatically.
+    #[cfg(feature = "link")]
+    pub fn check_ptr(&self) -> Result<()> {
+        if self.0.is_null() {
+            return Err(LVInteropError::InvalidHandle);
+        }
+        unsafe { crate::labview::memory_api()?.check_ptr(self.0.cast()) }.to_result(())
+    }
 }
 
+#[cfg(not(feature = "strict-null"))]
 impl<T: ?Sized> Deref for UPtr<T> {
     type Target = T;
 
@@ -58,6 +115,7 @@ impl<T: ?Sized> Deref for UPtr<T> {
     }
 }
 
+#[cfg(not(feature = "strict-null"))]
 impl<T: ?Sized> DerefMut for UPtr<T> {
     /// Deref to a mutable reference.
     ///
@@ -67,6 +125,113 @@ impl<T: ?Sized> DerefMut for UPtr<T> {
     }
 }
 
+/// A pointer from LabVIEW which is known not to be null.
+///
+/// [`UPtr`] accepts a null pointer and only reports it when the data is used.
+/// This is checked when it is created so an unwired terminal is rejected as
+/// soon as the call starts, and the accessors can't fail.
+///
+/// `Option<NonNullPtr<T>>` has the same layout as a raw pointer, with null as
+/// `None`, so it can be used directly as a parameter for a terminal which may
+/// be unwired. Otherwise convert a raw pointer or [`UPtr`] with `try_from`.
+///
+/// # Example
+/// ```
+/// use labview_interop::errors::MgErr;
+/// use labview_interop::memory::{NonNullPtr, UPtr};
+///
+/// #[no_mangle]
+/// pub extern "C" fn double_in_place(value: UPtr<f64>) -> MgErr {
+///     let Ok(mut value) = NonNullPtr::try_from(value) else {
+///         return MgErr::ARGUMENT_ERROR;
+///     };
+///     unsafe { *value.as_mut() *= 2.0 };
+///     MgErr::NO_ERROR
+/// }
+///
+/// /// The count is optional.
+/// #[no_mangle]
+/// pub extern "C" fn count_or_zero(count: Option<NonNullPtr<i32>>) -> i32 {
+///     count.map_or(0, |count| unsafe { *count.as_ref() })
+/// }
+///
+/// let mut value = 1.5;
+/// assert_eq!(double_in_place(unsafe { UPtr::from_raw(&mut value) }), MgErr::NO_ERROR);
+/// assert_eq!(value, 3.0);
+/// assert_eq!(double_in_place(unsafe { UPtr::from_raw(std::ptr::null_mut()) }), MgErr::ARGUMENT_ERROR);
+/// assert_eq!(count_or_zero(None), 0);
+/// ```
+#[repr(transparent)]
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct NonNullPtr<T: ?Sized>(NonNull<T>);
+
+impl<T: ?Sized> NonNullPtr<T> {
+    /// Wrap a pointer which has already been checked.
+    pub fn new(ptr: NonNull<T>) -> Self {
+        Self(ptr)
+    }
+
+    /// The raw pointer.
+    pub fn as_ptr(&self) -> *mut T {
+        self.0.as_ptr()
+    }
+
+    /// The pointer as a [`NonNull`].
+    pub fn as_non_null(&self) -> NonNull<T> {
+        self.0
+    }
+
+    /// Get a reference to the data.
+    ///
+    /// # Safety
+    ///
+    /// As [`NonNull::as_ref`], the pointer must be aligned, point to an
+    /// initialized `T` and the data must not be mutated while the reference
+    /// exists.
+    pub unsafe fn as_ref(&self) -> &T {
+        self.0.as_ref()
+    }
+
+    /// Get a mutable reference to the data.
+    ///
+    /// # Safety
+    ///
+    /// As [`NonNull::as_mut`], the pointer must be aligned, point to an
+    /// initialized `T` and the data must not be accessed through any other
+    /// pointer while the reference exists.
+    pub unsafe fn as_mut(&mut self) -> &mut T {
+        self.0.as_mut()
+    }
+}
+
+impl<T: ?Sized> TryFrom<*mut T> for NonNullPtr<T> {
+    type Error = LVInteropError;
+
+    /// Check the pointer isn't null, failing with
+    /// [`LVInteropError::NullPointer`] if it is.
+    fn try_from(ptr: *mut T) -> Result<Self> {
+        NonNull::new(ptr)
+            .map(Self)
+            .ok_or(LVInteropError::NullPointer)
+    }
+}
+
+impl<T: ?Sized> TryFrom<UPtr<T>> for NonNullPtr<T> {
+    type Error = LVInteropError;
+
+    /// Check the pointer isn't null, failing with
+    /// [`LVInteropError::NullPointer`] if it is.
+    fn try_from(ptr: UPtr<T>) -> Result<Self> {
+        Self::try_from(ptr.as_ptr())
+    }
+}
+
+impl<T: ?Sized> From<NonNullPtr<T>> for UPtr<T> {
+    fn from(ptr: NonNullPtr<T>) -> Self {
+        Self(ptr.as_ptr())
+    }
+}
+
 /// A handle from LabVIEW for the data.
 ///
 /// A handle is a double pointer so the underlying
@@ -76,6 +241,11 @@ impl<T: ?Sized> DerefMut for UPtr<T> {
 pub struct UHandle<T: ?Sized>(pub *mut *mut T);
 
 impl<T: ?Sized> UHandle<T> {
+    /// Whether the handle is null, which LabVIEW uses for empty strings and arrays.
+    pub fn is_null(&self) -> bool {
+        self.0.is_null()
+    }
+
     /// Get a reference to the internal type. Errors if the pointer is null.
     ///
     /// # Safety
@@ -87,10 +257,12 @@ impl<T: ?Sized> UHandle<T> {
     ///* The pointer must point to an initialized instance of T.
     ///* You must enforce Rust's aliasing rules, since the returned lifetime 'a is arbitrarily chosen and does not necessarily reflect the actual lifetime of the data. In particular, while this reference exists, the memory the pointer points to must not get mutated (except inside UnsafeCell).
     pub unsafe fn as_ref(&self) -> Result<&T> {
-        self.0
-            .as_ref()
-            .and_then(|ptr| ptr.as_ref())
-            .ok_or(LVInteropError::InvalidHandle)
+        if self.0.is_null() {
+            return Err(LVInteropError::InvalidHandle);
+        }
+        // The check must be made before the master pointer is read.
+        self.paranoid_check()?;
+        (*self.0).as_ref().ok_or(LVInteropError::InvalidHandle)
     }
 
     /// Get a mutable reference to the internal type. Errors if handle contains a null.
@@ -103,11 +275,24 @@ impl<T: ?Sized> UHandle<T> {
     /// * It must be “dereferenceable” in the sense defined in the module documentation.
     /// * The pointer must point to an initialized instance of T.
     /// * You must enforce Rust’s aliasing rules, since the returned lifetime 'a is arbitrarily chosen and does not necessarily reflect the actual lifetime of the data. In particular, while this reference exists, the memory the pointer points to must not get accessed (read or written) through any other pointer.
+    #[allow(clippy::mut_from_ref)]
     pub unsafe fn as_ref_mut(&self) -> Result<&mut T> {
-        self.0
-            .as_ref()
-            .and_then(|ptr| ptr.as_mut())
-            .ok_or(LVInteropError::InvalidHandle)
+        if self.0.is_null() {
+            return Err(LVInteropError::InvalidHandle);
+        }
+        // The check must be made before the master pointer is read.
+        self.paranoid_check()?;
+        (*self.0).as_mut().ok_or(LVInteropError::InvalidHandle)
+    }
+
+    /// At the paranoid [validation level](crate::validation) check the handle
+    /// with the memory manager before it is used.
+    fn paranoid_check(&self) -> Result<()> {
+        #[cfg(feature = "link")]
+        if crate::validation::validation_enabled(crate::validation::ValidationLevel::Paranoid) {
+            return self.check_handle();
+        }
+        Ok(())
     }
 
     /// Check the validity of the handle to ensure it wont panic later.
@@ -117,6 +302,7 @@ impl<T: ?Sized> UHandle<T> {
     }
 }
 
+#[cfg(not(feature = "strict-null"))]
 impl<T: ?Sized> Deref for UHandle<T> {
     type Target = T;
 
@@ -128,6 +314,7 @@ impl<T: ?Sized> Deref for UHandle<T> {
     }
 }
 
+#[cfg(not(feature = "strict-null"))]
 impl<T: ?Sized> DerefMut for UHandle<T> {
     /// Deref to a mutable reference.
     ///
@@ -137,6 +324,40 @@ impl<T: ?Sized> DerefMut for UHandle<T> {
     }
 }
 
+/// An untyped handle as passed to the memory manager functions.
+///
+/// This keeps the handle as a pointer rather than an integer so the pointer
+/// provenance is preserved, keeping the crate sound under strict provenance.
+#[repr(transparent)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct HandleValue(*mut c_void);
+
+impl HandleValue {
+    /// Wrap a raw handle pointer.
+    pub fn from_ptr(ptr: *mut c_void) -> Self {
+        Self(ptr)
+    }
+
+    /// The raw handle pointer.
+    pub fn as_ptr(self) -> *mut c_void {
+        self.0
+    }
+}
+
+impl<T: ?Sized> UHandle<T> {
+    /// The untyped value of the handle for the memory manager.
+    pub(crate) fn handle_value(&self) -> HandleValue {
+        HandleValue(self.0.cast())
+    }
+
+    /// A pointer to this handle as an untyped handle, for memory manager
+    /// functions which may replace the handle.
+    pub(crate) fn handle_value_ptr(&mut self) -> *mut HandleValue {
+        // UHandle is a transparent wrapper of a single pointer so has the same layout.
+        (self as *mut Self).cast()
+    }
+}
+
 #[cfg(feature = "link")]
 impl<T: ?Sized> UHandle<T> {
     /// Resize the handle to the desired size.
@@ -145,8 +366,595 @@ impl<T: ?Sized> UHandle<T> {
     ///
     /// * The handle must be valid.
     pub unsafe fn resize(&mut self, desired_size: usize) -> Result<()> {
-        let err = crate::labview::memory_api()?.set_handle_size(self.0 as usize, desired_size);
-        err.to_result(())
+        let size = crate::validation::canary::allocation_size(desired_size);
+        let err = crate::labview::memory_api()?.set_handle_size(self.handle_value(), size);
+        err.to_result(())?;
+        crate::validation::canary::write(self.handle_value(), desired_size, size);
+        Ok(())
+    }
+
+    /// Ask the memory manager whether this is a valid handle, using `DSCheckHandle`.
+    ///
+    /// This catches stale handles and handles from the wrong zone before they
+    /// are used, where otherwise LabVIEW would crash or its memory would be
+    /// corrupted. It is made automatically at the paranoid
+    /// [validation level](crate::validation).
+    ///
+    /// Without LabVIEW this only checks the handle isn't null.
+    pub fn check_handle(&self) -> Result<()> {
+        if self.0.is_null() {
+            return Err(LVInteropError::InvalidHandle);
+        }
+        unsafe { crate::labview::memory_api()?.check_handle(self.handle_value()) }.to_result(())
+    }
+
+    /// Get the size of the memory currently allocated to the handle.
+    ///
+    /// This can be larger than the size of the data it currently holds.
+    ///
+    /// # Safety
+    ///
+    /// * The handle must be valid.
+    pub unsafe fn handle_size(&self) -> Result<usize> {
+        let size = crate::labview::memory_api()?.get_handle_size(self.handle_value());
+        size.try_into().map_err(|_| LVInteropError::InvalidHandle)
+    }
+}
+
+/// Check whether the memory manager functions come from LabVIEW.
+///
+/// When the library is loaded by another host a pure Rust implementation is
+/// used instead so strings, arrays and errors still work.
+#[cfg(feature = "link")]
+pub fn is_labview_memory_manager() -> bool {
+    crate::labview::memory_api().is_ok_and(|api| api.is_labview())
+}
+
+/// The memory manager functions every handle in the crate is allocated,
+/// resized and disposed through.
+///
+/// By default these are LabVIEW's functions, or a pure Rust implementation
+/// when the library isn't loaded by LabVIEW. Another implementation can be
+/// installed with [`set_memory_manager`], for example a mock for tests or a
+/// wrapper around [`default_memory_manager`] which logs or counts the calls.
+///
+/// The methods match the LabVIEW functions of the same names and return the
+/// same error codes.
+///
+/// # Safety
+///
+/// Implementations must behave as the LabVIEW memory manager does: handles
+/// point to a pointer to the data, which stays valid until the handle is
+/// resized or disposed, and new or grown memory is usable for the size asked
+/// for. The crate relies on this for soundness.
+///
+/// # Example
+/// ```
+/// use labview_interop::memory::{
+///     default_memory_manager, set_memory_manager, HandleValue, MemoryManager,
+/// };
+/// use labview_interop::errors::MgErr;
+/// use std::ffi::c_void;
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+///
+/// /// Counts allocations and passes everything to the default.
+/// struct Counting(AtomicUsize);
+///
+/// unsafe impl MemoryManager for Counting {
+///     unsafe fn new_handle(&self, size: usize) -> HandleValue {
+///         self.0.fetch_add(1, Ordering::Relaxed);
+///         default_memory_manager().new_handle(size)
+///     }
+///     unsafe fn set_handle_size(&self, handle: HandleValue, size: usize) -> MgErr {
+///         default_memory_manager().set_handle_size(handle, size)
+///     }
+///     unsafe fn get_handle_size(&self, handle: HandleValue) -> i32 {
+///         default_memory_manager().get_handle_size(handle)
+///     }
+///     unsafe fn dispose_handle(&self, handle: HandleValue) -> MgErr {
+///         default_memory_manager().dispose_handle(handle)
+///     }
+///     unsafe fn check_handle(&self, handle: HandleValue) -> MgErr {
+///         default_memory_manager().check_handle(handle)
+///     }
+///     unsafe fn check_ptr(&self, ptr: *mut c_void) -> MgErr {
+///         default_memory_manager().check_ptr(ptr)
+///     }
+///     unsafe fn numeric_array_resize(
+///         &self,
+///         type_code: i32,
+///         number_of_dims: i32,
+///         handle_ptr: *mut HandleValue,
+///         total_new_size: usize,
+///     ) -> MgErr {
+///         default_memory_manager().numeric_array_resize(
+///             type_code,
+///             number_of_dims,
+///             handle_ptr,
+///             total_new_size,
+///         )
+///     }
+///     fn is_labview(&self) -> bool {
+///         default_memory_manager().is_labview()
+///     }
+/// }
+///
+/// static COUNTING: Counting = Counting(AtomicUsize::new(0));
+/// // Safety: this passes every call on to the default manager, so handles
+/// // allocated before it was installed are still handled by the same manager.
+/// unsafe { set_memory_manager(&COUNTING) };
+///
+/// let text = labview_interop::types::LStrOwned::from_data(b"counted").unwrap();
+/// assert_eq!(COUNTING.0.load(Ordering::Relaxed), 1);
+/// ```
+#[cfg(feature = "link")]
+pub unsafe trait MemoryManager: Send + Sync {
+    /// Allocate a new handle of `size` bytes, as `DSNewHandle`. Returns a null
+    /// handle if the allocation fails.
+    ///
+    /// # Safety
+    ///
+    /// * The handle must be disposed through the same memory manager.
+    unsafe fn new_handle(&self, size: usize) -> HandleValue;
+
+    /// Resize a handle, as `DSSetHandleSize`.
+    ///
+    /// # Safety
+    ///
+    /// * The handle must be valid.
+    unsafe fn set_handle_size(&self, handle: HandleValue, size: usize) -> MgErr;
+
+    /// The size of the memory allocated to a handle, as `DSGetHandleSize`.
+    ///
+    /// # Safety
+    ///
+    /// * The handle must be valid.
+    unsafe fn get_handle_size(&self, handle: HandleValue) -> i32;
+
+    /// Dispose a handle, as `DSDisposeHandle`.
+    ///
+    /// # Safety
+    ///
+    /// * The handle must be valid and not used again.
+    unsafe fn dispose_handle(&self, handle: HandleValue) -> MgErr;
+
+    /// Check a handle came from this memory manager, as `DSCheckHandle`.
+    ///
+    /// # Safety
+    ///
+    /// * The handle may be invalid but must be safe to pass to the memory manager.
+    unsafe fn check_handle(&self, handle: HandleValue) -> MgErr;
+
+    /// Check a pointer came from this memory manager, as `DSCheckPtr`.
+    ///
+    /// # Safety
+    ///
+    /// * The pointer may be invalid but must be safe to pass to the memory manager.
+    unsafe fn check_ptr(&self, ptr: *mut c_void) -> MgErr;
+
+    /// Copy `size` bytes from `source` to `destination`, which may overlap, as `MoveBlock`.
+    ///
+    /// # Safety
+    ///
+    /// * Both pointers must be valid for `size` bytes.
+    unsafe fn move_block(&self, source: *const c_void, destination: *mut c_void, size: usize) {
+        std::ptr::copy(source.cast::<u8>(), destination.cast::<u8>(), size)
+    }
+
+    /// Resize a numeric array, allocating it if the handle is null, as `NumericArrayResize`.
+    ///
+    /// # Safety
+    ///
+    /// * `handle_ptr` must point to a null or valid array handle.
+    unsafe fn numeric_array_resize(
+        &self,
+        type_code: i32,
+        number_of_dims: i32,
+        handle_ptr: *mut HandleValue,
+        total_new_size: usize,
+    ) -> MgErr;
+
+    /// True if these are LabVIEW's functions, so the handles can be given to LabVIEW.
+    fn is_labview(&self) -> bool {
+        false
+    }
+}
+
+/// The memory manager used when no other has been installed: LabVIEW's
+/// functions, or the pure Rust implementation outside LabVIEW.
+#[cfg(feature = "link")]
+pub fn default_memory_manager() -> &'static dyn MemoryManager {
+    crate::labview::default_memory_api()
+}
+
+/// Use `manager` for every memory manager call from now on.
+///
+/// # Safety
+///
+/// Handles must be resized and disposed by the memory manager which allocated
+/// them. Either no handles which are still alive may have been allocated by the
+/// previous manager, or `manager` must pass the calls for them on to it, for
+/// example to [`default_memory_manager`].
+#[cfg(feature = "link")]
+pub unsafe fn set_memory_manager(manager: &'static dyn MemoryManager) {
+    crate::labview::set_memory_api(manager);
+}
+
+/// The memory manager in use.
+#[cfg(feature = "link")]
+pub fn memory_manager() -> &'static dyn MemoryManager {
+    // This never fails now there is always a fallback.
+    crate::labview::memory_api().map_or_else(|_| default_memory_manager(), |api| api.as_dyn())
+}
+
+/// Data which may own handles that must be disposed along with it, such as a
+/// string handle or a cluster containing arrays.
+///
+/// Disposing a handle disposes the handles inside its data first, as LabVIEW
+/// does when it disposes a wire. Numeric types own nothing so are no-ops.
+///
+/// For clusters this can be derived with the `macros` feature, disposing each
+/// field in turn. Fields starting with an underscore are padding so are skipped.
+#[cfg(feature = "link")]
+pub trait DeepDispose: Sized {
+    /// Dispose every handle in the value, including the value itself if it is a handle.
+    ///
+    /// # Safety
+    ///
+    /// * The handles must be owned by the caller.
+    /// * The value must not be used again.
+    unsafe fn deep_dispose(self) -> Result<()>;
+}
+
+#[cfg(all(feature = "link", feature = "macros"))]
+pub use labview_interop_macros::DeepDispose;
+
+/// Data which can be copied along with the handles it contains, as LabVIEW
+/// copies a wire.
+///
+/// This allows data passed in from LabVIEW to be kept after the call returns,
+/// or written into an output without sharing handles with an input.
+///
+/// For clusters this can be derived with the `macros` feature, copying each
+/// field in turn. Fields starting with an underscore are padding so are copied
+/// as they are.
+#[cfg(feature = "link")]
+pub trait DeepCopy: DeepDispose {
+    /// Make a copy with new handles for everything it contains.
+    ///
+    /// # Safety
+    ///
+    /// * The handles in the value must be valid.
+    unsafe fn deep_copy(&self) -> Result<Self>;
+
+    /// Replace the value with a copy of `source`.
+    ///
+    /// The handles already in the value are resized and reused where they can
+    /// be, so an output handle from LabVIEW stays the same handle. The default
+    /// implementation disposes the current value and replaces it with
+    /// [`DeepCopy::deep_copy`].
+    ///
+    /// # Safety
+    ///
+    /// * The handles in both values must be valid.
+    /// * The handles in this value must be owned by the caller.
+    unsafe fn deep_copy_from(&mut self, source: &Self) -> Result<()> {
+        let copy = source.deep_copy()?;
+        std::mem::replace(self, copy).deep_dispose()
+    }
+}
+
+#[cfg(all(feature = "link", feature = "macros"))]
+pub use labview_interop_macros::DeepCopy;
+
+/// The data a handle points to, which may own further handles.
+///
+/// This is how [`OwnedUHandle`] and [`DeepDispose`] for handles dispose the
+/// contents of the handle before the handle itself.
+///
+/// It is implemented for the numeric, boolean, complex and timestamp types,
+/// strings and arrays. Deriving [`DeepDispose`] for a cluster implements it
+/// too. Without the `macros` feature, implement it by disposing a copy of the
+/// cluster read out with [`std::ptr::read`].
+#[cfg(feature = "link")]
+pub trait DisposeContents {
+    /// Dispose the handles owned by the data, leaving the data to be disposed.
+    ///
+    /// # Safety
+    ///
+    /// * The handles must be owned by the caller.
+    /// * The contents must not be used again.
+    unsafe fn dispose_contents(&mut self) -> Result<()>;
+}
+
+/// Plain data owns no handles so there is nothing to dispose. Clusters
+/// deriving [`DeepDispose`] get an implementation disposing their fields.
+#[cfg(feature = "link")]
+macro_rules! plain_contents {
+    ($($type:ty),+) => {
+        $(
+            impl DisposeContents for $type {
+                unsafe fn dispose_contents(&mut self) -> Result<()> {
+                    Ok(())
+                }
+            }
+        )+
+    };
+}
+
+#[cfg(feature = "link")]
+plain_contents!(
+    i8,
+    i16,
+    i32,
+    i64,
+    u8,
+    u16,
+    u32,
+    u64,
+    f32,
+    f64,
+    bool,
+    crate::types::LVBool,
+    crate::types::LVComplexSingle,
+    crate::types::LVComplexDouble,
+    crate::types::timestamp::LVTime
+);
+
+#[cfg(feature = "link")]
+impl<T: ?Sized + DisposeContents> DeepDispose for UHandle<T> {
+    /// Dispose the contents then the handle. A null handle is empty so is skipped.
+    unsafe fn deep_dispose(self) -> Result<()> {
+        if self.is_null() {
+            return Ok(());
+        }
+        if let Ok(contents) = self.as_ref_mut() {
+            contents.dispose_contents()?;
+        }
+        crate::validation::canary::forget(self.handle_value());
+        #[cfg(feature = "leak-tracking")]
+        crate::leak_tracking::untrack(self.0.cast());
+        crate::labview::memory_api()?
+            .dispose_handle(self.handle_value())
+            .to_result(())
+    }
+}
+
+/// A handle allocated by this library which is disposed when dropped.
+///
+/// Handles passed in from LabVIEW are owned by LabVIEW so use [`UHandle`].
+/// This dereferences to the [`UHandle`] so all the handle methods are available.
+///
+/// Handles inside the data, such as the strings in an array of strings, are
+/// disposed too through [`DisposeContents`].
+///
+/// # Returning handles to LabVIEW
+///
+/// LabVIEW takes ownership of a handle written to an output, either a
+/// pointer to handle parameter or a handle field in an output cluster or
+/// array. Use [`OwnedUHandle::into_lv_owned`] or
+/// [`OwnedUHandle::replace_output`] to hand it over without it being
+/// disposed on drop.
+///
+/// LabVIEW doesn't take ownership of posted event data, it copies it. Post a
+/// handle with [`LVUserEvent::post`](crate::sync::LVUserEvent::post) and
+/// leave it owned here so it is disposed as normal.
+///
+/// ```
+/// use labview_interop::errors::MgErr;
+/// use labview_interop::memory::UPtr;
+/// use labview_interop::types::{LStrHandle, LStrOwned};
+///
+/// /// The output string is configured as a pointer to handle.
+/// #[no_mangle]
+/// pub extern "C" fn read_name(output: UPtr<LStrHandle>) -> MgErr {
+///     let Ok(output) = (unsafe { output.as_ref_mut() }) else {
+///         return MgErr::ARGUMENT_ERROR;
+///     };
+///     let name = match LStrOwned::try_from("Sensor 1") {
+///         Ok(name) => name,
+///         Err(error) => return error.into(),
+///     };
+///     unsafe { name.replace_output(output) }.into()
+/// }
+/// ```
+#[cfg(feature = "link")]
+#[repr(transparent)]
+#[derive(Debug)]
+pub struct OwnedUHandle<T: ?Sized + DisposeContents>(UHandle<T>);
+
+#[cfg(feature = "link")]
+impl<T: ?Sized + DisposeContents> OwnedUHandle<T> {
+    /// Take ownership of a handle so it is disposed when dropped.
+    ///
+    /// # Safety
+    ///
+    /// * The handle must have been allocated by the LabVIEW memory manager.
+    /// * Nothing else may dispose of the handle.
+    pub unsafe fn from_raw(handle: UHandle<T>) -> Self {
+        #[cfg(feature = "leak-tracking")]
+        crate::leak_tracking::track(handle.0.cast(), std::any::type_name::<T>());
+        Self(handle)
+    }
+
+    /// Allocate a new handle of `size` bytes.
+    pub(crate) fn allocate(size: usize) -> Result<Self> {
+        let handle = unsafe { crate::labview::memory_api()?.new_handle(size) };
+        if handle.as_ptr().is_null() {
+            return Err(crate::errors::MgErr::MEMORY_FULL.into());
+        }
+        #[cfg(feature = "leak-tracking")]
+        crate::leak_tracking::track(handle.as_ptr(), std::any::type_name::<T>());
+        Ok(Self(UHandle(handle.as_ptr().cast())))
+    }
+
+    /// Take a copy of a handle and everything in it, such as an input from
+    /// LabVIEW which needs to outlive the call.
+    ///
+    /// # Safety
+    ///
+    /// * The handle must be null or valid.
+    pub unsafe fn deep_copy_of(handle: &UHandle<T>) -> Result<Self>
+    where
+        UHandle<T>: DeepCopy,
+    {
+        Ok(Self::from_raw(handle.deep_copy()?))
+    }
+
+    /// Release ownership of the handle without disposing it.
+    ///
+    /// Use [`OwnedUHandle::into_lv_owned`] when the handle is returned to
+    /// LabVIEW so the transfer is clear in the code.
+    pub fn into_raw(self) -> UHandle<T> {
+        let handle = UHandle(self.0 .0);
+        #[cfg(feature = "leak-tracking")]
+        crate::leak_tracking::untrack(handle.0.cast());
+        std::mem::forget(self);
+        handle
+    }
+
+    /// Transfer ownership of the handle to LabVIEW, such as to store it in an
+    /// output cluster or array.
+    ///
+    /// The handle is no longer disposed on drop. LabVIEW disposes it once it
+    /// owns the output, so it must be written to one before the call returns
+    /// or it leaks.
+    pub fn into_lv_owned(self) -> UHandle<T> {
+        self.into_raw()
+    }
+
+    /// Transfer ownership of the handle to LabVIEW through an output
+    /// parameter, disposing the handle LabVIEW passed in.
+    ///
+    /// This is for outputs configured as a pointer to handle, where LabVIEW
+    /// passes its current handle, or null, and reads back the one left there.
+    ///
+    /// # Safety
+    ///
+    /// * `output` must be null or a valid handle owned by LabVIEW for this call.
+    pub unsafe fn replace_output(self, output: &mut UHandle<T>) -> Result<()> {
+        let previous = std::mem::replace(output, self.into_lv_owned());
+        previous.deep_dispose()
+    }
+}
+
+#[cfg(feature = "link")]
+impl<T: ?Sized + DisposeContents> std::ops::Deref for OwnedUHandle<T> {
+    type Target = UHandle<T>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+#[cfg(feature = "link")]
+impl<T: ?Sized + DisposeContents> std::ops::DerefMut for OwnedUHandle<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+#[cfg(feature = "link")]
+impl<T: ?Sized + DisposeContents> Drop for OwnedUHandle<T> {
+    fn drop(&mut self) {
+        // A null handle was never allocated.
+        if self.0 .0.is_null() {
+            return;
+        }
+        #[cfg(feature = "leak-tracking")]
+        crate::leak_tracking::untrack(self.0 .0.cast());
+        // Nothing can be done about a failure while dropping.
+        let _ = unsafe { UHandle(self.0 .0).deep_dispose() };
+    }
+}
+
+/// Safety: the memory manager functions can be called from any thread and
+/// the owned handle isn't shared with LabVIEW.
+#[cfg(feature = "link")]
+unsafe impl<T: ?Sized + DisposeContents + Send> Send for OwnedUHandle<T> {}
+
+/// A [`UHandle`] which can be moved to another thread.
+///
+/// A raw handle isn't `Send` as the compiler can't know who else is using it.
+/// This asserts that the handle stays valid and is only used from one thread
+/// at a time for as long as the wrapper exists, so a worker thread can fill a
+/// handle without a hand written `unsafe impl Send`. See the [module
+/// documentation](self#threads) for what is safe to call from the thread.
+///
+/// Prefer [`OwnedUHandle`] when the handle is allocated in Rust and disposed
+/// there, which is `Send` already.
+///
+/// # Example
+/// ```
+/// use labview_interop::memory::SendableHandle;
+/// use labview_interop::types::LStrOwned;
+///
+/// let string = LStrOwned::from_data(b"").unwrap().into_raw();
+/// // The handle is only used by the thread until it is joined.
+/// let sendable = unsafe { SendableHandle::new(string) };
+/// let worker = std::thread::spawn(move || {
+///     let mut handle = sendable.into_inner();
+///     handle.set(b"filled in the background").unwrap();
+///     unsafe { SendableHandle::new(handle) }
+/// });
+/// let string = worker.join().unwrap().into_inner();
+/// assert_eq!(string.as_bytes().unwrap(), b"filled in the background");
+/// drop(unsafe { LStrOwned::from_raw(string) });
+/// ```
+#[repr(transparent)]
+#[derive(PartialEq, Eq, Debug)]
+pub struct SendableHandle<T: ?Sized>(UHandle<T>);
+
+impl<T: ?Sized> SendableHandle<T> {
+    /// Wrap a handle to move it to another thread.
+    ///
+    /// # Safety
+    ///
+    /// * The handle must be null or allocated by the memory manager, and stay
+    ///   valid until it is unwrapped with [`SendableHandle::into_inner`].
+    /// * Nothing else may use the handle while it is wrapped. LabVIEW must not
+    ///   own it, so it can't be an input to the current call unless the call
+    ///   waits for the thread to finish with it.
+    pub unsafe fn new(handle: UHandle<T>) -> Self {
+        Self(handle)
+    }
+
+    /// The handle, on the thread it was moved to.
+    pub fn into_inner(self) -> UHandle<T> {
+        self.0
+    }
+}
+
+/// Safety: the caller of `new` guarantees the handle is valid and unshared,
+/// and the memory manager functions can be called from any thread.
+unsafe impl<T: ?Sized + Send> Send for SendableHandle<T> {}
+
+/// Serializes as the handle it owns.
+#[cfg(all(feature = "serde", feature = "link"))]
+impl<T: ?Sized + DisposeContents> serde::Serialize for OwnedUHandle<T>
+where
+    UHandle<T>: serde::Serialize,
+{
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        serde::Serialize::serialize(&self.0, serializer)
+    }
+}
+
+/// Takes ownership of the handle allocated by deserializing.
+#[cfg(all(feature = "serde", feature = "link"))]
+impl<'de, T: ?Sized + DisposeContents> serde::Deserialize<'de> for OwnedUHandle<T>
+where
+    UHandle<T>: serde::Deserialize<'de>,
+{
+    fn deserialize<D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Self, D::Error> {
+        let handle = <UHandle<T> as serde::Deserialize>::deserialize(deserializer)?;
+        // Safety: deserializing allocates a new handle owned by the caller.
+        Ok(unsafe { Self::from_raw(handle) })
     }
 }
 
@@ -155,3 +963,217 @@ impl<T: ?Sized> UHandle<T> {
 #[repr(transparent)]
 #[doc(hidden)]
 pub struct MagicCookie(u32);
+
+impl MagicCookie {
+    /// The cookie LabVIEW uses for "not a refnum".
+    pub const NULL: MagicCookie = MagicCookie(0);
+
+    pub const fn from_raw(value: u32) -> Self {
+        Self(value)
+    }
+
+    pub const fn as_raw(self) -> u32 {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "link")]
+    #[test]
+    fn test_default_memory_manager_outside_labview() {
+        assert!(!default_memory_manager().is_labview());
+        assert!(!memory_manager().is_labview());
+        assert!(!is_labview_memory_manager());
+    }
+
+    #[test]
+    fn test_non_null_ptr_rejects_null() {
+        let null: UPtr<i32> = unsafe { UPtr::from_raw(std::ptr::null_mut()) };
+        assert!(matches!(
+            NonNullPtr::try_from(null),
+            Err(LVInteropError::NullPointer)
+        ));
+
+        let mut value = 3;
+        let mut ptr = NonNullPtr::try_from(&mut value as *mut i32).unwrap();
+        unsafe { *ptr.as_mut() += 1 };
+        assert_eq!(unsafe { *UPtr::from(ptr).as_ref().unwrap() }, 4);
+        assert_eq!(
+            std::mem::size_of::<Option<NonNullPtr<i32>>>(),
+            std::mem::size_of::<*mut i32>()
+        );
+    }
+
+    #[test]
+    fn test_handle_value_preserves_pointer() {
+        let mut value = 42i32;
+        let mut pointer: *mut i32 = &mut value;
+        let mut handle = UHandle(&mut pointer);
+
+        assert_eq!(handle.handle_value().as_ptr(), handle.0.cast());
+        let through_value = unsafe { *handle.handle_value_ptr().cast::<*mut *mut i32>() };
+        assert_eq!(unsafe { **through_value }, 42);
+        assert_eq!(unsafe { *handle.as_ref().unwrap() }, 42);
+    }
+
+    #[cfg(feature = "link")]
+    #[test]
+    fn test_sendable_handle_moves_to_thread() {
+        fn assert_send<T: Send>() {}
+        assert_send::<SendableHandle<crate::types::string::LStr>>();
+        assert_send::<OwnedUHandle<crate::types::string::LStr>>();
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<crate::sync::LVUserEvent<*mut u8>>();
+        assert_send_sync::<crate::sync::LVOccurrence>();
+
+        let owned = crate::types::LStrOwned::from_data(b"").unwrap();
+        let sendable = unsafe { SendableHandle::new(UHandle(owned.0 .0)) };
+        std::thread::spawn(move || {
+            let mut handle = sendable.into_inner();
+            handle.set(b"from a thread").unwrap();
+        })
+        .join()
+        .unwrap();
+        assert_eq!(owned.as_bytes().unwrap(), b"from a thread");
+    }
+
+    #[cfg(feature = "link")]
+    #[test]
+    fn test_replace_output_transfers_ownership() {
+        use crate::types::{LStrHandle, LStrOwned};
+
+        let mut output: LStrHandle = UHandle(std::ptr::null_mut());
+        let first = LStrOwned::from_data(b"first").unwrap();
+        unsafe { first.replace_output(&mut output) }.unwrap();
+        assert_eq!(output.as_bytes().unwrap(), b"first");
+
+        // The previous handle is disposed when it is replaced.
+        let second = LStrOwned::from_data(b"second").unwrap();
+        unsafe { second.replace_output(&mut output) }.unwrap();
+        assert_eq!(output.as_bytes().unwrap(), b"second");
+        unsafe { output.deep_dispose() }.unwrap();
+    }
+
+    #[cfg(feature = "link")]
+    #[test]
+    fn test_owned_handle_round_trip() {
+        use crate::types::{LStrHandle, LStrOwned};
+
+        let mut owned = LStrOwned::from_data(b"abc").unwrap();
+        owned.set(b"round trip").unwrap();
+
+        // Releasing and taking back ownership keeps the same handle and data.
+        let raw = owned.into_raw();
+        let owned = unsafe { LStrOwned::from_raw(UHandle(raw.0)) };
+        assert_eq!(owned.0 .0, raw.0);
+        assert_eq!(owned.as_bytes().unwrap(), b"round trip");
+
+        // A null handle is never disposed.
+        let null: LStrHandle = UHandle(std::ptr::null_mut());
+        drop(unsafe { LStrOwned::from_raw(null) });
+    }
+
+    #[cfg(feature = "link")]
+    #[test]
+    fn test_owned_handle_of_plain_data() {
+        let owned = OwnedUHandle::<f64>::allocate(std::mem::size_of::<f64>()).unwrap();
+        unsafe { *owned.as_ref_mut().unwrap() = 2.0 };
+        assert_eq!(unsafe { *owned.as_ref().unwrap() }, 2.0);
+    }
+
+    #[cfg(feature = "leak-tracking")]
+    #[test]
+    fn test_owned_array_disposes_cluster_handles() {
+        use crate::leak_tracking::live_handles;
+        use crate::types::{LStrHandle, LStrOwned, LVArrayOwned};
+
+        // Implemented as `#[derive(DeepDispose)]` does for a cluster.
+        #[repr(C)]
+        struct Channel {
+            name: LStrHandle,
+            gain: f64,
+        }
+
+        impl DeepDispose for Channel {
+            unsafe fn deep_dispose(self) -> Result<()> {
+                self.name.deep_dispose()?;
+                self.gain.deep_dispose()
+            }
+        }
+
+        impl DisposeContents for Channel {
+            unsafe fn dispose_contents(&mut self) -> Result<()> {
+                std::ptr::read(self).deep_dispose()
+            }
+        }
+
+        unsafe impl crate::types::array::ArrayElement for Channel {}
+
+        let channel = || {
+            let name = LStrOwned::from_data(b"ai").unwrap();
+            let channel = Channel {
+                name: UHandle(name.0 .0),
+                gain: 1.0,
+            };
+            // Forgotten rather than released so the name stays tracked.
+            std::mem::forget(name);
+            channel
+        };
+
+        let channels = LVArrayOwned::<1, Channel>::new_zeroed([2].into()).unwrap();
+        for index in 0..2 {
+            unsafe {
+                channels
+                    .as_ref_mut()
+                    .unwrap()
+                    .set_value_unchecked(index, channel())
+            };
+        }
+        drop(channels);
+
+        let single = OwnedUHandle::<Channel>::allocate(std::mem::size_of::<Channel>()).unwrap();
+        unsafe { std::ptr::write(single.as_ref_mut().unwrap(), channel()) };
+        drop(single);
+
+        let leaked = live_handles()
+            .into_iter()
+            .filter(|handle| handle.backtrace.contains("disposes_cluster_handles"))
+            .count();
+        assert_eq!(leaked, 0);
+    }
+
+    #[cfg(feature = "leak-tracking")]
+    #[test]
+    fn test_owned_handle_disposed_once() {
+        use crate::leak_tracking::tracked_id;
+
+        let owned = crate::types::LStrOwned::from_data(b"tracked").unwrap();
+        let address = owned.0 .0.cast();
+        assert!(tracked_id(address).is_some());
+
+        // Released handles aren't disposed on drop.
+        let raw = owned.into_raw();
+        assert_eq!(tracked_id(address), None);
+        assert_eq!(raw.as_bytes().unwrap(), b"tracked");
+
+        let owned = unsafe { crate::types::LStrOwned::from_raw(raw) };
+        let id = tracked_id(address).unwrap();
+        drop(owned);
+        assert_ne!(tracked_id(address), Some(id));
+    }
+
+    #[cfg(feature = "link")]
+    #[test]
+    fn test_check_handle() {
+        let null: UHandle<i32> = UHandle(std::ptr::null_mut());
+        assert!(null.check_handle().is_err());
+
+        let owned = crate::types::LStrOwned::from_data(b"abc").unwrap();
+        let raw = owned.into_raw();
+        assert!(raw.check_handle().is_ok());
+        drop(unsafe { crate::types::LStrOwned::from_raw(raw) });
+    }
+}
@@ -0,0 +1,172 @@
+//! A [`plotters`](https://docs.rs/plotters) backend drawing into LabVIEW pixel data.
+//!
+//! [`LVPixelBackend`] draws into a 2D `u32` array in the `0x00RRGGBB` format
+//! used by LabVIEW's picture functions, so an export can return a finished
+//! chart. Display it by bundling the array as the image data of a 32 bit
+//! image and using Draw Unflattened Pixmap, or wire it to an intensity graph
+//! with a matching colour table.
+//!
+//! The array is indexed by row then column so its dimensions are
+//! `[height, width]`.
+//!
+//! # Example
+//! ```
+//! use labview_interop::errors::{MgErr, Result};
+//! use labview_interop::types::LVArrayHandle;
+//! use plotters::prelude::*;
+//!
+//! fn draw_trace(image: &mut LVArrayHandle<2, u32>, trace: &[f64]) -> Result<()> {
+//!     let backend = image.pixel_backend(320, 240)?;
+//!     let root = backend.into_drawing_area();
+//!     root.fill(&WHITE).unwrap();
+//!     let points = trace
+//!         .iter()
+//!         .enumerate()
+//!         .map(|(index, value)| (index as i32, 120 - (*value * 100.0) as i32));
+//!     root.draw(&PathElement::new(points.collect::<Vec<_>>(), BLUE))
+//!         .unwrap();
+//!     Ok(())
+//! }
+//!
+//! #[no_mangle]
+//! pub extern "C" fn plot_trace(mut image: LVArrayHandle<2, u32>) -> MgErr {
+//!     draw_trace(&mut image, &[0.0, 0.5, 1.0, 0.5]).into()
+//! }
+//! ```
+
+use std::convert::Infallible;
+
+use plotters_backend::{BackendColor, BackendCoord, DrawingBackend, DrawingErrorKind};
+
+use crate::errors::{LVInteropError, Result};
+#[cfg(feature = "link")]
+use crate::types::array::LVArrayDims;
+#[cfg(feature = "link")]
+use crate::types::LVArrayHandle;
+
+/// A plotters backend drawing into `0x00RRGGBB` pixels stored row by row.
+pub struct LVPixelBackend<'a> {
+    pixels: &'a mut [u32],
+    width: u32,
+    height: u32,
+}
+
+impl<'a> LVPixelBackend<'a> {
+    /// Draw into `pixels`, which must hold `width * height` values.
+    pub fn new(pixels: &'a mut [u32], width: u32, height: u32) -> Result<Self> {
+        if pixels.len() != width as usize * height as usize {
+            return Err(LVInteropError::ArrayDimensionMismatch);
+        }
+        Ok(Self {
+            pixels,
+            width,
+            height,
+        })
+    }
+}
+
+fn to_pixel((red, green, blue): (u8, u8, u8)) -> u32 {
+    (red as u32) << 16 | (green as u32) << 8 | blue as u32
+}
+
+fn from_pixel(pixel: u32) -> (u8, u8, u8) {
+    ((pixel >> 16) as u8, (pixel >> 8) as u8, pixel as u8)
+}
+
+fn blend(background: u8, foreground: u8, alpha: f64) -> u8 {
+    (background as f64 * (1.0 - alpha) + foreground as f64 * alpha).round() as u8
+}
+
+impl DrawingBackend for LVPixelBackend<'_> {
+    type ErrorType = Infallible;
+
+    fn get_size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    fn ensure_prepared(&mut self) -> std::result::Result<(), DrawingErrorKind<Infallible>> {
+        Ok(())
+    }
+
+    fn present(&mut self) -> std::result::Result<(), DrawingErrorKind<Infallible>> {
+        Ok(())
+    }
+
+    fn draw_pixel(
+        &mut self,
+        (x, y): BackendCoord,
+        color: BackendColor,
+    ) -> std::result::Result<(), DrawingErrorKind<Infallible>> {
+        // Plotters draws shapes which overlap the edges so clip here.
+        let (Ok(x), Ok(y)) = (u32::try_from(x), u32::try_from(y)) else {
+            return Ok(());
+        };
+        if x >= self.width || y >= self.height || color.alpha <= 0.0 {
+            return Ok(());
+        }
+        let pixel = &mut self.pixels[y as usize * self.width as usize + x as usize];
+        *pixel = if color.alpha >= 1.0 {
+            to_pixel(color.rgb)
+        } else {
+            let (red, green, blue) = from_pixel(*pixel);
+            to_pixel((
+                blend(red, color.rgb.0, color.alpha),
+                blend(green, color.rgb.1, color.alpha),
+                blend(blue, color.rgb.2, color.alpha),
+            ))
+        };
+        Ok(())
+    }
+}
+
+#[cfg(feature = "link")]
+impl LVArrayHandle<2, u32> {
+    /// Resize the array to `width` by `height` pixels and draw into it.
+    ///
+    /// The pixels aren't cleared so fill the drawing area before drawing.
+    pub fn pixel_backend(&mut self, width: u32, height: u32) -> Result<LVPixelBackend<'_>> {
+        self.resize_array(LVArrayDims::try_from(&[height as usize, width as usize])?)?;
+        let pixels = unsafe { self.as_ref_mut()? }.data_as_slice_mut();
+        LVPixelBackend::new(pixels, width, height)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_draw_pixel_blends_and_clips() {
+        let mut pixels = [0x00FF_FFFF; 6];
+        let mut backend = LVPixelBackend::new(&mut pixels, 3, 2).unwrap();
+        let red = BackendColor {
+            alpha: 1.0,
+            rgb: (255, 0, 0),
+        };
+        let half_blue = BackendColor {
+            alpha: 0.5,
+            rgb: (0, 0, 255),
+        };
+        backend.draw_pixel((2, 1), red).unwrap();
+        backend.draw_pixel((0, 0), half_blue).unwrap();
+        backend.draw_pixel((3, 0), red).unwrap();
+        backend.draw_pixel((-1, 0), red).unwrap();
+
+        assert_eq!(
+            pixels,
+            [
+                0x0080_80FF,
+                0xFFFFFF,
+                0xFFFFFF,
+                0xFFFFFF,
+                0xFFFFFF,
+                0xFF0000
+            ]
+        );
+    }
+
+    #[test]
+    fn test_size_mismatch() {
+        assert!(LVPixelBackend::new(&mut [0; 5], 3, 2).is_err());
+    }
+}
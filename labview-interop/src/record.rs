@@ -0,0 +1,587 @@
+//! Recording calls from LabVIEW so they can be replayed in a native test.
+//!
+//! Bugs reported from a LabVIEW system are often hard to reproduce without
+//! that system. With recording switched on each export calling
+//! [`record_call!`](crate::record_call) saves its inputs to a capture file. The
+//! captures can then be copied back and fed to the same Rust functions by a
+//! [`Replayer`] in an ordinary `cargo test`.
+//!
+//! Recording is off by default and costs an atomic load per call when off. It
+//! is switched on with [`start`] or by setting the `LVINTEROP_RECORD_DIR`
+//! environment variable to a directory before LabVIEW loads the library.
+//!
+//! Only inputs are recorded. Numeric scalars and arrays of numerics are
+//! replayed as the same types, using the Rust memory manager for arrays when
+//! not running in LabVIEW. Strings are replayed as bytes.
+//!
+//! # Example
+//! ```
+//! use labview_interop::errors::MgErr;
+//! use labview_interop::record::{Capture, Replayer};
+//! use labview_interop::record_call;
+//!
+//! #[no_mangle]
+//! pub extern "C" fn add_offset(value: f64, offset: i32, result: &mut f64) -> MgErr {
+//!     record_call!("add_offset", value, offset);
+//!     *result = value + offset as f64;
+//!     MgErr::NO_ERROR
+//! }
+//!
+//! // In a test, replay the captures copied from the LabVIEW system.
+//! let replayer = Replayer::new().export("add_offset", |capture| {
+//!     let mut result = 0.0;
+//!     add_offset(capture.arg(0)?, capture.arg(1)?, &mut result);
+//!     assert_eq!(result, 3.5);
+//!     Ok(())
+//! });
+//! # let capture = Capture::from_bytes(
+//! #     &Capture::new("add_offset", vec![
+//! #         labview_interop::record::Record::record(&1.5f64),
+//! #         labview_interop::record::Record::record(&2i32),
+//! #     ]).to_bytes()
+//! # ).unwrap();
+//! replayer.replay(&capture).unwrap();
+//! ```
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use crate::errors::{LVInteropError, Result};
+use crate::types::{LStrHandle, LVBool};
+
+/// The environment variable naming a directory to record into from load.
+pub const RECORD_DIR_VARIABLE: &str = "LVINTEROP_RECORD_DIR";
+
+/// The extension of capture files.
+pub const CAPTURE_EXTENSION: &str = "lvrec";
+
+const MAGIC: &[u8; 6] = b"LVREC\x01";
+
+static RECORDING: AtomicBool = AtomicBool::new(false);
+static RECORD_DIR: Mutex<Option<PathBuf>> = Mutex::new(None);
+static SEQUENCE: AtomicU64 = AtomicU64::new(0);
+
+/// A recorded input.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Recorded {
+    /// A numeric scalar or array. Scalars have no dimensions.
+    ///
+    /// The type code is the LabVIEW numeric type code and the data is little endian.
+    Numeric {
+        type_code: u8,
+        dims: Vec<usize>,
+        data: Vec<u8>,
+    },
+    /// A string or other binary data.
+    Bytes(Vec<u8>),
+}
+
+/// A numeric type which can be recorded on its own or in an array.
+pub trait RecordElement: Copy {
+    /// The LabVIEW type code for the type.
+    const TYPE_CODE: u8;
+    const SIZE: usize;
+    fn write_le(&self, output: &mut Vec<u8>);
+    fn read_le(input: &[u8]) -> Self;
+}
+
+macro_rules! record_element {
+    ($($type:ty => $code:expr),+) => {
+        $(
+            impl RecordElement for $type {
+                const TYPE_CODE: u8 = $code;
+                const SIZE: usize = std::mem::size_of::<$type>();
+                fn write_le(&self, output: &mut Vec<u8>) {
+                    output.extend_from_slice(&self.to_le_bytes());
+                }
+                fn read_le(input: &[u8]) -> Self {
+                    let mut bytes = [0; std::mem::size_of::<$type>()];
+                    bytes.copy_from_slice(input);
+                    <$type>::from_le_bytes(bytes)
+                }
+            }
+        )+
+    };
+}
+
+record_element!(
+    i8 => 0x01, i16 => 0x02, i32 => 0x03, i64 => 0x04,
+    u8 => 0x05, u16 => 0x06, u32 => 0x07, u64 => 0x08,
+    f32 => 0x09, f64 => 0x0A
+);
+
+impl RecordElement for LVBool {
+    const TYPE_CODE: u8 = 0x21;
+    const SIZE: usize = 1;
+    fn write_le(&self, output: &mut Vec<u8>) {
+        output.push(bool::from(*self) as u8);
+    }
+    fn read_le(input: &[u8]) -> Self {
+        (input[0] != 0).into()
+    }
+}
+
+/// A type which can be recorded as an export input.
+pub trait Record {
+    fn record(&self) -> Recorded;
+}
+
+impl<T: RecordElement> Record for T {
+    fn record(&self) -> Recorded {
+        let mut data = Vec::with_capacity(T::SIZE);
+        self.write_le(&mut data);
+        Recorded::Numeric {
+            type_code: T::TYPE_CODE,
+            dims: Vec::new(),
+            data,
+        }
+    }
+}
+
+impl Record for LStrHandle {
+    /// A null handle is recorded as an empty string.
+    fn record(&self) -> Recorded {
+        let bytes = unsafe { self.as_ref() }
+            .map(|string| string.as_slice().to_vec())
+            .unwrap_or_default();
+        Recorded::Bytes(bytes)
+    }
+}
+
+#[cfg(target_pointer_width = "64")]
+impl<const D: usize, T: RecordElement> Record for crate::types::LVArrayHandle<D, T> {
+    /// A null handle is recorded as an empty array.
+    fn record(&self) -> Recorded {
+        let dims: [usize; D] = self.dimensions_or_empty().into();
+        let elements = unsafe { self.as_ref() }
+            .map(|array| array.data_as_slice())
+            .unwrap_or_default();
+        let mut data = Vec::with_capacity(elements.len() * T::SIZE);
+        for element in elements {
+            element.write_le(&mut data);
+        }
+        Recorded::Numeric {
+            type_code: T::TYPE_CODE,
+            dims: dims.to_vec(),
+            data,
+        }
+    }
+}
+
+/// A type which can be recreated from a recorded input.
+pub trait Replay: Sized {
+    fn replay(recorded: &Recorded) -> Result<Self>;
+}
+
+fn invalid(message: impl Into<String>) -> LVInteropError {
+    LVInteropError::InvalidCapture(message.into())
+}
+
+/// Check the recorded value is a numeric of `T` with `D` dimensions, returning
+/// the dimensions and the data.
+fn numeric_data<T: RecordElement, const D: usize>(
+    recorded: &Recorded,
+) -> Result<([usize; D], &[u8])> {
+    let Recorded::Numeric {
+        type_code,
+        dims,
+        data,
+    } = recorded
+    else {
+        return Err(invalid("expected a numeric but found bytes"));
+    };
+    if *type_code != T::TYPE_CODE {
+        return Err(invalid(format!(
+            "expected type code {:#04x} but found {type_code:#04x}",
+            T::TYPE_CODE
+        )));
+    }
+    let dims: [usize; D] = dims
+        .as_slice()
+        .try_into()
+        .map_err(|_| invalid(format!("expected {D} dimensions but found {}", dims.len())))?;
+    // The dimensions come from a file so may be too large to multiply.
+    let size = dims
+        .iter()
+        .try_fold(T::SIZE, |size, &dim| size.checked_mul(dim))
+        .ok_or_else(|| invalid(format!("dimensions {dims:?} are too large")))?;
+    if size != data.len() {
+        return Err(invalid("numeric data doesn't match its dimensions"));
+    }
+    Ok((dims, data))
+}
+
+impl<T: RecordElement> Replay for T {
+    fn replay(recorded: &Recorded) -> Result<Self> {
+        let ([], data) = numeric_data::<T, 0>(recorded)?;
+        Ok(T::read_le(data))
+    }
+}
+
+impl Replay for Vec<u8> {
+    fn replay(recorded: &Recorded) -> Result<Self> {
+        match recorded {
+            Recorded::Bytes(bytes) => Ok(bytes.clone()),
+            Recorded::Numeric { .. } => Err(invalid("expected bytes but found a numeric")),
+        }
+    }
+}
+
+impl Replay for String {
+    fn replay(recorded: &Recorded) -> Result<Self> {
+        let bytes = Vec::<u8>::replay(recorded)?;
+        Ok(String::from_utf8_lossy(&bytes).into_owned())
+    }
+}
+
+#[cfg(all(feature = "link", target_pointer_width = "64"))]
+impl<const D: usize, T> Replay for crate::types::LVArrayOwned<D, T>
+where
+    T: RecordElement + crate::types::array::NumericArrayResizable,
+{
+    fn replay(recorded: &Recorded) -> Result<Self> {
+        let (dims, data) = numeric_data::<T, D>(recorded)?;
+        let array = Self::new(crate::types::array::LVArrayDims::try_from(&dims)?)?;
+        let elements = unsafe { array.as_ref_mut()? }.data_as_slice_mut();
+        for (element, bytes) in elements.iter_mut().zip(data.chunks_exact(T::SIZE)) {
+            *element = T::read_le(bytes);
+        }
+        Ok(array)
+    }
+}
+
+/// The recorded inputs of one call.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Capture {
+    export: String,
+    args: Vec<Recorded>,
+}
+
+impl Capture {
+    pub fn new(export: impl Into<String>, args: Vec<Recorded>) -> Self {
+        Self {
+            export: export.into(),
+            args,
+        }
+    }
+
+    /// The name of the export which was called.
+    pub fn export(&self) -> &str {
+        &self.export
+    }
+
+    pub fn args(&self) -> &[Recorded] {
+        &self.args
+    }
+
+    /// Recreate argument `index` as `T`.
+    pub fn arg<T: Replay>(&self, index: usize) -> Result<T> {
+        let recorded = self.args.get(index).ok_or_else(|| {
+            invalid(format!(
+                "{} has {} arguments recorded so there is no argument {index}",
+                self.export,
+                self.args.len()
+            ))
+        })?;
+        T::replay(recorded)
+    }
+
+    /// Encode the capture in the capture file format.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut output = MAGIC.to_vec();
+        write_bytes(&mut output, self.export.as_bytes());
+        write_length(&mut output, self.args.len());
+        for arg in &self.args {
+            match arg {
+                Recorded::Numeric {
+                    type_code,
+                    dims,
+                    data,
+                } => {
+                    output.push(0);
+                    output.push(*type_code);
+                    write_length(&mut output, dims.len());
+                    for dim in dims {
+                        write_length(&mut output, *dim);
+                    }
+                    write_bytes(&mut output, data);
+                }
+                Recorded::Bytes(bytes) => {
+                    output.push(1);
+                    write_bytes(&mut output, bytes);
+                }
+            }
+        }
+        output
+    }
+
+    /// Decode a capture from the capture file format.
+    pub fn from_bytes(input: &[u8]) -> Result<Self> {
+        let mut reader = Reader(input);
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err(invalid("not a capture file or an unsupported version"));
+        }
+        let export = String::from_utf8(reader.bytes()?.to_vec())
+            .map_err(|_| invalid("export name isn't UTF-8"))?;
+        let count = reader.length()?;
+        let mut args = Vec::new();
+        for _ in 0..count {
+            let arg = match reader.take(1)?[0] {
+                0 => {
+                    let type_code = reader.take(1)?[0];
+                    let dim_count = reader.length()?;
+                    let dims = (0..dim_count)
+                        .map(|_| reader.length())
+                        .collect::<Result<Vec<_>>>()?;
+                    let data = reader.bytes()?.to_vec();
+                    Recorded::Numeric {
+                        type_code,
+                        dims,
+                        data,
+                    }
+                }
+                1 => Recorded::Bytes(reader.bytes()?.to_vec()),
+                tag => return Err(invalid(format!("unknown argument tag {tag}"))),
+            };
+            args.push(arg);
+        }
+        Ok(Self { export, args })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, self.to_bytes())
+            .map_err(|error| invalid(format!("can't write {}: {error}", path.display())))
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let bytes = std::fs::read(path)
+            .map_err(|error| invalid(format!("can't read {}: {error}", path.display())))?;
+        Self::from_bytes(&bytes)
+    }
+}
+
+fn write_length(output: &mut Vec<u8>, length: usize) {
+    output.extend_from_slice(&(length as u64).to_le_bytes());
+}
+
+fn write_bytes(output: &mut Vec<u8>, bytes: &[u8]) {
+    write_length(output, bytes.len());
+    output.extend_from_slice(bytes);
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, count: usize) -> Result<&'a [u8]> {
+        if self.0.len() < count {
+            return Err(invalid("capture file is truncated"));
+        }
+        let (taken, rest) = self.0.split_at(count);
+        self.0 = rest;
+        Ok(taken)
+    }
+
+    fn length(&mut self) -> Result<usize> {
+        let length = u64::read_le(self.take(8)?);
+        usize::try_from(length).map_err(|_| invalid("length is too large"))
+    }
+
+    fn bytes(&mut self) -> Result<&'a [u8]> {
+        let length = self.length()?;
+        self.take(length)
+    }
+}
+
+/// Start recording calls into `dir`, creating it if needed.
+pub fn start(dir: impl Into<PathBuf>) -> Result<()> {
+    let dir = dir.into();
+    std::fs::create_dir_all(&dir)
+        .map_err(|error| invalid(format!("can't create {}: {error}", dir.display())))?;
+    *RECORD_DIR
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(dir);
+    RECORDING.store(true, Ordering::Release);
+    Ok(())
+}
+
+/// Stop recording calls.
+pub fn stop() {
+    RECORDING.store(false, Ordering::Release);
+}
+
+pub fn is_recording() -> bool {
+    RECORDING.load(Ordering::Acquire)
+}
+
+/// Save a capture of a call if recording, returning the file it was saved to.
+///
+/// This is normally called through [`record_call!`](crate::record_call).
+pub fn save_call(export: &str, args: Vec<Recorded>) -> Result<Option<PathBuf>> {
+    if !is_recording() {
+        return Ok(None);
+    }
+    let Some(dir) = RECORD_DIR
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .clone()
+    else {
+        return Ok(None);
+    };
+    let sequence = SEQUENCE.fetch_add(1, Ordering::Relaxed);
+    let path = dir.join(format!(
+        "{export}-{}-{sequence:06}.{CAPTURE_EXTENSION}",
+        std::process::id()
+    ));
+    Capture::new(export, args).save(&path)?;
+    Ok(Some(path))
+}
+
+#[ctor::ctor]
+fn start_from_environment() {
+    if let Some(dir) = std::env::var_os(RECORD_DIR_VARIABLE) {
+        let _ = start(dir);
+    }
+}
+
+/// Record the inputs of an export when recording is on.
+///
+/// The first argument is the export name and the rest are the inputs, which
+/// must implement [`Record`]. Errors saving the capture are ignored so
+/// recording never changes the result of the export.
+#[macro_export]
+macro_rules! record_call {
+    ($export:expr $(, $arg:expr)* $(,)?) => {
+        if $crate::record::is_recording() {
+            let _ = $crate::record::save_call(
+                $export,
+                vec![$($crate::record::Record::record(&$arg)),*],
+            );
+        }
+    };
+}
+
+type ReplayFn = Box<dyn Fn(&Capture) -> Result<()>>;
+
+/// Calls the Rust functions behind each export from captures.
+#[derive(Default)]
+pub struct Replayer {
+    exports: BTreeMap<String, ReplayFn>,
+}
+
+impl Replayer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the function which replays captures of `export`.
+    pub fn export(
+        mut self,
+        export: impl Into<String>,
+        replay: impl Fn(&Capture) -> Result<()> + 'static,
+    ) -> Self {
+        self.exports.insert(export.into(), Box::new(replay));
+        self
+    }
+
+    /// Replay a single capture.
+    pub fn replay(&self, capture: &Capture) -> Result<()> {
+        let replay = self
+            .exports
+            .get(capture.export())
+            .ok_or_else(|| invalid(format!("no replay for export {}", capture.export())))?;
+        replay(capture)
+    }
+
+    /// Replay every capture in `dir` in the order they were recorded, returning
+    /// how many were replayed.
+    pub fn replay_dir(&self, dir: &Path) -> Result<usize> {
+        let paths = capture_files(dir)?;
+        for path in &paths {
+            self.replay(&Capture::load(path)?)?;
+        }
+        Ok(paths.len())
+    }
+}
+
+/// List the capture files in `dir` in the order they were recorded.
+pub fn capture_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let entries = std::fs::read_dir(dir)
+        .map_err(|error| invalid(format!("can't read {}: {error}", dir.display())))?;
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == CAPTURE_EXTENSION))
+        .collect();
+    // Sort by the process and sequence number rather than the export name.
+    paths.sort_by_key(|path| {
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+        let mut parts = stem.rsplitn(3, '-');
+        let sequence = parts.next().and_then(|part| part.parse::<u64>().ok());
+        let process = parts.next().and_then(|part| part.parse::<u32>().ok());
+        (process, sequence)
+    });
+    Ok(paths)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_capture_round_trip() {
+        let capture = Capture::new(
+            "configure",
+            vec![
+                5.5f64.record(),
+                LVBool::from(true).record(),
+                Recorded::Bytes(b"COM1".to_vec()),
+            ],
+        );
+        let decoded = Capture::from_bytes(&capture.to_bytes()).unwrap();
+        assert_eq!(decoded, capture);
+        assert_eq!(decoded.arg::<f64>(0).unwrap(), 5.5);
+        assert!(bool::from(decoded.arg::<LVBool>(1).unwrap()));
+        assert_eq!(decoded.arg::<String>(2).unwrap(), "COM1");
+
+        assert!(decoded.arg::<i32>(0).is_err());
+        assert!(decoded.arg::<f64>(3).is_err());
+        assert!(Capture::from_bytes(&capture.to_bytes()[..20]).is_err());
+    }
+
+    #[test]
+    fn test_rejects_oversized_dimensions() {
+        let recorded = Recorded::Numeric {
+            type_code: f64::TYPE_CODE,
+            dims: vec![usize::MAX / 2, 3],
+            data: vec![],
+        };
+        assert!(matches!(
+            numeric_data::<f64, 2>(&recorded),
+            Err(LVInteropError::InvalidCapture(_))
+        ));
+    }
+
+    #[cfg(all(feature = "link", target_pointer_width = "64"))]
+    #[test]
+    fn test_array_replays_as_owned_array() {
+        use crate::types::LVArrayOwned;
+
+        let array = LVArrayOwned::<2, i16>::new([2, 2].into()).unwrap();
+        unsafe { array.as_ref_mut() }
+            .unwrap()
+            .data_as_slice_mut()
+            .copy_from_slice(&[1, -2, 3, -4]);
+        let capture = Capture::new("sum", vec![array.record()]);
+
+        let replayer = Replayer::new().export("sum", |capture| {
+            let array: LVArrayOwned<2, i16> = capture.arg(0)?;
+            let array = unsafe { array.as_ref()? };
+            assert_eq!(array.dimension_sizes(), [2, 2].into());
+            assert_eq!(array.data_as_slice(), [1, -2, 3, -4]);
+            Ok(())
+        });
+        replayer.replay(&capture).unwrap();
+        assert!(replayer.replay(&Capture::new("other", vec![])).is_err());
+    }
+}
@@ -0,0 +1,254 @@
+//! Registries of Rust resources handed to LabVIEW as refnums.
+//!
+//! A [`RefnumRegistry`] stores values such as device sessions, files or sockets
+//! and gives LabVIEW a refnum to pass back into later calls. The refnums are
+//! [`LVRefNum`]s tagged with the value type, so a refnum from one registry
+//! can't be passed to another of a different type. Configure the parameter as
+//! an unsigned 32 bit integer on the Call Library node.
+//!
+//! Values added with [`RefnumRegistry::insert_with_cleanup`] are also released
+//! by LabVIEW when the VI which created them goes idle or is aborted, so a VI
+//! stopped without calling its close function doesn't leak the resource until
+//! the library unloads. This uses [`on_cleanup`](crate::cleanup::on_cleanup).
+//!
+//! # Example
+//! ```
+//! use labview_interop::registry::{CleanupMode, RefnumRegistry};
+//!
+//! struct Session {
+//!     address: String,
+//! }
+//!
+//! static SESSIONS: RefnumRegistry<Session> = RefnumRegistry::new();
+//!
+//! let refnum = SESSIONS.insert_with_cleanup(
+//!     Session { address: "COM1".to_string() },
+//!     CleanupMode::OnIdle,
+//! );
+//! let address = SESSIONS.with(refnum, |session| session.address.clone()).unwrap();
+//! assert_eq!(address, "COM1");
+//! SESSIONS.remove(refnum).unwrap();
+//! assert!(SESSIONS.with(refnum, |_| ()).is_err());
+//! ```
+
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+pub use crate::cleanup::CleanupMode;
+#[cfg(feature = "link")]
+use crate::cleanup::{self, CleanupRegistration};
+use crate::errors::{LVInteropError, Result};
+use crate::types::LVRefNum;
+
+struct Entry<T> {
+    value: Arc<Mutex<T>>,
+    #[cfg(feature = "link")]
+    cleanup: Option<CleanupRegistration>,
+}
+
+/// A registry of values identified by refnums.
+///
+/// This is designed to be used as a `static` so refnums stay valid between calls.
+pub struct RefnumRegistry<T> {
+    entries: Mutex<BTreeMap<u32, Entry<T>>>,
+    next: AtomicU32,
+}
+
+impl<T> Default for RefnumRegistry<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> RefnumRegistry<T> {
+    /// Create an empty registry.
+    pub const fn new() -> Self {
+        Self {
+            entries: Mutex::new(BTreeMap::new()),
+            next: AtomicU32::new(1),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, BTreeMap<u32, Entry<T>>> {
+        self.entries
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Find an unused refnum. 0 is never used as LabVIEW treats it as not a refnum.
+    fn allocate(&self, entries: &BTreeMap<u32, Entry<T>>) -> LVRefNum<T> {
+        loop {
+            let refnum = self.next.fetch_add(1, Ordering::Relaxed);
+            if refnum != 0 && !entries.contains_key(&refnum) {
+                return LVRefNum::from_raw(refnum);
+            }
+        }
+    }
+
+    /// Add a value, returning the refnum to give to LabVIEW.
+    ///
+    /// The value is kept until it is [removed](RefnumRegistry::remove).
+    pub fn insert(&self, value: T) -> LVRefNum<T> {
+        let mut entries = self.lock();
+        let refnum = self.allocate(&entries);
+        entries.insert(
+            refnum.as_raw(),
+            Entry {
+                value: Arc::new(Mutex::new(value)),
+                #[cfg(feature = "link")]
+                cleanup: None,
+            },
+        );
+        refnum
+    }
+
+    /// Call `f` with the value for the refnum.
+    ///
+    /// Only this value is locked while `f` runs so other refnums can be used in parallel.
+    pub fn with<R>(&self, refnum: LVRefNum<T>, f: impl FnOnce(&mut T) -> R) -> Result<R> {
+        let value = self
+            .lock()
+            .get(&refnum.as_raw())
+            .map(|entry| entry.value.clone())
+            .ok_or(LVInteropError::InvalidRefnum(refnum.as_raw()))?;
+        let mut value = value
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        Ok(f(&mut value))
+    }
+
+    /// Check whether the refnum is in the registry.
+    pub fn contains(&self, refnum: LVRefNum<T>) -> bool {
+        self.lock().contains_key(&refnum.as_raw())
+    }
+
+    /// The number of values in the registry.
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    /// Take the entry out of the registry without cancelling its cleanup.
+    fn take(&self, refnum: LVRefNum<T>) -> Option<Entry<T>> {
+        self.lock().remove(&refnum.as_raw())
+    }
+
+    /// Remove the value for the refnum, cancelling any cleanup registered with LabVIEW.
+    ///
+    /// The value is dropped once any calls using it through
+    /// [`with`](RefnumRegistry::with) have finished.
+    pub fn remove(&self, refnum: LVRefNum<T>) -> Result<()> {
+        let entry = self
+            .take(refnum)
+            .ok_or(LVInteropError::InvalidRefnum(refnum.as_raw()))?;
+        #[cfg(feature = "link")]
+        if let Some(registration) = entry.cleanup {
+            registration.cancel();
+        }
+        drop(entry);
+        Ok(())
+    }
+
+    /// Remove every value, for example when the library is closed.
+    pub fn clear(&self) {
+        let refnums: Vec<u32> = self.lock().keys().copied().collect();
+        for refnum in refnums {
+            let _ = self.remove(LVRefNum::from_raw(refnum));
+        }
+    }
+}
+
+#[cfg(feature = "link")]
+impl<T: Send + 'static> RefnumRegistry<T> {
+    /// Add a value which LabVIEW removes at the point given by `mode`, returning
+    /// the refnum to give to LabVIEW.
+    ///
+    /// The cleanup is registered for the VI calling this function. Removing the
+    /// value first cancels the cleanup. Outside LabVIEW there is no VI to go idle
+    /// so the value is kept until it is removed.
+    pub fn insert_with_cleanup(&'static self, value: T, mode: CleanupMode) -> LVRefNum<T> {
+        let refnum = self.insert(value);
+        let registration = cleanup::on_cleanup(mode, move || {
+            // The cleanup has been run so there is nothing to cancel.
+            drop(self.take(refnum));
+        });
+        if let Ok(registration) = registration {
+            let stored = match self.lock().get_mut(&refnum.as_raw()) {
+                Some(entry) => {
+                    entry.cleanup = Some(registration);
+                    true
+                }
+                None => false,
+            };
+            // Removed on another thread already. Cancelled without the registry
+            // locked as the cleanup table is locked to cancel it.
+            if !stored {
+                registration.cancel();
+            }
+        }
+        refnum
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_insert_with_remove() {
+        let registry = RefnumRegistry::new();
+        let first = registry.insert(String::from("first"));
+        let second = registry.insert(String::from("second"));
+        assert!(!first.is_null());
+        assert_ne!(first, second);
+        assert_eq!(registry.len(), 2);
+
+        registry.with(first, |value| value.push('!')).unwrap();
+        assert_eq!(
+            registry.with(first, |value| value.clone()).unwrap(),
+            "first!"
+        );
+
+        registry.remove(first).unwrap();
+        assert!(!registry.contains(first));
+        assert!(matches!(
+            registry.with(first, |_| ()),
+            Err(LVInteropError::InvalidRefnum(refnum)) if refnum == first.as_raw()
+        ));
+        assert!(registry.remove(first).is_err());
+
+        registry.clear();
+        assert!(registry.is_empty());
+    }
+
+    /// Releases another refnum in the same registry when dropped.
+    struct Parent(Option<LVRefNum<Parent>>);
+
+    static PARENTS: RefnumRegistry<Parent> = RefnumRegistry::new();
+
+    impl Drop for Parent {
+        fn drop(&mut self) {
+            if let Some(child) = self.0 {
+                // Deadlocks if the registry is still locked.
+                let _ = PARENTS.remove(child);
+            }
+        }
+    }
+
+    #[test]
+    fn test_dropped_value_removes_refnum() {
+        let child = PARENTS.insert(Parent(None));
+        let parent = PARENTS.insert(Parent(Some(child)));
+        PARENTS.remove(parent).unwrap();
+        assert!(!PARENTS.contains(child));
+
+        let child = PARENTS.insert(Parent(None));
+        let parent = PARENTS.insert(Parent(Some(child)));
+        PARENTS.clear();
+        assert!(!PARENTS.contains(parent) && !PARENTS.contains(child));
+    }
+}
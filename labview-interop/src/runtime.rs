@@ -0,0 +1,230 @@
+//! A tokio runtime for running async Rust code from LabVIEW.
+//!
+//! Exports are synchronous calls from LabVIEW so async libraries need a runtime
+//! to run on. This module owns a multi-threaded runtime for the library which
+//! is started on first use and shut down by the lifecycle cleanup, so it never
+//! outlives the library.
+//!
+//! There are two ways for an export to use it:
+//!
+//! * [`block_on`] runs a future to completion on the calling thread. This is
+//!   the simplest but ties up the LabVIEW execution thread until it is done.
+//! * [`spawn_with_event`] and [`spawn_with_occurrence`] start the future in the
+//!   background and return immediately. The result is posted to a user event or
+//!   the VI waits on an occurrence, as with other background work.
+//!
+//! The runtime can be started with a set number of worker threads with the
+//! `interop_runtime_start` export and stopped with `interop_runtime_shutdown`.
+//!
+//! # Example
+//! ```
+//! use labview_interop::errors::MgErr;
+//! use labview_interop::runtime;
+//! use labview_interop::sync::LVUserEvent;
+//!
+//! async fn read_sensor() -> f64 {
+//!     1.5
+//! }
+//!
+//! /// Start a read and post the value to the user event when it arrives.
+//! #[no_mangle]
+//! pub extern "C" fn start_read(event: *mut LVUserEvent<f64>) -> MgErr {
+//!     let Some(event) = (unsafe { event.as_ref() }).copied() else {
+//!         return MgErr::ARGUMENT_ERROR;
+//!     };
+//!     // Safety: an `f64` holds no handles.
+//!     unsafe { runtime::spawn_with_event(event, read_sensor()) }.into()
+//! }
+//!
+//! assert_eq!(runtime::block_on(read_sensor()).unwrap(), 1.5);
+//! ```
+
+use std::future::Future;
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+
+use tokio::runtime::{Builder, Handle, Runtime};
+use tokio::task::JoinHandle;
+
+use crate::errors::{LVInteropError, MgErr, Result};
+use crate::memory::DeepDispose;
+use crate::sync::{LVOccurrence, LVUserEvent};
+
+static RUNTIME: Mutex<Option<Runtime>> = Mutex::new(None);
+
+fn lock_runtime() -> MutexGuard<'static, Option<Runtime>> {
+    RUNTIME
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+fn build_runtime(worker_threads: usize) -> Result<Runtime> {
+    let mut builder = Builder::new_multi_thread();
+    builder.enable_all().thread_name("labview-interop-runtime");
+    if worker_threads > 0 {
+        builder.worker_threads(worker_threads);
+    }
+    Ok(builder.build()?)
+}
+
+/// Start the runtime with the given number of worker threads.
+///
+/// Zero uses one thread per core. This does nothing if the runtime is
+/// already running, so call it before anything else uses the runtime.
+pub fn start(worker_threads: usize) -> Result<()> {
+    let mut runtime = lock_runtime();
+    if runtime.is_none() {
+        *runtime = Some(build_runtime(worker_threads)?);
+    }
+    Ok(())
+}
+
+/// Whether the runtime is running.
+pub fn is_running() -> bool {
+    lock_runtime().is_some()
+}
+
+/// A handle to the runtime, starting it with the default settings if needed.
+pub fn handle() -> Result<Handle> {
+    let mut runtime = lock_runtime();
+    if let Some(runtime) = runtime.as_ref() {
+        return Ok(runtime.handle().clone());
+    }
+    let started = build_runtime(0)?;
+    let handle = started.handle().clone();
+    *runtime = Some(started);
+    Ok(handle)
+}
+
+/// Run a future to completion, blocking the calling thread.
+///
+/// # Panics
+///
+/// If called from inside the runtime, such as from a spawned task.
+pub fn block_on<F: Future>(future: F) -> Result<F::Output> {
+    Ok(handle()?.block_on(future))
+}
+
+/// Run a future in the background on the runtime.
+pub fn spawn<F>(future: F) -> Result<JoinHandle<F::Output>>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    Ok(handle()?.spawn(future))
+}
+
+/// Run a future in the background and post its output to a user event.
+///
+/// The output is disposed after posting as with
+/// [`LVUserEvent::post_and_dispose`], so it can hold handles allocated for the
+/// event. If the future panics or the runtime shuts down first nothing is
+/// posted, so the VI should have a timeout on the event structure.
+///
+/// # Safety
+///
+/// Every handle in the output must be owned by the future, such as one it
+/// allocated or copied, as they are disposed. Returning an input handle or a
+/// copy of a handle still in use elsewhere frees it while it is used.
+pub unsafe fn spawn_with_event<F, T>(event: LVUserEvent<T>, future: F) -> Result<()>
+where
+    F: Future<Output = T> + Send + 'static,
+    T: DeepDispose + 'static,
+{
+    spawn(async move {
+        let data = future.await;
+        // Nothing is waiting on the task to report a failed post to.
+        // Safety: the caller guarantees the output owns its handles.
+        let _ = unsafe { event.post_and_dispose(data) };
+    })?;
+    Ok(())
+}
+
+/// Run a future in the background and set an occurrence when it finishes.
+///
+/// The occurrence is also set if the future panics or is dropped by the
+/// runtime shutting down, so a VI waiting on it is never left hanging. The
+/// future should store its result somewhere the VI can read it afterwards.
+pub fn spawn_with_occurrence<F>(occurrence: LVOccurrence, future: F) -> Result<()>
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    /// Sets the occurrence however the task ends.
+    struct SetOnDrop(LVOccurrence);
+
+    impl Drop for SetOnDrop {
+        fn drop(&mut self) {
+            let _ = self.0.set();
+        }
+    }
+
+    let guard = SetOnDrop(occurrence);
+    spawn(async move {
+        let _guard = guard;
+        future.await;
+    })?;
+    Ok(())
+}
+
+/// Stop the runtime, waiting up to `timeout` for its tasks to finish.
+///
+/// Tasks still running at the timeout are dropped at their next await. The
+/// runtime starts again on the next use. This is called by the lifecycle
+/// cleanup export.
+///
+/// # Panics
+///
+/// If called from inside the runtime.
+pub fn shutdown(timeout: Duration) {
+    // Take the runtime out first so the lock isn't held while tasks finish.
+    let runtime = lock_runtime().take();
+    if let Some(runtime) = runtime {
+        runtime.shutdown_timeout(timeout);
+    }
+}
+
+/// Start the runtime from LabVIEW with a number of worker threads.
+///
+/// Zero or less uses one thread per core. Call this from the library's open VI
+/// before anything uses the runtime.
+#[no_mangle]
+pub extern "C" fn interop_runtime_start(worker_threads: i32) -> MgErr {
+    start(worker_threads.max(0) as usize).into()
+}
+
+/// Stop the runtime from LabVIEW, waiting up to `timeout_ms` for its tasks.
+#[no_mangle]
+pub extern "C" fn interop_runtime_shutdown(timeout_ms: u32) -> MgErr {
+    match std::panic::catch_unwind(|| shutdown(Duration::from_millis(timeout_ms.into()))) {
+        Ok(()) => MgErr::NO_ERROR,
+        Err(_) => LVInteropError::Panic("runtime shutdown panicked".into()).into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    // The runtime is global so this is a single test to avoid other tests stopping it.
+    #[test]
+    fn test_runtime_starts_on_use_and_restarts_after_shutdown() {
+        assert_eq!(block_on(async { 1 + 1 }).unwrap(), 2);
+        assert!(is_running());
+
+        let (sender, receiver) = mpsc::channel();
+        spawn(async move { sender.send(3).unwrap() }).unwrap();
+        assert_eq!(receiver.recv_timeout(Duration::from_secs(5)), Ok(3));
+
+        shutdown(Duration::from_secs(1));
+        assert!(!is_running());
+        assert_eq!(block_on(async { 4 }).unwrap(), 4);
+        shutdown(Duration::from_secs(1));
+
+        // Cleanup from inside the runtime reports the panic rather than unwinding.
+        let result = block_on(async { crate::lifecycle::library_cleanup() }).unwrap();
+        assert!(matches!(result, Err(LVInteropError::Panic(_))));
+        assert!(!is_running());
+        crate::lifecycle::library_init().unwrap();
+    }
+}
@@ -0,0 +1,315 @@
+//! The scope module tracks background work started by the library
+//! so that it can never outlive the library itself.
+//!
+//! Threads started through [`scope()`] are given a [`ScopeToken`] which they
+//! should check to know when to stop. When the library is unloaded the scope
+//! is cancelled and the unload waits for the tracked work to finish so LabVIEW
+//! doesn't crash executing code from an unloaded library. The exports from
+//! [`lv_lifecycle_exports!`](crate::lv_lifecycle_exports) also shut the scope
+//! down when the VI which initialised the library is aborted.
+//!
+//! # Example
+//! ```
+//! use labview_interop::scope::scope;
+//! use std::time::Duration;
+//!
+//! scope()
+//!     .spawn(|token| {
+//!         while !token.is_cancelled() {
+//!             // Do some background work.
+//!             token.wait_cancelled(Duration::from_millis(10));
+//!         }
+//!     })
+//!     .unwrap();
+//!
+//! // Typically called from a close export to stop everything.
+//! assert!(scope().shutdown(Duration::from_secs(1)));
+//! ```
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Condvar, Mutex, MutexGuard};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// How often waiting checks whether finished threads have exited.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// How long unloading the library will wait for background work to finish.
+const UNLOAD_TIMEOUT: Duration = Duration::from_secs(5);
+
+static LIBRARY_SCOPE: LibraryScope = LibraryScope::new();
+
+/// The scope tied to the lifetime of the library.
+pub fn scope() -> &'static LibraryScope {
+    &LIBRARY_SCOPE
+}
+
+/// Tracks background threads so they can be cancelled and awaited together.
+pub struct LibraryScope {
+    cancelled: AtomicBool,
+    running: Mutex<usize>,
+    changed: Condvar,
+    handles: Mutex<Vec<JoinHandle<()>>>,
+}
+
+impl LibraryScope {
+    /// Create a new, empty scope.
+    ///
+    /// Most code should use the library scope from [`scope()`].
+    pub const fn new() -> Self {
+        Self {
+            cancelled: AtomicBool::new(false),
+            running: Mutex::new(0),
+            changed: Condvar::new(),
+            handles: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Spawn a thread tracked by this scope.
+    ///
+    /// The closure receives a [`ScopeToken`] which should be polled to
+    /// know when the work should stop.
+    pub fn spawn<F>(&'static self, work: F) -> std::io::Result<()>
+    where
+        F: FnOnce(ScopeToken) + Send + 'static,
+    {
+        *self.lock_running() += 1;
+        let token = ScopeToken { scope: self };
+        let spawn_result = std::thread::Builder::new().spawn(move || {
+            let _guard = FinishedGuard { scope: token.scope };
+            work(token);
+        });
+
+        match spawn_result {
+            Ok(handle) => {
+                self.lock_handles().push(handle);
+                // Clean up anything that has already finished so this doesn't grow unbounded.
+                self.reap(true);
+                Ok(())
+            }
+            Err(error) => {
+                let mut running = self.lock_running();
+                *running -= 1;
+                self.changed.notify_all();
+                Err(error)
+            }
+        }
+    }
+
+    /// The number of tracked threads which haven't been joined yet.
+    ///
+    /// Threads which have finished are joined first, so a thread is counted
+    /// until it has completely exited.
+    pub fn running(&self) -> usize {
+        self.reap(true)
+    }
+
+    /// The running count without blocking, or `None` if it is locked.
+    #[cfg(all(feature = "crash-dump", target_os = "windows"))]
+    pub(crate) fn try_running(&self) -> Option<usize> {
+        self.running.try_lock().ok().map(|running| *running)
+    }
+
+    /// True if the scope has been cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Acquire)
+    }
+
+    /// Signal all tracked work to stop without waiting for it.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Release);
+        // Take the lock so waiting tokens can't miss the notification.
+        let _running = self.lock_running();
+        self.changed.notify_all();
+    }
+
+    /// Wait for all tracked work to complete and join the threads.
+    ///
+    /// Returns false if the timeout expired with work still running.
+    pub fn wait(&self, timeout: Duration) -> bool {
+        self.wait_stopped(timeout, true)
+    }
+
+    /// Cancel all tracked work, wait for it to complete and join the threads.
+    ///
+    /// Returns false if the timeout expired with work still running. If everything
+    /// stopped then the scope is reset so it can be used again, for example when
+    /// the VIs using the library are run again after an abort.
+    pub fn shutdown(&self, timeout: Duration) -> bool {
+        self.cancel();
+        if !self.wait(timeout) {
+            return false;
+        }
+        self.cancelled.store(false, Ordering::Release);
+        true
+    }
+
+    /// Wait until no tracked threads are left, joining them if `join` is set.
+    fn wait_stopped(&self, timeout: Duration, join: bool) -> bool {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if self.reap(join) == 0 {
+                return true;
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return false;
+            }
+            // Finishing work wakes this up, but the thread exits a little later
+            // so poll until it has.
+            let running = self.lock_running();
+            let _ = self
+                .changed
+                .wait_timeout(running, (deadline - now).min(POLL_INTERVAL));
+        }
+    }
+
+    /// Remove the threads which have finished their work, joining them if
+    /// `join` is set, and return how many are left.
+    ///
+    /// They are only removed from the running count after the join so the count
+    /// covers the code which runs as the thread exits.
+    fn reap(&self, join: bool) -> usize {
+        let finished: Vec<_> = {
+            let mut handles = self.lock_handles();
+            let (finished, running) = handles.drain(..).partition(|handle| handle.is_finished());
+            *handles = running;
+            finished
+        };
+        let count = finished.len();
+        if join {
+            for handle in finished {
+                let _ = handle.join();
+            }
+        }
+        let mut running = self.lock_running();
+        *running -= count;
+        *running
+    }
+
+    fn lock_running(&self) -> MutexGuard<'_, usize> {
+        self.running
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn lock_handles(&self) -> MutexGuard<'_, Vec<JoinHandle<()>>> {
+        self.handles
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Default for LibraryScope {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Wakes anything waiting on the scope when work finishes, even if it panics.
+struct FinishedGuard {
+    scope: &'static LibraryScope,
+}
+
+impl Drop for FinishedGuard {
+    fn drop(&mut self) {
+        let _running = self.scope.lock_running();
+        self.scope.changed.notify_all();
+    }
+}
+
+/// Given to work spawned in a scope to check for cancellation.
+#[derive(Clone, Copy)]
+pub struct ScopeToken {
+    scope: &'static LibraryScope,
+}
+
+impl ScopeToken {
+    /// True if the work should stop.
+    pub fn is_cancelled(&self) -> bool {
+        self.scope.is_cancelled()
+    }
+
+    /// Sleep for up to `timeout`, waking early if the scope is cancelled.
+    ///
+    /// Returns true if the scope has been cancelled.
+    pub fn wait_cancelled(&self, timeout: Duration) -> bool {
+        let running = self.scope.lock_running();
+        let _running = self
+            .scope
+            .changed
+            .wait_timeout_while(running, timeout, |_| !self.is_cancelled())
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        self.is_cancelled()
+    }
+}
+
+/// Stop background work before the library is unloaded.
+///
+/// This is called when the library unloads, after the unload hooks from
+/// [`lifecycle`](crate::lifecycle). Threads are not joined here as joining
+/// during library unload can deadlock on Windows, so this only waits for each
+/// thread to return from its work. Only the standard library's thread exit
+/// code can still be running after that.
+pub(crate) fn stop_scope_on_unload() {
+    LIBRARY_SCOPE.cancel();
+    LIBRARY_SCOPE.wait_stopped(UNLOAD_TIMEOUT, false);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shutdown_stops_running_work() {
+        static TEST_SCOPE: LibraryScope = LibraryScope::new();
+
+        TEST_SCOPE
+            .spawn(|token| while !token.wait_cancelled(Duration::from_secs(10)) {})
+            .unwrap();
+        assert_eq!(TEST_SCOPE.running(), 1);
+
+        assert!(TEST_SCOPE.shutdown(Duration::from_secs(5)));
+        assert_eq!(TEST_SCOPE.running(), 0);
+        assert!(!TEST_SCOPE.is_cancelled());
+    }
+
+    #[test]
+    fn test_wait_times_out_with_running_work() {
+        static TEST_SCOPE: LibraryScope = LibraryScope::new();
+
+        TEST_SCOPE
+            .spawn(|token| while !token.wait_cancelled(Duration::from_secs(10)) {})
+            .unwrap();
+        assert!(!TEST_SCOPE.wait(Duration::from_millis(10)));
+
+        assert!(TEST_SCOPE.shutdown(Duration::from_secs(5)));
+    }
+
+    #[test]
+    fn test_running_until_thread_exits() {
+        use std::sync::atomic::AtomicBool;
+
+        static TEST_SCOPE: LibraryScope = LibraryScope::new();
+        static EXITED: AtomicBool = AtomicBool::new(false);
+
+        /// Runs as the thread exits, after the work has returned.
+        struct ExitCode;
+
+        impl Drop for ExitCode {
+            fn drop(&mut self) {
+                std::thread::sleep(Duration::from_millis(100));
+                EXITED.store(true, Ordering::Release);
+            }
+        }
+
+        thread_local! {
+            static EXIT_CODE: ExitCode = const { ExitCode };
+        }
+
+        TEST_SCOPE.spawn(|_| EXIT_CODE.with(|_| ())).unwrap();
+        assert!(TEST_SCOPE.wait(Duration::from_secs(5)));
+        assert!(EXITED.load(Ordering::Acquire));
+        assert_eq!(TEST_SCOPE.running(), 0);
+    }
+}
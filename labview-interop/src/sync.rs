@@ -3,14 +3,15 @@
 //! back to labview.
 //!
 
+#[cfg(target_pointer_width = "64")]
+pub mod queue;
+
 use std::ffi::c_void;
-use std::marker::PhantomData;
 
 use crate::errors::Result;
 use crate::labview::sync_api;
-use crate::memory::MagicCookie;
-
-type LVUserEventRef = MagicCookie;
+use crate::memory::{DeepDispose, MagicCookie};
+use crate::types::LVRefNum;
 
 /// Representation of a LabVIEW user event reference with type data.
 ///
@@ -19,6 +20,16 @@ type LVUserEventRef = MagicCookie;
 ///
 /// From LabVIEW you can set the terminal to be `adapt to type` and `handles by value`
 ///
+/// This is a refnum tagged with the event data type, see [`LVRefNum`].
+///
+/// The event data can be any type with the LabVIEW layout, including clusters
+/// declared with `labview_layout!` which contain string and array handles.
+/// LabVIEW copies the data and every handle inside it when the event is
+/// posted, so the handles always stay owned by the caller. Use
+/// [`LVUserEvent::post`] to send data that is borrowed, such as an input from
+/// LabVIEW, or [`LVUserEvent::post_and_dispose`] to send data built in Rust
+/// just for the event.
+///
 /// # Example
 /// ```
 /// # use labview_interop::sync::LVUserEvent;
@@ -26,59 +37,178 @@ type LVUserEventRef = MagicCookie;
 ///#[no_mangle]
 ///pub extern "C" fn generate_event_3(lv_user_event: *mut LVUserEvent<i32>) -> MgErr {
 ///    let event = unsafe { *lv_user_event };
-///    let result = event.post(&mut 3);
+///    let result = event.post(&3);
 ///    match result {
 ///        Ok(_) => MgErr::NO_ERROR,
 ///        Err(err) => err.into(),
 ///    }
 ///}
 /// ```
-#[derive(Copy, Clone)]
 #[repr(transparent)]
 pub struct LVUserEvent<T> {
-    reference: LVUserEventRef,
-    _marker: PhantomData<T>,
+    reference: LVRefNum<T>,
+}
+
+impl<T> Clone for LVUserEvent<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for LVUserEvent<T> {}
+
+impl<T> From<LVRefNum<T>> for LVUserEvent<T> {
+    fn from(reference: LVRefNum<T>) -> Self {
+        Self { reference }
+    }
 }
 
 impl<T> LVUserEvent<T> {
+    /// The refnum of the user event.
+    pub fn refnum(&self) -> LVRefNum<T> {
+        self.reference
+    }
+
+    /// Whether the event is "not a refnum", such as an unwired terminal.
+    pub fn is_null(&self) -> bool {
+        self.reference.is_null()
+    }
+
     /// Generate the user event with the provided data.
     ///
-    /// Right now the data needs to be a mutable reference as the
-    /// LabVIEW API does not specify whether it will not be modified.
-    pub fn post(&self, data: &mut T) -> Result<()> {
+    /// LabVIEW copies the data, including the contents of any handles, into
+    /// the event queue so it is only read and can be reused or dropped
+    /// straight after. This can be called from any thread.
+    pub fn post(&self, data: &T) -> Result<()> {
         let mg_err = unsafe {
-            sync_api()?.post_lv_user_event(self.reference, data as *mut T as *mut c_void)
+            sync_api()?.post_lv_user_event(self.reference.cookie(), data as *const T as *mut c_void)
         };
         mg_err.to_result(())
     }
 }
 
-/// A LabVIEW occurence which can be used to provide synchronisation
+impl<T: DeepDispose> LVUserEvent<T> {
+    /// Generate the user event with data built for it, then dispose the data.
+    ///
+    /// As LabVIEW posts a copy of the data, the handles in `data` would leak
+    /// if they were allocated just for the event. This posts the data and
+    /// disposes every handle in it, whether or not the post succeeded.
+    ///
+    /// # Safety
+    ///
+    /// * The handles in `data` must be valid and owned by the caller.
+    ///
+    /// # Example
+    /// ```
+    /// use labview_interop::errors::MgErr;
+    /// use labview_interop::labview_layout;
+    /// use labview_interop::memory::{DeepDispose, UHandle};
+    /// use labview_interop::sync::LVUserEvent;
+    /// use labview_interop::types::{LStrHandle, LStrOwned, LVArrayHandle};
+    ///
+    /// labview_layout!(
+    ///     pub struct Reading {
+    ///         channel: LStrHandle,
+    ///         samples: LVArrayHandle<1, f64>,
+    ///     }
+    /// );
+    ///
+    /// impl DeepDispose for Reading {
+    ///     unsafe fn deep_dispose(self) -> labview_interop::errors::Result<()> {
+    ///         let Reading { channel, samples } = self;
+    ///         channel.deep_dispose()?;
+    ///         samples.deep_dispose()
+    ///     }
+    /// }
+    ///
+    /// #[no_mangle]
+    /// pub extern "C" fn post_reading(event: *mut LVUserEvent<Reading>) -> MgErr {
+    ///     let event = unsafe { *event };
+    ///     let reading = Reading {
+    ///         channel: LStrOwned::from_data(b"ai0").unwrap().into_raw(),
+    ///         samples: UHandle(std::ptr::null_mut()),
+    ///     };
+    ///     unsafe { event.post_and_dispose(reading) }.into()
+    /// }
+    /// ```
+    pub unsafe fn post_and_dispose(&self, data: T) -> Result<()> {
+        let posted = self.post(&data);
+        let disposed = data.deep_dispose();
+        posted.and(disposed)
+    }
+}
+
+impl<T> std::fmt::Debug for LVUserEvent<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("LVUserEvent").field(&self.reference).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    struct Payload<'a>(&'a Cell<bool>);
+
+    impl DeepDispose for Payload<'_> {
+        unsafe fn deep_dispose(self) -> Result<()> {
+            self.0.set(true);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_post_and_dispose_disposes_when_post_fails() {
+        let disposed = Cell::new(false);
+        let event: LVUserEvent<Payload> = LVRefNum::null().into();
+        // There is no LabVIEW to post to in tests.
+        let result = unsafe { event.post_and_dispose(Payload(&disposed)) };
+        assert!(result.is_err());
+        assert!(disposed.get());
+    }
+}
+
+/// A LabVIEW occurrence which can be used to provide synchronisation
 /// between execution of Rust and LabVIEW code.
 ///
+/// This is the classic completion signal for asynchronous work: the VI passes
+/// an occurrence from Generate Occurrence to Rust and waits on it with Wait on
+/// Occurrence, and a Rust thread calls [`LVOccurrence::set`] when the work is
+/// done. It is a plain reference so it can be copied to other threads.
+///
 /// From LabVIEW you can set the terminal to be `adapt to type` and `handles by value`
 ///
 /// # Example
 /// ```
-/// # use labview_interop::sync::Occurence;
+/// # use labview_interop::sync::LVOccurrence;
 /// # use labview_interop::errors::MgErr;
 /// #[no_mangle]
-///pub extern "C" fn generate_occurence(occurence: *mut Occurence) -> MgErr {
-///    let result = unsafe { (*occurence).set() };
-///    match result {
-///        Ok(_) => MgErr::NO_ERROR,
-///        Err(err) => err.into(),
-///    }
-///}
+/// pub extern "C" fn start_work(occurrence: *mut LVOccurrence) -> MgErr {
+///     let Some(occurrence) = (unsafe { occurrence.as_ref() }).copied() else {
+///         return MgErr::ARGUMENT_ERROR;
+///     };
+///     std::thread::spawn(move || {
+///         // Do the work...
+///         let _ = occurrence.set();
+///     });
+///     MgErr::NO_ERROR
+/// }
 /// ```
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 #[repr(transparent)]
-pub struct Occurence(MagicCookie);
+pub struct LVOccurrence(MagicCookie);
 
-impl Occurence {
-    /// "set" generates the occurence event which can be detected by LabVIEW.
+impl LVOccurrence {
+    /// "set" generates the occurrence event which can be detected by LabVIEW.
+    ///
+    /// This can be called from any thread.
     pub fn set(&self) -> Result<()> {
         let mg_err = unsafe { sync_api()?.occur(self.0) };
         mg_err.to_result(())
     }
 }
+
+/// The previous, misspelt, name for [`LVOccurrence`].
+#[deprecated(note = "renamed to LVOccurrence")]
+pub type Occurence = LVOccurrence;
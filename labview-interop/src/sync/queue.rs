@@ -0,0 +1,474 @@
+//! A bounded queue from Rust producers to a LabVIEW consumer loop.
+//!
+//! [`LvQueue`] is a simpler alternative to user events for VIs which consume
+//! data in a loop. Rust threads push items, blocking when the queue is full so
+//! a slow consumer applies backpressure. LabVIEW dequeues items through
+//! exports generated with [`lv_queue_exports!`](crate::lv_queue_exports).
+//!
+//! The queue sets an occurrence whenever an item is pushed so the VI can wait
+//! without polling:
+//!
+//! 1. Create an occurrence with Generate Occurrence and pass it to the
+//!    `set_occurrence` export once.
+//! 2. In the loop, call the `dequeue` export. If nothing was dequeued, use
+//!    Wait on Occurrence with the timeout and "ignore previous" false, then
+//!    call the `dequeue` export again.
+//!
+//! The dequeue export doesn't block so it never ties up a LabVIEW execution
+//! thread. Simple consumers can instead call an optional `dequeue_timeout`
+//! export, which waits in Rust for up to the timeout. That blocks the thread
+//! running the Call Library node, so configure it to run in any thread.
+//!
+//! An item stays queued until it has been written to LabVIEW's output, so it
+//! isn't lost if that fails.
+//!
+//! # Example
+//! ```
+//! use labview_interop::sync::queue::LvQueue;
+//! use labview_interop::lv_queue_exports;
+//!
+//! static READINGS: LvQueue<f64> = LvQueue::new(1000);
+//!
+//! lv_queue_exports!(READINGS: f64, set_occurrence = readings_set_occurrence, dequeue = readings_dequeue);
+//!
+//! // From a Rust acquisition thread.
+//! READINGS.push(1.5).unwrap();
+//! assert_eq!(READINGS.try_pop(), Some(1.5));
+//! ```
+
+use std::collections::VecDeque;
+use std::sync::{Condvar, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use super::LVOccurrence;
+use crate::errors::{LVInteropError, MgErr};
+use crate::types::{LVBool, LvData};
+
+struct QueueState<T> {
+    items: VecDeque<T>,
+    occurrence: Option<LVOccurrence>,
+    closed: bool,
+}
+
+/// Why an item couldn't be pushed. The item is returned.
+#[derive(Debug, PartialEq, Eq)]
+pub enum PushError<T> {
+    /// The queue was still full at the timeout.
+    Full(T),
+    /// The queue has been closed.
+    Closed(T),
+}
+
+impl<T> PushError<T> {
+    pub fn into_inner(self) -> T {
+        match self {
+            PushError::Full(item) | PushError::Closed(item) => item,
+        }
+    }
+}
+
+/// A bounded queue of items for LabVIEW.
+///
+/// This is designed to be used as a `static` shared by the producers and the
+/// generated exports.
+pub struct LvQueue<T> {
+    state: Mutex<QueueState<T>>,
+    not_full: Condvar,
+    not_empty: Condvar,
+    capacity: usize,
+}
+
+impl<T> LvQueue<T> {
+    /// Create a queue holding up to `capacity` items. A capacity of 0 is treated as 1.
+    pub const fn new(capacity: usize) -> Self {
+        Self {
+            state: Mutex::new(QueueState {
+                items: VecDeque::new(),
+                occurrence: None,
+                closed: false,
+            }),
+            not_full: Condvar::new(),
+            not_empty: Condvar::new(),
+            capacity: if capacity == 0 { 1 } else { capacity },
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, QueueState<T>> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Push an item, waiting for space while the queue is full.
+    pub fn push(&self, item: T) -> Result<(), PushError<T>> {
+        self.push_until(item, None)
+    }
+
+    /// Push an item, waiting up to `timeout` for space.
+    pub fn push_timeout(&self, item: T, timeout: Duration) -> Result<(), PushError<T>> {
+        self.push_until(item, Some(Instant::now() + timeout))
+    }
+
+    /// Push an item if there is space.
+    pub fn try_push(&self, item: T) -> Result<(), PushError<T>> {
+        self.push_until(item, Some(Instant::now()))
+    }
+
+    fn push_until(&self, item: T, deadline: Option<Instant>) -> Result<(), PushError<T>> {
+        let mut state = self.lock();
+        loop {
+            if state.closed {
+                return Err(PushError::Closed(item));
+            }
+            if state.items.len() < self.capacity {
+                break;
+            }
+            state = match deadline {
+                None => self
+                    .not_full
+                    .wait(state)
+                    .unwrap_or_else(|poisoned| poisoned.into_inner()),
+                Some(deadline) => {
+                    let remaining = deadline.saturating_duration_since(Instant::now());
+                    if remaining.is_zero() {
+                        return Err(PushError::Full(item));
+                    }
+                    self.not_full
+                        .wait_timeout(state, remaining)
+                        .unwrap_or_else(|poisoned| poisoned.into_inner())
+                        .0
+                }
+            };
+        }
+        state.items.push_back(item);
+        let occurrence = state.occurrence;
+        drop(state);
+        self.not_empty.notify_one();
+        if let Some(occurrence) = occurrence {
+            // If LabVIEW has gone the next dequeue will find the item anyway.
+            let _ = occurrence.set();
+        }
+        Ok(())
+    }
+
+    /// Take the next item if there is one.
+    pub fn try_pop(&self) -> Option<T> {
+        self.pop_timeout(Duration::ZERO)
+    }
+
+    /// Take the next item, waiting up to `timeout` for one.
+    ///
+    /// [`Duration::MAX`] waits until an item is pushed or the queue is closed.
+    pub fn pop_timeout(&self, timeout: Duration) -> Option<T> {
+        let mut state = self.wait_for_item(timeout)?;
+        let item = state.items.pop_front();
+        drop(state);
+        self.not_full.notify_one();
+        item
+    }
+
+    /// Pass the next item to `write`, waiting up to `timeout` for one, and
+    /// remove it from the queue only if `write` succeeds.
+    ///
+    /// This is how the generated exports write items into LabVIEW's output so
+    /// an item which fails to convert stays at the front of the queue. Returns
+    /// `None` if there was no item. The queue is locked while `write` runs so
+    /// it shouldn't block.
+    pub fn dequeue_timeout<R, E>(
+        &self,
+        timeout: Duration,
+        write: impl FnOnce(&T) -> Result<R, E>,
+    ) -> Option<Result<R, E>> {
+        let mut state = self.wait_for_item(timeout)?;
+        let result = write(state.items.front()?);
+        if result.is_ok() {
+            state.items.pop_front();
+            drop(state);
+            self.not_full.notify_one();
+        }
+        Some(result)
+    }
+
+    /// Wait up to `timeout` for an item, returning the locked state if there is one.
+    fn wait_for_item(&self, timeout: Duration) -> Option<MutexGuard<'_, QueueState<T>>> {
+        let deadline = Instant::now().checked_add(timeout);
+        let mut state = self.lock();
+        while state.items.is_empty() {
+            if state.closed {
+                return None;
+            }
+            state = match deadline {
+                None => self
+                    .not_empty
+                    .wait(state)
+                    .unwrap_or_else(|poisoned| poisoned.into_inner()),
+                Some(deadline) => {
+                    let remaining = deadline.saturating_duration_since(Instant::now());
+                    if remaining.is_zero() {
+                        return None;
+                    }
+                    self.not_empty
+                        .wait_timeout(state, remaining)
+                        .unwrap_or_else(|poisoned| poisoned.into_inner())
+                        .0
+                }
+            };
+        }
+        Some(state)
+    }
+
+    /// Set the occurrence to set when items are pushed.
+    ///
+    /// If items are already waiting it is set straight away.
+    pub fn set_occurrence(&self, occurrence: LVOccurrence) {
+        let mut state = self.lock();
+        state.occurrence = Some(occurrence);
+        if !state.items.is_empty() {
+            let _ = occurrence.set();
+        }
+    }
+
+    /// Stop accepting items and wake any blocked producers.
+    ///
+    /// Items already queued can still be dequeued.
+    pub fn close(&self) {
+        self.lock().closed = true;
+        self.not_full.notify_all();
+        self.not_empty.notify_all();
+    }
+
+    /// Accept items again after [`close`](LvQueue::close).
+    pub fn reopen(&self) {
+        self.lock().closed = false;
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.lock().closed
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock().items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+}
+
+/// Write the next item for a generated dequeue export.
+///
+/// # Safety
+///
+/// * `value` and `dequeued` must be null or valid for writes.
+#[doc(hidden)]
+pub unsafe fn dequeue_export<T: LvData>(
+    queue: &LvQueue<T>,
+    timeout: Duration,
+    value: *mut T::Repr,
+    dequeued: *mut LVBool,
+) -> MgErr {
+    let (Some(value), Some(dequeued)) = (value.as_mut(), dequeued.as_mut()) else {
+        return LVInteropError::InvalidHandle.into();
+    };
+    let result = queue.dequeue_timeout(timeout, |item| item.to_lv(value));
+    *dequeued = matches!(result, Some(Ok(()))).into();
+    match result {
+        Some(result) => result.into(),
+        None => MgErr::NO_ERROR,
+    }
+}
+
+/// Generate the exports LabVIEW uses to consume an [`LvQueue`].
+///
+/// * `set_occurrence(occurrence: *mut LVOccurrence) -> MgErr` registers the
+///   occurrence. Configure the parameter as "Adapt to Type", "Handles by Value".
+/// * `dequeue(value: *mut Repr, dequeued: *mut LVBool) -> MgErr` writes the next
+///   item into `value` with [`LvData::to_lv`](crate::types::LvData::to_lv) and
+///   sets `dequeued`. It returns straight away if the queue is empty.
+/// * The optional `dequeue_timeout(timeout_ms: i32, value: *mut Repr, dequeued:
+///   *mut LVBool) -> MgErr` is the same but waits up to `timeout_ms` for an
+///   item, or until the queue is closed if it is negative.
+///
+/// `Repr` is the [`LvData::Repr`](crate::types::LvData::Repr) of the item type,
+/// so pass numerics as a pointer to value and strings or arrays as a handle pointer.
+///
+/// # Example
+/// ```
+/// use labview_interop::sync::queue::LvQueue;
+/// use labview_interop::lv_queue_exports;
+///
+/// static SAMPLES: LvQueue<i32> = LvQueue::new(100);
+///
+/// lv_queue_exports!(
+///     SAMPLES: i32,
+///     set_occurrence = samples_set_occurrence,
+///     dequeue = samples_dequeue,
+///     dequeue_timeout = samples_dequeue_timeout
+/// );
+/// ```
+#[macro_export]
+macro_rules! lv_queue_exports {
+    (
+        $queue:path : $type:ty,
+        set_occurrence = $set_occurrence:ident,
+        dequeue = $dequeue:ident
+        $(, dequeue_timeout = $dequeue_timeout:ident)? $(,)?
+    ) => {
+        #[no_mangle]
+        pub extern "C" fn $set_occurrence(
+            occurrence: *mut $crate::sync::LVOccurrence,
+        ) -> $crate::errors::MgErr {
+            $crate::export::catch_panic_status(|| match unsafe { occurrence.as_ref() } {
+                Some(occurrence) => {
+                    $queue.set_occurrence(*occurrence);
+                    $crate::errors::MgErr::NO_ERROR
+                }
+                None => $crate::errors::LVInteropError::InvalidHandle.into(),
+            })
+        }
+
+        #[no_mangle]
+        pub extern "C" fn $dequeue(
+            value: *mut <$type as $crate::types::LvData>::Repr,
+            dequeued: *mut $crate::types::LVBool,
+        ) -> $crate::errors::MgErr {
+            $crate::export::catch_panic_status(|| unsafe {
+                $crate::sync::queue::dequeue_export(
+                    &$queue,
+                    ::std::time::Duration::ZERO,
+                    value,
+                    dequeued,
+                )
+            })
+        }
+
+        $(
+            #[no_mangle]
+            pub extern "C" fn $dequeue_timeout(
+                timeout_ms: i32,
+                value: *mut <$type as $crate::types::LvData>::Repr,
+                dequeued: *mut $crate::types::LVBool,
+            ) -> $crate::errors::MgErr {
+                // A negative timeout waits forever, as in LabVIEW.
+                let timeout = u64::try_from(timeout_ms)
+                    .map(::std::time::Duration::from_millis)
+                    .unwrap_or(::std::time::Duration::MAX);
+                $crate::export::catch_panic_status(|| unsafe {
+                    $crate::sync::queue::dequeue_export(&$queue, timeout, value, dequeued)
+                })
+            }
+        )?
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_push_blocks_until_space() {
+        let queue = Arc::new(LvQueue::new(2));
+        queue.push(1).unwrap();
+        queue.push(2).unwrap();
+        assert_eq!(queue.try_push(3), Err(PushError::Full(3)));
+
+        let producer = {
+            let queue = queue.clone();
+            std::thread::spawn(move || queue.push(3))
+        };
+        std::thread::sleep(Duration::from_millis(20));
+        assert_eq!(queue.try_pop(), Some(1));
+        producer.join().unwrap().unwrap();
+        assert_eq!(queue.try_pop(), Some(2));
+        assert_eq!(queue.try_pop(), Some(3));
+        assert_eq!(queue.try_pop(), None);
+    }
+
+    #[test]
+    fn test_close_wakes_producers() {
+        let queue = Arc::new(LvQueue::new(1));
+        queue.push("first").unwrap();
+        let producer = {
+            let queue = queue.clone();
+            std::thread::spawn(move || queue.push("second"))
+        };
+        std::thread::sleep(Duration::from_millis(20));
+        queue.close();
+        assert_eq!(producer.join().unwrap(), Err(PushError::Closed("second")));
+        assert_eq!(queue.try_pop(), Some("first"));
+        assert!(queue.push_timeout("third", Duration::ZERO).is_err());
+    }
+
+    #[test]
+    fn test_pop_timeout_waits_for_item() {
+        let queue = Arc::new(LvQueue::new(1));
+        assert_eq!(queue.pop_timeout(Duration::from_millis(10)), None);
+
+        let consumer = {
+            let queue = queue.clone();
+            std::thread::spawn(move || queue.pop_timeout(Duration::MAX))
+        };
+        std::thread::sleep(Duration::from_millis(20));
+        queue.push(5).unwrap();
+        assert_eq!(consumer.join().unwrap(), Some(5));
+
+        // Closing wakes consumers once the queue is empty.
+        let consumer = {
+            let queue = queue.clone();
+            std::thread::spawn(move || queue.pop_timeout(Duration::MAX))
+        };
+        std::thread::sleep(Duration::from_millis(20));
+        queue.close();
+        assert_eq!(consumer.join().unwrap(), None);
+    }
+
+    #[test]
+    fn test_failed_write_keeps_item() {
+        let queue = LvQueue::new(2);
+        queue.push(1).unwrap();
+        queue.push(2).unwrap();
+        let failed = queue.dequeue_timeout(Duration::ZERO, |_| Err::<(), _>("conversion failed"));
+        assert_eq!(failed, Some(Err("conversion failed")));
+        assert_eq!(queue.len(), 2);
+
+        let written = queue.dequeue_timeout(Duration::ZERO, |item| Ok::<_, ()>(*item * 10));
+        assert_eq!(written, Some(Ok(10)));
+        assert_eq!(queue.try_pop(), Some(2));
+    }
+
+    #[test]
+    fn test_dequeue_export() {
+        static QUEUE: LvQueue<f64> = LvQueue::new(4);
+        crate::lv_queue_exports!(
+            QUEUE: f64,
+            set_occurrence = test_queue_set_occurrence,
+            dequeue = test_queue_dequeue,
+            dequeue_timeout = test_queue_dequeue_timeout
+        );
+
+        let mut value = 0.0;
+        let mut dequeued = LVBool::from(true);
+        assert_eq!(
+            test_queue_dequeue(&mut value, &mut dequeued),
+            MgErr::NO_ERROR
+        );
+        assert!(!bool::from(dequeued));
+
+        QUEUE.push(2.5).unwrap();
+        assert_eq!(
+            test_queue_dequeue_timeout(100, &mut value, &mut dequeued),
+            MgErr::NO_ERROR
+        );
+        assert!(bool::from(dequeued));
+        assert_eq!(value, 2.5);
+        assert_ne!(
+            test_queue_dequeue(std::ptr::null_mut(), &mut dequeued),
+            MgErr::NO_ERROR
+        );
+    }
+}
@@ -0,0 +1,366 @@
+//! The TDMS module writes TDMS files directly from Rust using NI's
+//! TDM C DLL (nilibddc), which is installed with the TDM C API.
+//!
+//! This lets acquisition code stream data straight to files that LabVIEW reads
+//! natively without passing it back through LabVIEW to log it.
+//!
+//! The DLL is loaded the first time a file is created or opened so libraries
+//! using this feature still load on systems without it installed.
+//!
+//! # Example
+//! ```no_run
+//! use labview_interop::tdms::TdmsFile;
+//!
+//! # fn main() -> labview_interop::errors::Result<()> {
+//! let file = TdmsFile::create("measurements.tdms", "Acquisition")?;
+//! file.set_property("operator", "A. Tester")?;
+//!
+//! let group = file.add_group("Voltages", "")?;
+//! let channel = group.add_channel::<f64>("AI0", "", "V")?;
+//! channel.set_property("gain", 2.5)?;
+//! channel.append(&[0.1, 0.2, 0.3])?;
+//!
+//! file.save()?;
+//! # Ok(())
+//! # }
+//! ```
+
+// The generated wrapper for DDC_CreateFile takes the container as well as its arguments.
+#![allow(clippy::too_many_arguments)]
+
+use std::ffi::{c_char, c_int, c_void, CString};
+use std::marker::PhantomData;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use dlopen2::wrapper::{Container, WrapperApi};
+
+use crate::errors::{LVInteropError, Result};
+
+#[cfg(target_os = "windows")]
+const TDMS_LIBRARY: &str = "nilibddc.dll";
+#[cfg(not(target_os = "windows"))]
+const TDMS_LIBRARY: &str = "libnilibddc.so";
+
+type DdcHandle = *mut c_void;
+
+/// The data type codes used by the TDM C DLL.
+mod data_type {
+    pub const UINT8: i32 = 5;
+    pub const INT16: i32 = 2;
+    pub const INT32: i32 = 3;
+    pub const FLOAT: i32 = 9;
+    pub const DOUBLE: i32 = 10;
+    pub const STRING: i32 = 23;
+}
+
+#[derive(WrapperApi)]
+struct TdmsApi {
+    #[dlopen2_name = "DDC_CreateFile"]
+    create_file: unsafe extern "C" fn(
+        path: *const c_char,
+        file_type: *const c_char,
+        name: *const c_char,
+        description: *const c_char,
+        title: *const c_char,
+        author: *const c_char,
+        file: *mut DdcHandle,
+    ) -> c_int,
+    #[dlopen2_name = "DDC_OpenFileEx"]
+    open_file: unsafe extern "C" fn(
+        path: *const c_char,
+        file_type: *const c_char,
+        read_only: c_int,
+        file: *mut DdcHandle,
+    ) -> c_int,
+    #[dlopen2_name = "DDC_SaveFile"]
+    save_file: unsafe extern "C" fn(file: DdcHandle) -> c_int,
+    #[dlopen2_name = "DDC_CloseFile"]
+    close_file: unsafe extern "C" fn(file: DdcHandle) -> c_int,
+    #[dlopen2_name = "DDC_AddChannelGroup"]
+    add_channel_group: unsafe extern "C" fn(
+        file: DdcHandle,
+        name: *const c_char,
+        description: *const c_char,
+        group: *mut DdcHandle,
+    ) -> c_int,
+    #[dlopen2_name = "DDC_AddChannel"]
+    add_channel: unsafe extern "C" fn(
+        group: DdcHandle,
+        data_type: c_int,
+        name: *const c_char,
+        description: *const c_char,
+        unit: *const c_char,
+        channel: *mut DdcHandle,
+    ) -> c_int,
+    #[dlopen2_name = "DDC_AppendDataValues"]
+    append_data_values:
+        unsafe extern "C" fn(channel: DdcHandle, values: *const c_void, count: usize) -> c_int,
+    #[dlopen2_name = "DDC_CreateFileProperty"]
+    create_file_property:
+        unsafe extern "C" fn(file: DdcHandle, name: *const c_char, data_type: c_int, ...) -> c_int,
+    #[dlopen2_name = "DDC_CreateChannelGroupProperty"]
+    create_group_property:
+        unsafe extern "C" fn(group: DdcHandle, name: *const c_char, data_type: c_int, ...) -> c_int,
+    #[dlopen2_name = "DDC_CreateChannelProperty"]
+    create_channel_property: unsafe extern "C" fn(
+        channel: DdcHandle,
+        name: *const c_char,
+        data_type: c_int,
+        ...
+    ) -> c_int,
+}
+
+fn tdms_api() -> Result<&'static Container<TdmsApi>> {
+    static TDMS_API: OnceLock<Option<Container<TdmsApi>>> = OnceLock::new();
+    TDMS_API
+        .get_or_init(|| unsafe { Container::load(TDMS_LIBRARY) }.ok())
+        .as_ref()
+        .ok_or(LVInteropError::TdmsUnavailable)
+}
+
+fn check(code: c_int) -> Result<()> {
+    match code {
+        0 => Ok(()),
+        code => Err(LVInteropError::TdmsError(code)),
+    }
+}
+
+fn c_string(value: &str) -> Result<CString> {
+    CString::new(value).map_err(|_| LVInteropError::NulInString(value.to_string()))
+}
+
+/// Element types which can be stored in a TDMS channel.
+pub trait TdmsDataType: Copy {
+    /// The code used by the TDM C DLL for the type.
+    const DATA_TYPE: i32;
+}
+
+impl TdmsDataType for u8 {
+    const DATA_TYPE: i32 = data_type::UINT8;
+}
+
+impl TdmsDataType for i16 {
+    const DATA_TYPE: i32 = data_type::INT16;
+}
+
+impl TdmsDataType for i32 {
+    const DATA_TYPE: i32 = data_type::INT32;
+}
+
+impl TdmsDataType for f32 {
+    const DATA_TYPE: i32 = data_type::FLOAT;
+}
+
+impl TdmsDataType for f64 {
+    const DATA_TYPE: i32 = data_type::DOUBLE;
+}
+
+/// A property value for a file, group or channel.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TdmsProperty<'a> {
+    Int32(i32),
+    Double(f64),
+    String(&'a str),
+}
+
+impl From<i32> for TdmsProperty<'_> {
+    fn from(value: i32) -> Self {
+        Self::Int32(value)
+    }
+}
+
+impl From<f64> for TdmsProperty<'_> {
+    fn from(value: f64) -> Self {
+        Self::Double(value)
+    }
+}
+
+impl<'a> From<&'a str> for TdmsProperty<'a> {
+    fn from(value: &'a str) -> Self {
+        Self::String(value)
+    }
+}
+
+/// The property functions of the DLL share a variadic signature.
+type CreateProperty = unsafe extern "C" fn(DdcHandle, *const c_char, c_int, ...) -> c_int;
+
+fn create_property(
+    create: CreateProperty,
+    handle: DdcHandle,
+    name: &str,
+    value: TdmsProperty<'_>,
+) -> Result<()> {
+    let name = c_string(name)?;
+    let code = unsafe {
+        match value {
+            TdmsProperty::Int32(value) => create(handle, name.as_ptr(), data_type::INT32, value),
+            TdmsProperty::Double(value) => create(handle, name.as_ptr(), data_type::DOUBLE, value),
+            TdmsProperty::String(value) => {
+                let value = c_string(value)?;
+                create(handle, name.as_ptr(), data_type::STRING, value.as_ptr())
+            }
+        }
+    };
+    check(code)
+}
+
+/// An open TDMS file. The file is closed when this is dropped.
+pub struct TdmsFile {
+    api: &'static Container<TdmsApi>,
+    handle: DdcHandle,
+}
+
+impl TdmsFile {
+    /// Create a new TDMS file with the given name stored in the file properties.
+    ///
+    /// # Errors
+    ///
+    /// * [`LVInteropError::TdmsUnavailable`] if the TDM C DLL isn't installed.
+    /// * [`LVInteropError::TdmsError`] if the file can't be created, for example if it already exists.
+    pub fn create(path: impl AsRef<Path>, name: &str) -> Result<Self> {
+        let api = tdms_api()?;
+        let path = c_string(&path.as_ref().to_string_lossy())?;
+        let file_type = c_string("TDMS")?;
+        let name = c_string(name)?;
+        let empty = c_string("")?;
+        let mut handle = std::ptr::null_mut();
+        check(unsafe {
+            api.create_file(
+                path.as_ptr(),
+                file_type.as_ptr(),
+                name.as_ptr(),
+                empty.as_ptr(),
+                empty.as_ptr(),
+                empty.as_ptr(),
+                &mut handle,
+            )
+        })?;
+        Ok(Self { api, handle })
+    }
+
+    /// Open an existing TDMS file to add data to it.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let api = tdms_api()?;
+        let path = c_string(&path.as_ref().to_string_lossy())?;
+        let file_type = c_string("TDMS")?;
+        let mut handle = std::ptr::null_mut();
+        check(unsafe { api.open_file(path.as_ptr(), file_type.as_ptr(), 0, &mut handle) })?;
+        Ok(Self { api, handle })
+    }
+
+    /// Add a channel group to the file.
+    pub fn add_group(&self, name: &str, description: &str) -> Result<TdmsGroup<'_>> {
+        let name = c_string(name)?;
+        let description = c_string(description)?;
+        let mut handle = std::ptr::null_mut();
+        check(unsafe {
+            self.api.add_channel_group(
+                self.handle,
+                name.as_ptr(),
+                description.as_ptr(),
+                &mut handle,
+            )
+        })?;
+        Ok(TdmsGroup { file: self, handle })
+    }
+
+    /// Create a property on the file.
+    pub fn set_property<'a>(&self, name: &str, value: impl Into<TdmsProperty<'a>>) -> Result<()> {
+        create_property(
+            self.api.create_file_property,
+            self.handle,
+            name,
+            value.into(),
+        )
+    }
+
+    /// Write everything added so far to disk.
+    pub fn save(&self) -> Result<()> {
+        check(unsafe { self.api.save_file(self.handle) })
+    }
+}
+
+impl Drop for TdmsFile {
+    fn drop(&mut self) {
+        unsafe { self.api.close_file(self.handle) };
+    }
+}
+
+/// A channel group in a [`TdmsFile`].
+pub struct TdmsGroup<'file> {
+    file: &'file TdmsFile,
+    handle: DdcHandle,
+}
+
+impl<'file> TdmsGroup<'file> {
+    /// Add a channel of `T` to the group.
+    pub fn add_channel<T: TdmsDataType>(
+        &self,
+        name: &str,
+        description: &str,
+        unit: &str,
+    ) -> Result<TdmsChannel<'file, T>> {
+        let name = c_string(name)?;
+        let description = c_string(description)?;
+        let unit = c_string(unit)?;
+        let mut handle = std::ptr::null_mut();
+        check(unsafe {
+            self.file.api.add_channel(
+                self.handle,
+                T::DATA_TYPE,
+                name.as_ptr(),
+                description.as_ptr(),
+                unit.as_ptr(),
+                &mut handle,
+            )
+        })?;
+        Ok(TdmsChannel {
+            file: self.file,
+            handle,
+            _marker: PhantomData,
+        })
+    }
+
+    /// Create a property on the group.
+    pub fn set_property<'a>(&self, name: &str, value: impl Into<TdmsProperty<'a>>) -> Result<()> {
+        create_property(
+            self.file.api.create_group_property,
+            self.handle,
+            name,
+            value.into(),
+        )
+    }
+}
+
+/// A channel of `T` in a [`TdmsGroup`].
+pub struct TdmsChannel<'file, T> {
+    file: &'file TdmsFile,
+    handle: DdcHandle,
+    _marker: PhantomData<T>,
+}
+
+impl<T: TdmsDataType> TdmsChannel<'_, T> {
+    /// Append values to the channel.
+    ///
+    /// Data is buffered by the DLL until the file is saved.
+    pub fn append(&self, values: &[T]) -> Result<()> {
+        check(unsafe {
+            self.file.api.append_data_values(
+                self.handle,
+                values.as_ptr() as *const c_void,
+                values.len(),
+            )
+        })
+    }
+
+    /// Create a property on the channel.
+    pub fn set_property<'a>(&self, name: &str, value: impl Into<TdmsProperty<'a>>) -> Result<()> {
+        create_property(
+            self.file.api.create_channel_property,
+            self.handle,
+            name,
+            value.into(),
+        )
+    }
+}
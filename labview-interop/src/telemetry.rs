@@ -0,0 +1,471 @@
+//! A [`tracing`](https://docs.rs/tracing) layer which forwards events and spans to LabVIEW.
+//!
+//! [`LabviewLayer`] turns every event, and every time a span is entered or
+//! exited, into a [`TraceEntry`]. Span exits include the time since the span
+//! was entered so the layer doubles as simple timing. Entries reach LabVIEW in
+//! either or both of two ways:
+//!
+//! * Posted to a user event registered with `interop_trace_register`, as a
+//!   [`TraceRecord`] cluster. This suits an event structure in a UI loop.
+//! * Kept in a ring buffer which LabVIEW reads with `interop_trace_pop`. This
+//!   suits polling and doesn't need an event. The oldest entries are dropped
+//!   once it is full.
+//!
+//! Configure the cluster for either as, in order:
+//!
+//! 1. `timestamp`: a timestamp.
+//! 2. `kind`: a U16 enum of Event, Enter and Exit.
+//! 3. `level`: a U16 enum of Error, Warn, Info, Debug and Trace.
+//! 4. `target`: a string, usually the Rust module path.
+//! 5. `name`: a string with the span name, or the event name.
+//! 6. `message`: a string with the message and fields.
+//! 7. `elapsed`: a DBL with the seconds since the span was entered, for exits.
+//!
+//! # Example
+//! ```
+//! use labview_interop::telemetry::{self, TraceKind};
+//!
+//! // Install the layer as the global subscriber, usually from an on load hook.
+//! telemetry::init().unwrap();
+//! telemetry::set_buffer_capacity(100);
+//!
+//! tracing::info_span!("connect", port = "COM1").in_scope(|| {
+//!     tracing::info!(attempt = 1, "opening port");
+//! });
+//!
+//! let entries: Vec<_> = std::iter::from_fn(telemetry::pop).collect();
+//! assert_eq!(entries[0].kind, TraceKind::Enter);
+//! assert_eq!(entries[1].message, "opening port attempt=1");
+//! assert_eq!(entries[2].kind, TraceKind::Exit);
+//! ```
+
+use std::collections::VecDeque;
+use std::fmt::Write;
+use std::ptr::addr_of_mut;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant, SystemTime};
+
+use tracing_core::field::{Field, Visit};
+use tracing_core::span::{Attributes, Id};
+use tracing_core::{Event, Level, Metadata, Subscriber};
+use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+use tracing_subscriber::registry::LookupSpan;
+
+use crate::errors::{LVInteropError, MgErr, Result};
+use crate::labview_layout;
+use crate::memory::DeepDispose;
+use crate::sync::LVUserEvent;
+use crate::types::{LStrHandle, LStrOwned, LVBool, LVTime};
+
+/// What a trace entry records.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u16)]
+pub enum TraceKind {
+    /// An event, such as from `tracing::info!`.
+    Event = 0,
+    /// A span was entered.
+    Enter = 1,
+    /// A span was exited.
+    Exit = 2,
+}
+
+/// An event or span transition captured by [`LabviewLayer`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceEntry {
+    pub timestamp: SystemTime,
+    pub kind: TraceKind,
+    pub level: Level,
+    pub target: String,
+    /// The span name for enter and exit, or the event name.
+    pub name: String,
+    /// The message followed by the other fields as `name=value`.
+    pub message: String,
+    /// The time since the span was entered, for exits.
+    pub elapsed: Option<Duration>,
+}
+
+labview_layout!(
+    /// The LabVIEW cluster for a [`TraceEntry`].
+    pub struct TraceRecord {
+        pub timestamp: LVTime,
+        pub kind: u16,
+        /// The level as a zero based enum: Error, Warn, Info, Debug, Trace.
+        pub level: u16,
+        pub target: LStrHandle,
+        pub name: LStrHandle,
+        pub message: LStrHandle,
+        /// Seconds since the span was entered, or 0.
+        pub elapsed: f64,
+    }
+);
+
+impl TraceRecord {
+    /// Build the cluster for an entry, allocating its strings.
+    pub fn new(entry: &TraceEntry) -> Result<Self> {
+        let target = LStrOwned::try_from(entry.target.as_str())?;
+        let name = LStrOwned::try_from(entry.name.as_str())?;
+        let message = LStrOwned::try_from(entry.message.as_str())?;
+        Ok(Self {
+            timestamp: entry.timestamp.into(),
+            kind: entry.kind as u16,
+            level: level_index(entry.level),
+            target: target.into_raw(),
+            name: name.into_raw(),
+            message: message.into_raw(),
+            elapsed: entry.elapsed.map_or(0.0, |elapsed| elapsed.as_secs_f64()),
+        })
+    }
+}
+
+impl DeepDispose for TraceRecord {
+    unsafe fn deep_dispose(self) -> Result<()> {
+        let TraceRecord {
+            target,
+            name,
+            message,
+            ..
+        } = self;
+        target.deep_dispose()?;
+        name.deep_dispose()?;
+        message.deep_dispose()
+    }
+}
+
+/// The zero based position of the level in the LabVIEW enum.
+fn level_index(level: Level) -> u16 {
+    match level {
+        Level::ERROR => 0,
+        Level::WARN => 1,
+        Level::INFO => 2,
+        Level::DEBUG => 3,
+        Level::TRACE => 4,
+    }
+}
+
+/// The most verbose level forwarded, as a zero based enum index. Above
+/// Trace forwards everything.
+static MAX_LEVEL: AtomicU8 = AtomicU8::new(u8::MAX);
+
+/// Forward entries up to `index` as a zero based enum of Error, Warn, Info,
+/// Debug and Trace. A negative index forwards nothing.
+fn set_max_level(index: i32) {
+    let index = index.clamp(-1, i32::from(u8::MAX - 1)) + 1;
+    MAX_LEVEL.store(index as u8, Ordering::Relaxed);
+}
+
+fn level_enabled(level: Level) -> bool {
+    u16::from(MAX_LEVEL.load(Ordering::Relaxed)) > level_index(level)
+}
+
+struct Sink {
+    event: Option<LVUserEvent<TraceRecord>>,
+    buffer: VecDeque<TraceEntry>,
+    capacity: usize,
+}
+
+static SINK: Mutex<Sink> = Mutex::new(Sink {
+    event: None,
+    buffer: VecDeque::new(),
+    capacity: 0,
+});
+
+fn lock_sink() -> MutexGuard<'static, Sink> {
+    SINK.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+fn forward(entry: TraceEntry) {
+    let mut sink = lock_sink();
+    let event = sink.event;
+    let record = event.map(|_| TraceRecord::new(&entry));
+    if sink.capacity > 0 {
+        if sink.buffer.len() == sink.capacity {
+            sink.buffer.pop_front();
+        }
+        sink.buffer.push_back(entry);
+    }
+    drop(sink);
+
+    // There is nowhere to report a failure to post.
+    if let (Some(event), Some(Ok(record))) = (event, record) {
+        let _ = unsafe { event.post_and_dispose(record) };
+    }
+}
+
+/// Post entries to `event` from now on.
+pub fn register_event(event: LVUserEvent<TraceRecord>) {
+    lock_sink().event = Some(event);
+}
+
+/// Stop posting entries to the user event.
+pub fn unregister_event() {
+    lock_sink().event = None;
+}
+
+/// Keep up to `capacity` entries for [`pop`]. Zero, the default, turns the
+/// buffer off and discards anything in it.
+pub fn set_buffer_capacity(capacity: usize) {
+    let mut sink = lock_sink();
+    sink.capacity = capacity;
+    let excess = sink.buffer.len().saturating_sub(capacity);
+    sink.buffer.drain(..excess);
+}
+
+/// Take the oldest entry from the buffer.
+pub fn pop() -> Option<TraceEntry> {
+    lock_sink().buffer.pop_front()
+}
+
+/// Collects the message and fields of an event or span.
+struct FieldWriter<'a>(&'a mut String);
+
+impl Visit for FieldWriter<'_> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.0.insert_str(0, value);
+        } else {
+            let _ = write!(self.0, " {}={value}", field.name());
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            self.0.insert_str(0, &format!("{value:?}"));
+        } else {
+            let _ = write!(self.0, " {}={value:?}", field.name());
+        }
+    }
+}
+
+/// The fields of a span, stored when it is created.
+struct SpanFields(String);
+
+/// When the span was last entered.
+struct Entered(Instant);
+
+/// A `tracing-subscriber` layer which forwards to LabVIEW.
+///
+/// Combine it with other layers on a registry, or use [`init`] to install it alone.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct LabviewLayer;
+
+impl LabviewLayer {
+    fn span_entry<S>(&self, id: &Id, ctx: &Context<'_, S>, kind: TraceKind) -> Option<TraceEntry>
+    where
+        S: Subscriber + for<'a> LookupSpan<'a>,
+    {
+        let span = ctx.span(id)?;
+        let metadata = span.metadata();
+        if !level_enabled(*metadata.level()) {
+            return None;
+        }
+        let mut extensions = span.extensions_mut();
+        let elapsed = match kind {
+            TraceKind::Enter => {
+                extensions.replace(Entered(Instant::now()));
+                None
+            }
+            _ => extensions
+                .remove::<Entered>()
+                .map(|entered| entered.0.elapsed()),
+        };
+        let message = extensions
+            .get_mut::<SpanFields>()
+            .map(|fields| fields.0.trim_start().to_string())
+            .unwrap_or_default();
+        Some(entry(metadata, kind, message, elapsed))
+    }
+}
+
+fn entry(
+    metadata: &Metadata,
+    kind: TraceKind,
+    message: String,
+    elapsed: Option<Duration>,
+) -> TraceEntry {
+    TraceEntry {
+        timestamp: SystemTime::now(),
+        kind,
+        level: *metadata.level(),
+        target: metadata.target().to_string(),
+        name: metadata.name().to_string(),
+        message,
+        elapsed,
+    }
+}
+
+impl<S> Layer<S> for LabviewLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            let mut fields = String::new();
+            attrs.record(&mut FieldWriter(&mut fields));
+            span.extensions_mut().insert(SpanFields(fields));
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+        if !level_enabled(*metadata.level()) {
+            return;
+        }
+        let mut message = String::new();
+        event.record(&mut FieldWriter(&mut message));
+        let message = message.trim_start().to_string();
+        forward(entry(metadata, TraceKind::Event, message, None));
+    }
+
+    fn on_enter(&self, id: &Id, ctx: Context<'_, S>) {
+        if let Some(entry) = self.span_entry(id, &ctx, TraceKind::Enter) {
+            forward(entry);
+        }
+    }
+
+    fn on_exit(&self, id: &Id, ctx: Context<'_, S>) {
+        if let Some(entry) = self.span_entry(id, &ctx, TraceKind::Exit) {
+            forward(entry);
+        }
+    }
+}
+
+/// Install a registry with the LabVIEW layer as the global default subscriber.
+///
+/// # Errors
+///
+/// * If a global subscriber has already been installed.
+pub fn init() -> std::result::Result<(), tracing_core::dispatcher::SetGlobalDefaultError> {
+    let subscriber = tracing_subscriber::registry().with(LabviewLayer);
+    tracing_core::dispatcher::set_global_default(subscriber.into())
+}
+
+/// Write a string into a handle from LabVIEW, which may be null.
+fn write_string(handle: &mut LStrHandle, value: &str) -> Result<()> {
+    if handle.is_null() {
+        *handle = LStrOwned::try_from(value)?.into_raw();
+        Ok(())
+    } else {
+        handle.set_str(value)
+    }
+}
+
+/// Register a user event for the trace entries from LabVIEW.
+///
+/// This also installs the layer as the global subscriber if there isn't one.
+/// `max_level` is the most verbose level to forward, as a zero based enum of
+/// Error, Warn, Info, Debug and Trace, and applies to the buffer too.
+/// Configure `event` as adapt to type with handles by value.
+///
+/// # Safety
+///
+/// * `event` must be null or point to a user event refnum of [`TraceRecord`].
+#[no_mangle]
+pub unsafe extern "C" fn interop_trace_register(
+    event: *const LVUserEvent<TraceRecord>,
+    max_level: i32,
+) -> MgErr {
+    let Some(event) = event.as_ref() else {
+        return MgErr::ARGUMENT_ERROR;
+    };
+    // Another subscriber may be installed with the layer already.
+    let _ = init();
+    set_max_level(max_level);
+    register_event(*event);
+    MgErr::NO_ERROR
+}
+
+/// Stop posting trace entries from LabVIEW, such as before destroying the user event.
+#[no_mangle]
+pub extern "C" fn interop_trace_unregister() -> MgErr {
+    unregister_event();
+    MgErr::NO_ERROR
+}
+
+/// Set the size of the trace buffer from LabVIEW, installing the layer if needed.
+///
+/// Zero turns the buffer off. `max_level` is as for `interop_trace_register`.
+#[no_mangle]
+pub extern "C" fn interop_trace_buffer(capacity: u32, max_level: i32) -> MgErr {
+    let _ = init();
+    set_max_level(max_level);
+    set_buffer_capacity(capacity as usize);
+    MgErr::NO_ERROR
+}
+
+/// Take the oldest trace entry from the buffer into a [`TraceRecord`] cluster.
+///
+/// `popped` is set false and the record left as it is when the buffer is
+/// empty. Configure `record` as a pointer to the cluster.
+///
+/// # Safety
+///
+/// * `record` and `popped` must be null or point to the types from LabVIEW.
+#[no_mangle]
+pub unsafe extern "C" fn interop_trace_pop(record: *mut TraceRecord, popped: *mut LVBool) -> MgErr {
+    if record.is_null() || popped.is_null() {
+        return LVInteropError::InvalidHandle.into();
+    }
+    let Some(entry) = pop() else {
+        *popped = false.into();
+        return MgErr::NO_ERROR;
+    };
+    let result = (|| {
+        // Packed on 32 bit so fields are accessed by pointer.
+        let handles = [
+            (addr_of_mut!((*record).target), &entry.target),
+            (addr_of_mut!((*record).name), &entry.name),
+            (addr_of_mut!((*record).message), &entry.message),
+        ];
+        for (field, value) in handles {
+            let mut handle = field.read_unaligned();
+            write_string(&mut handle, value)?;
+            field.write_unaligned(handle);
+        }
+        addr_of_mut!((*record).timestamp).write_unaligned(entry.timestamp.into());
+        addr_of_mut!((*record).kind).write_unaligned(entry.kind as u16);
+        addr_of_mut!((*record).level).write_unaligned(level_index(entry.level));
+        addr_of_mut!((*record).elapsed)
+            .write_unaligned(entry.elapsed.map_or(0.0, |elapsed| elapsed.as_secs_f64()));
+        Ok(())
+    })();
+    *popped = result.is_ok().into();
+    result.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The buffer and level are global so this is a single test.
+    #[test]
+    fn test_span_timing_fields_and_levels() {
+        let subscriber = tracing_subscriber::registry().with(LabviewLayer);
+        let dispatch = tracing_core::Dispatch::new(subscriber);
+        set_buffer_capacity(10);
+        tracing_core::dispatcher::with_default(&dispatch, || {
+            let span = tracing::debug_span!("measure", channel = 3);
+            let _entered = span.enter();
+            tracing::warn!(volts = 1.5, "over range");
+        });
+
+        let entries: Vec<_> = std::iter::from_fn(pop).collect();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].kind, TraceKind::Enter);
+        assert_eq!(entries[0].name, "measure");
+        assert_eq!(entries[0].message, "channel=3");
+        assert_eq!(entries[1].level, Level::WARN);
+        assert_eq!(entries[1].message, "over range volts=1.5");
+        assert_eq!(entries[2].kind, TraceKind::Exit);
+        assert!(entries[2].elapsed.is_some());
+
+        assert!(level_enabled(Level::TRACE));
+        set_max_level(1);
+        assert!(level_enabled(Level::WARN));
+        assert!(!level_enabled(Level::INFO));
+        set_max_level(-1);
+        assert!(!level_enabled(Level::ERROR));
+        set_max_level(i32::MAX);
+        assert!(level_enabled(Level::TRACE));
+    }
+}
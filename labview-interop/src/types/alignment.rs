@@ -0,0 +1,300 @@
+//! Alignment helpers for feeding array data to SIMD and FFT code.
+//!
+//! LabVIEW only aligns array data for the element type so the buffers are
+//! rarely aligned to the 16, 32 or 64 bytes vector kernels prefer. These
+//! helpers split a buffer around its aligned section so the body can use the
+//! aligned path, or copy the data to an aligned buffer when the whole array
+//! must be aligned.
+//!
+//! Alignments are in bytes and must be a power of two.
+
+use std::alloc::Layout;
+use std::ptr::NonNull;
+
+#[cfg(target_pointer_width = "64")]
+use super::LVArrayHandle;
+
+/// Check whether the slice data starts on an `align` byte boundary.
+///
+/// Empty slices and slices of zero sized types are always aligned as there is
+/// nothing to load.
+///
+/// # Panics
+///
+/// If `align` is not a power of two.
+pub fn is_aligned_to<T>(values: &[T], align: usize) -> bool {
+    assert!(align.is_power_of_two(), "alignment must be a power of two");
+    values.is_empty()
+        || std::mem::size_of::<T>() == 0
+        || values.as_ptr().addr().is_multiple_of(align)
+}
+
+/// A slice split around its aligned section.
+#[derive(Debug, PartialEq)]
+pub struct AlignedSplit<'a, T> {
+    /// The elements before the first aligned element.
+    pub head: &'a [T],
+    /// The aligned elements. This starts on the requested alignment and is a whole
+    /// number of alignment blocks long.
+    pub body: &'a [T],
+    /// The elements after the last full alignment block.
+    pub tail: &'a [T],
+}
+
+/// A mutable slice split around its aligned section.
+///
+/// See [`AlignedSplit`] for the meaning of each part.
+#[derive(Debug, PartialEq)]
+pub struct AlignedSplitMut<'a, T> {
+    pub head: &'a mut [T],
+    pub body: &'a mut [T],
+    pub tail: &'a mut [T],
+}
+
+/// Find the head and body lengths for splitting the slice.
+fn split_points<T>(values: &[T], align: usize) -> (usize, usize) {
+    assert!(align.is_power_of_two(), "alignment must be a power of two");
+    let size = std::mem::size_of::<T>();
+    // Zero sized types and elements larger than the alignment block can't be split.
+    if size == 0 || !align.is_multiple_of(size) {
+        return (values.len(), 0);
+    }
+    let offset = values.as_ptr().align_offset(align);
+    if offset >= values.len() {
+        return (values.len(), 0);
+    }
+    let block = align / size;
+    let body = (values.len() - offset) / block * block;
+    (offset, body)
+}
+
+/// Split the slice into an unaligned head, an aligned body and an unaligned tail.
+///
+/// If the alignment can't be reached, for example when the slice is shorter than
+/// the distance to the next boundary, everything is returned in the head.
+///
+/// # Panics
+///
+/// If `align` is not a power of two.
+pub fn split_aligned<T>(values: &[T], align: usize) -> AlignedSplit<'_, T> {
+    let (head_len, body_len) = split_points(values, align);
+    let (head, rest) = values.split_at(head_len);
+    let (body, tail) = rest.split_at(body_len);
+    AlignedSplit { head, body, tail }
+}
+
+/// Split the mutable slice into an unaligned head, an aligned body and an unaligned tail.
+///
+/// # Panics
+///
+/// If `align` is not a power of two.
+pub fn split_aligned_mut<T>(values: &mut [T], align: usize) -> AlignedSplitMut<'_, T> {
+    let (head_len, body_len) = split_points(values, align);
+    let (head, rest) = values.split_at_mut(head_len);
+    let (body, tail) = rest.split_at_mut(body_len);
+    AlignedSplitMut { head, body, tail }
+}
+
+/// A heap buffer aligned to a requested boundary.
+struct AlignedBuffer<T: Copy> {
+    data: NonNull<T>,
+    len: usize,
+    layout: Layout,
+}
+
+impl<T: Copy> AlignedBuffer<T> {
+    /// Allocate an aligned copy of the values.
+    ///
+    /// The values must not be empty or zero sized, which are always aligned anyway.
+    fn copy_from(values: &[T], align: usize) -> Self {
+        let layout = Layout::array::<T>(values.len())
+            .and_then(|layout| layout.align_to(align))
+            .expect("aligned buffer size overflowed");
+        // Safety: the layout is not zero sized as the values are not empty.
+        let ptr = unsafe { std::alloc::alloc(layout) };
+        let Some(data) = NonNull::new(ptr.cast::<T>()) else {
+            std::alloc::handle_alloc_error(layout);
+        };
+        // Safety: the new allocation is large enough for the values and can't overlap them.
+        unsafe { std::ptr::copy_nonoverlapping(values.as_ptr(), data.as_ptr(), values.len()) };
+        Self {
+            data,
+            len: values.len(),
+            layout,
+        }
+    }
+
+    fn as_slice(&self) -> &[T] {
+        unsafe { std::slice::from_raw_parts(self.data.as_ptr(), self.len) }
+    }
+
+    fn as_slice_mut(&mut self) -> &mut [T] {
+        unsafe { std::slice::from_raw_parts_mut(self.data.as_ptr(), self.len) }
+    }
+}
+
+impl<T: Copy> Drop for AlignedBuffer<T> {
+    fn drop(&mut self) {
+        // Safety: allocated in copy_from with the same layout.
+        unsafe { std::alloc::dealloc(self.data.as_ptr().cast(), self.layout) };
+    }
+}
+
+/// Call `f` with the values aligned to `align` bytes.
+///
+/// Aligned data is passed straight through. Otherwise the values are copied to
+/// an aligned buffer first, so prefer [`split_aligned`] where the kernel can
+/// handle the unaligned ends itself.
+///
+/// # Panics
+///
+/// If `align` is not a power of two.
+pub fn with_aligned<T: Copy, R>(values: &[T], align: usize, f: impl FnOnce(&[T]) -> R) -> R {
+    if is_aligned_to(values, align) {
+        return f(values);
+    }
+    let buffer = AlignedBuffer::copy_from(values, align);
+    f(buffer.as_slice())
+}
+
+/// Call `f` with the values aligned to `align` bytes and keep any changes.
+///
+/// Aligned data is passed straight through. Otherwise the values are copied to
+/// an aligned buffer and copied back once `f` returns.
+///
+/// # Panics
+///
+/// If `align` is not a power of two.
+pub fn with_aligned_mut<T: Copy, R>(
+    values: &mut [T],
+    align: usize,
+    f: impl FnOnce(&mut [T]) -> R,
+) -> R {
+    if is_aligned_to(values, align) {
+        return f(values);
+    }
+    let mut buffer = AlignedBuffer::copy_from(values, align);
+    let result = f(buffer.as_slice_mut());
+    values.copy_from_slice(buffer.as_slice());
+    result
+}
+
+#[cfg(target_pointer_width = "64")]
+impl<const D: usize, T> LVArrayHandle<D, T> {
+    /// Check whether the array data starts on an `align` byte boundary.
+    ///
+    /// A null handle is an empty array so is always aligned.
+    pub fn is_aligned_to(&self, align: usize) -> bool {
+        match unsafe { self.as_ref() } {
+            Ok(array) => is_aligned_to(array.data_as_slice(), align),
+            Err(_) => true,
+        }
+    }
+
+    /// Split the array data around its `align` byte aligned section.
+    ///
+    /// See [`split_aligned`].
+    pub fn split_aligned(&self, align: usize) -> AlignedSplit<'_, T> {
+        match unsafe { self.as_ref() } {
+            Ok(array) => split_aligned(array.data_as_slice(), align),
+            Err(_) => split_aligned(&[], align),
+        }
+    }
+
+    /// Split the array data mutably around its `align` byte aligned section.
+    ///
+    /// See [`split_aligned_mut`].
+    pub fn split_aligned_mut(&mut self, align: usize) -> AlignedSplitMut<'_, T> {
+        match unsafe { self.as_ref_mut() } {
+            Ok(array) => split_aligned_mut(array.data_as_slice_mut(), align),
+            Err(_) => split_aligned_mut(&mut [], align),
+        }
+    }
+}
+
+#[cfg(target_pointer_width = "64")]
+impl<const D: usize, T: Copy> LVArrayHandle<D, T> {
+    /// Call `f` with the array data aligned to `align` bytes, copying it if required.
+    ///
+    /// See [`with_aligned`].
+    pub fn with_aligned_data<R>(&self, align: usize, f: impl FnOnce(&[T]) -> R) -> R {
+        match unsafe { self.as_ref() } {
+            Ok(array) => with_aligned(array.data_as_slice(), align, f),
+            Err(_) => f(&[]),
+        }
+    }
+
+    /// Call `f` with the array data aligned to `align` bytes, copying it in and
+    /// back out if required.
+    ///
+    /// See [`with_aligned_mut`].
+    pub fn with_aligned_data_mut<R>(&mut self, align: usize, f: impl FnOnce(&mut [T]) -> R) -> R {
+        match unsafe { self.as_ref_mut() } {
+            Ok(array) => with_aligned_mut(array.data_as_slice_mut(), align, f),
+            Err(_) => f(&mut []),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A buffer which starts on a 64 byte boundary so offsets into it are predictable.
+    #[repr(align(64))]
+    struct Aligned([f32; 32]);
+
+    fn buffer() -> Aligned {
+        let mut values = [0.0; 32];
+        for (index, value) in values.iter_mut().enumerate() {
+            *value = index as f32;
+        }
+        Aligned(values)
+    }
+
+    #[test]
+    fn test_split_aligned_offsets() {
+        let values = buffer();
+        // Start one element in so 3 elements are needed to reach the next 16 bytes.
+        let split = split_aligned(&values.0[1..22], 16);
+        assert_eq!(split.head, &[1.0, 2.0, 3.0]);
+        assert_eq!(split.body.len(), 16);
+        assert_eq!(split.body[0], 4.0);
+        assert!(is_aligned_to(split.body, 16));
+        assert_eq!(split.tail, &[20.0, 21.0]);
+
+        let split = split_aligned(&values.0[..], 32);
+        assert!(split.head.is_empty());
+        assert_eq!(split.body.len(), 32);
+        assert!(split.tail.is_empty());
+    }
+
+    #[test]
+    fn test_split_aligned_unreachable() {
+        let values = buffer();
+        let split = split_aligned(&values.0[1..3], 16);
+        assert_eq!(split.head, &[1.0, 2.0]);
+        assert!(split.body.is_empty());
+        assert!(split.tail.is_empty());
+    }
+
+    #[test]
+    fn test_with_aligned_copies_unaligned() {
+        let mut values = buffer();
+        let unaligned = &mut values.0[1..9];
+        assert!(!is_aligned_to(unaligned, 32));
+
+        let sum = with_aligned(unaligned, 32, |aligned| {
+            assert!(is_aligned_to(aligned, 32));
+            aligned.iter().sum::<f32>()
+        });
+        assert_eq!(sum, 36.0);
+
+        with_aligned_mut(unaligned, 32, |aligned| {
+            assert!(is_aligned_to(aligned, 32));
+            aligned.iter_mut().for_each(|value| *value *= 2.0);
+        });
+        assert_eq!(values.0[1..9], [2.0, 4.0, 6.0, 8.0, 10.0, 12.0, 14.0, 16.0]);
+        assert_eq!(values.0[9], 9.0);
+    }
+}
@@ -0,0 +1,112 @@
+//! [`bytes`] support for byte arrays. This requires 64 bit to access the
+//! array data.
+//!
+//! An owned byte array can be turned into [`Bytes`] without copying, with the
+//! handle disposed once the last reference to the buffer is dropped.
+
+use super::{LVArrayDims, LVArrayHandle, LVArrayOwned};
+use crate::errors::Result;
+use bytes::{Bytes, BytesMut};
+
+/// Owner of the array data for [`Bytes::from_owner`].
+struct ByteArrayOwner(LVArrayOwned<1, u8>);
+
+impl AsRef<[u8]> for ByteArrayOwner {
+    fn as_ref(&self) -> &[u8] {
+        // The array can't be resized while the buffer owns it so the data stays put.
+        match unsafe { self.0.as_ref() } {
+            Ok(array) => array.data_as_slice(),
+            Err(_) => &[],
+        }
+    }
+}
+
+impl LVArrayOwned<1, u8> {
+    /// Allocate a new byte array holding a copy of the bytes.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let array = Self::new(LVArrayDims::try_from(&[bytes.len()])?)?;
+        if let Ok(array) = unsafe { array.as_ref_mut() } {
+            array.data_as_slice_mut().copy_from_slice(bytes);
+        }
+        Ok(array)
+    }
+
+    /// Convert the array into [`Bytes`] without copying.
+    ///
+    /// The handle is disposed once the buffer and all its clones are dropped.
+    pub fn into_bytes(self) -> Bytes {
+        Bytes::from_owner(ByteArrayOwner(self))
+    }
+}
+
+impl LVArrayHandle<1, u8> {
+    /// Copy the array contents into a [`BytesMut`].
+    ///
+    /// [`BytesMut`] must own its allocation so this always copies. Use
+    /// [`LVArrayOwned::into_bytes`] to share an owned array without copying.
+    pub fn to_bytes_mut(&self) -> BytesMut {
+        match unsafe { self.as_ref() } {
+            Ok(array) => BytesMut::from(array.data_as_slice()),
+            Err(_) => BytesMut::new(),
+        }
+    }
+
+    /// Replace the array contents with the bytes, resizing it to fit.
+    pub fn copy_from_bytes(&mut self, bytes: &[u8]) -> Result<()> {
+        self.resize_array(LVArrayDims::try_from(&[bytes.len()])?)?;
+        if let Ok(array) = unsafe { self.as_ref_mut() } {
+            array.data_as_slice_mut().copy_from_slice(bytes);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bytes_round_trip() {
+        let array = LVArrayOwned::<1, u8>::from_bytes(b"hello").unwrap();
+        assert_eq!(array.to_bytes_mut(), b"hello"[..]);
+
+        let bytes = array.into_bytes();
+        let clone = bytes.clone();
+        drop(bytes);
+        assert_eq!(clone, b"hello"[..]);
+    }
+
+    #[test]
+    fn test_copy_bytes_into_handle() {
+        use crate::memory::UHandle;
+
+        let mut handle: LVArrayHandle<1, u8> = UHandle(std::ptr::null_mut());
+        assert!(handle.to_bytes_mut().is_empty());
+
+        // A null handle is allocated, then resized to fit each copy.
+        handle.copy_from_bytes(b"abc").unwrap();
+        assert_eq!(handle.to_bytes_mut(), b"abc"[..]);
+        handle.copy_from_bytes(b"longer").unwrap();
+        assert_eq!(handle.to_bytes_mut(), b"longer"[..]);
+        handle.copy_from_bytes(b"").unwrap();
+        assert!(handle.to_bytes_mut().is_empty());
+        drop(unsafe { LVArrayOwned::from_raw(handle) });
+    }
+
+    #[cfg(feature = "leak-tracking")]
+    #[test]
+    fn test_bytes_dispose_array_when_dropped() {
+        use crate::leak_tracking::tracked_id;
+
+        let array = LVArrayOwned::<1, u8>::from_bytes(b"shared").unwrap();
+        let address = array.0.cast();
+        let id = tracked_id(address).unwrap();
+        let bytes = array.into_bytes();
+        let slice = bytes.slice(1..3);
+        drop(bytes);
+        assert_eq!(tracked_id(address), Some(id));
+        assert_eq!(slice, b"ha"[..]);
+        drop(slice);
+        assert_ne!(tracked_id(address), Some(id));
+    }
+}
@@ -1,7 +1,9 @@
 //! Memory manager functions for arrays.
 
-use super::{LVArrayDims, LVArrayHandle};
-use crate::errors::Result;
+use super::{data_offset, LVArray, LVArrayDims, LVArrayHandle, LVArrayOwned};
+use crate::errors::{LVInteropError, Result};
+use crate::memory::{DeepCopy, DeepDispose, DisposeContents, OwnedUHandle, UHandle};
+use crate::types::LVBool;
 
 pub trait NumericArrayResizable {
     /// The code used by the LabVIEW memory manager to represent the type.
@@ -48,13 +50,232 @@ impl NumericArrayResizable for f64 {
     const TYPE_CODE: i32 = 0x0A;
 }
 
+/// Booleans are stored as bytes so are resized as `u8`.
+impl NumericArrayResizable for LVBool {
+    const TYPE_CODE: i32 = 0x05;
+}
+
+impl<const D: usize, T: Copy> LVArrayHandle<D, T> {
+    /// Copy `data` into the array with `MoveBlock`. This is much faster than
+    /// copying element by element for large arrays.
+    ///
+    /// The data is in row major order and its length must match the current
+    /// size of the array. Resize the array first to change it.
+    ///
+    /// # Errors
+    ///
+    /// * [`LVInteropError::ArrayDimensionMismatch`] if the lengths don't match.
+    pub fn copy_from_slice(&mut self, data: &[T]) -> Result<()> {
+        if self.dimensions_or_empty().element_count() != data.len() {
+            return Err(LVInteropError::ArrayDimensionMismatch);
+        }
+        let Ok(array) = (unsafe { self.as_ref_mut() }) else {
+            // A null handle is empty so there is nothing to copy.
+            return Ok(());
+        };
+        let destination = std::ptr::addr_of_mut!(array.data).cast::<std::ffi::c_void>();
+        unsafe {
+            crate::labview::memory_api()?.move_block(
+                data.as_ptr().cast(),
+                destination,
+                std::mem::size_of_val(data),
+            )
+        };
+        Ok(())
+    }
+
+    /// Copy the array into `output` with `MoveBlock`.
+    ///
+    /// The data is in row major order and the length of `output` must match
+    /// the number of elements.
+    ///
+    /// # Errors
+    ///
+    /// * [`LVInteropError::ArrayDimensionMismatch`] if the lengths don't match.
+    pub fn copy_to_slice(&self, output: &mut [T]) -> Result<()> {
+        if self.dimensions_or_empty().element_count() != output.len() {
+            return Err(LVInteropError::ArrayDimensionMismatch);
+        }
+        let Ok(array) = (unsafe { self.as_ref() }) else {
+            return Ok(());
+        };
+        let source = std::ptr::addr_of!(array.data).cast::<std::ffi::c_void>();
+        unsafe {
+            crate::labview::memory_api()?.move_block(
+                source,
+                output.as_mut_ptr().cast(),
+                std::mem::size_of_val(output),
+            )
+        };
+        Ok(())
+    }
+}
+
+/// Elements of arrays which can be resized with [`LVArrayHandle::resize_elements`].
+///
+/// This covers the elements [`NumericArrayResizable`] can't, such as strings,
+/// nested arrays and clusters. Removed elements are disposed with
+/// [`DeepDispose`], which can be derived for clusters.
+///
+/// # Safety
+///
+/// A value of all zero bytes must be valid, as new elements are zeroed. This
+/// is how LabVIEW initialises elements, with null handles for empty strings
+/// and arrays.
+pub unsafe trait ArrayElement: DeepDispose {}
+
+impl<T: NumericArrayResizable> DeepDispose for T {
+    unsafe fn deep_dispose(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+unsafe impl<T: NumericArrayResizable> ArrayElement for T {}
+
+impl<T: NumericArrayResizable + Copy> DeepCopy for T {
+    unsafe fn deep_copy(&self) -> Result<Self> {
+        Ok(*self)
+    }
+
+    unsafe fn deep_copy_from(&mut self, source: &Self) -> Result<()> {
+        *self = *source;
+        Ok(())
+    }
+}
+
+impl<const D: usize, T: ArrayElement + DeepCopy> DeepCopy for LVArrayHandle<D, T> {
+    unsafe fn deep_copy(&self) -> Result<Self> {
+        let mut copy: Self = UHandle(std::ptr::null_mut());
+        copy.deep_copy_from(self)?;
+        Ok(copy)
+    }
+
+    /// Copy the array into this handle, resizing it and reusing the handles
+    /// of existing elements. A null handle is allocated.
+    unsafe fn deep_copy_from(&mut self, source: &Self) -> Result<()> {
+        if self.0 == source.0 {
+            return Ok(());
+        }
+        let Ok(source) = source.as_ref() else {
+            // A null source is an empty array.
+            if !self.is_null() {
+                self.resize_elements([0; D].into())?;
+            }
+            return Ok(());
+        };
+        if self.is_null() {
+            *self = LVArrayOwned::new_zeroed(source.dimension_sizes())?.into_raw();
+        } else {
+            self.resize_elements(source.dimension_sizes())?;
+        }
+        let target = self.as_ref_mut()?;
+        for index in 0..source.element_count() {
+            // Elements are copied by value as they may be unaligned on 32 bit.
+            let mut element = target.get_value_unchecked(index);
+            element.deep_copy_from(&source.get_value_unchecked(index))?;
+            target.set_value_unchecked(index, element);
+        }
+        Ok(())
+    }
+}
+
+unsafe impl<T: ?Sized + DisposeContents> ArrayElement for UHandle<T> {}
+
+impl<const D: usize, T: DeepDispose> DisposeContents for LVArray<D, T> {
+    unsafe fn dispose_contents(&mut self) -> Result<()> {
+        for index in 0..self.element_count() {
+            self.get_value_unchecked(index).deep_dispose()?;
+        }
+        Ok(())
+    }
+}
+
+impl<const D: usize, T: ArrayElement> LVArrayHandle<D, T> {
+    /// Resize an array of elements which aren't numeric, such as strings or clusters.
+    ///
+    /// New elements are zeroed, which is an empty string or array for handles.
+    /// Elements removed when shrinking are disposed first. The data is kept in
+    /// place so resizing a multi-dimension array in anything but the first
+    /// dimension moves elements to different indexes.
+    ///
+    /// A null handle is an error, as LabVIEW only passes one by value and the
+    /// allocated handle couldn't reach the caller. Replace the null handle with
+    /// one from [`LVArrayOwned::new_zeroed`] instead.
+    ///
+    /// # Example
+    /// ```
+    /// use labview_interop::errors::Result;
+    /// use labview_interop::types::{LStrHandle, LStrOwned, LVArrayHandle, LVArrayOwned};
+    ///
+    /// fn write_names(names: &mut LVArrayHandle<1, LStrHandle>) -> Result<()> {
+    ///     if names.is_null() {
+    ///         *names = LVArrayOwned::new_zeroed([2].into())?.into_raw();
+    ///     } else {
+    ///         names.resize_elements([2].into())?;
+    ///     }
+    ///     for (element, name) in names.iter_mut().zip(["first", "second"]) {
+    ///         if element.is_null() {
+    ///             *element = LStrOwned::from_data(name.as_bytes())?.into_raw();
+    ///         } else {
+    ///             element.set_str(name)?;
+    ///         }
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn resize_elements(&mut self, new_dims: LVArrayDims<D>) -> Result<()> {
+        let array = unsafe { self.as_ref_mut()? };
+        let old_count = array.element_count();
+        let new_count = new_dims.element_count();
+
+        for index in new_count..old_count {
+            // Safety: the index is within the current size and zeroed elements
+            // are valid, so a failed resize leaves nothing to dispose twice.
+            unsafe {
+                array.get_value_unchecked(index).deep_dispose()?;
+                array.set_value_unchecked(index, std::mem::zeroed());
+            }
+        }
+
+        let size = new_count
+            .checked_mul(std::mem::size_of::<T>())
+            .and_then(|data| data.checked_add(data_offset::<D, T>()))
+            .ok_or(crate::errors::MgErr::MEMORY_FULL)?;
+        let allocation = crate::validation::canary::allocation_size(size);
+        let api = crate::labview::memory_api()?;
+        unsafe { api.set_handle_size(self.handle_value(), allocation) }.to_result(())?;
+        unsafe { crate::validation::canary::write(self.handle_value(), size, allocation) };
+
+        let array = unsafe { self.as_ref_mut()? };
+        if new_count > old_count {
+            let data = std::ptr::addr_of_mut!(array.data).cast::<T>();
+            // Safety: the handle has been resized to hold `new_count` elements.
+            unsafe {
+                data.add(old_count)
+                    .cast::<u8>()
+                    .write_bytes(0, (new_count - old_count) * std::mem::size_of::<T>());
+            }
+        }
+        array.dim_sizes = new_dims;
+        Ok(())
+    }
+}
+
 impl<const D: usize, T: NumericArrayResizable> LVArrayHandle<D, T> {
     /// Resize the array to the new size.
+    ///
+    /// A null handle, which LabVIEW passes for an empty array, is allocated by
+    /// the memory manager, even for empty dimensions, so the array can always
+    /// be written afterwards. The new handle is stored in `self` so for an
+    /// output LabVIEW must pass the array as a pointer to a handle, which is
+    /// the only way it passes a null handle. Resize it in place with
+    /// `unsafe { output.as_ref_mut()? }.resize_array(dims)` where `output` is
+    /// a `UPtr<LVArrayHandle<D, T>>`.
     pub fn resize_array(&mut self, new_dims: LVArrayDims<D>) -> Result<()> {
         // Check if they match so resize isn't needed.
         // We can't perform this unaligned read on 32 bit so skip it.
         #[cfg(target_pointer_width = "64")]
-        if new_dims == self.dim_sizes {
+        if !self.is_null() && new_dims == self.dimensions_or_empty() {
             return Ok(());
         }
 
@@ -63,15 +284,267 @@ impl<const D: usize, T: NumericArrayResizable> LVArrayHandle<D, T> {
             crate::labview::memory_api()?.numeric_array_resize(
                 T::TYPE_CODE,
                 D as i32,
-                self as *mut LVArrayHandle<D, T> as *mut usize as *mut crate::labview::UHandleValue,
+                self.handle_value_ptr(),
                 new_size,
             )
         };
-        let result = mg_err.to_result(());
+        mg_err.to_result(())?;
+        unsafe { self.as_ref_mut()? }.dim_sizes = new_dims;
+
+        // Add room for the canary after the data at the paranoid level.
+        let size = new_size * std::mem::size_of::<T>() + data_offset::<D, T>();
+        let allocation = crate::validation::canary::allocation_size(size);
+        if allocation != size {
+            let api = crate::labview::memory_api()?;
+            unsafe { api.set_handle_size(self.handle_value(), allocation) }.to_result(())?;
+        }
+        unsafe { crate::validation::canary::write(self.handle_value(), size, allocation) };
+        Ok(())
+    }
+}
 
-        if result.is_ok() {
-            self.dim_sizes = new_dims;
+impl<T: NumericArrayResizable> LVArrayHandle<1, T> {
+    /// Replace the contents of the array with the items of an iterator,
+    /// resizing it to fit.
+    ///
+    /// The array is sized from the iterator's size hint so iterators of a known
+    /// length are written with a single resize.
+    pub fn set_from_iter<I: IntoIterator<Item = T>>(&mut self, iter: I) -> Result<()> {
+        let iter = iter.into_iter();
+        let mut capacity = iter.size_hint().0;
+        self.resize_array(LVArrayDims::try_from(&[capacity])?)?;
+        let mut len = 0;
+        for value in iter {
+            if len == capacity {
+                capacity = (capacity * 2).max(8);
+                self.resize_array(LVArrayDims::try_from(&[capacity])?)?;
+            }
+            // Safety: the array has been resized to hold at least `len + 1` elements.
+            unsafe { self.as_ref_mut()?.set_value_unchecked(len, value) };
+            len += 1;
         }
-        result
+        if len != capacity {
+            self.resize_array(LVArrayDims::try_from(&[len])?)?;
+        }
+        Ok(())
+    }
+}
+
+impl<const D: usize, T: ArrayElement> LVArrayOwned<D, T> {
+    /// Allocate a new array of zeroed elements, such as null string handles.
+    pub fn new_zeroed(dims: LVArrayDims<D>) -> Result<Self> {
+        let handle = unsafe { crate::labview::memory_api()?.new_handle(data_offset::<D, T>()) };
+        if handle.as_ptr().is_null() {
+            return Err(crate::errors::MgErr::MEMORY_FULL.into());
+        }
+        let empty: LVArrayHandle<D, T> = UHandle(handle.as_ptr().cast());
+        unsafe { empty.as_ref_mut()? }.dim_sizes = [0; D].into();
+        let mut owned = unsafe { OwnedUHandle::from_raw(empty) };
+        owned.resize_elements(dims)?;
+        Ok(owned)
+    }
+}
+
+impl<const D: usize, T: NumericArrayResizable> LVArrayOwned<D, T> {
+    /// Allocate a new array with the given dimensions.
+    ///
+    /// The contents are left as the memory manager allocates them so should be
+    /// written before use.
+    pub fn new(dims: LVArrayDims<D>) -> Result<Self> {
+        // NumericArrayResize allocates a new handle when passed a null handle.
+        let mut handle: Self = unsafe { OwnedUHandle::from_raw(UHandle(std::ptr::null_mut())) };
+        handle.resize_array(dims)?;
+        // Take ownership again so leak tracking sees the allocated handle.
+        Ok(unsafe { OwnedUHandle::from_raw(handle.into_raw()) })
+    }
+
+    /// Allocate a new array with the given dimensions holding a copy of `data`.
+    ///
+    /// The data is in row major order and its length must match the dimensions.
+    pub fn from_shape_slice(dims: LVArrayDims<D>, data: &[T]) -> Result<Self>
+    where
+        T: Copy,
+    {
+        if dims.element_count() != data.len() {
+            return Err(LVInteropError::ArrayDimensionMismatch);
+        }
+        let mut array = Self::new(dims)?;
+        array.copy_from_slice(data)?;
+        Ok(array)
+    }
+}
+
+impl<T: NumericArrayResizable + Copy> LVArrayOwned<1, T> {
+    /// Allocate a new 1D array holding a copy of the data.
+    pub fn from_slice(data: &[T]) -> Result<Self> {
+        Self::from_shape_slice(LVArrayDims::try_from(&[data.len()])?, data)
+    }
+
+    /// Allocate a new 1D array holding the contents of the vector.
+    ///
+    /// The data is copied into memory manager memory as the vector's
+    /// allocation can't be handed to LabVIEW.
+    pub fn from_vec(data: Vec<T>) -> Result<Self> {
+        Self::from_slice(&data)
+    }
+}
+
+impl<T: NumericArrayResizable> LVArrayOwned<1, T> {
+    /// Allocate a new 1D array holding the items of an iterator.
+    ///
+    /// This isn't `FromIterator` as the allocation can fail.
+    pub fn try_from_iter<I: IntoIterator<Item = T>>(iter: I) -> Result<Self> {
+        let mut array = Self::new([0].into())?;
+        array.set_from_iter(iter)?;
+        Ok(array)
+    }
+}
+
+impl<T: NumericArrayResizable + Copy> TryFrom<Vec<T>> for LVArrayOwned<1, T> {
+    type Error = LVInteropError;
+
+    fn try_from(value: Vec<T>) -> Result<Self> {
+        Self::from_vec(value)
+    }
+}
+
+impl<T: NumericArrayResizable + Copy> TryFrom<&[T]> for LVArrayOwned<1, T> {
+    type Error = LVInteropError;
+
+    fn try_from(value: &[T]) -> Result<Self> {
+        Self::from_slice(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_owned_array_resize() {
+        let mut array = LVArrayOwned::<1, f64>::new([3].into()).unwrap();
+        unsafe { array.as_ref_mut() }
+            .unwrap()
+            .data_as_slice_mut()
+            .copy_from_slice(&[1.0, 2.0, 3.0]);
+
+        array.resize_array([5].into()).unwrap();
+        let array = unsafe { array.as_ref() }.unwrap();
+        assert_eq!(array.element_count(), 5);
+        assert_eq!(array.data_as_slice()[..3], [1.0, 2.0, 3.0]);
+    }
+
+    #[test]
+    fn test_resize_allocates_null_handle() {
+        let mut slot: LVArrayHandle<1, f64> = UHandle(std::ptr::null_mut());
+        let output = unsafe { crate::memory::UPtr::from_raw(&mut slot) };
+        unsafe { output.as_ref_mut() }
+            .unwrap()
+            .resize_array([0].into())
+            .unwrap();
+        assert!(!slot.is_null());
+        assert_eq!(unsafe { slot.as_ref() }.unwrap().element_count(), 0);
+        drop(unsafe { LVArrayOwned::from_raw(slot) });
+
+        let mut slot: LVArrayHandle<2, i32> = UHandle(std::ptr::null_mut());
+        slot.resize_array([2, 3].into()).unwrap();
+        assert_eq!(slot.dimensions_or_empty(), [2, 3].into());
+        drop(unsafe { LVArrayOwned::from_raw(slot) });
+    }
+
+    #[test]
+    fn test_owned_array_from_vec() {
+        let array = LVArrayOwned::<1, i32>::from_vec(vec![4, 5, 6]).unwrap();
+        let array = unsafe { array.as_ref() }.unwrap();
+        assert_eq!(array.dimension_sizes(), [3].into());
+        assert_eq!(array.data_as_slice(), [4, 5, 6]);
+
+        let matrix = LVArrayOwned::<2, u8>::from_shape_slice([2, 2].into(), &[1, 2, 3, 4]).unwrap();
+        assert_eq!(
+            unsafe { matrix.as_ref() }.unwrap().data_as_slice(),
+            [1, 2, 3, 4]
+        );
+        assert!(LVArrayOwned::<2, u8>::from_shape_slice([2, 3].into(), &[1, 2]).is_err());
+    }
+
+    #[test]
+    fn test_resize_string_array() {
+        use crate::types::{LStrHandle, LStrOwned};
+
+        let mut names: LVArrayHandle<1, LStrHandle> = UHandle(std::ptr::null_mut());
+        assert!(names.resize_elements([3].into()).is_err());
+        names = LVArrayOwned::new_zeroed([0].into()).unwrap().into_raw();
+        names.resize_elements([3].into()).unwrap();
+        assert!(names.iter().all(|name| name.is_null()));
+        for (index, name) in names.iter_mut().enumerate() {
+            *name = LStrOwned::from_data(index.to_string().as_bytes())
+                .unwrap()
+                .into_raw();
+        }
+
+        names.resize_elements([1].into()).unwrap();
+        assert_eq!(names.as_slice().len(), 1);
+        assert_eq!(names[[0]].as_bytes().unwrap(), b"0");
+
+        unsafe { names.deep_dispose() }.unwrap();
+    }
+
+    #[test]
+    fn test_owned_array_disposes_nested_arrays() {
+        let mut nested = LVArrayOwned::<1, LVArrayHandle<1, i32>>::new_zeroed([2].into()).unwrap();
+        nested[[1]] = LVArrayOwned::<1, i32>::from_slice(&[1, 2])
+            .unwrap()
+            .into_raw();
+        assert_eq!(nested[[1]].as_slice(), &[1, 2]);
+        drop(nested);
+    }
+
+    #[test]
+    fn test_deep_copy_string_array() {
+        use crate::types::{LStrHandle, LStrOwned};
+
+        let mut source = LVArrayOwned::<1, LStrHandle>::new_zeroed([2].into()).unwrap();
+        source[[0]] = LStrOwned::from_data(b"first").unwrap().into_raw();
+        source[[1]] = LStrOwned::from_data(b"second").unwrap().into_raw();
+
+        let copy = unsafe { LVArrayOwned::deep_copy_of(&source) }.unwrap();
+        assert_ne!(copy[[0]].0, source[[0]].0);
+        drop(source);
+        assert_eq!(copy[[1]].as_bytes().unwrap(), b"second");
+
+        // Copying into an existing array reuses its element handles.
+        let mut target = LVArrayOwned::<1, LStrHandle>::new_zeroed([1].into()).unwrap();
+        target[[0]] = LStrOwned::from_data(b"old").unwrap().into_raw();
+        let reused = target[[0]].0;
+        unsafe { target.deep_copy_from(&copy) }.unwrap();
+        assert_eq!(target[[0]].0, reused);
+        assert_eq!(target[[0]].as_bytes().unwrap(), b"first");
+        assert_eq!(target[[1]].as_bytes().unwrap(), b"second");
+    }
+
+    #[test]
+    fn test_copy_slices() {
+        let mut array = LVArrayOwned::<2, u16>::new([2, 2].into()).unwrap();
+        array.copy_from_slice(&[1, 2, 3, 4]).unwrap();
+        let mut output = [0; 4];
+        array.copy_to_slice(&mut output).unwrap();
+        assert_eq!(output, [1, 2, 3, 4]);
+        assert!(array.copy_from_slice(&[1, 2]).is_err());
+        assert!(array.copy_to_slice(&mut [0; 5]).is_err());
+    }
+
+    #[test]
+    fn test_set_from_iter() {
+        let mut array = LVArrayOwned::<1, u32>::try_from_iter((1..=3).map(|x| x * 10)).unwrap();
+        assert_eq!(
+            unsafe { array.as_ref() }.unwrap().data_as_slice(),
+            [10, 20, 30]
+        );
+
+        // Without a size hint the array grows as it goes then is trimmed.
+        array.set_from_iter((0..20).filter(|x| x % 2 == 0)).unwrap();
+        let array = unsafe { array.as_ref() }.unwrap();
+        assert_eq!(array.dimension_sizes(), [10].into());
+        assert_eq!(array.data_as_slice()[9], 18);
     }
 }
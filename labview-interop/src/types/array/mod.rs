@@ -1,22 +1,70 @@
 //! The arrays module covers LabVIEW multidimensional array.
 //!
 
+#[cfg(all(feature = "bytes", target_pointer_width = "64"))]
+mod bytes;
 #[cfg(feature = "link")]
 mod memory;
 #[cfg(all(feature = "ndarray", target_pointer_width = "64"))]
 mod ndarray;
+#[cfg(all(feature = "serde", feature = "link", target_pointer_width = "64"))]
+mod serialize;
+#[cfg(target_pointer_width = "64")]
+mod transpose;
+#[cfg(target_pointer_width = "64")]
+mod view;
+
+#[cfg(feature = "link")]
+pub use memory::{ArrayElement, NumericArrayResizable};
+#[cfg(target_pointer_width = "64")]
+pub use view::ArrayView;
 
 use crate::errors::LVInteropError;
 use crate::labview_layout;
+#[cfg(feature = "link")]
+use crate::memory::OwnedUHandle;
 use crate::memory::UHandle;
+use crate::validation::{validation_enabled, ValidationLevel};
 
 #[repr(transparent)]
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub struct LVArrayDims<const D: usize>([i32; D]);
 
 impl<const D: usize> LVArrayDims<D> {
+    /// The total number of elements. Negative dimensions are treated as empty.
     pub fn element_count(&self) -> usize {
-        self.0.iter().fold(1, |size, dim| size * *dim as usize)
+        self.0
+            .iter()
+            .fold(1, |size, dim| size * (*dim).max(0) as usize)
+    }
+
+    /// The offset of an element in the row-major data, or `None` if any index
+    /// is out of bounds.
+    pub fn offset(&self, index: [usize; D]) -> Option<usize> {
+        self.0
+            .iter()
+            .zip(index)
+            .try_fold(0, |offset, (dim, index)| {
+                let dim = (*dim).max(0) as usize;
+                (index < dim).then_some(offset * dim + index)
+            })
+    }
+
+    /// Check the dimensions against the expected sizes.
+    ///
+    /// The error includes the parameter name along with the expected and actual
+    /// dimensions so it is meaningful when it reaches the LabVIEW error cluster.
+    pub fn require(&self, parameter: &str, expected: [usize; D]) -> Result<(), LVInteropError> {
+        let actual = self.0.map(|dim| dim.max(0) as usize);
+        if actual == expected {
+            Ok(())
+        } else {
+            Err(LVInteropError::UnexpectedArrayDimensions {
+                parameter: parameter.to_string(),
+                expected: expected.to_vec(),
+                actual: actual.to_vec(),
+            })
+        }
     }
 }
 
@@ -112,6 +160,17 @@ impl<const D: usize, T> LVArray<D, T> {
         self.dimension_sizes().element_count()
     }
 
+    /// Panic if `index` is out of range at the paranoid validation level.
+    fn paranoid_bounds_check(&self, index: usize) {
+        if validation_enabled(ValidationLevel::Paranoid) {
+            let count = self.element_count();
+            assert!(
+                index < count,
+                "array index {index} is out of range for {count} elements"
+            );
+        }
+    }
+
     /// Get the value directly from the array. This is an unsafe method used on
     /// 32 bit targets where the packed structure means we cannot access a slice.
     ///
@@ -119,8 +178,10 @@ impl<const D: usize, T> LVArray<D, T> {
     ///
     /// # Safety
     ///
-    /// If the index is out of the range then it is undefined behaviour.
+    /// If the index is out of the range then it is undefined behaviour. At the
+    /// paranoid [validation level](crate::validation) this panics instead.
     pub unsafe fn get_value_unchecked(&self, index: usize) -> T {
+        self.paranoid_bounds_check(index);
         let data_ptr = std::ptr::addr_of!(self.data) as *const T;
         let element_ptr = data_ptr.add(index);
         std::ptr::read_unaligned(element_ptr)
@@ -135,8 +196,10 @@ impl<const D: usize, T> LVArray<D, T> {
     ///
     /// # Safety
     ///
-    /// If the index is out of range then it is undefined behaviour.
+    /// If the index is out of range then it is undefined behaviour. At the
+    /// paranoid [validation level](crate::validation) this panics instead.
     pub unsafe fn set_value_unchecked(&mut self, index: usize, value: T) {
+        self.paranoid_bounds_check(index);
         let data_ptr = std::ptr::addr_of_mut!(self.data) as *mut T;
         let element_ptr = data_ptr.add(index);
         std::ptr::write_unaligned(element_ptr, value);
@@ -173,6 +236,238 @@ impl<const D: usize, T> LVArray<D, T> {
 /// Definition of a handle to an array. Helper for FFI definitin.
 pub type LVArrayHandle<const D: usize, T> = UHandle<LVArray<D, T>>;
 
+/// An array handle allocated by this library which is disposed when dropped.
+#[cfg(feature = "link")]
+pub type LVArrayOwned<const D: usize, T> = OwnedUHandle<LVArray<D, T>>;
+
+/// The offset of the data from the start of an array, after the dimensions.
+#[cfg(feature = "link")]
+pub(crate) fn data_offset<const D: usize, T>() -> usize {
+    let dims = std::mem::size_of::<LVArrayDims<D>>();
+    // 32 bit arrays are packed so the data follows the dimensions directly.
+    #[cfg(target_pointer_width = "64")]
+    let dims = dims.next_multiple_of(std::mem::align_of::<T>());
+    dims
+}
+
+impl<const D: usize, T> LVArrayHandle<D, T> {
+    /// Get the dimensions of the array, treating a null handle as an empty array.
+    ///
+    /// LabVIEW may pass empty arrays as a null handle so this is the safe way
+    /// to inspect the size of an input.
+    pub fn dimensions_or_empty(&self) -> LVArrayDims<D> {
+        unsafe { self.as_ref() }
+            .map(|array| array.dimension_sizes())
+            .unwrap_or([0; D].into())
+    }
+
+    /// Require the array to have the given dimensions.
+    ///
+    /// See [`expect_dims!`](crate::expect_dims) to include the parameter name in the error.
+    pub fn require_dims(&self, expected: [usize; D]) -> Result<(), LVInteropError> {
+        self.validate()?;
+        self.dimensions_or_empty().require("array", expected)
+    }
+
+    /// Check the array is consistent at the current [validation level](crate::validation).
+    ///
+    /// A null handle is a valid empty array.
+    pub fn validate(&self) -> Result<(), LVInteropError> {
+        if !validation_enabled(ValidationLevel::Basic) {
+            return Ok(());
+        }
+        let Ok(array) = (unsafe { self.as_ref() }) else {
+            return Ok(());
+        };
+        let dims = array.dimension_sizes();
+        if dims.0.iter().any(|dim| *dim < 0) {
+            return Err(LVInteropError::ValidationFailed(format!(
+                "array has negative dimensions {:?}",
+                dims.0
+            )));
+        }
+
+        #[cfg(feature = "link")]
+        if validation_enabled(ValidationLevel::Paranoid) {
+            let required = dims.element_count() * std::mem::size_of::<T>() + data_offset::<D, T>();
+            let allocated = unsafe { self.handle_size()? };
+            if allocated < required {
+                return Err(LVInteropError::ValidationFailed(format!(
+                    "array handle holds {allocated} bytes but dimensions {:?} need {required}",
+                    dims.0
+                )));
+            }
+            unsafe { crate::validation::canary::check(self.handle_value(), required, allocated)? };
+        }
+        Ok(())
+    }
+}
+
+#[cfg(target_pointer_width = "64")]
+impl<const D: usize, T> LVArrayHandle<D, T> {
+    /// Borrow the array data as a slice, without copying.
+    ///
+    /// A null handle is an empty array so gives an empty slice. Multi-dimension
+    /// arrays are in row-major order, with the last dimension varying fastest.
+    pub fn as_slice(&self) -> &[T] {
+        match unsafe { self.as_ref() } {
+            Ok(array) => array.data_as_slice(),
+            Err(_) => &[],
+        }
+    }
+
+    /// Borrow the array data as a mutable slice, without copying.
+    ///
+    /// A null handle is an empty array so gives an empty slice.
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        match unsafe { self.as_ref_mut() } {
+            Ok(array) => array.data_as_slice_mut(),
+            Err(_) => &mut [],
+        }
+    }
+
+    /// Get the element at an index in each dimension, or `None` if it is out of bounds.
+    ///
+    /// For a 2D array the index is `[row, column]`.
+    pub fn get(&self, index: [usize; D]) -> Option<&T> {
+        let offset = self.dimensions_or_empty().offset(index)?;
+        self.as_slice().get(offset)
+    }
+
+    /// Get a mutable reference to the element at an index in each dimension,
+    /// or `None` if it is out of bounds.
+    pub fn get_mut(&mut self, index: [usize; D]) -> Option<&mut T> {
+        let offset = self.dimensions_or_empty().offset(index)?;
+        self.as_mut_slice().get_mut(offset)
+    }
+
+    /// Iterate over the elements in row-major order.
+    pub fn iter(&self) -> std::slice::Iter<'_, T> {
+        self.as_slice().iter()
+    }
+
+    /// Iterate mutably over the elements in row-major order.
+    pub fn iter_mut(&mut self) -> std::slice::IterMut<'_, T> {
+        self.as_mut_slice().iter_mut()
+    }
+}
+
+/// Index with an index in each dimension. This panics if it is out of bounds.
+#[cfg(target_pointer_width = "64")]
+impl<const D: usize, T> std::ops::Index<[usize; D]> for LVArrayHandle<D, T> {
+    type Output = T;
+
+    fn index(&self, index: [usize; D]) -> &T {
+        let dims = self.dimensions_or_empty();
+        self.get(index).unwrap_or_else(|| {
+            panic!(
+                "index {index:?} is out of bounds for dimensions {:?}",
+                dims.0
+            )
+        })
+    }
+}
+
+#[cfg(target_pointer_width = "64")]
+impl<const D: usize, T> std::ops::IndexMut<[usize; D]> for LVArrayHandle<D, T> {
+    fn index_mut(&mut self, index: [usize; D]) -> &mut T {
+        let dims = self.dimensions_or_empty();
+        self.get_mut(index).unwrap_or_else(|| {
+            panic!(
+                "index {index:?} is out of bounds for dimensions {:?}",
+                dims.0
+            )
+        })
+    }
+}
+
+#[cfg(target_pointer_width = "64")]
+impl<'a, const D: usize, T> IntoIterator for &'a LVArrayHandle<D, T> {
+    type Item = &'a T;
+    type IntoIter = std::slice::Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[cfg(target_pointer_width = "64")]
+impl<'a, const D: usize, T> IntoIterator for &'a mut LVArrayHandle<D, T> {
+    type Item = &'a mut T;
+    type IntoIter = std::slice::IterMut<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+impl<const D: usize, T: Copy> LVArrayHandle<D, T> {
+    /// Copy the elements into a vector in row-major order.
+    ///
+    /// A null handle is an empty array so gives an empty vector. With the
+    /// `ndarray` feature use `as_ndarray()?.to_owned()` to keep the shape.
+    pub fn to_vec(&self) -> Vec<T> {
+        let Ok(array) = (unsafe { self.as_ref() }) else {
+            return Vec::new();
+        };
+        #[cfg(target_pointer_width = "64")]
+        let data = array.data_as_slice().to_vec();
+        // The packed 32 bit layout must be read an element at a time.
+        #[cfg(target_pointer_width = "32")]
+        let data = (0..array.element_count())
+            .map(|index| unsafe { array.get_value_unchecked(index) })
+            .collect();
+        data
+    }
+}
+
+impl<T: Copy> LVArrayHandle<2, T> {
+    /// Copy the elements into a vector of rows.
+    pub fn to_rows(&self) -> Vec<Vec<T>> {
+        let [rows, columns] = self.dimensions_or_empty().0.map(|dim| dim.max(0) as usize);
+        if columns == 0 {
+            return vec![Vec::new(); rows];
+        }
+        self.to_vec()
+            .chunks_exact(columns)
+            .map(<[T]>::to_vec)
+            .collect()
+    }
+}
+
+impl<T> LVArrayHandle<1, T> {
+    /// Require the 1D array to have exactly `len` elements.
+    pub fn require_len(&self, len: usize) -> Result<(), LVInteropError> {
+        self.require_dims([len])
+    }
+}
+
+/// Validate the dimensions of an array handle, naming the parameter in the error.
+///
+/// This returns a [`crate::errors::Result`] so it can be used with `?` at the start
+/// of an exported function to standardise input validation.
+///
+/// # Example
+/// ```
+/// use labview_interop::expect_dims;
+/// use labview_interop::errors::{MgErr, Result};
+/// use labview_interop::types::LVArrayHandle;
+///
+/// fn check_inputs(matrix: LVArrayHandle<2, f64>, offsets: LVArrayHandle<1, f64>) -> Result<()> {
+///     expect_dims!(matrix, [3, 3])?;
+///     expect_dims!(offsets, [3])?;
+///     Ok(())
+/// }
+/// ```
+#[macro_export]
+macro_rules! expect_dims {
+    ($array:expr, [$($dim:expr),+ $(,)?]) => {
+        $array
+            .dimensions_or_empty()
+            .require(stringify!($array), [$($dim),+])
+    };
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -188,6 +483,77 @@ mod tests {
         assert_eq!(dims.element_count(), 2);
     }
 
+    #[test]
+    fn test_negative_dimension_is_empty() {
+        let dims = LVArrayDims::<2>([-1, 3]);
+        assert_eq!(dims.element_count(), 0);
+    }
+
+    #[cfg(target_pointer_width = "64")]
+    #[test]
+    fn test_null_handle_is_empty_slice() {
+        let mut array: LVArrayHandle<2, f64> = UHandle(std::ptr::null_mut());
+        assert!(array.as_slice().is_empty());
+        assert!(array.as_mut_slice().is_empty());
+    }
+
+    #[cfg(all(feature = "link", target_pointer_width = "64"))]
+    #[test]
+    fn test_as_mut_slice_writes_data() {
+        let mut array =
+            LVArrayOwned::<2, i32>::from_shape_slice([2, 2].into(), &[1, 2, 3, 4]).unwrap();
+        array.as_mut_slice()[3] = 5;
+        assert_eq!(array.as_slice(), &[1, 2, 3, 5]);
+    }
+
+    #[test]
+    fn test_offset_is_row_major() {
+        let dims = LVArrayDims::<2>([2, 3]);
+        assert_eq!(dims.offset([0, 2]), Some(2));
+        assert_eq!(dims.offset([1, 0]), Some(3));
+        assert_eq!(dims.offset([1, 2]), Some(5));
+        assert_eq!(dims.offset([2, 0]), None);
+        assert_eq!(dims.offset([0, 3]), None);
+    }
+
+    #[cfg(all(feature = "link", target_pointer_width = "64"))]
+    #[test]
+    fn test_get_2d() {
+        let mut array =
+            LVArrayOwned::<2, i32>::from_shape_slice([2, 3].into(), &[1, 2, 3, 4, 5, 6]).unwrap();
+        assert_eq!(array.get([1, 0]), Some(&4));
+        assert_eq!(array.get([0, 3]), None);
+        *array.get_mut([0, 1]).unwrap() = 7;
+        array[[1, 2]] = 8;
+        assert_eq!(array[[0, 1]], 7);
+        assert_eq!(array.as_slice(), &[1, 7, 3, 4, 5, 8]);
+    }
+
+    #[cfg(feature = "link")]
+    #[test]
+    fn test_to_vec_and_rows() {
+        let array =
+            LVArrayOwned::<2, i32>::from_shape_slice([2, 3].into(), &[1, 2, 3, 4, 5, 6]).unwrap();
+        assert_eq!(array.to_vec(), vec![1, 2, 3, 4, 5, 6]);
+        assert_eq!(array.to_rows(), vec![vec![1, 2, 3], vec![4, 5, 6]]);
+
+        let empty: LVArrayHandle<2, i32> = UHandle(std::ptr::null_mut());
+        assert!(empty.to_vec().is_empty());
+        assert!(empty.to_rows().is_empty());
+    }
+
+    #[cfg(all(feature = "link", target_pointer_width = "64"))]
+    #[test]
+    fn test_iterators() {
+        let mut array = LVArrayOwned::<1, f64>::from_slice(&[1.0, 2.0, 3.0]).unwrap();
+        for value in &mut *array {
+            *value *= 2.0;
+        }
+        assert_eq!(array.iter().sum::<f64>(), 12.0);
+        let doubled: Vec<_> = (&*array).into_iter().map(|value| value / 2.0).collect();
+        assert_eq!(doubled, vec![1.0, 2.0, 3.0]);
+    }
+
     #[test]
     fn test_dim_equality() {
         let dims1 = LVArrayDims::<3>([2, 3, 4]);
@@ -202,6 +568,22 @@ mod tests {
         assert_eq!(lvdims, [1i32, 2].into())
     }
 
+    #[test]
+    fn test_require_dims_ok() {
+        let dims = LVArrayDims::<2>([2, 3]);
+        assert!(dims.require("input", [2, 3]).is_ok());
+    }
+
+    #[test]
+    fn test_require_dims_mismatch_describes_parameter() {
+        let dims = LVArrayDims::<2>([2, 3]);
+        let error = dims.require("input", [3, 3]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Parameter `input` has dimensions [2, 3] but [3, 3] are required."
+        );
+    }
+
     #[test]
     fn test_dims_from_usize_out_of_range() {
         let dims = &[1usize, i32::MAX as usize + 1];
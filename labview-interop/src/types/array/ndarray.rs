@@ -1,10 +1,55 @@
 //! NDArray support for the LabVIEW array types. This requires 64 bit to
 //! access internal array elements.
+//!
+//! LabVIEW stores arrays in row major order which is the standard layout in
+//! ndarray so views can be made without copying.
 
 use super::memory::NumericArrayResizable;
 use super::{LVArray, LVArrayHandle};
-use crate::errors::Result;
-use ndarray::{ArrayView, ArrayViewMut, Dim, Ix};
+use crate::errors::{LVInteropError, Result};
+use ndarray::{ArrayView, ArrayViewD, ArrayViewMut, ArrayViewMutD, Dim, Ix, IxDyn};
+
+impl<const D: usize, T> LVArray<D, T> {
+    /// Get the dimensions in the dynamic NDArray format.
+    fn ndarray_dyn_dim(&self) -> IxDyn {
+        IxDyn(&self.dimension_sizes().0.map(|dim| dim.max(0) as usize))
+    }
+
+    /// Get the LabVIEW array as an NDArray view with dynamic dimensions.
+    pub fn ndarray_view_dyn(&self) -> ArrayViewD<'_, T> {
+        ArrayView::from_shape(self.ndarray_dyn_dim(), self.data_as_slice()).unwrap()
+    }
+
+    /// Get the LabVIEW array as an NDArray mutable view with dynamic dimensions.
+    pub fn ndarray_view_mut_dyn(&mut self) -> ArrayViewMutD<'_, T> {
+        let dim_sizes = self.ndarray_dyn_dim();
+        ArrayViewMut::from_shape(dim_sizes, self.data_as_slice_mut()).unwrap()
+    }
+}
+
+impl<const D: usize, T> LVArrayHandle<D, T> {
+    /// Borrow the array as an NDArray view with dynamic dimensions.
+    ///
+    /// A null handle is viewed as an empty array.
+    pub fn as_ndarray(&self) -> Result<ArrayViewD<'_, T>> {
+        match unsafe { self.as_ref() } {
+            Ok(array) => Ok(array.ndarray_view_dyn()),
+            Err(_) => ArrayView::from_shape(IxDyn(&[0; D]), &[])
+                .map_err(|_| LVInteropError::ArrayDimensionMismatch),
+        }
+    }
+
+    /// Borrow the array as a mutable NDArray view with dynamic dimensions.
+    ///
+    /// A null handle is viewed as an empty array.
+    pub fn as_ndarray_mut(&mut self) -> Result<ArrayViewMutD<'_, T>> {
+        match unsafe { self.as_ref_mut() } {
+            Ok(array) => Ok(array.ndarray_view_mut_dyn()),
+            Err(_) => ArrayViewMut::from_shape(IxDyn(&[0; D]), &mut [])
+                .map_err(|_| LVInteropError::ArrayDimensionMismatch),
+        }
+    }
+}
 
 macro_rules! array_with_dim {
     ($dim:literal) => {
@@ -16,14 +61,14 @@ macro_rules! array_with_dim {
             }
 
             /// Get the LabVIEW array as an NDArray view.
-            pub fn ndarray_view(&self) -> ArrayView<T, Dim<[Ix; $dim]>> {
+            pub fn ndarray_view(&self) -> ArrayView<'_, T, Dim<[Ix; $dim]>> {
                 let dim_sizes = self.ndarray_dim();
                 let data = self.data_as_slice();
                 ArrayView::from_shape(dim_sizes, data).unwrap()
             }
 
             /// Get the LabVIEW array as an NDArray mutable view.
-            pub fn ndarray_view_mut(&mut self) -> ArrayViewMut<T, Dim<[Ix; $dim]>> {
+            pub fn ndarray_view_mut(&mut self) -> ArrayViewMut<'_, T, Dim<[Ix; $dim]>> {
                 let dim_sizes = self.ndarray_dim();
                 let data = self.data_as_slice_mut();
                 ArrayViewMut::from_shape(dim_sizes, data).unwrap()
@@ -47,7 +92,8 @@ macro_rules! array_with_dim {
                 array: ArrayView<'array, T, Dim<[Ix; $dim]>>,
             ) -> Result<()> {
                 // If the size isn't right either resize if available or error.
-                if array.raw_dim() != self.ndarray_dim() {
+                let current_dims: [usize; $dim] = self.dimensions_or_empty().into();
+                if array.shape() != current_dims {
                     #[cfg(feature = "link")]
                     {
                         self.resize_array(array.shape().try_into()?)?;
@@ -75,3 +121,31 @@ array_with_dim!(3);
 array_with_dim!(4);
 array_with_dim!(5);
 array_with_dim!(6);
+
+#[cfg(all(test, feature = "link"))]
+mod tests {
+    use super::*;
+    use crate::types::LVArrayOwned;
+
+    #[test]
+    fn test_dyn_views_are_row_major() {
+        let mut array = LVArrayOwned::<2, f64>::from_shape_slice(
+            [2, 3].into(),
+            &[1.0, 2.0, 3.0, 4.0, 5.0, 6.0],
+        )
+        .unwrap();
+        let view = array.as_ndarray().unwrap();
+        assert_eq!(view.shape(), [2, 3]);
+        assert_eq!(view[[1, 0]], 4.0);
+
+        array.as_ndarray_mut().unwrap()[[0, 2]] = 10.0;
+        assert_eq!(unsafe { array.as_ref() }.unwrap().data_as_slice()[2], 10.0);
+    }
+
+    #[test]
+    fn test_null_handle_is_empty_view() {
+        let handle: LVArrayHandle<3, i32> = crate::memory::UHandle(std::ptr::null_mut());
+        let view = handle.as_ndarray().unwrap();
+        assert_eq!(view.shape(), [0, 0, 0]);
+    }
+}
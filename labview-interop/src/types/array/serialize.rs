@@ -0,0 +1,156 @@
+//! serde support for array handles.
+//!
+//! Arrays serialize as nested sequences, one level per dimension, which is how
+//! LabVIEW's JSON VIs write multidimensional arrays. A null handle is an empty
+//! array. Deserializing allocates a new handle which the caller must dispose,
+//! and fails if the rows of a multidimensional array differ in length.
+//!
+//! Binary formats such as LabVIEW's flattened data write multidimensional
+//! arrays as the dimensions followed by the elements, which nested sequences
+//! can't express, so multidimensional arrays are an error in formats which
+//! aren't human readable.
+
+use serde::de::{self, DeserializeSeed, SeqAccess, Visitor};
+use serde::ser::{self, SerializeSeq};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use super::{ArrayElement, LVArrayDims, LVArrayHandle, LVArrayOwned};
+
+const BINARY_DIMENSIONS: &str =
+    "multidimensional arrays can only be serialized to human readable formats such as JSON";
+
+impl<const D: usize, T: Serialize> Serialize for LVArrayHandle<D, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if D > 1 && !serializer.is_human_readable() {
+            return Err(ser::Error::custom(BINARY_DIMENSIONS));
+        }
+        self.validate().map_err(ser::Error::custom)?;
+        let dims: [usize; D] = self.dimensions_or_empty().into();
+        let data = unsafe { self.as_ref() }
+            .map(|array| array.data_as_slice())
+            .unwrap_or(&[]);
+        Rows { dims: &dims, data }.serialize(serializer)
+    }
+}
+
+/// The elements of the remaining dimensions.
+struct Rows<'a, T> {
+    dims: &'a [usize],
+    data: &'a [T],
+}
+
+impl<T: Serialize> Serialize for Rows<'_, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let Some((&rows, inner)) = self
+            .dims
+            .split_first()
+            .filter(|(_, inner)| !inner.is_empty())
+        else {
+            return serializer.collect_seq(self.data);
+        };
+        let stride = inner.iter().product::<usize>();
+        let mut seq = serializer.serialize_seq(Some(rows))?;
+        for row in 0..rows {
+            seq.serialize_element(&Rows {
+                dims: inner,
+                data: &self.data[row * stride..(row + 1) * stride],
+            })?;
+        }
+        seq.end()
+    }
+}
+
+impl<'de, const D: usize, T> Deserialize<'de> for LVArrayHandle<D, T>
+where
+    T: Deserialize<'de> + ArrayElement,
+{
+    fn deserialize<De: Deserializer<'de>>(deserializer: De) -> Result<Self, De::Error> {
+        if D > 1 && !deserializer.is_human_readable() {
+            return Err(de::Error::custom(BINARY_DIMENSIONS));
+        }
+        let mut shape = [None; D];
+        let mut data: Vec<T> = Vec::new();
+        let read = Level {
+            depth: 0,
+            shape: &mut shape,
+            data: &mut data,
+        }
+        .deserialize(deserializer);
+        let allocated = read.and_then(|()| {
+            let dims = shape.map(|length| length.unwrap_or(0));
+            LVArrayDims::try_from(&dims)
+                .and_then(LVArrayOwned::<D, T>::new_zeroed)
+                .map_err(de::Error::custom)
+        });
+        let mut array = match allocated {
+            Ok(array) => array,
+            Err(error) => {
+                for element in data {
+                    // Already failing so only the first error is reported.
+                    let _ = unsafe { element.deep_dispose() };
+                }
+                return Err(error);
+            }
+        };
+        let elements = array.as_mut_slice().iter_mut().zip(data);
+        for (slot, element) in elements {
+            // Safety: the slot is a zeroed element which owns nothing.
+            unsafe { std::ptr::write(slot, element) };
+        }
+        Ok(array.into_raw())
+    }
+}
+
+/// Reads one dimension of the array, checking every row has the same length.
+struct Level<'a, const D: usize, T> {
+    depth: usize,
+    shape: &'a mut [Option<usize>; D],
+    data: &'a mut Vec<T>,
+}
+
+impl<'de, const D: usize, T: Deserialize<'de>> DeserializeSeed<'de> for Level<'_, D, T> {
+    type Value = ();
+
+    fn deserialize<De: Deserializer<'de>>(self, deserializer: De) -> Result<(), De::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de, const D: usize, T: Deserialize<'de>> Visitor<'de> for Level<'_, D, T> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(formatter, "an array with {} dimensions", D - self.depth)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        let mut length = 0;
+        if self.depth + 1 >= D {
+            while let Some(element) = seq.next_element()? {
+                self.data.push(element);
+                length += 1;
+            }
+        } else {
+            while seq
+                .next_element_seed(Level {
+                    depth: self.depth + 1,
+                    shape: &mut *self.shape,
+                    data: &mut *self.data,
+                })?
+                .is_some()
+            {
+                length += 1;
+            }
+        }
+        match self.shape[self.depth] {
+            Some(expected) if expected != length => Err(de::Error::custom(format!(
+                "rows of dimension {} have lengths {expected} and {length}",
+                self.depth
+            ))),
+            _ => {
+                self.shape[self.depth] = Some(length);
+                Ok(())
+            }
+        }
+    }
+}
@@ -0,0 +1,137 @@
+//! Transposing 2D arrays. This requires 64 bit to access the array data.
+//!
+//! LabVIEW stores 2D arrays in row major order. Libraries which expect column
+//! major data, or images stored with the other axis first, need the data
+//! transposed before it can be used.
+
+use super::LVArrayHandle;
+#[cfg(feature = "link")]
+use super::{LVArrayDims, NumericArrayResizable};
+use crate::errors::{LVInteropError, Result};
+
+/// The size of the square blocks copied by [`transpose_blocked`]. 32x32 doubles
+/// is 8KB so a source and destination block fit in the L1 cache together.
+const BLOCK_SIZE: usize = 32;
+
+/// Transpose a square row major matrix with `size` rows.
+fn transpose_square<T>(data: &mut [T], size: usize) {
+    debug_assert_eq!(data.len(), size * size);
+    for row in 0..size {
+        for column in (row + 1)..size {
+            data.swap(row * size + column, column * size + row);
+        }
+    }
+}
+
+/// Copy the transpose of the `rows` x `columns` row major `source` into `destination`.
+///
+/// The copy works in blocks so the strided side stays in the cache for large arrays.
+fn transpose_blocked<T: Copy>(source: &[T], rows: usize, columns: usize, destination: &mut [T]) {
+    debug_assert_eq!(source.len(), rows * columns);
+    debug_assert_eq!(destination.len(), rows * columns);
+    for row_block in (0..rows).step_by(BLOCK_SIZE) {
+        for column_block in (0..columns).step_by(BLOCK_SIZE) {
+            for row in row_block..(row_block + BLOCK_SIZE).min(rows) {
+                for column in column_block..(column_block + BLOCK_SIZE).min(columns) {
+                    destination[column * rows + row] = source[row * columns + column];
+                }
+            }
+        }
+    }
+}
+
+impl<T> LVArrayHandle<2, T> {
+    /// Transpose a square array in place.
+    ///
+    /// A null handle is an empty array so is left as it is. Use
+    /// [`LVArrayHandle::transpose_to`] for arrays which aren't square.
+    pub fn transpose_in_place(&mut self) -> Result<()> {
+        let [rows, columns]: [usize; 2] = self.dimensions_or_empty().into();
+        if rows != columns {
+            return Err(LVInteropError::UnexpectedArrayDimensions {
+                parameter: "array".to_string(),
+                expected: vec![rows, rows],
+                actual: vec![rows, columns],
+            });
+        }
+        if let Ok(array) = unsafe { self.as_ref_mut() } {
+            transpose_square(array.data_as_slice_mut(), rows);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "link")]
+impl<T: NumericArrayResizable + Copy> LVArrayHandle<2, T> {
+    /// Write the transpose of this array into `other`, resizing it to fit.
+    pub fn transpose_to(&self, other: &mut LVArrayHandle<2, T>) -> Result<()> {
+        let [rows, columns]: [usize; 2] = self.dimensions_or_empty().into();
+        other.resize_array(LVArrayDims::try_from(&[columns, rows])?)?;
+        if let (Ok(source), Ok(destination)) =
+            (unsafe { self.as_ref() }, unsafe { other.as_ref_mut() })
+        {
+            transpose_blocked(
+                source.data_as_slice(),
+                rows,
+                columns,
+                destination.data_as_slice_mut(),
+            );
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transpose_square() {
+        let mut data = [1, 2, 3, 4, 5, 6, 7, 8, 9];
+        transpose_square(&mut data, 3);
+        assert_eq!(data, [1, 4, 7, 2, 5, 8, 3, 6, 9]);
+    }
+
+    #[test]
+    fn test_transpose_blocked_crosses_blocks() {
+        let (rows, columns) = (BLOCK_SIZE + 3, 2 * BLOCK_SIZE + 1);
+        let source: Vec<usize> = (0..rows * columns).collect();
+        let mut destination = vec![0; rows * columns];
+        transpose_blocked(&source, rows, columns, &mut destination);
+        for row in 0..rows {
+            for column in 0..columns {
+                assert_eq!(
+                    destination[column * rows + row],
+                    source[row * columns + column]
+                );
+            }
+        }
+    }
+
+    #[cfg(feature = "link")]
+    #[test]
+    fn test_transpose_to_resizes() {
+        use crate::types::LVArrayOwned;
+
+        let source = LVArrayOwned::<2, i32>::new([2, 3].into()).unwrap();
+        unsafe { source.as_ref_mut() }
+            .unwrap()
+            .data_as_slice_mut()
+            .copy_from_slice(&[1, 2, 3, 4, 5, 6]);
+        let mut destination = LVArrayOwned::<2, i32>::new([1, 1].into()).unwrap();
+
+        source.transpose_to(&mut destination).unwrap();
+        let destination = unsafe { destination.as_ref() }.unwrap();
+        assert_eq!(destination.dimension_sizes(), [3, 2].into());
+        assert_eq!(destination.data_as_slice(), [1, 4, 2, 5, 3, 6]);
+    }
+
+    #[cfg(feature = "link")]
+    #[test]
+    fn test_transpose_in_place_requires_square() {
+        use crate::types::LVArrayOwned;
+
+        let mut array = LVArrayOwned::<2, i32>::new([2, 3].into()).unwrap();
+        assert!(array.transpose_in_place().is_err());
+    }
+}
@@ -0,0 +1,230 @@
+//! Strided views of array data. This requires 64 bit to access the array data.
+//!
+//! An [`ArrayView`] borrows the data of an array along with a shape and the
+//! stride of each dimension, so transposing a view or taking a row, column or
+//! range of it only changes the strides rather than copying the data. This
+//! lets code expecting column major data read LabVIEW's row major arrays
+//! without repacking them.
+//!
+//! # Example
+//! ```
+//! use labview_interop::types::ArrayView;
+//!
+//! // A 2x3 row major array as LabVIEW stores it.
+//! let data = [1, 2, 3, 4, 5, 6];
+//! let view = ArrayView::new(&data, [2, 3]).unwrap();
+//!
+//! assert_eq!(view.column(1).to_vec(), vec![2, 5]);
+//! let transposed = view.transpose();
+//! assert_eq!(transposed.shape(), [3, 2]);
+//! assert_eq!(transposed.get([2, 1]), Some(&6));
+//! assert_eq!(transposed.to_vec(), vec![1, 4, 2, 5, 3, 6]);
+//! ```
+
+use std::ops::Range;
+
+use super::LVArrayHandle;
+use crate::errors::{LVInteropError, Result};
+
+/// A view of array data with a shape and a stride for each dimension.
+///
+/// The strides are in elements. Views are cheap to copy.
+#[derive(Debug)]
+pub struct ArrayView<'a, const D: usize, T> {
+    data: &'a [T],
+    offset: usize,
+    shape: [usize; D],
+    strides: [usize; D],
+}
+
+impl<const D: usize, T> Clone for ArrayView<'_, D, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<const D: usize, T> Copy for ArrayView<'_, D, T> {}
+
+impl<'a, const D: usize, T> ArrayView<'a, D, T> {
+    /// View row major data with the given shape, as LabVIEW stores it.
+    ///
+    /// # Errors
+    ///
+    /// * [`LVInteropError::ArrayDimensionMismatch`] if the shape doesn't match the data length.
+    pub fn new(data: &'a [T], shape: [usize; D]) -> Result<Self> {
+        if shape.iter().product::<usize>() != data.len() {
+            return Err(LVInteropError::ArrayDimensionMismatch);
+        }
+        let mut strides = [1; D];
+        for axis in (0..D.saturating_sub(1)).rev() {
+            strides[axis] = strides[axis + 1] * shape[axis + 1];
+        }
+        Ok(Self {
+            data,
+            offset: 0,
+            shape,
+            strides,
+        })
+    }
+
+    /// The size of each dimension.
+    pub fn shape(&self) -> [usize; D] {
+        self.shape
+    }
+
+    /// The distance in elements between neighbours in each dimension.
+    pub fn strides(&self) -> [usize; D] {
+        self.strides
+    }
+
+    /// The number of elements in the view.
+    pub fn len(&self) -> usize {
+        self.shape.iter().product()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Get the element at an index in each dimension, or `None` if it is out of bounds.
+    pub fn get(&self, index: [usize; D]) -> Option<&'a T> {
+        let mut position = self.offset;
+        for ((index, size), stride) in index.iter().zip(self.shape).zip(self.strides) {
+            if *index >= size {
+                return None;
+            }
+            position += index * stride;
+        }
+        self.data.get(position)
+    }
+
+    /// Reverse the order of the dimensions without copying.
+    ///
+    /// For a 2D array this swaps rows and columns, so iterating the transposed
+    /// view reads the original data in column major order.
+    pub fn transpose(mut self) -> Self {
+        self.shape.reverse();
+        self.strides.reverse();
+        self
+    }
+
+    /// Restrict one dimension to a range without copying.
+    ///
+    /// # Panics
+    ///
+    /// If the axis doesn't exist or the range is out of bounds.
+    pub fn slice_axis(mut self, axis: usize, range: Range<usize>) -> Self {
+        assert!(
+            range.start <= range.end && range.end <= self.shape[axis],
+            "range {range:?} is out of bounds for axis {axis} of size {}",
+            self.shape[axis]
+        );
+        if range.start < range.end {
+            self.offset += range.start * self.strides[axis];
+        }
+        self.shape[axis] = range.end - range.start;
+        self
+    }
+
+    /// Iterate over the elements in the order of the view, last dimension fastest.
+    pub fn iter(&self) -> impl Iterator<Item = &'a T> + '_ {
+        (0..self.len()).map(move |mut flat| {
+            let mut position = self.offset;
+            for axis in (0..D).rev() {
+                position += (flat % self.shape[axis]) * self.strides[axis];
+                flat /= self.shape[axis];
+            }
+            &self.data[position]
+        })
+    }
+
+    /// Copy the elements into a vector in the order of the view.
+    ///
+    /// For a transposed 2D view this gives the column major data.
+    pub fn to_vec(&self) -> Vec<T>
+    where
+        T: Clone,
+    {
+        self.iter().cloned().collect()
+    }
+}
+
+impl<'a, T> ArrayView<'a, 2, T> {
+    /// View a single row.
+    ///
+    /// # Panics
+    ///
+    /// If the row is out of bounds.
+    pub fn row(&self, row: usize) -> ArrayView<'a, 1, T> {
+        let view = self.slice_axis(0, row..row + 1);
+        ArrayView {
+            data: view.data,
+            offset: view.offset,
+            shape: [view.shape[1]],
+            strides: [view.strides[1]],
+        }
+    }
+
+    /// View a single column.
+    ///
+    /// # Panics
+    ///
+    /// If the column is out of bounds.
+    pub fn column(&self, column: usize) -> ArrayView<'a, 1, T> {
+        self.transpose().row(column)
+    }
+}
+
+impl<const D: usize, T> LVArrayHandle<D, T> {
+    /// A row major view of the array data. A null handle gives an empty view.
+    pub fn view(&self) -> ArrayView<'_, D, T> {
+        let shape = self.dimensions_or_empty().0.map(|dim| dim.max(0) as usize);
+        let data = self.as_slice();
+        // The dimensions describe the data so the lengths match.
+        ArrayView::new(data, shape).unwrap_or(ArrayView {
+            data: &[],
+            offset: 0,
+            shape: [0; D],
+            strides: [0; D],
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_row_major_strides() {
+        let data = [0; 24];
+        let view = ArrayView::new(&data, [2, 3, 4]).unwrap();
+        assert_eq!(view.strides(), [12, 4, 1]);
+        assert!(ArrayView::new(&data, [2, 3]).is_err());
+    }
+
+    #[test]
+    fn test_rows_and_columns() {
+        let data = [1, 2, 3, 4, 5, 6];
+        let view = ArrayView::new(&data, [2, 3]).unwrap();
+        assert_eq!(view.row(1).to_vec(), vec![4, 5, 6]);
+        assert_eq!(view.column(2).to_vec(), vec![3, 6]);
+        assert_eq!(view.get([1, 3]), None);
+    }
+
+    #[test]
+    fn test_slice_axis() {
+        let data: Vec<i32> = (0..12).collect();
+        let view = ArrayView::new(&data, [3, 4]).unwrap().slice_axis(1, 1..3);
+        assert_eq!(view.shape(), [3, 2]);
+        assert_eq!(view.to_vec(), vec![1, 2, 5, 6, 9, 10]);
+        assert_eq!(view.transpose().to_vec(), vec![1, 5, 9, 2, 6, 10]);
+        assert!(view.slice_axis(0, 1..1).is_empty());
+    }
+
+    #[test]
+    fn test_null_handle_is_empty_view() {
+        let array: LVArrayHandle<2, f64> = crate::memory::UHandle(std::ptr::null_mut());
+        assert_eq!(array.view().shape(), [0, 0]);
+        assert_eq!(array.view().transpose().iter().count(), 0);
+    }
+}
@@ -32,6 +32,184 @@ impl From<LVBool> for bool {
     }
 }
 
+#[cfg(feature = "serde")]
+impl serde::Serialize for LVBool {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bool((*self).into())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for LVBool {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        <bool as serde::Deserialize>::deserialize(deserializer).map(LVBool::from)
+    }
+}
+
+/// The order of bits within each packed word.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BitOrder {
+    /// The first boolean is the least significant bit.
+    /// This matches LabVIEW's "Boolean Array To Number".
+    #[default]
+    LsbFirst,
+    /// The first boolean is the most significant bit.
+    MsbFirst,
+}
+
+/// Unsigned words which booleans can be packed into.
+pub trait BitWord: Copy + Default {
+    /// The number of bits in the word.
+    const BITS: usize;
+    /// True if the bit at `index` (0 is the least significant) is set.
+    fn bit(self, index: usize) -> bool;
+    /// Set the bit at `index` (0 is the least significant).
+    fn set_bit(&mut self, index: usize);
+}
+
+macro_rules! bit_word {
+    ($($word:ty),+) => {
+        $(
+            impl BitWord for $word {
+                const BITS: usize = <$word>::BITS as usize;
+
+                fn bit(self, index: usize) -> bool {
+                    self & (1 << index) != 0
+                }
+
+                fn set_bit(&mut self, index: usize) {
+                    *self |= 1 << index;
+                }
+            }
+        )+
+    };
+}
+
+bit_word!(u8, u16, u32, u64);
+
+fn bit_index<W: BitWord>(position: usize, order: BitOrder) -> usize {
+    match order {
+        BitOrder::LsbFirst => position,
+        BitOrder::MsbFirst => W::BITS - 1 - position,
+    }
+}
+
+/// Pack booleans into words, filling each word before starting the next.
+///
+/// Unused bits in the last word are zero.
+///
+/// # Example
+/// ```
+/// use labview_interop::types::boolean::{pack_bits, BitOrder};
+///
+/// let words: Vec<u8> = pack_bits([true, false, true], BitOrder::LsbFirst);
+/// assert_eq!(words, vec![0b101]);
+/// ```
+pub fn pack_bits<W: BitWord>(bits: impl IntoIterator<Item = bool>, order: BitOrder) -> Vec<W> {
+    let mut words = Vec::new();
+    for (index, bit) in bits.into_iter().enumerate() {
+        let position = index % W::BITS;
+        if position == 0 {
+            words.push(W::default());
+        }
+        if bit {
+            if let Some(word) = words.last_mut() {
+                word.set_bit(bit_index::<W>(position, order));
+            }
+        }
+    }
+    words
+}
+
+/// Unpack the first `count` booleans from packed words.
+///
+/// If the words hold fewer than `count` bits the result is shorter.
+pub fn unpack_bits<W: BitWord>(words: &[W], count: usize, order: BitOrder) -> Vec<bool> {
+    words
+        .iter()
+        .flat_map(|word| {
+            (0..W::BITS).map(move |position| word.bit(bit_index::<W>(position, order)))
+        })
+        .take(count)
+        .collect()
+}
+
+/// Convert LabVIEW booleans to Rust booleans.
+pub fn to_bools(values: &[LVBool]) -> Vec<bool> {
+    values.iter().map(|value| (*value).into()).collect()
+}
+
+/// Convert Rust booleans to LabVIEW booleans.
+pub fn from_bools(values: &[bool]) -> Vec<LVBool> {
+    values.iter().map(|value| (*value).into()).collect()
+}
+
+impl super::LVArrayHandle<1, LVBool> {
+    /// Copy the boolean array into a `Vec<bool>`. A null handle is an empty array.
+    pub fn to_bools(&self) -> Vec<bool> {
+        self.to_vec().into_iter().map(bool::from).collect()
+    }
+
+    /// Pack the boolean array into words.
+    pub fn pack_bits<W: BitWord>(&self, order: BitOrder) -> Vec<W> {
+        pack_bits(self.to_bools(), order)
+    }
+
+    /// Copy the boolean array into a [`BitVec`](bitvec::vec::BitVec).
+    #[cfg(feature = "bitvec")]
+    pub fn to_bitvec(&self) -> bitvec::vec::BitVec {
+        self.to_vec().into_iter().map(bool::from).collect()
+    }
+}
+
+#[cfg(feature = "link")]
+impl super::LVArrayHandle<1, LVBool> {
+    /// Set the boolean array from Rust booleans, resizing it to match.
+    pub fn copy_from_bools(&mut self, values: &[bool]) -> crate::errors::Result<()> {
+        self.set_from_iter(values.iter().map(|value| LVBool::from(*value)))
+    }
+
+    /// Set the boolean array to the first `count` bits of the packed words.
+    pub fn unpack_bits<W: BitWord>(
+        &mut self,
+        words: &[W],
+        count: usize,
+        order: BitOrder,
+    ) -> crate::errors::Result<()> {
+        self.copy_from_bools(&unpack_bits(words, count, order))
+    }
+
+    /// Set the boolean array from a [`BitSlice`](bitvec::slice::BitSlice), resizing it to match.
+    #[cfg(feature = "bitvec")]
+    pub fn copy_from_bitslice<T, O>(
+        &mut self,
+        bits: &bitvec::slice::BitSlice<T, O>,
+    ) -> crate::errors::Result<()>
+    where
+        T: bitvec::store::BitStore,
+        O: bitvec::order::BitOrder,
+    {
+        self.set_from_iter(bits.iter().by_vals().map(LVBool::from))
+    }
+}
+
+#[cfg(feature = "link")]
+impl super::LVArrayOwned<1, LVBool> {
+    /// Allocate a new boolean array from Rust booleans.
+    pub fn from_bools(values: &[bool]) -> crate::errors::Result<Self> {
+        Self::try_from_iter(values.iter().map(|value| LVBool::from(*value)))
+    }
+}
+
+#[cfg(feature = "link")]
+impl TryFrom<&[bool]> for super::LVArrayOwned<1, LVBool> {
+    type Error = crate::errors::LVInteropError;
+
+    fn try_from(values: &[bool]) -> crate::errors::Result<Self> {
+        Self::from_bools(values)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -51,18 +229,74 @@ mod tests {
     #[test]
     fn test_boolean_lvfalse_to_bool() {
         let value: bool = LV_FALSE.into();
-        assert_eq!(value, false)
+        assert!(!value)
     }
 
     #[test]
     fn test_boolean_lvtrue_to_bool() {
         let value: bool = LV_TRUE.into();
-        assert_eq!(value, true)
+        assert!(value)
     }
 
     #[test]
     fn test_any_non_zero_to_bool() {
         let value: bool = LVBool(23).into();
-        assert_eq!(value, true)
+        assert!(value)
+    }
+
+    #[test]
+    fn test_pack_lsb_first_matches_labview() {
+        let bits = [true, true, false, false, false, false, false, false, true];
+        let words: Vec<u8> = pack_bits(bits, BitOrder::LsbFirst);
+        assert_eq!(words, vec![0b0000_0011, 0b0000_0001]);
+    }
+
+    #[test]
+    fn test_pack_msb_first() {
+        let words: Vec<u16> = pack_bits([true, false, true], BitOrder::MsbFirst);
+        assert_eq!(words, vec![0b1010_0000_0000_0000]);
+    }
+
+    #[test]
+    fn test_unpack_round_trip() {
+        let bits = vec![
+            true, false, true, true, false, true, false, false, true, true,
+        ];
+        for order in [BitOrder::LsbFirst, BitOrder::MsbFirst] {
+            let words: Vec<u32> = pack_bits(bits.iter().copied(), order);
+            assert_eq!(unpack_bits(&words, bits.len(), order), bits);
+        }
+    }
+
+    #[cfg(feature = "link")]
+    #[test]
+    fn test_bool_array_round_trip() {
+        use crate::types::LVArrayOwned;
+
+        let mut array = LVArrayOwned::<1, LVBool>::try_from(&[true, false, true][..]).unwrap();
+        assert_eq!(array.to_bools(), vec![true, false, true]);
+        array.copy_from_bools(&[false]).unwrap();
+        assert_eq!(array.to_bools(), vec![false]);
+    }
+
+    #[cfg(all(feature = "bitvec", feature = "link"))]
+    #[test]
+    fn test_bitvec_round_trip() {
+        use crate::types::LVArrayOwned;
+        use bitvec::prelude::*;
+
+        let mut array = LVArrayOwned::<1, LVBool>::new([0].into()).unwrap();
+        array
+            .copy_from_bitslice(bits![u8, Msb0; 1, 1, 0, 1])
+            .unwrap();
+        assert_eq!(array.to_bools(), vec![true, true, false, true]);
+        assert_eq!(array.to_bitvec(), bitvec![1, 1, 0, 1]);
+    }
+
+    #[test]
+    fn test_lv_bool_slices() {
+        let lv_bools = from_bools(&[true, false]);
+        assert_eq!(lv_bools, vec![LV_TRUE, LV_FALSE]);
+        assert_eq!(to_bools(&lv_bools), vec![true, false]);
     }
 }
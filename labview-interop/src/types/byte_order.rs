@@ -0,0 +1,140 @@
+//! Byte order helpers for exchanging big endian data.
+//!
+//! LabVIEW flattens data in big endian order and many instruments use it on
+//! the wire. These helpers convert buffers in place so no intermediate copies
+//! are needed. The loops are simple enough for the compiler to vectorize.
+
+#[cfg(target_pointer_width = "64")]
+use super::LVArrayHandle;
+
+/// Numeric types whose byte order can be reversed.
+pub trait SwapBytes: Copy {
+    /// Reverse the byte order of the value.
+    fn swap_bytes(self) -> Self;
+}
+
+macro_rules! swap_bytes_int {
+    ($($int:ty),+) => {
+        $(
+            impl SwapBytes for $int {
+                fn swap_bytes(self) -> Self {
+                    <$int>::swap_bytes(self)
+                }
+            }
+        )+
+    };
+}
+
+swap_bytes_int!(i8, i16, i32, i64, u8, u16, u32, u64);
+
+impl SwapBytes for f32 {
+    fn swap_bytes(self) -> Self {
+        f32::from_bits(self.to_bits().swap_bytes())
+    }
+}
+
+impl SwapBytes for f64 {
+    fn swap_bytes(self) -> Self {
+        f64::from_bits(self.to_bits().swap_bytes())
+    }
+}
+
+/// Reverse the byte order of every value in the slice.
+pub fn swap_bytes_in_place<T: SwapBytes>(values: &mut [T]) {
+    for value in values.iter_mut() {
+        *value = value.swap_bytes();
+    }
+}
+
+/// Convert big endian values read into the slice to the native byte order.
+///
+/// This does nothing on big endian targets.
+pub fn from_big_endian_in_place<T: SwapBytes>(values: &mut [T]) {
+    if cfg!(target_endian = "little") {
+        swap_bytes_in_place(values);
+    }
+}
+
+/// Convert native values in the slice to big endian before writing them out.
+///
+/// This does nothing on big endian targets.
+pub fn to_big_endian_in_place<T: SwapBytes>(values: &mut [T]) {
+    from_big_endian_in_place(values)
+}
+
+fn swap_chunks<const N: usize>(buffer: &mut [u8]) {
+    for chunk in buffer.chunks_exact_mut(N) {
+        chunk.reverse();
+    }
+}
+
+/// Reverse the byte order of each `width` byte element in a flattened buffer.
+///
+/// Use this for flattened data such as a string from "Flatten To String" where
+/// the buffer isn't typed. A trailing partial element is left unchanged.
+pub fn swap_buffer_in_place(buffer: &mut [u8], width: usize) {
+    match width {
+        0 | 1 => {}
+        2 => swap_chunks::<2>(buffer),
+        4 => swap_chunks::<4>(buffer),
+        8 => swap_chunks::<8>(buffer),
+        width => {
+            for chunk in buffer.chunks_exact_mut(width) {
+                chunk.reverse();
+            }
+        }
+    }
+}
+
+#[cfg(target_pointer_width = "64")]
+impl<const D: usize, T: SwapBytes> LVArrayHandle<D, T> {
+    /// Reverse the byte order of every element in the array.
+    ///
+    /// A null handle is an empty array so is left unchanged.
+    pub fn swap_bytes_in_place(&mut self) {
+        if let Ok(array) = unsafe { self.as_ref_mut() } {
+            swap_bytes_in_place(array.data_as_slice_mut());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_swap_values() {
+        let mut values = [0x0102u16, 0xA0B0];
+        swap_bytes_in_place(&mut values);
+        assert_eq!(values, [0x0201, 0xB0A0]);
+    }
+
+    #[test]
+    fn test_swap_float_round_trip() {
+        let mut values = [1.5f64, -2.25];
+        let bytes: Vec<u8> = values
+            .iter()
+            .flat_map(|value| value.to_be_bytes())
+            .collect();
+        to_big_endian_in_place(&mut values);
+        let swapped: Vec<u8> = values
+            .iter()
+            .flat_map(|value| value.to_ne_bytes())
+            .collect();
+        assert_eq!(swapped, bytes);
+
+        from_big_endian_in_place(&mut values);
+        assert_eq!(values, [1.5, -2.25]);
+    }
+
+    #[test]
+    fn test_swap_buffer_leaves_partial_element() {
+        let mut buffer = [1u8, 2, 3, 4, 5, 6];
+        swap_buffer_in_place(&mut buffer, 4);
+        assert_eq!(buffer, [4, 3, 2, 1, 5, 6]);
+
+        let mut buffer = [1u8, 2, 3, 4, 5, 6];
+        swap_buffer_in_place(&mut buffer, 3);
+        assert_eq!(buffer, [3, 2, 1, 6, 5, 4]);
+    }
+}
@@ -0,0 +1,101 @@
+//! LabVIEW complex numbers.
+//!
+//! [`LVComplex`] matches the layout of the complex single (CSG) and complex
+//! double (CDB) types, with the real part first. Arrays of them can be resized
+//! like the other numeric arrays so FFT results can be returned directly.
+//!
+//! With the `num-complex` feature the [`num_complex::Complex`] types can be
+//! used in place of [`LVComplex`] as they have the same layout.
+//!
+//! # Example
+//! ```
+//! use labview_interop::types::{LVArrayHandle, LVComplex};
+//! use labview_interop::errors::MgErr;
+//!
+//! #[no_mangle]
+//! pub extern "C" fn conjugate(mut values: LVArrayHandle<1, LVComplex<f64>>) -> MgErr {
+//!     let Ok(values) = (unsafe { values.as_ref_mut() }) else {
+//!         return MgErr::NO_ERROR;
+//!     };
+//!     for value in values.data_as_slice_mut() {
+//!         value.im = -value.im;
+//!     }
+//!     MgErr::NO_ERROR
+//! }
+//! ```
+
+/// A complex number in LabVIEW's layout.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct LVComplex<T> {
+    pub re: T,
+    pub im: T,
+}
+
+/// A LabVIEW complex single (CSG).
+pub type LVComplexSingle = LVComplex<f32>;
+/// A LabVIEW complex double (CDB).
+pub type LVComplexDouble = LVComplex<f64>;
+
+impl<T> LVComplex<T> {
+    pub const fn new(re: T, im: T) -> Self {
+        Self { re, im }
+    }
+}
+
+#[cfg(feature = "link")]
+mod resize {
+    use super::LVComplex;
+    use crate::types::array::NumericArrayResizable;
+
+    impl NumericArrayResizable for LVComplex<f32> {
+        const TYPE_CODE: i32 = 0x0C;
+    }
+
+    impl NumericArrayResizable for LVComplex<f64> {
+        const TYPE_CODE: i32 = 0x0D;
+    }
+
+    #[cfg(feature = "num-complex")]
+    impl NumericArrayResizable for num_complex::Complex<f32> {
+        const TYPE_CODE: i32 = 0x0C;
+    }
+
+    #[cfg(feature = "num-complex")]
+    impl NumericArrayResizable for num_complex::Complex<f64> {
+        const TYPE_CODE: i32 = 0x0D;
+    }
+}
+
+#[cfg(feature = "num-complex")]
+impl<T> From<num_complex::Complex<T>> for LVComplex<T> {
+    fn from(value: num_complex::Complex<T>) -> Self {
+        Self::new(value.re, value.im)
+    }
+}
+
+#[cfg(feature = "num-complex")]
+impl<T> From<LVComplex<T>> for num_complex::Complex<T> {
+    fn from(value: LVComplex<T>) -> Self {
+        num_complex::Complex::new(value.re, value.im)
+    }
+}
+
+#[cfg(all(test, feature = "link", target_pointer_width = "64"))]
+mod tests {
+    use super::*;
+    use crate::types::LVArrayOwned;
+
+    #[test]
+    fn test_layout() {
+        assert_eq!(std::mem::size_of::<LVComplexSingle>(), 8);
+        assert_eq!(std::mem::size_of::<LVComplexDouble>(), 16);
+    }
+
+    #[test]
+    fn test_complex_array() {
+        let values = [LVComplex::new(1.0, -1.0), LVComplex::new(0.5, 2.0)];
+        let array = LVArrayOwned::<1, LVComplexDouble>::from_slice(&values).unwrap();
+        assert_eq!(unsafe { array.as_ref() }.unwrap().data_as_slice(), values);
+    }
+}
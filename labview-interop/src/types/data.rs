@@ -0,0 +1,569 @@
+//! A common trait for data exchanged with LabVIEW.
+//!
+//! Each kind of LabVIEW data has three forms which generic code may need:
+//!
+//! * The in memory representation LabVIEW passes to a Call Library node, for
+//!   example an [`LStrHandle`] for a string.
+//! * The type descriptor LabVIEW uses to describe the type.
+//! * The flattened form produced by Flatten To String, used for variants, user
+//!   events carrying flattened data and files.
+//!
+//! [`LvData`] brings these together so generic code needs one bound rather
+//! than a bound per form. It is implemented for the numeric types, booleans,
+//! strings, timestamps, 1D numeric arrays and, through their flattened form,
+//! [maps and sets](super::map). Clusters of these held in place
+//! can derive it with the `macros` feature, or implement it with
+//! [`lv_data_cluster!`](crate::lv_data_cluster) without it.
+//!
+//! Variants, queues and maps use this trait. User events and the serde format
+//! still have their own bounds, and types such as paths, waveforms and
+//! multi-dimensional arrays don't implement it yet. Moving them over changes
+//! public signatures so is left for a breaking release.
+//!
+//! Converting to handle representations resizes them so requires the `link`
+//! feature.
+//!
+//! # Example
+//! ```
+//! use labview_interop::types::data::{flatten, unflatten, LvData};
+//!
+//! fn round_trip<T: LvData>(value: &T) -> T {
+//!     unflatten(&flatten(value).unwrap()).unwrap()
+//! }
+//!
+//! assert_eq!(round_trip(&vec![1.5f64, 2.5]), vec![1.5, 2.5]);
+//! assert_eq!(f64::type_descriptor(), [0x00, 0x04, 0x00, 0x0A]);
+//! ```
+
+#[cfg(feature = "link")]
+use super::array::LVArrayDims;
+#[cfg(feature = "link")]
+use super::string::LV_ENCODING;
+#[cfg(feature = "link")]
+use super::{LStrHandle, LVArrayHandle};
+use super::{LVBool, LVTime};
+use crate::errors::{LVInteropError, Result};
+
+#[cfg(feature = "macros")]
+pub use labview_interop_macros::LvData;
+
+/// Type codes used in LabVIEW type descriptors.
+pub mod type_code {
+    pub const I8: u8 = 0x01;
+    pub const I16: u8 = 0x02;
+    pub const I32: u8 = 0x03;
+    pub const I64: u8 = 0x04;
+    pub const U8: u8 = 0x05;
+    pub const U16: u8 = 0x06;
+    pub const U32: u8 = 0x07;
+    pub const U64: u8 = 0x08;
+    pub const F32: u8 = 0x09;
+    pub const F64: u8 = 0x0A;
+    pub const EXTENDED: u8 = 0x0B;
+    pub const COMPLEX_F32: u8 = 0x0C;
+    pub const COMPLEX_F64: u8 = 0x0D;
+    pub const COMPLEX_EXTENDED: u8 = 0x0E;
+    pub const ENUM_U8: u8 = 0x15;
+    pub const ENUM_U16: u8 = 0x16;
+    pub const ENUM_U32: u8 = 0x17;
+    pub const BOOLEAN: u8 = 0x21;
+    pub const STRING: u8 = 0x30;
+    pub const PATH: u8 = 0x32;
+    pub const ARRAY: u8 = 0x40;
+    pub const CLUSTER: u8 = 0x50;
+    pub const VARIANT: u8 = 0x53;
+    /// Measure data, which includes the timestamp.
+    pub const MEASURE_DATA: u8 = 0x54;
+    pub const REFNUM: u8 = 0x70;
+}
+
+/// The measure data subtype for a timestamp.
+pub(crate) const TIMESTAMP_SUBTYPE: u16 = 6;
+
+/// Data which can be exchanged with LabVIEW.
+pub trait LvData: Sized {
+    /// How LabVIEW holds the data in memory.
+    type Repr;
+
+    /// The flattened type descriptor, as from Flatten To String.
+    fn type_descriptor() -> Vec<u8>;
+
+    /// Read the value from LabVIEW's representation.
+    fn from_lv(repr: &Self::Repr) -> Result<Self>;
+
+    /// Write the value into LabVIEW's representation, resizing handles as needed.
+    fn to_lv(&self, repr: &mut Self::Repr) -> Result<()>;
+
+    /// Append the flattened form of the value.
+    ///
+    /// This fails if a length doesn't fit in the `i32` LabVIEW flattens it as.
+    fn flatten(&self, output: &mut Vec<u8>) -> Result<()>;
+
+    /// Read a value from the start of `input`, advancing past it.
+    fn unflatten(input: &mut &[u8]) -> Result<Self>;
+}
+
+/// Flatten a value as Flatten To String does.
+pub fn flatten<T: LvData>(value: &T) -> Result<Vec<u8>> {
+    let mut output = Vec::new();
+    value.flatten(&mut output)?;
+    Ok(output)
+}
+
+/// Unflatten a value, requiring all of `input` to be used.
+pub fn unflatten<T: LvData>(mut input: &[u8]) -> Result<T> {
+    let value = T::unflatten(&mut input)?;
+    if !input.is_empty() {
+        return Err(LVInteropError::InvalidFlattenedData(format!(
+            "{} bytes left over",
+            input.len()
+        )));
+    }
+    Ok(value)
+}
+
+/// Build a type descriptor from its type code and the data following it.
+pub fn descriptor(code: u8, body: &[u8]) -> Vec<u8> {
+    let size = (4 + body.len()) as u16;
+    let mut descriptor = Vec::with_capacity(size as usize);
+    descriptor.extend_from_slice(&size.to_be_bytes());
+    descriptor.extend_from_slice(&[0, code]);
+    descriptor.extend_from_slice(body);
+    descriptor
+}
+
+/// Build the type descriptor for a cluster of the elements.
+pub fn cluster_descriptor(elements: &[Vec<u8>]) -> Vec<u8> {
+    let mut body = (elements.len() as u16).to_be_bytes().to_vec();
+    for element in elements {
+        body.extend_from_slice(element);
+    }
+    descriptor(type_code::CLUSTER, &body)
+}
+
+/// Take `count` bytes from the start of `input`.
+pub fn take<'a>(input: &mut &'a [u8], count: usize) -> Result<&'a [u8]> {
+    if input.len() < count {
+        return Err(LVInteropError::InvalidFlattenedData(format!(
+            "needed {count} bytes but only {} are left",
+            input.len()
+        )));
+    }
+    let (taken, rest) = input.split_at(count);
+    *input = rest;
+    Ok(taken)
+}
+
+/// Read a length or dimension, which LabVIEW flattens as an `i32`.
+pub(crate) fn unflatten_length(input: &mut &[u8]) -> Result<usize> {
+    let length = i32::unflatten(input)?;
+    usize::try_from(length)
+        .map_err(|_| LVInteropError::InvalidFlattenedData(format!("negative length {length}")))
+}
+
+/// Write a length or dimension, which must fit in an `i32`.
+pub(crate) fn flatten_length(length: usize, output: &mut Vec<u8>) -> Result<()> {
+    let length = i32::try_from(length).map_err(|_| {
+        LVInteropError::FlattenFailed(format!("length {length} is too long for LabVIEW"))
+    })?;
+    length.flatten(output)
+}
+
+macro_rules! lv_data_numeric {
+    ($($type:ty => $code:expr),+) => {
+        $(
+            impl LvData for $type {
+                type Repr = $type;
+
+                fn type_descriptor() -> Vec<u8> {
+                    descriptor($code, &[])
+                }
+
+                fn from_lv(repr: &Self::Repr) -> Result<Self> {
+                    Ok(*repr)
+                }
+
+                fn to_lv(&self, repr: &mut Self::Repr) -> Result<()> {
+                    *repr = *self;
+                    Ok(())
+                }
+
+                fn flatten(&self, output: &mut Vec<u8>) -> Result<()> {
+                    output.extend_from_slice(&self.to_be_bytes());
+                    Ok(())
+                }
+
+                fn unflatten(input: &mut &[u8]) -> Result<Self> {
+                    let mut bytes = [0; std::mem::size_of::<$type>()];
+                    bytes.copy_from_slice(take(input, std::mem::size_of::<$type>())?);
+                    Ok(<$type>::from_be_bytes(bytes))
+                }
+            }
+
+            #[cfg(feature = "link")]
+            impl LvData for Vec<$type> {
+                type Repr = LVArrayHandle<1, $type>;
+
+                fn type_descriptor() -> Vec<u8> {
+                    array_descriptor(&<$type>::type_descriptor())
+                }
+
+                /// A null handle is an empty array.
+                fn from_lv(repr: &Self::Repr) -> Result<Self> {
+                    repr.validate()?;
+                    Ok(unsafe { repr.as_ref() }
+                        .map(|array| array.data_as_slice().to_vec())
+                        .unwrap_or_default())
+                }
+
+                fn to_lv(&self, repr: &mut Self::Repr) -> Result<()> {
+                    repr.resize_array(LVArrayDims::try_from(&[self.len()])?)?;
+                    unsafe { repr.as_ref_mut()? }
+                        .data_as_slice_mut()
+                        .copy_from_slice(self);
+                    Ok(())
+                }
+
+                fn flatten(&self, output: &mut Vec<u8>) -> Result<()> {
+                    flatten_elements(self, output)
+                }
+
+                fn unflatten(input: &mut &[u8]) -> Result<Self> {
+                    unflatten_elements(input)
+                }
+            }
+        )+
+    };
+}
+
+lv_data_numeric!(
+    i8 => type_code::I8, i16 => type_code::I16, i32 => type_code::I32, i64 => type_code::I64,
+    u8 => type_code::U8, u16 => type_code::U16, u32 => type_code::U32, u64 => type_code::U64,
+    f32 => type_code::F32, f64 => type_code::F64
+);
+
+/// The descriptor of a variable sized 1D array of the element.
+pub(crate) fn array_descriptor(element: &[u8]) -> Vec<u8> {
+    let mut body = 1u16.to_be_bytes().to_vec();
+    // A variable sized dimension.
+    body.extend_from_slice(&(-1i32).to_be_bytes());
+    body.extend_from_slice(element);
+    descriptor(type_code::ARRAY, &body)
+}
+
+fn flatten_elements<T: LvData>(elements: &[T], output: &mut Vec<u8>) -> Result<()> {
+    flatten_length(elements.len(), output)?;
+    for element in elements {
+        element.flatten(output)?;
+    }
+    Ok(())
+}
+
+fn unflatten_elements<T: LvData>(input: &mut &[u8]) -> Result<Vec<T>> {
+    let length = unflatten_length(input)?;
+    // Don't trust the length for the allocation as the data may be corrupt.
+    let mut elements = Vec::with_capacity(length.min(input.len()));
+    for _ in 0..length {
+        elements.push(T::unflatten(input)?);
+    }
+    Ok(elements)
+}
+
+impl LvData for LVBool {
+    type Repr = LVBool;
+
+    fn type_descriptor() -> Vec<u8> {
+        descriptor(type_code::BOOLEAN, &[])
+    }
+
+    fn from_lv(repr: &Self::Repr) -> Result<Self> {
+        Ok(*repr)
+    }
+
+    fn to_lv(&self, repr: &mut Self::Repr) -> Result<()> {
+        *repr = *self;
+        Ok(())
+    }
+
+    fn flatten(&self, output: &mut Vec<u8>) -> Result<()> {
+        output.push(bool::from(*self) as u8);
+        Ok(())
+    }
+
+    fn unflatten(input: &mut &[u8]) -> Result<Self> {
+        Ok((take(input, 1)?[0] != 0).into())
+    }
+}
+
+impl LvData for bool {
+    type Repr = LVBool;
+
+    fn type_descriptor() -> Vec<u8> {
+        LVBool::type_descriptor()
+    }
+
+    fn from_lv(repr: &Self::Repr) -> Result<Self> {
+        Ok((*repr).into())
+    }
+
+    fn to_lv(&self, repr: &mut Self::Repr) -> Result<()> {
+        *repr = (*self).into();
+        Ok(())
+    }
+
+    fn flatten(&self, output: &mut Vec<u8>) -> Result<()> {
+        LVBool::from(*self).flatten(output)
+    }
+
+    fn unflatten(input: &mut &[u8]) -> Result<Self> {
+        LVBool::unflatten(input).map(bool::from)
+    }
+}
+
+impl LvData for LVTime {
+    type Repr = LVTime;
+
+    fn type_descriptor() -> Vec<u8> {
+        descriptor(type_code::MEASURE_DATA, &TIMESTAMP_SUBTYPE.to_be_bytes())
+    }
+
+    fn from_lv(repr: &Self::Repr) -> Result<Self> {
+        Ok(*repr)
+    }
+
+    fn to_lv(&self, repr: &mut Self::Repr) -> Result<()> {
+        *repr = *self;
+        Ok(())
+    }
+
+    fn flatten(&self, output: &mut Vec<u8>) -> Result<()> {
+        output.extend_from_slice(&self.to_be_bytes());
+        Ok(())
+    }
+
+    fn unflatten(input: &mut &[u8]) -> Result<Self> {
+        let mut bytes = [0; 16];
+        bytes.copy_from_slice(take(input, 16)?);
+        Ok(LVTime::from_be_bytes(bytes))
+    }
+}
+
+/// Strings are converted to and from LabVIEW's encoding.
+#[cfg(feature = "link")]
+impl LvData for String {
+    type Repr = LStrHandle;
+
+    fn type_descriptor() -> Vec<u8> {
+        // A variable sized string.
+        descriptor(type_code::STRING, &(-1i32).to_be_bytes())
+    }
+
+    /// A null handle is an empty string.
+    fn from_lv(repr: &Self::Repr) -> Result<Self> {
+        repr.validate()?;
+        Ok(unsafe { repr.as_ref() }
+            .map(|string| string.to_rust_string().into_owned())
+            .unwrap_or_default())
+    }
+
+    fn to_lv(&self, repr: &mut Self::Repr) -> Result<()> {
+        repr.set_str(self)
+    }
+
+    fn flatten(&self, output: &mut Vec<u8>) -> Result<()> {
+        let (bytes, _, _) = LV_ENCODING.encode(self);
+        flatten_length(bytes.len(), output)?;
+        output.extend_from_slice(&bytes);
+        Ok(())
+    }
+
+    fn unflatten(input: &mut &[u8]) -> Result<Self> {
+        let length = unflatten_length(input)?;
+        let bytes = take(input, length)?;
+        Ok(LV_ENCODING
+            .decode_without_bom_handling(bytes)
+            .0
+            .into_owned())
+    }
+}
+
+#[cfg(feature = "link")]
+impl LvData for Vec<bool> {
+    type Repr = LVArrayHandle<1, LVBool>;
+
+    fn type_descriptor() -> Vec<u8> {
+        array_descriptor(&bool::type_descriptor())
+    }
+
+    /// A null handle is an empty array.
+    fn from_lv(repr: &Self::Repr) -> Result<Self> {
+        repr.validate()?;
+        Ok(unsafe { repr.as_ref() }
+            .map(|array| {
+                array
+                    .data_as_slice()
+                    .iter()
+                    .map(|value| (*value).into())
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    fn to_lv(&self, repr: &mut Self::Repr) -> Result<()> {
+        repr.resize_array(LVArrayDims::try_from(&[self.len()])?)?;
+        let output = unsafe { repr.as_ref_mut()? }.data_as_slice_mut();
+        for (output, value) in output.iter_mut().zip(self) {
+            *output = (*value).into();
+        }
+        Ok(())
+    }
+
+    fn flatten(&self, output: &mut Vec<u8>) -> Result<()> {
+        flatten_elements(self, output)
+    }
+
+    fn unflatten(input: &mut &[u8]) -> Result<Self> {
+        unflatten_elements(input)
+    }
+}
+
+/// Implement [`LvData`] for a cluster which LabVIEW holds in place.
+///
+/// This is for use without the `macros` feature, which provides the same
+/// implementation as `#[derive(LvData)]`.
+///
+/// The cluster should be declared with [`labview_layout!`](crate::labview_layout),
+/// implement [`Clone`] and only contain fields whose [`LvData::Repr`] is the
+/// field type itself, such as numerics, [`LVBool`] and [`LVTime`]. List every
+/// field in order with its type.
+///
+/// # Example
+/// ```
+/// use labview_interop::labview_layout;
+/// use labview_interop::lv_data_cluster;
+/// use labview_interop::types::data::{flatten, unflatten};
+/// use labview_interop::types::LVBool;
+///
+/// labview_layout!(
+///     #[derive(Clone, Debug, PartialEq)]
+///     pub struct Setpoint {
+///         channel: u16,
+///         value: f64,
+///         enabled: LVBool,
+///     }
+/// );
+///
+/// lv_data_cluster!(Setpoint { channel: u16, value: f64, enabled: LVBool });
+///
+/// let setpoint = Setpoint { channel: 2, value: 1.5, enabled: true.into() };
+/// assert_eq!(unflatten::<Setpoint>(&flatten(&setpoint).unwrap()).unwrap(), setpoint);
+/// ```
+#[macro_export]
+macro_rules! lv_data_cluster {
+    ($cluster:ty { $($field:ident : $type:ty),+ $(,)? }) => {
+        impl $crate::types::data::LvData for $cluster {
+            type Repr = Self;
+
+            fn type_descriptor() -> Vec<u8> {
+                $crate::types::data::cluster_descriptor(&[
+                    $(<$type as $crate::types::data::LvData>::type_descriptor()),+
+                ])
+            }
+
+            fn from_lv(repr: &Self::Repr) -> $crate::errors::Result<Self> {
+                Ok(repr.clone())
+            }
+
+            fn to_lv(&self, repr: &mut Self::Repr) -> $crate::errors::Result<()> {
+                *repr = self.clone();
+                Ok(())
+            }
+
+            fn flatten(&self, output: &mut Vec<u8>) -> $crate::errors::Result<()> {
+                $(
+                    let $field: $type = self.$field;
+                    $crate::types::data::LvData::flatten(&$field, output)?;
+                )+
+                Ok(())
+            }
+
+            fn unflatten(input: &mut &[u8]) -> $crate::errors::Result<Self> {
+                Ok(Self {
+                    $($field: <$type as $crate::types::data::LvData>::unflatten(input)?),+
+                })
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flatten_matches_labview() {
+        assert_eq!(flatten(&-2i16).unwrap(), [0xFF, 0xFE]);
+        assert_eq!(flatten(&1.0f32).unwrap(), [0x3F, 0x80, 0x00, 0x00]);
+        assert_eq!(flatten(&true).unwrap(), [1]);
+        assert_eq!(
+            flatten(&LVTime::from_parts(1, 1 << 63)).unwrap(),
+            [0, 0, 0, 0, 0, 0, 0, 1, 0x80, 0, 0, 0, 0, 0, 0, 0]
+        );
+    }
+
+    #[test]
+    fn test_length_must_fit_i32() {
+        let mut output = Vec::new();
+        flatten_length(i32::MAX as usize, &mut output).unwrap();
+        assert_eq!(output, [0x7F, 0xFF, 0xFF, 0xFF]);
+        assert!(flatten_length(i32::MAX as usize + 1, &mut output).is_err());
+    }
+
+    #[test]
+    fn test_type_descriptors() {
+        assert_eq!(u8::type_descriptor(), [0x00, 0x04, 0x00, 0x05]);
+        assert_eq!(
+            LVTime::type_descriptor(),
+            [0x00, 0x06, 0x00, 0x54, 0x00, 0x06]
+        );
+        assert_eq!(
+            cluster_descriptor(&[i32::type_descriptor(), bool::type_descriptor()]),
+            [0x00, 0x0E, 0x00, 0x50, 0x00, 0x02, 0x00, 0x04, 0x00, 0x03, 0x00, 0x04, 0x00, 0x21]
+        );
+    }
+
+    #[cfg(feature = "link")]
+    #[test]
+    fn test_string_and_array_round_trip() {
+        assert_eq!(
+            flatten(&"abc".to_string()).unwrap(),
+            [0, 0, 0, 3, b'a', b'b', b'c']
+        );
+        assert_eq!(
+            String::type_descriptor(),
+            [0x00, 0x08, 0x00, 0x30, 0xFF, 0xFF, 0xFF, 0xFF]
+        );
+        assert_eq!(
+            Vec::<f64>::type_descriptor(),
+            [0x00, 0x0E, 0x00, 0x40, 0x00, 0x01, 0xFF, 0xFF, 0xFF, 0xFF, 0x00, 0x04, 0x00, 0x0A]
+        );
+
+        let values = vec![1u16, 2, 3];
+        assert_eq!(
+            unflatten::<Vec<u16>>(&flatten(&values).unwrap()).unwrap(),
+            values
+        );
+        assert!(unflatten::<Vec<u16>>(&[0, 0, 0, 2, 0, 1]).is_err());
+        assert!(unflatten::<u8>(&[1, 2]).is_err());
+    }
+
+    #[cfg(feature = "link")]
+    #[test]
+    fn test_array_handle_round_trip() {
+        use crate::types::LVArrayOwned;
+
+        let mut handle = LVArrayOwned::<1, i32>::new([0].into()).unwrap();
+        vec![4, 5, 6].to_lv(&mut *handle).unwrap();
+        assert_eq!(Vec::<i32>::from_lv(&handle).unwrap(), [4, 5, 6]);
+    }
+}
@@ -0,0 +1,274 @@
+//! Collect several errors and warnings into a single error cluster.
+//!
+//! Some operations can fail in more than one place at once, for example
+//! validating the configuration of every channel in a task. Rather than
+//! stopping at the first failure these can be gathered into an [`ErrorCollector`]
+//! which is written to the error cluster with a combined description.
+//!
+//! The most recently written collector keeps its entries so LabVIEW can
+//! retrieve them individually with the `interop_collected_error_count` and
+//! `interop_collected_error` exports.
+
+use std::borrow::Cow;
+use std::fmt::Write;
+use std::sync::Mutex;
+
+use crate::errors::{LVInteropError, MgErr};
+use crate::types::lv_errors::catch_panic;
+use crate::types::{ErrorClusterPtr, ToLvError};
+
+/// The entries of the last collector written to an error cluster.
+static LAST_COLLECTED: Mutex<Vec<ErrorEntry>> = Mutex::new(Vec::new());
+
+/// A single error or warning captured by an [`ErrorCollector`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrorEntry {
+    pub code: MgErr,
+    pub is_error: bool,
+    pub source: String,
+    pub description: String,
+}
+
+impl ErrorEntry {
+    /// Capture the details of an error.
+    pub fn from_error<E: ToLvError + ?Sized>(error: &E) -> Self {
+        Self {
+            code: error.code(),
+            is_error: error.is_error(),
+            source: error.source().into_owned(),
+            description: error.localized_description().into_owned(),
+        }
+    }
+}
+
+impl ToLvError for ErrorEntry {
+    fn code(&self) -> MgErr {
+        self.code
+    }
+
+    fn is_error(&self) -> bool {
+        self.is_error
+    }
+
+    fn source(&self) -> Cow<'_, str> {
+        self.source.as_str().into()
+    }
+
+    fn description(&self) -> Cow<'_, str> {
+        self.description.as_str().into()
+    }
+}
+
+/// Gathers several errors and warnings to report as one.
+///
+/// When written to an error cluster:
+/// * The code and source are from the first error, or the first warning if there are no errors.
+/// * The cluster is only set to an error state if an error was collected.
+/// * The description lists every entry.
+///
+/// # Example
+/// ```
+/// use labview_interop::errors::LVInteropError;
+/// use labview_interop::types::{ErrorCollector, LVWarning};
+///
+/// fn validate_channels(rates: &[f64]) -> Result<(), ErrorCollector> {
+///     let mut errors = ErrorCollector::new();
+///     for (channel, rate) in rates.iter().enumerate() {
+///         if *rate <= 0.0 {
+///             errors.push(LVWarning::new(5001, format!("Channel {channel} is disabled.")));
+///         }
+///     }
+///     errors.finish()
+/// }
+///
+/// assert!(validate_channels(&[1.0, 2.0]).is_ok());
+/// assert_eq!(validate_channels(&[1.0, 0.0, -1.0]).unwrap_err().len(), 2);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ErrorCollector {
+    entries: Vec<ErrorEntry>,
+}
+
+impl ErrorCollector {
+    /// Create an empty collector.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an error or warning to the collector.
+    pub fn push<E: ToLvError>(&mut self, error: E) {
+        self.entries.push(ErrorEntry::from_error(&error));
+    }
+
+    /// Collect the error from a result, returning the value if successful.
+    pub fn check<T, E: ToLvError>(&mut self, result: Result<T, E>) -> Option<T> {
+        match result {
+            Ok(value) => Some(value),
+            Err(error) => {
+                self.push(error);
+                None
+            }
+        }
+    }
+
+    /// The collected entries in the order they were added.
+    pub fn entries(&self) -> &[ErrorEntry] {
+        &self.entries
+    }
+
+    /// The number of collected entries.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// True if nothing has been collected.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// True if any of the collected entries is an error rather than a warning.
+    pub fn has_errors(&self) -> bool {
+        self.entries.iter().any(|entry| entry.is_error)
+    }
+
+    /// Finish collecting. Returns the collector as the error if anything was collected.
+    ///
+    /// A collector with only warnings still writes the cluster as a warning,
+    /// so this can be returned from [`with_lverrorhandling!`](crate::with_lverrorhandling).
+    pub fn finish(self) -> Result<(), Self> {
+        if self.is_empty() {
+            Ok(())
+        } else {
+            Err(self)
+        }
+    }
+
+    /// The entry which sets the code and source of the cluster.
+    fn primary(&self) -> Option<&ErrorEntry> {
+        self.entries
+            .iter()
+            .find(|entry| entry.is_error)
+            .or_else(|| self.entries.first())
+    }
+}
+
+impl ToLvError for ErrorCollector {
+    fn code(&self) -> MgErr {
+        self.primary().map_or(MgErr::NO_ERROR, |entry| entry.code)
+    }
+
+    fn is_error(&self) -> bool {
+        self.has_errors()
+    }
+
+    fn source(&self) -> Cow<'_, str> {
+        self.primary()
+            .map_or("".into(), |entry| entry.source.as_str().into())
+    }
+
+    fn description(&self) -> Cow<'_, str> {
+        let errors = self.entries.iter().filter(|entry| entry.is_error).count();
+        let warnings = self.entries.len() - errors;
+        let mut description = format!("{errors} error(s) and {warnings} warning(s):");
+        for entry in &self.entries {
+            let kind = if entry.is_error { "Error" } else { "Warning" };
+            // Writing to a string cannot fail.
+            let _ = write!(description, "\n{kind} {}", i32::from(entry.code));
+            if !entry.source.is_empty() {
+                let _ = write!(description, " in {}", entry.source);
+            }
+            let _ = write!(description, ": {}", entry.description);
+        }
+        description.into()
+    }
+
+    /// Writes the combined error and keeps the entries for `interop_collected_error`.
+    fn write_error(&self, error_cluster: ErrorClusterPtr) -> Result<(), LVInteropError> {
+        *lock_last_collected() = self.entries.clone();
+        let cluster = unsafe { error_cluster.as_ref_mut()? };
+        let description = self.description();
+        if self.is_error() {
+            cluster.set_error(self.code(), &self.source(), &description)
+        } else {
+            cluster.set_warning(self.code(), &self.source(), &description)
+        }
+    }
+}
+
+fn lock_last_collected() -> std::sync::MutexGuard<'static, Vec<ErrorEntry>> {
+    LAST_COLLECTED
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// The number of entries in the last collector written to an error cluster.
+#[no_mangle]
+pub extern "C" fn interop_collected_error_count() -> i32 {
+    catch_panic(|| lock_last_collected().len() as i32).unwrap_or(0)
+}
+
+/// Write an entry of the last collector written to an error cluster into `error_cluster`.
+///
+/// Returns an error code if the index is out of range.
+#[no_mangle]
+pub extern "C" fn interop_collected_error(index: i32, error_cluster: ErrorClusterPtr) -> MgErr {
+    crate::export::catch_panic_status(|| collected_error(index, error_cluster))
+}
+
+fn collected_error(index: i32, error_cluster: ErrorClusterPtr) -> MgErr {
+    let entry = usize::try_from(index)
+        .ok()
+        .and_then(|index| lock_last_collected().get(index).cloned());
+    let Some(entry) = entry else {
+        return LVInteropError::InvalidHandle.into();
+    };
+    match entry.write_error(error_cluster) {
+        Ok(()) => MgErr::NO_ERROR,
+        Err(error) => error.into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::LVWarning;
+
+    #[test]
+    fn test_warnings_only_is_warning() {
+        let mut collector = ErrorCollector::new();
+        collector.push(LVWarning::new(5001, "first"));
+        collector.push(LVWarning::new(5002, "second"));
+        assert!(!collector.is_error());
+        assert_eq!(collector.code(), MgErr::from(5001));
+    }
+
+    #[test]
+    fn test_first_error_sets_code_and_source() {
+        let mut collector = ErrorCollector::new();
+        collector.push(LVWarning::new(5001, "warn"));
+        assert_eq!(
+            collector.check::<(), _>(Err(LVInteropError::InvalidHandle)),
+            None
+        );
+        collector.push(LVWarning::new(5002, "late").with_source("channel 2"));
+        assert!(collector.is_error());
+        assert_eq!(collector.code(), MgErr::from(42));
+        assert_eq!(collector.len(), 3);
+    }
+
+    #[test]
+    fn test_description_lists_entries() {
+        let mut collector = ErrorCollector::new();
+        collector.push(LVWarning::new(5001, "disabled").with_source("channel 1"));
+        collector.push(LVWarning::new(5002, "clipped"));
+        assert_eq!(
+            collector.description(),
+            "0 error(s) and 2 warning(s):\nWarning 5001 in channel 1: disabled\nWarning 5002: clipped"
+        );
+    }
+
+    #[test]
+    fn test_finish_empty_is_ok() {
+        assert!(ErrorCollector::new().finish().is_ok());
+    }
+}
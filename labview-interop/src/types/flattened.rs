@@ -0,0 +1,831 @@
+//! [serde] support for LabVIEW's flattened data format.
+//!
+//! This reads and writes the format of Flatten To String and Unflatten From
+//! String, so any type implementing `Serialize` or `Deserialize` can be
+//! exchanged with LabVIEW as a flattened string. It is an escape hatch for
+//! data the crate doesn't model directly, such as deeply nested clusters.
+//!
+//! The Rust types map to LabVIEW types as follows. Everything is big endian
+//! with no padding, as Flatten To String writes with the default settings.
+//!
+//! | Rust | LabVIEW |
+//! |------|---------|
+//! | Integers and floats | The numeric of the same size |
+//! | `bool` | Boolean |
+//! | `String`, `&str`, `char` | String, in LabVIEW's encoding |
+//! | `Vec<T>`, slices | 1D array of `T` |
+//! | Structs and tuples | Cluster of the fields in order |
+//! | Maps | 1D array of clusters of key and value |
+//! | `Option<T>` | 1D array of `T` with zero or one elements |
+//! | Unit enum variants | U16 enum of the variant index |
+//!
+//! Field names aren't part of the format so the field order must match the
+//! cluster order. An array of arrays is flattened as LabVIEW flattens an array
+//! of clusters containing an array, and is not a 2D array. Enum variants with
+//! data are written as the U16 index followed by the data, which LabVIEW has
+//! no type for but can be read piece by piece.
+//!
+//! String handles, 1D array handles and [`LVBool`](super::LVBool) implement
+//! serde too, so clusters declared with
+//! [`labview_layout!`](crate::labview_layout) can derive the traits and be
+//! flattened directly. Declare the handle fields as
+//! [`LStrOwned`](super::LStrOwned) and [`LVArrayOwned`](super::LVArrayOwned)
+//! so the handles are disposed if a later field fails to unflatten. Array
+//! handles with more dimensions are an error, as serde would write them as
+//! nested arrays rather than LabVIEW's flattened 2D arrays.
+//!
+//! As the format doesn't describe itself, `deserialize_any` and types which
+//! rely on it, such as `#[serde(untagged)]` enums, aren't supported.
+//!
+//! # Example
+//! ```
+//! use labview_interop::types::flattened::{from_flattened, to_flattened};
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Serialize, Deserialize, Debug, PartialEq)]
+//! struct Channel {
+//!     name: String,
+//!     gain: f64,
+//!     samples: Vec<i16>,
+//! }
+//!
+//! let channel = Channel {
+//!     name: "ai0".to_string(),
+//!     gain: 0.5,
+//!     samples: vec![1, -1],
+//! };
+//! let flattened = to_flattened(&channel).unwrap();
+//! assert_eq!(flattened[..7], [0, 0, 0, 3, b'a', b'i', b'0']);
+//! assert_eq!(from_flattened::<Channel>(&flattened).unwrap(), channel);
+//! ```
+//!
+//! In an export the flattened string arrives as a string handle:
+//! ```
+//! use labview_interop::errors::{LVInteropError, MgErr};
+//! use labview_interop::types::flattened::from_flattened;
+//! use labview_interop::types::LStrHandle;
+//!
+//! #[derive(serde::Deserialize)]
+//! struct Settings {
+//!     rate: f64,
+//!     channels: Vec<String>,
+//! }
+//!
+//! #[no_mangle]
+//! pub extern "C" fn configure(settings: LStrHandle) -> MgErr {
+//!     let settings: Settings = match settings.as_bytes().and_then(|bytes| from_flattened(bytes)) {
+//!         Ok(settings) => settings,
+//!         Err(error) => return error.into(),
+//!     };
+//!     // ...
+//!     MgErr::NO_ERROR
+//! }
+//! ```
+
+use std::borrow::Cow;
+use std::fmt::Display;
+
+use serde::de::{self, DeserializeSeed, IntoDeserializer, Visitor};
+use serde::ser::{self, Serialize};
+
+use super::string::LV_ENCODING;
+use crate::errors::{LVInteropError, Result};
+
+/// Flatten a value as Flatten To String does.
+pub fn to_flattened<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>> {
+    let mut serializer = Serializer::new();
+    value.serialize(&mut serializer)?;
+    Ok(serializer.into_inner())
+}
+
+/// Unflatten a value as Unflatten From String does, requiring all of `input`
+/// to be used.
+pub fn from_flattened<'de, T: de::Deserialize<'de>>(input: &'de [u8]) -> Result<T> {
+    let mut deserializer = Deserializer::from_bytes(input);
+    let value = T::deserialize(&mut deserializer)?;
+    deserializer.end()?;
+    Ok(value)
+}
+
+impl ser::Error for LVInteropError {
+    fn custom<T: Display>(msg: T) -> Self {
+        LVInteropError::FlattenFailed(msg.to_string())
+    }
+}
+
+impl de::Error for LVInteropError {
+    fn custom<T: Display>(msg: T) -> Self {
+        LVInteropError::InvalidFlattenedData(msg.to_string())
+    }
+}
+
+/// The error for types the format can't represent.
+fn not_self_describing() -> LVInteropError {
+    de::Error::custom("flattened data doesn't describe itself so the type must be known")
+}
+
+/// A serializer writing LabVIEW flattened data.
+#[derive(Debug, Default)]
+pub struct Serializer {
+    output: Vec<u8>,
+}
+
+impl Serializer {
+    /// A serializer writing to a new buffer.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The flattened data written so far.
+    pub fn into_inner(self) -> Vec<u8> {
+        self.output
+    }
+
+    fn write_length(&mut self, length: usize) -> Result<()> {
+        let length = i32::try_from(length).map_err(|_| {
+            <LVInteropError as ser::Error>::custom(format!(
+                "length {length} is too long for LabVIEW"
+            ))
+        })?;
+        self.output.extend_from_slice(&length.to_be_bytes());
+        Ok(())
+    }
+
+    fn write_variant(&mut self, variant_index: u32) -> Result<()> {
+        let index = u16::try_from(variant_index).map_err(|_| {
+            <LVInteropError as ser::Error>::custom(format!(
+                "variant {variant_index} doesn't fit in a U16 enum"
+            ))
+        })?;
+        self.output.extend_from_slice(&index.to_be_bytes());
+        Ok(())
+    }
+
+    /// Start an array, leaving space for the length which may not be known yet.
+    fn start_array(&mut self) -> Compound<'_> {
+        let length_at = self.output.len();
+        self.output.extend_from_slice(&[0; 4]);
+        Compound {
+            length_at: Some(length_at),
+            count: 0,
+            serializer: self,
+        }
+    }
+
+    fn start_cluster(&mut self) -> Compound<'_> {
+        Compound {
+            length_at: None,
+            count: 0,
+            serializer: self,
+        }
+    }
+}
+
+macro_rules! serialize_numeric {
+    ($($method:ident: $type:ty),+) => {
+        $(
+            fn $method(self, value: $type) -> Result<()> {
+                self.output.extend_from_slice(&value.to_be_bytes());
+                Ok(())
+            }
+        )+
+    };
+}
+
+impl<'a> ser::Serializer for &'a mut Serializer {
+    type Ok = ();
+    type Error = LVInteropError;
+    type SerializeSeq = Compound<'a>;
+    type SerializeTuple = Compound<'a>;
+    type SerializeTupleStruct = Compound<'a>;
+    type SerializeTupleVariant = Compound<'a>;
+    type SerializeMap = Compound<'a>;
+    type SerializeStruct = Compound<'a>;
+    type SerializeStructVariant = Compound<'a>;
+
+    serialize_numeric!(
+        serialize_i8: i8, serialize_i16: i16, serialize_i32: i32, serialize_i64: i64,
+        serialize_u8: u8, serialize_u16: u16, serialize_u32: u32, serialize_u64: u64,
+        serialize_f32: f32, serialize_f64: f64
+    );
+
+    fn serialize_bool(self, value: bool) -> Result<()> {
+        self.output.push(value as u8);
+        Ok(())
+    }
+
+    fn serialize_char(self, value: char) -> Result<()> {
+        self.serialize_str(value.encode_utf8(&mut [0; 4]))
+    }
+
+    fn serialize_str(self, value: &str) -> Result<()> {
+        let (bytes, _, _) = LV_ENCODING.encode(value);
+        self.serialize_bytes(&bytes)
+    }
+
+    fn serialize_bytes(self, value: &[u8]) -> Result<()> {
+        self.write_length(value.len())?;
+        self.output.extend_from_slice(value);
+        Ok(())
+    }
+
+    fn serialize_none(self) -> Result<()> {
+        self.write_length(0)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<()> {
+        self.write_length(1)?;
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<()> {
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<()> {
+        Ok(())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+    ) -> Result<()> {
+        self.write_variant(variant_index)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        self.write_variant(variant_index)?;
+        value.serialize(self)
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Compound<'a>> {
+        Ok(self.start_array())
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Compound<'a>> {
+        Ok(self.start_cluster())
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> Result<Compound<'a>> {
+        Ok(self.start_cluster())
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Compound<'a>> {
+        self.write_variant(variant_index)?;
+        Ok(self.start_cluster())
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Compound<'a>> {
+        Ok(self.start_array())
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Compound<'a>> {
+        Ok(self.start_cluster())
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Compound<'a>> {
+        self.write_variant(variant_index)?;
+        Ok(self.start_cluster())
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+/// Writes the elements of an array or cluster.
+#[doc(hidden)]
+pub struct Compound<'a> {
+    serializer: &'a mut Serializer,
+    /// Where to write the element count for an array.
+    length_at: Option<usize>,
+    count: usize,
+}
+
+impl Compound<'_> {
+    fn element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.count += 1;
+        value.serialize(&mut *self.serializer)
+    }
+
+    fn finish(self) -> Result<()> {
+        if let Some(length_at) = self.length_at {
+            let count = i32::try_from(self.count).map_err(|_| {
+                <LVInteropError as ser::Error>::custom(format!(
+                    "{} elements are too many for LabVIEW",
+                    self.count
+                ))
+            })?;
+            self.serializer.output[length_at..length_at + 4].copy_from_slice(&count.to_be_bytes());
+        }
+        Ok(())
+    }
+}
+
+impl ser::SerializeSeq for Compound<'_> {
+    type Ok = ();
+    type Error = LVInteropError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTuple for Compound<'_> {
+    type Ok = ();
+    type Error = LVInteropError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleStruct for Compound<'_> {
+    type Ok = ();
+    type Error = LVInteropError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleVariant for Compound<'_> {
+    type Ok = ();
+    type Error = LVInteropError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+/// Each entry is a cluster of the key and value, counted as one element.
+impl ser::SerializeMap for Compound<'_> {
+    type Ok = ();
+    type Error = LVInteropError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<()> {
+        self.element(key)
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        value.serialize(&mut *self.serializer)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStruct for Compound<'_> {
+    type Ok = ();
+    type Error = LVInteropError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        _key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStructVariant for Compound<'_> {
+    type Ok = ();
+    type Error = LVInteropError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        _key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+/// A deserializer reading LabVIEW flattened data.
+#[derive(Debug)]
+pub struct Deserializer<'de> {
+    input: &'de [u8],
+}
+
+impl<'de> Deserializer<'de> {
+    /// A deserializer reading from the start of `input`.
+    pub fn from_bytes(input: &'de [u8]) -> Self {
+        Self { input }
+    }
+
+    /// Check all of the input has been read.
+    pub fn end(&self) -> Result<()> {
+        if !self.input.is_empty() {
+            return Err(LVInteropError::InvalidFlattenedData(format!(
+                "{} bytes left over",
+                self.input.len()
+            )));
+        }
+        Ok(())
+    }
+
+    fn take(&mut self, count: usize) -> Result<&'de [u8]> {
+        if self.input.len() < count {
+            return Err(LVInteropError::InvalidFlattenedData(format!(
+                "needed {count} bytes but only {} are left",
+                self.input.len()
+            )));
+        }
+        let (taken, rest) = self.input.split_at(count);
+        self.input = rest;
+        Ok(taken)
+    }
+
+    fn take_array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        let mut bytes = [0; N];
+        bytes.copy_from_slice(self.take(N)?);
+        Ok(bytes)
+    }
+
+    /// Read a length or dimension, which LabVIEW flattens as an `i32`.
+    fn read_length(&mut self) -> Result<usize> {
+        let length = i32::from_be_bytes(self.take_array()?);
+        usize::try_from(length)
+            .map_err(|_| LVInteropError::InvalidFlattenedData(format!("negative length {length}")))
+    }
+
+    fn read_bytes(&mut self) -> Result<&'de [u8]> {
+        let length = self.read_length()?;
+        self.take(length)
+    }
+
+    fn read_str(&mut self) -> Result<Cow<'de, str>> {
+        let bytes = self.read_bytes()?;
+        Ok(LV_ENCODING.decode_without_bom_handling(bytes).0)
+    }
+}
+
+macro_rules! deserialize_numeric {
+    ($($method:ident: $type:ty => $visit:ident),+) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+                visitor.$visit(<$type>::from_be_bytes(self.take_array()?))
+            }
+        )+
+    };
+}
+
+impl<'de> de::Deserializer<'de> for &mut Deserializer<'de> {
+    type Error = LVInteropError;
+
+    deserialize_numeric!(
+        deserialize_i8: i8 => visit_i8, deserialize_i16: i16 => visit_i16,
+        deserialize_i32: i32 => visit_i32, deserialize_i64: i64 => visit_i64,
+        deserialize_u8: u8 => visit_u8, deserialize_u16: u16 => visit_u16,
+        deserialize_u32: u32 => visit_u32, deserialize_u64: u64 => visit_u64,
+        deserialize_f32: f32 => visit_f32, deserialize_f64: f64 => visit_f64
+    );
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
+        Err(not_self_describing())
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
+        Err(not_self_describing())
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
+        Err(not_self_describing())
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_bool(self.take(1)?[0] != 0)
+    }
+
+    fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let string = self.read_str()?;
+        let mut chars = string.chars();
+        match (chars.next(), chars.next()) {
+            (Some(value), None) => visitor.visit_char(value),
+            _ => Err(de::Error::invalid_length(
+                string.chars().count(),
+                &"one character",
+            )),
+        }
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.read_str()? {
+            Cow::Borrowed(string) => visitor.visit_borrowed_str(string),
+            Cow::Owned(string) => visitor.visit_string(string),
+        }
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_borrowed_bytes(self.read_bytes()?)
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.read_length()? {
+            0 => visitor.visit_none(),
+            1 => visitor.visit_some(self),
+            length => Err(de::Error::invalid_length(length, &"zero or one elements")),
+        }
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let remaining = self.read_length()?;
+        visitor.visit_seq(Elements {
+            deserializer: self,
+            remaining,
+        })
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value> {
+        visitor.visit_seq(Elements {
+            deserializer: self,
+            remaining: len,
+        })
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value> {
+        self.deserialize_tuple(len, visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let remaining = self.read_length()?;
+        visitor.visit_map(Elements {
+            deserializer: self,
+            remaining,
+        })
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        self.deserialize_tuple(fields.len(), visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_enum(self)
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+/// Reads a known number of array elements, map entries or cluster fields.
+struct Elements<'a, 'de> {
+    deserializer: &'a mut Deserializer<'de>,
+    remaining: usize,
+}
+
+impl<'de> de::SeqAccess<'de> for Elements<'_, 'de> {
+    type Error = LVInteropError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>> {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+        self.remaining -= 1;
+        seed.deserialize(&mut *self.deserializer).map(Some)
+    }
+
+    /// The length may be corrupt so is capped by the data left.
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.remaining.min(self.deserializer.input.len()))
+    }
+}
+
+impl<'de> de::MapAccess<'de> for Elements<'_, 'de> {
+    type Error = LVInteropError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>> {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+        self.remaining -= 1;
+        seed.deserialize(&mut *self.deserializer).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value> {
+        seed.deserialize(&mut *self.deserializer)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.remaining.min(self.deserializer.input.len()))
+    }
+}
+
+impl<'de> de::EnumAccess<'de> for &mut Deserializer<'de> {
+    type Error = LVInteropError;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self)> {
+        let index = u16::from_be_bytes(self.take_array()?);
+        let variant = seed.deserialize(IntoDeserializer::<LVInteropError>::into_deserializer(
+            u32::from(index),
+        ))?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for &mut Deserializer<'de> {
+    type Error = LVInteropError;
+
+    fn unit_variant(self) -> Result<()> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value> {
+        de::Deserializer::deserialize_tuple(self, len, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        de::Deserializer::deserialize_tuple(self, fields.len(), visitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::{Deserialize, Serialize};
+    use std::collections::BTreeMap;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    enum Mode {
+        Off,
+        Fixed(f32),
+        Ramp { from: f32, to: f32 },
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Setup {
+        name: String,
+        enabled: bool,
+        mode: Mode,
+        limits: (i8, u64),
+        tags: Vec<String>,
+        offset: Option<f64>,
+        lookup: BTreeMap<u16, String>,
+    }
+
+    #[test]
+    fn test_flatten_matches_labview() {
+        assert_eq!(to_flattened(&-2i16).unwrap(), [0xFF, 0xFE]);
+        assert_eq!(to_flattened(&true).unwrap(), [1]);
+        assert_eq!(to_flattened("ab").unwrap(), [0, 0, 0, 2, b'a', b'b']);
+        assert_eq!(
+            to_flattened(&vec![1u16, 2]).unwrap(),
+            [0, 0, 0, 2, 0, 1, 0, 2]
+        );
+        // A cluster is its fields with no padding or length.
+        assert_eq!(to_flattened(&(1u8, 2u32)).unwrap(), [1, 0, 0, 0, 2]);
+        assert_eq!(to_flattened(&Mode::Off).unwrap(), [0, 0]);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let setup = Setup {
+            name: "Bench 1".to_string(),
+            enabled: true,
+            mode: Mode::Ramp { from: 0.5, to: 2.0 },
+            limits: (-4, 1 << 40),
+            tags: vec!["a".to_string(), String::new()],
+            offset: Some(0.25),
+            lookup: BTreeMap::from([(1, "one".to_string()), (2, "two".to_string())]),
+        };
+        let flattened = to_flattened(&setup).unwrap();
+        assert_eq!(from_flattened::<Setup>(&flattened).unwrap(), setup);
+
+        assert_eq!(
+            from_flattened::<Mode>(&to_flattened(&Mode::Fixed(1.5)).unwrap()).unwrap(),
+            Mode::Fixed(1.5)
+        );
+        assert_eq!(from_flattened::<Option<i32>>(&[0, 0, 0, 0]).unwrap(), None);
+    }
+
+    #[test]
+    fn test_invalid_data() {
+        // Truncated, left over and negative lengths.
+        assert!(matches!(
+            from_flattened::<Vec<u16>>(&[0, 0, 0, 2, 0, 1]),
+            Err(LVInteropError::InvalidFlattenedData(_))
+        ));
+        assert!(from_flattened::<u8>(&[1, 2]).is_err());
+        assert!(from_flattened::<String>(&[0xFF, 0xFF, 0xFF, 0xFF]).is_err());
+        // An unknown enum variant.
+        assert!(from_flattened::<Mode>(&[0, 9]).is_err());
+    }
+
+    #[cfg(feature = "link")]
+    #[test]
+    fn test_multidimensional_arrays_are_rejected() {
+        use crate::types::LVArrayOwned;
+
+        let matrix = LVArrayOwned::<2, u8>::from_shape_slice([1, 2].into(), &[1, 2]).unwrap();
+        assert!(to_flattened(&matrix).is_err());
+        assert!(from_flattened::<LVArrayOwned<2, u8>>(&[0, 0, 0, 1, 0, 0, 0, 1, 5]).is_err());
+
+        let row = LVArrayOwned::<1, u8>::from_slice(&[1, 2]).unwrap();
+        assert_eq!(to_flattened(&row).unwrap(), [0, 0, 0, 2, 1, 2]);
+    }
+}
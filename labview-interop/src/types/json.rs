@@ -0,0 +1,146 @@
+//! JSON conversion for LabVIEW data.
+//!
+//! With the `json` feature, strings, arrays and booleans from LabVIEW can be
+//! converted to and from [`serde_json::Value`], as can clusters declared with
+//! [`labview_layout!`](crate::labview_layout) which derive `Serialize` or
+//! `Deserialize`. This is how LabVIEW's Flatten To JSON and Unflatten From JSON
+//! VIs represent the same data: clusters are objects keyed by the element
+//! labels and multidimensional arrays are nested arrays. Rename fields with
+//! `#[serde(rename = "...")]` where the label isn't a valid Rust name.
+//!
+//! [`read_json`] and [`write_json`] exchange the JSON text itself through a
+//! string handle, for passing data to and from those VIs.
+//!
+//! Converting JSON into handles allocates new handles which the caller owns.
+//! Declare the handle fields of clusters as [`LStrOwned`](super::LStrOwned)
+//! and [`LVArrayOwned`](super::LVArrayOwned), which have the same layout as
+//! the handles, so they are disposed when the cluster is dropped, including
+//! the fields already read when a later field fails to convert. Fields of the
+//! raw handle types have no drop, so those handles would be leaked.
+//!
+//! # Example
+//! ```
+//! use labview_interop::labview_layout;
+//! use labview_interop::types::json::{from_value, to_value};
+//! use labview_interop::types::{LStrOwned, LVArrayOwned, LVBool};
+//! use serde::{Deserialize, Serialize};
+//! use serde_json::json;
+//!
+//! labview_layout!(
+//!     #[derive(Serialize, Deserialize)]
+//!     pub struct Channel {
+//!         name: LStrOwned,
+//!         enabled: LVBool,
+//!         #[serde(rename = "Scale Factors")]
+//!         scale: LVArrayOwned<1, f64>,
+//!     }
+//! );
+//!
+//! let value = json!({"name": "ai0", "enabled": true, "Scale Factors": [1.0, 0.5]});
+//! let channel: Channel = from_value(value.clone()).unwrap();
+//! assert_eq!(channel.name.as_bytes().unwrap(), b"ai0");
+//! assert_eq!(to_value(&channel).unwrap(), value);
+//! ```
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+
+use super::LStrHandle;
+use crate::errors::{LVInteropError, Result};
+
+fn invalid_json(error: serde_json::Error) -> LVInteropError {
+    LVInteropError::InvalidJson(error.to_string())
+}
+
+/// Convert LabVIEW data to a JSON value.
+pub fn to_value<T: Serialize + ?Sized>(value: &T) -> Result<Value> {
+    serde_json::to_value(value).map_err(invalid_json)
+}
+
+/// Convert a JSON value to LabVIEW data, allocating any handles.
+pub fn from_value<T: DeserializeOwned>(value: Value) -> Result<T> {
+    serde_json::from_value(value).map_err(invalid_json)
+}
+
+/// Parse JSON text from a LabVIEW string, such as from Flatten To JSON.
+///
+/// A null handle is an empty string so fails to parse.
+pub fn read_json<T: DeserializeOwned>(json: &LStrHandle) -> Result<T> {
+    let text = match unsafe { json.as_ref() } {
+        Ok(string) => string.to_rust_string(),
+        Err(_) => "".into(),
+    };
+    serde_json::from_str(&text).map_err(invalid_json)
+}
+
+/// Write a value as JSON text into a LabVIEW string, such as for Unflatten
+/// From JSON.
+pub fn write_json<T: Serialize + ?Sized>(value: &T, output: &mut LStrHandle) -> Result<()> {
+    let text = serde_json::to_string(value).map_err(invalid_json)?;
+    output.set_str(&text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::DeepDispose;
+    use crate::types::{LStrOwned, LVArrayHandle, LVArrayOwned};
+    use serde_json::json;
+
+    #[test]
+    fn test_array_dimensions() {
+        let matrix =
+            LVArrayOwned::<2, i32>::from_shape_slice([2, 3].into(), &[1, 2, 3, 4, 5, 6]).unwrap();
+        assert_eq!(to_value(&matrix).unwrap(), json!([[1, 2, 3], [4, 5, 6]]));
+
+        let copy: LVArrayOwned<2, i32> = from_value(json!([[1, 2, 3], [4, 5, 6]])).unwrap();
+        assert_eq!(copy.dimensions_or_empty(), [2, 3].into());
+        assert_eq!(copy.to_vec(), [1, 2, 3, 4, 5, 6]);
+
+        let empty: LVArrayOwned<2, i32> = from_value(json!([])).unwrap();
+        assert_eq!(empty.dimensions_or_empty(), [0, 0].into());
+        assert!(matches!(
+            from_value::<LVArrayOwned<2, i32>>(json!([[1, 2], [3]])),
+            Err(LVInteropError::InvalidJson(_))
+        ));
+    }
+
+    #[test]
+    fn test_strings_and_text() {
+        let null: LStrHandle = crate::memory::UHandle(std::ptr::null_mut());
+        assert_eq!(to_value(&null).unwrap(), json!(""));
+
+        let names: LVArrayHandle<1, LStrHandle> = from_value(json!(["a", "bc"])).unwrap();
+        assert_eq!(names[[1]].as_bytes().unwrap(), b"bc");
+
+        let mut text = LStrOwned::from_data(b"").unwrap();
+        write_json(&names, &mut text).unwrap();
+        assert_eq!(text.as_bytes().unwrap(), br#"["a","bc"]"#);
+        assert_eq!(read_json::<Value>(&text).unwrap(), json!(["a", "bc"]));
+        assert!(read_json::<Value>(&null).is_err());
+        unsafe { names.deep_dispose() }.unwrap();
+    }
+
+    #[cfg(feature = "leak-tracking")]
+    #[test]
+    fn test_failed_conversion_disposes_owned_fields() {
+        use crate::leak_tracking::live_handles;
+
+        #[derive(serde::Deserialize)]
+        #[allow(dead_code)]
+        struct Channel {
+            name: LStrOwned,
+            scale: LVArrayOwned<1, f64>,
+        }
+
+        // The name is allocated before the scale fails to convert.
+        let result = from_value::<Channel>(json!({"name": "ai0", "scale": "none"}));
+        assert!(matches!(result, Err(LVInteropError::InvalidJson(_))));
+        let leaked = live_handles()
+            .into_iter()
+            .filter(|handle| handle.backtrace.contains("disposes_owned_fields"))
+            .count();
+        assert_eq!(leaked, 0);
+    }
+}
@@ -0,0 +1,106 @@
+//! A thread local last error for exports which only return a status code.
+//!
+//! Some existing VIs call functions that return a bare status without an error
+//! cluster. Those functions can record the full error with [`set_last_error`]
+//! (or return through [`status_with_last_error`]) and the VI can then call the
+//! `interop_get_last_error` export to retrieve the details.
+//!
+//! As the error is stored per thread, both Call Library nodes must run in the
+//! same thread. Setting them to "Run in UI thread" guarantees this.
+
+use std::cell::RefCell;
+
+use super::error_collector::ErrorEntry;
+use super::{ErrorClusterPtr, ToLvError};
+use crate::errors::MgErr;
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<ErrorEntry>> = const { RefCell::new(None) };
+}
+
+/// Record `error` as the last error on this thread.
+pub fn set_last_error<E: ToLvError + ?Sized>(error: &E) {
+    let entry = ErrorEntry::from_error(error);
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(entry));
+}
+
+/// Clear the last error on this thread.
+pub fn clear_last_error() {
+    LAST_ERROR.with(|last| *last.borrow_mut() = None);
+}
+
+/// A copy of the last error on this thread.
+pub fn last_error() -> Option<ErrorEntry> {
+    LAST_ERROR.with(|last| last.borrow().clone())
+}
+
+/// Take the last error on this thread, clearing it.
+pub fn take_last_error() -> Option<ErrorEntry> {
+    LAST_ERROR.with(|last| last.borrow_mut().take())
+}
+
+/// Convert a result to a bare status code, recording any error as the last error.
+///
+/// # Example
+/// ```
+/// use labview_interop::errors::{LVInteropError, MgErr};
+/// use labview_interop::types::last_error::status_with_last_error;
+///
+/// #[no_mangle]
+/// pub extern "C" fn legacy_start() -> MgErr {
+///     status_with_last_error(Err::<(), _>(LVInteropError::NoLabviewApi))
+/// }
+/// ```
+pub fn status_with_last_error<E: ToLvError>(result: Result<(), E>) -> MgErr {
+    match result {
+        Ok(()) => MgErr::NO_ERROR,
+        Err(error) => {
+            set_last_error(&error);
+            error.code()
+        }
+    }
+}
+
+/// Write the last error on the calling thread into the error cluster and clear it.
+///
+/// The cluster is left unchanged if there is no last error.
+#[no_mangle]
+pub extern "C" fn interop_get_last_error(error_cluster: ErrorClusterPtr) -> MgErr {
+    match take_last_error() {
+        Some(entry) => match entry.write_error(error_cluster) {
+            Ok(()) => entry.code(),
+            Err(error) => error.into(),
+        },
+        None => MgErr::NO_ERROR,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::LVInteropError;
+
+    #[test]
+    fn test_status_records_last_error() {
+        clear_last_error();
+        assert_eq!(
+            status_with_last_error::<LVInteropError>(Ok(())),
+            MgErr::NO_ERROR
+        );
+        assert!(last_error().is_none());
+
+        let status = status_with_last_error(Err::<(), _>(LVInteropError::InvalidHandle));
+        assert_eq!(status, MgErr::from(42));
+        assert!(last_error().is_some());
+        assert!(take_last_error().is_some());
+        assert!(last_error().is_none());
+    }
+
+    #[test]
+    fn test_last_error_is_per_thread() {
+        set_last_error(&LVInteropError::InvalidHandle);
+        let other_thread = std::thread::spawn(last_error).join().unwrap();
+        assert!(other_thread.is_none());
+        assert!(last_error().is_some());
+    }
+}
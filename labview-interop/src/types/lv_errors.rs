@@ -1,8 +1,13 @@
 //! Functions for working with the LabVIEW error clusters.
 //!
-//! This is only available in 64 bit currently due to restrictions
-//! on unaligned pointer access.
+//! On 32 bit the cluster is packed so the fields can't be borrowed. They are
+//! always copied out and written back instead so the same code works for both.
+use std::any::Any;
 use std::borrow::Cow;
+use std::cell::RefCell;
+use std::fmt::Write;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 use crate::errors::LVInteropError;
 use crate::errors::MgErr;
@@ -13,6 +18,7 @@ use crate::types::LVBool;
 
 use super::boolean::LV_FALSE;
 use super::boolean::LV_TRUE;
+use super::error_collector::ErrorEntry;
 
 labview_layout!(
     /// The cluster format used by LabVIEW for transmitting errors.
@@ -31,12 +37,129 @@ labview_layout!(
 /// so that null pointeres can be detected.
 pub type ErrorClusterPtr = UPtr<ErrorCluster>;
 
+/// The size to pre-grow error source strings to. Zero disables preallocation.
+static SOURCE_PREALLOCATION: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    /// Reused buffer for formatting the source so preallocated writes don't allocate.
+    static SOURCE_BUFFER: RefCell<String> = const { RefCell::new(String::new()) };
+}
+
+/// Enable preallocated error source strings.
+///
+/// When set, the source handle of an error cluster is grown to at least `bytes`
+/// the first time an error or warning is written to it. Later writes that fit
+/// are then made in place without resizing the handle, which removes the memory
+/// manager calls from tight loops that report errors frequently.
+///
+/// Pass zero to return to the default behaviour of sizing the handle to each message.
+pub fn set_source_preallocation(bytes: usize) {
+    SOURCE_PREALLOCATION.store(bytes, Ordering::Relaxed);
+}
+
+/// The currently configured source preallocation in bytes.
+pub fn source_preallocation() -> usize {
+    SOURCE_PREALLOCATION.load(Ordering::Relaxed)
+}
+
+/// Lets tests in other modules pass a cluster to an export and dispose the source it wrote.
+#[cfg(all(test, feature = "link"))]
+impl ErrorCluster {
+    pub(crate) fn with_empty_source() -> Self {
+        ErrorCluster {
+            status: LV_FALSE,
+            code: MgErr::NO_ERROR,
+            source: crate::types::LStrOwned::from_data(b"").unwrap().into_raw(),
+        }
+    }
+
+    pub(crate) fn take_source(&mut self) -> crate::types::LStrOwned {
+        let source = std::ptr::addr_of_mut!(self.source);
+        unsafe {
+            let handle = std::ptr::read_unaligned(source);
+            std::ptr::write_unaligned(source, crate::memory::UHandle(std::ptr::null_mut()));
+            crate::types::LStrOwned::from_raw(handle)
+        }
+    }
+}
+
 impl ErrorCluster {
+    /// True if the cluster currently holds an error (as opposed to a warning or no error).
+    pub fn is_error(&self) -> bool {
+        self.status.into()
+    }
+
+    /// The code currently held by the cluster.
+    pub fn code(&self) -> MgErr {
+        self.code
+    }
+
+    /// True if the cluster holds a warning: a code without the error status.
+    pub fn is_warning(&self) -> bool {
+        !self.is_error() && self.code != MgErr::NO_ERROR
+    }
+
+    /// True if a new error or warning should replace the contents of this
+    /// cluster under the rules of LabVIEW's Merge Errors.
+    fn merge_replaces(&self, incoming_is_error: bool) -> bool {
+        if self.is_error() {
+            false
+        } else {
+            incoming_is_error || !self.is_warning()
+        }
+    }
+
+    /// Merge `other` into this cluster following LabVIEW's Merge Errors.
+    ///
+    /// The first error is kept, so an existing error is never replaced. If
+    /// neither holds an error the first warning is kept.
+    ///
+    /// | This cluster | Other   | Result  |
+    /// |--------------|---------|---------|
+    /// | Error        | Any     | This    |
+    /// | Warning      | Error   | Other   |
+    /// | Warning      | Warning | This    |
+    /// | No error     | Any     | Other   |
+    pub fn merge(&mut self, other: &ErrorCluster) -> Result<(), LVInteropError> {
+        if other.code == MgErr::NO_ERROR && !other.is_error() {
+            return Ok(());
+        }
+        if self.merge_replaces(other.is_error()) {
+            self.copy_from(other)?;
+        }
+        Ok(())
+    }
+
+    /// Run `op` on a copy of the source handle and write it back.
+    ///
+    /// A reference to the field isn't allowed in the packed 32 bit layout.
+    fn with_source<R>(&mut self, op: impl FnOnce(&mut LStrHandle) -> R) -> R {
+        let source = std::ptr::addr_of_mut!(self.source);
+        // Safety: the handle is a plain pointer so copying it is fine.
+        let mut handle = unsafe { std::ptr::read_unaligned(source) };
+        let result = op(&mut handle);
+        unsafe { std::ptr::write_unaligned(source, handle) };
+        result
+    }
+
     /// Set a description and source in the format that LabVIEW will interpret for display.
     fn set_source(&mut self, source: &str, description: &str) -> Result<(), LVInteropError> {
-        // Probably a clever way to avoid this allocation but for now we will take it.
-        let full_source = format_error_source(source, description);
-        self.source.set_str(&full_source)
+        let preallocation = source_preallocation();
+        self.with_source(|handle| {
+            if preallocation == 0 {
+                // Probably a clever way to avoid this allocation but for now we will take it.
+                let full_source = format_error_source(source, description);
+                return handle.set_str(&full_source);
+            }
+
+            handle.reserve(preallocation)?;
+            SOURCE_BUFFER.with(|buffer| {
+                let mut buffer = buffer.borrow_mut();
+                buffer.clear();
+                write_error_source(&mut buffer, source, description);
+                handle.set_str_in_place(&buffer)
+            })
+        })
     }
 
     /// Set the error cluster to a warning state.
@@ -51,6 +174,51 @@ impl ErrorCluster {
         self.set_source(source, description)
     }
 
+    /// Clear the cluster to the no error state.
+    pub fn clear(&mut self) -> Result<(), LVInteropError> {
+        self.code = MgErr::NO_ERROR;
+        self.status = LV_FALSE;
+        self.with_source(|handle| {
+            if handle.valid() {
+                handle.set_in_place(&[])?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Copy the status, code and source from another cluster.
+    pub fn copy_from(&mut self, other: &ErrorCluster) -> Result<(), LVInteropError> {
+        self.code = other.code;
+        self.status = other.status;
+        let other_source = unsafe { std::ptr::read_unaligned(std::ptr::addr_of!(other.source)) };
+        self.with_source(|handle| match unsafe { other_source.as_ref() } {
+            Ok(source) => handle.set(source.as_slice()),
+            Err(_) if handle.valid() => handle.set_in_place(&[]),
+            Err(_) => Ok(()),
+        })
+    }
+
+    /// Append a name to the call chain in the source, keeping the description.
+    ///
+    /// LabVIEW lists the call chain in the source with the innermost caller
+    /// first, separated by `->`. Appending the name of each Rust function as
+    /// the error is passed up builds the same chain so errors raised deep in
+    /// Rust read like native LabVIEW errors. The status and code are unchanged.
+    ///
+    /// Nothing is appended if the cluster is empty.
+    pub fn append_source(&mut self, name: &str) -> Result<(), LVInteropError> {
+        if !self.is_error() && !self.is_warning() {
+            return Ok(());
+        }
+        self.with_source(|handle| {
+            let current = match unsafe { handle.as_ref() } {
+                Ok(source) => source.to_rust_string().into_owned(),
+                Err(_) => String::new(),
+            };
+            handle.set_str(&append_call_chain(&current, name))
+        })
+    }
+
     /// Set the error cluster to an error state.
     pub fn set_error(
         &mut self,
@@ -64,12 +232,36 @@ impl ErrorCluster {
     }
 }
 
-fn format_error_source(source: &str, description: &str) -> String {
-    match (source, description) {
-        ("", description) => format!("<ERR>\n{description}"),
-        (source, "") => source.to_string(),
-        (source, description) => format!("{source}\n<ERR>\n{description}"),
+/// Add `name` to the end of the call chain in a full source string.
+fn append_call_chain(full_source: &str, name: &str) -> String {
+    // Without a source the description follows the marker directly.
+    if full_source.starts_with("<ERR>") {
+        return format!("{name}\n{full_source}");
     }
+    let (chain, rest) = match full_source.find("\n<ERR>") {
+        Some(index) => full_source.split_at(index),
+        None => (full_source, ""),
+    };
+    if chain.is_empty() {
+        format!("{name}{rest}")
+    } else {
+        format!("{chain}->{name}{rest}")
+    }
+}
+
+fn format_error_source(source: &str, description: &str) -> String {
+    let mut output = String::new();
+    write_error_source(&mut output, source, description);
+    output
+}
+
+fn write_error_source(output: &mut String, source: &str, description: &str) {
+    // Writing to a string cannot fail.
+    let _ = match (source, description) {
+        ("", description) => write!(output, "<ERR>\n{description}"),
+        (source, "") => write!(output, "{source}"),
+        (source, description) => write!(output, "{source}\n<ERR>\n{description}"),
+    };
 }
 
 /// A trait that can be implemented on types to allow them to be written into a
@@ -93,6 +285,17 @@ pub trait ToLvError {
     /// The description of the error;
     fn description(&self) -> Cow<'_, str>;
 
+    /// The description to show the user.
+    ///
+    /// This is the message registered for the code in the current language
+    /// (see [`error_codes`](crate::error_codes)) or the description if there isn't one.
+    fn localized_description(&self) -> Cow<'_, str> {
+        match crate::error_codes::localized_message(self.code()) {
+            Some(message) => message.into(),
+            None => self.description(),
+        }
+    }
+
     /// Write into the LabVIEW Error Pointer.
     ///
     /// The pointer is the type that is recieved through the Call Library Node so
@@ -102,7 +305,7 @@ pub trait ToLvError {
         let code = self.code();
         let source = self.source();
         let source = source.as_ref();
-        let description = self.description();
+        let description = self.localized_description();
         let description = description.as_ref();
         if self.is_error() {
             cluster.set_error(code, source, description)
@@ -110,18 +313,441 @@ pub trait ToLvError {
             cluster.set_warning(code, source, description)
         }
     }
+
+    /// Write into the LabVIEW Error Pointer following LabVIEW's Merge Errors.
+    ///
+    /// Unlike [`ToLvError::write_error`] this keeps an error already in the
+    /// cluster, and a warning already in the cluster unless this is an error.
+    /// See [`ErrorCluster::merge`].
+    fn merge_error(&self, error_cluster: ErrorClusterPtr) -> Result<(), LVInteropError> {
+        let cluster = unsafe { error_cluster.as_ref()? };
+        if cluster.merge_replaces(self.is_error()) {
+            self.write_error(error_cluster)
+        } else {
+            Ok(())
+        }
+    }
 }
 
+#[cfg(not(feature = "std-error"))]
 impl ToLvError for LVInteropError {
     fn description(&self) -> Cow<'_, str> {
         self.to_string().into()
     }
 }
 
+/// Any standard error can be written to an error cluster.
+///
+/// The code is the code of the first error in the source chain registered with
+/// [`register_error_type`](crate::error_codes::register_error_type), or 42 if
+/// there is none. The description includes the full source chain.
+#[cfg(feature = "std-error")]
+impl<E: std::error::Error + 'static> ToLvError for E {
+    fn code(&self) -> MgErr {
+        crate::error_codes::registered_code(self).unwrap_or(42.into())
+    }
+
+    fn description(&self) -> Cow<'_, str> {
+        let mut description = self.to_string();
+        write_causes(&mut description, self.source());
+        description.into()
+    }
+}
+
+/// Append each error in a source chain to a description.
+#[cfg(any(feature = "std-error", feature = "anyhow"))]
+fn write_causes(description: &mut String, mut source: Option<&(dyn std::error::Error + 'static)>) {
+    while let Some(error) = source {
+        // Writing to a string cannot fail.
+        let _ = write!(description, "\nCaused by: {error}");
+        source = error.source();
+    }
+}
+
+// Coherence doesn't allow both, as `anyhow` could implement `std::error::Error`
+// for its error in future. Failing here, rather than leaving out one of the
+// implementations, means enabling one feature never silently removes the other.
+#[cfg(all(feature = "anyhow", feature = "std-error"))]
+compile_error!(
+    "the `anyhow` and `std-error` features of labview-interop can't be enabled together"
+);
+
+/// An `anyhow` error can be written to an error cluster.
+///
+/// The description includes the full chain of causes and the source is the
+/// root cause. The code is 42.
+///
+/// This can't be enabled with the `std-error` feature as the two implementations
+/// would overlap.
+#[cfg(feature = "anyhow")]
+impl ToLvError for anyhow::Error {
+    fn source(&self) -> Cow<'_, str> {
+        self.root_cause().to_string().into()
+    }
+
+    fn description(&self) -> Cow<'_, str> {
+        let mut description = self.to_string();
+        let error: &(dyn std::error::Error + 'static) = self.as_ref();
+        write_causes(&mut description, error.source());
+        description.into()
+    }
+}
+
+/// Write the error into the cluster and get the status code to return to LabVIEW.
+pub(crate) fn report_error<E: ToLvError>(error_cluster: ErrorClusterPtr, error: &E) -> MgErr {
+    match error.write_error(error_cluster) {
+        Ok(()) if error.is_error() => error.code(),
+        Ok(()) => MgErr::NO_ERROR,
+        Err(write_error) => write_error.into(),
+    }
+}
+
+/// A warning to report to LabVIEW alongside a successful result.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LVWarning {
+    code: MgErr,
+    source: String,
+    description: String,
+}
+
+impl LVWarning {
+    /// Create a warning with the code and description.
+    pub fn new(code: impl Into<MgErr>, description: impl Into<String>) -> Self {
+        Self {
+            code: code.into(),
+            source: String::new(),
+            description: description.into(),
+        }
+    }
+
+    /// Set the source of the warning.
+    pub fn with_source(mut self, source: impl Into<String>) -> Self {
+        self.source = source.into();
+        self
+    }
+}
+
+impl ToLvError for LVWarning {
+    fn code(&self) -> MgErr {
+        self.code
+    }
+
+    fn is_error(&self) -> bool {
+        false
+    }
+
+    fn source(&self) -> Cow<'_, str> {
+        self.source.as_str().into()
+    }
+
+    fn description(&self) -> Cow<'_, str> {
+        self.description.as_str().into()
+    }
+}
+
+/// The result of an operation which can also succeed with a warning.
+///
+/// LabVIEW error clusters have three states: no error, warning and error.
+/// This lets Rust code express the warning state without treating it as an
+/// error. Standard results convert into this with `into()`.
+///
+/// # Example
+/// ```
+/// use labview_interop::errors::LVInteropError;
+/// use labview_interop::types::{LVWarning, LvResult};
+///
+/// fn read_config(used_defaults: bool) -> LvResult<u32> {
+///     if used_defaults {
+///         LvResult::Warning(10, LVWarning::new(5001, "Config not found, using defaults."))
+///     } else {
+///         LvResult::Ok(20)
+///     }
+/// }
+/// ```
+#[must_use]
+#[derive(Debug)]
+pub enum LvResult<T, E = LVInteropError> {
+    /// Success with no warning.
+    Ok(T),
+    /// Success with a warning to report.
+    Warning(T, LVWarning),
+    /// Failure with an error to report.
+    Err(E),
+}
+
+impl<T, E> LvResult<T, E> {
+    /// True if successful, with or without a warning.
+    pub fn is_ok(&self) -> bool {
+        !self.is_err()
+    }
+
+    /// True if successful with a warning.
+    pub fn is_warning(&self) -> bool {
+        matches!(self, Self::Warning(..))
+    }
+
+    /// True if this is an error.
+    pub fn is_err(&self) -> bool {
+        matches!(self, Self::Err(_))
+    }
+
+    /// The warning, if there is one.
+    pub fn warning(&self) -> Option<&LVWarning> {
+        match self {
+            Self::Warning(_, warning) => Some(warning),
+            _ => None,
+        }
+    }
+
+    /// Map the success value, keeping any warning.
+    pub fn map<U>(self, op: impl FnOnce(T) -> U) -> LvResult<U, E> {
+        match self {
+            Self::Ok(value) => LvResult::Ok(op(value)),
+            Self::Warning(value, warning) => LvResult::Warning(op(value), warning),
+            Self::Err(error) => LvResult::Err(error),
+        }
+    }
+
+    /// Convert to a standard result, discarding any warning.
+    pub fn into_result(self) -> Result<T, E> {
+        match self {
+            Self::Ok(value) | Self::Warning(value, _) => Ok(value),
+            Self::Err(error) => Err(error),
+        }
+    }
+}
+
+impl<T, E: ToLvError> LvResult<T, E> {
+    /// Write the warning or error into the error cluster and get the status code for LabVIEW.
+    ///
+    /// The cluster is left untouched on success without a warning and warnings
+    /// return [`MgErr::NO_ERROR`] as they are not a failure.
+    pub fn write_error(self, error_cluster: ErrorClusterPtr) -> MgErr {
+        match self {
+            Self::Ok(_) => MgErr::NO_ERROR,
+            Self::Warning(_, warning) => report_error(error_cluster, &warning),
+            Self::Err(error) => report_error(error_cluster, &error),
+        }
+    }
+}
+
+/// Report a result to an error cluster at the end of an export.
+///
+/// This is an alternative to [`with_lverrorhandling!`](crate::with_lverrorhandling)
+/// for exports which build the result themselves. Errors and warnings are
+/// written to the cluster and the status code to return to LabVIEW is
+/// returned. Unlike the macro this doesn't skip the work when an error is
+/// passed in or catch panics.
+///
+/// # Example
+/// ```
+/// use labview_interop::errors::{LVInteropError, MgErr};
+/// use labview_interop::types::{ErrorClusterPtr, LvResultExt};
+///
+/// fn parse_gain(text: &str) -> Result<f64, LVInteropError> {
+///     text.parse()
+///         .map_err(|_| LVInteropError::ValidationFailed(format!("{text} isn't a number")))
+/// }
+///
+/// #[no_mangle]
+/// pub extern "C" fn set_gain(gain: f64, error: ErrorClusterPtr) -> MgErr {
+///     let result = parse_gain("2.5").map(|parsed| parsed * gain);
+///     result.lv_report(&error)
+/// }
+/// ```
+pub trait LvResultExt {
+    /// Write any error or warning into the cluster and get the status code for LabVIEW.
+    ///
+    /// The cluster is left untouched on success and warnings return
+    /// [`MgErr::NO_ERROR`].
+    fn lv_report(self, error_cluster: &ErrorClusterPtr) -> MgErr;
+}
+
+impl<T, E: ToLvError> LvResultExt for LvResult<T, E> {
+    fn lv_report(self, error_cluster: &ErrorClusterPtr) -> MgErr {
+        // Safety: this is the same pointer the caller already holds.
+        self.write_error(unsafe { UPtr::from_raw(error_cluster.as_ptr()) })
+    }
+}
+
+impl<T, E: ToLvError> LvResultExt for Result<T, E> {
+    fn lv_report(self, error_cluster: &ErrorClusterPtr) -> MgErr {
+        LvResult::from(self).lv_report(error_cluster)
+    }
+}
+
+impl<T, E> From<Result<T, E>> for LvResult<T, E> {
+    fn from(value: Result<T, E>) -> Self {
+        match value {
+            Ok(value) => Self::Ok(value),
+            Err(error) => Self::Err(error),
+        }
+    }
+}
+
+/// The error reported when the body of an export panics.
+fn panic_entry(payload: Box<dyn Any + Send>) -> ErrorEntry {
+    let message = payload
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic payload".to_string());
+    let error = LVInteropError::Panic(message);
+    ErrorEntry {
+        description: error.to_string(),
+        source: String::new(),
+        is_error: true,
+        code: error.into(),
+    }
+}
+
+/// Run `body`, catching any panic so it doesn't unwind into LabVIEW.
+pub(crate) fn catch_panic<R>(body: impl FnOnce() -> R) -> Result<R, ErrorEntry> {
+    catch_unwind(AssertUnwindSafe(body)).map_err(panic_entry)
+}
+
+/// Implementation of [`with_lverrorhandling!`](crate::with_lverrorhandling).
+#[doc(hidden)]
+pub fn run_with_error_cluster<E, R>(
+    error_cluster: ErrorClusterPtr,
+    body: impl FnOnce() -> R,
+) -> MgErr
+where
+    E: ToLvError,
+    R: Into<LvResult<(), E>>,
+{
+    crate::deferred::run_deferred();
+    match unsafe { error_cluster.as_ref() } {
+        Ok(cluster) if cluster.is_error() => return cluster.code(),
+        Ok(_) => {}
+        Err(error) => return error.into(),
+    }
+    match catch_panic(body) {
+        // A warning wired in is kept over a new one, as Merge Errors would.
+        Ok(result) => match result.into() {
+            LvResult::Warning((), warning) => match warning.merge_error(error_cluster) {
+                Ok(()) => MgErr::NO_ERROR,
+                Err(error) => error.into(),
+            },
+            result => result.write_error(error_cluster),
+        },
+        Err(panic) => report_error(error_cluster, &panic),
+    }
+}
+
+/// Implementation of [`with_lverrorhandling!`](crate::with_lverrorhandling) with
+/// separate error in and error out clusters.
+#[doc(hidden)]
+pub fn run_with_error_in_out<E, R>(
+    error_in: ErrorClusterPtr,
+    error_out: ErrorClusterPtr,
+    body: impl FnOnce() -> R,
+) -> MgErr
+where
+    E: ToLvError,
+    R: Into<LvResult<(), E>>,
+{
+    crate::deferred::run_deferred();
+    // Error in is optional so a null pointer is treated as no error.
+    let incoming = unsafe { error_in.as_ref() }.ok();
+    let output = match unsafe { error_out.as_ref_mut() } {
+        Ok(output) => output,
+        Err(error) => return error.into(),
+    };
+
+    if let Some(incoming) = incoming.filter(|incoming| incoming.is_error()) {
+        return match output.copy_from(incoming) {
+            Ok(()) => incoming.code(),
+            Err(error) => error.into(),
+        };
+    }
+
+    let result = match catch_panic(body) {
+        Ok(result) => result.into(),
+        Err(panic) => return report_error(error_out, &panic),
+    };
+    // Warnings wired in pass through unless the body raises an error.
+    let incoming_warning = incoming.filter(|incoming| incoming.is_warning());
+    match (result, incoming_warning) {
+        (LvResult::Err(error), _) => report_error(error_out, &error),
+        (_, Some(warning)) => output
+            .copy_from(warning)
+            .map_or_else(Into::into, |()| MgErr::NO_ERROR),
+        (LvResult::Warning((), warning), None) => report_error(error_out, &warning),
+        (LvResult::Ok(()), None) => output.clear().map_or_else(Into::into, |()| MgErr::NO_ERROR),
+    }
+}
+
+/// Run the body of an export with LabVIEW style error handling.
+///
+/// * Any [deferred](crate::deferred) work is run first.
+/// * If an error is wired into the cluster the body is skipped and the error passed through.
+/// * The body returns a `Result<(), E>` or [`LvResult<(), E>`] where `E` implements [`ToLvError`].
+/// * Errors and warnings are written into the cluster and the status code is returned.
+/// * A warning wired in is kept unless the body returns an error, following
+///   LabVIEW's Merge Errors, so a new warning doesn't replace it.
+/// * A panic in the body is caught rather than unwinding into LabVIEW and is
+///   reported as [`LVInteropError::Panic`] with code -15.
+///
+/// For VIs which pass separate error in and error out clusters use
+/// `with_lverrorhandling!(error_in = ..., error_out = ..., body)`. The error in
+/// cluster is only read and everything, including an incoming error or warning
+/// passed through, is written to the error out cluster.
+///
+/// # Example
+/// ```
+/// use labview_interop::with_lverrorhandling;
+/// use labview_interop::errors::{LVInteropError, MgErr};
+/// use labview_interop::types::{ErrorClusterPtr, LVWarning, LvResult};
+///
+/// #[no_mangle]
+/// pub extern "C" fn checked_divide(
+///     numerator: f64,
+///     denominator: f64,
+///     output: *mut f64,
+///     error_cluster: ErrorClusterPtr,
+/// ) -> MgErr {
+///     with_lverrorhandling!(error_cluster, {
+///         let result = numerator / denominator;
+///         unsafe { *output = result };
+///         if result.is_finite() {
+///             LvResult::<(), LVInteropError>::Ok(())
+///         } else {
+///             LvResult::Warning((), LVWarning::new(5001, "Result is not finite."))
+///         }
+///     })
+/// }
+/// ```
+///
+/// With separate error clusters:
+/// ```
+/// use labview_interop::with_lverrorhandling;
+/// use labview_interop::errors::{LVInteropError, MgErr};
+/// use labview_interop::types::ErrorClusterPtr;
+///
+/// #[no_mangle]
+/// pub extern "C" fn reset_device(error_in: ErrorClusterPtr, error_out: ErrorClusterPtr) -> MgErr {
+///     with_lverrorhandling!(error_in = error_in, error_out = error_out, {
+///         Ok::<(), LVInteropError>(())
+///     })
+/// }
+/// ```
+#[macro_export]
+macro_rules! with_lverrorhandling {
+    (error_in = $error_in:expr, error_out = $error_out:expr, $body:expr) => {
+        $crate::types::lv_errors::run_with_error_in_out($error_in, $error_out, || $body)
+    };
+    ($error_cluster:expr, $body:expr) => {
+        $crate::types::lv_errors::run_with_error_cluster($error_cluster, || $body)
+    };
+}
+
 #[cfg(test)]
 mod tests {
 
     use super::*;
+    #[cfg(feature = "link")]
+    use crate::memory::UHandle;
 
     #[test]
     fn test_source_writer_empty_description() {
@@ -136,10 +762,175 @@ mod tests {
         assert_eq!(source, expected)
     }
 
+    #[test]
+    fn test_source_writer_reuses_buffer() {
+        let mut buffer = String::from("stale");
+        buffer.clear();
+        write_error_source(&mut buffer, "Rust", "An Error Occured");
+        assert_eq!(buffer, "Rust\n<ERR>\nAn Error Occured")
+    }
+
+    #[test]
+    fn test_append_call_chain() {
+        assert_eq!(
+            append_call_chain("read_register\n<ERR>\nTimed out", "open_device"),
+            "read_register->open_device\n<ERR>\nTimed out"
+        );
+        assert_eq!(
+            append_call_chain("<ERR>\nTimed out", "open_device"),
+            "open_device\n<ERR>\nTimed out"
+        );
+        assert_eq!(
+            append_call_chain("Caller.vi", "open_device"),
+            "Caller.vi->open_device"
+        );
+        assert_eq!(append_call_chain("", "open_device"), "open_device");
+    }
+
     #[test]
     fn test_source_writer_empty_source() {
         let source = format_error_source("", "An Error Occured");
         let expected = "<ERR>\nAn Error Occured";
         assert_eq!(source, expected)
     }
+
+    #[cfg(feature = "link")]
+    #[test]
+    fn test_panic_is_written_to_cluster() {
+        let source = crate::types::LStrOwned::from_data(b"").unwrap().into_raw();
+        let mut cluster = ErrorCluster {
+            status: LV_FALSE,
+            code: MgErr::NO_ERROR,
+            source,
+        };
+        let cluster_ptr = unsafe { ErrorClusterPtr::from_raw(&mut cluster) };
+        let status = run_with_error_cluster::<LVInteropError, Result<(), LVInteropError>>(
+            cluster_ptr,
+            || panic!("axis {} lost", 2),
+        );
+
+        assert_eq!(status, MgErr::from(-15));
+        assert!(cluster.is_error());
+        let source = unsafe { cluster.source.as_ref() }.unwrap().to_rust_string();
+        assert!(source.ends_with("Rust code panicked: axis 2 lost"));
+        drop(unsafe { crate::types::LStrOwned::from_raw(UHandle(cluster.source.0)) });
+    }
+
+    /// A cluster with a null source handle, which is left alone by `merge`
+    /// unless it copies from another cluster.
+    fn cluster(status: LVBool, code: i32) -> ErrorCluster {
+        ErrorCluster {
+            status,
+            code: code.into(),
+            source: crate::memory::UHandle(std::ptr::null_mut()),
+        }
+    }
+
+    #[test]
+    fn test_merge_replaces() {
+        let error = cluster(LV_TRUE, 5000);
+        let warning = cluster(LV_FALSE, 5001);
+        let no_error = cluster(LV_FALSE, 0);
+
+        assert!(!error.merge_replaces(true));
+        assert!(!error.merge_replaces(false));
+        assert!(warning.merge_replaces(true));
+        assert!(!warning.merge_replaces(false));
+        assert!(no_error.merge_replaces(true));
+        assert!(no_error.merge_replaces(false));
+        assert!(warning.is_warning() && !error.is_warning() && !no_error.is_warning());
+    }
+
+    #[test]
+    fn test_merge_keeps_existing_error() {
+        let mut existing = cluster(LV_TRUE, 5000);
+        existing.merge(&cluster(LV_TRUE, 5001)).unwrap();
+        assert_eq!(existing.code(), MgErr::from(5000));
+
+        let mut existing = cluster(LV_FALSE, 5002);
+        existing.merge(&cluster(LV_FALSE, 0)).unwrap();
+        assert!(existing.is_warning());
+        assert_eq!(existing.code(), MgErr::from(5002));
+    }
+
+    #[cfg(feature = "link")]
+    #[test]
+    fn test_merge_error_keeps_existing_warning() {
+        let source = crate::types::LStrOwned::from_data(b"upstream")
+            .unwrap()
+            .into_raw();
+        let mut cluster = ErrorCluster {
+            status: LV_FALSE,
+            code: MgErr::from(5001),
+            source,
+        };
+        let cluster_ptr = std::ptr::addr_of_mut!(cluster);
+
+        LVWarning::new(5002, "later")
+            .merge_error(unsafe { ErrorClusterPtr::from_raw(cluster_ptr) })
+            .unwrap();
+        assert_eq!(cluster.code(), MgErr::from(5001));
+
+        LVInteropError::InvalidHandle
+            .merge_error(unsafe { ErrorClusterPtr::from_raw(cluster_ptr) })
+            .unwrap();
+        assert!(cluster.is_error());
+        drop(unsafe { crate::types::LStrOwned::from_raw(UHandle(cluster.source.0)) });
+    }
+
+    #[cfg(feature = "anyhow")]
+    #[test]
+    fn test_anyhow_description_includes_causes() {
+        let error = anyhow::anyhow!("port closed").context("reading config");
+        assert_eq!(
+            ToLvError::description(&error),
+            "reading config\nCaused by: port closed"
+        );
+        assert_eq!(ToLvError::source(&error), "port closed");
+        assert_eq!(ToLvError::code(&error), MgErr::from(42));
+    }
+
+    #[test]
+    fn test_lv_report_ok_leaves_cluster() {
+        let mut cluster = cluster(LV_FALSE, 5001);
+        let cluster_ptr = unsafe { ErrorClusterPtr::from_raw(&mut cluster) };
+        let status = Ok::<(), LVInteropError>(()).lv_report(&cluster_ptr);
+        assert_eq!(status, MgErr::NO_ERROR);
+        assert_eq!(cluster.code(), MgErr::from(5001));
+    }
+
+    #[test]
+    fn test_incoming_warning_kept_over_new_warning() {
+        let mut cluster = cluster(LV_FALSE, 5001);
+        let cluster_ptr = unsafe { ErrorClusterPtr::from_raw(&mut cluster) };
+        let status = run_with_error_cluster::<LVInteropError, _>(cluster_ptr, || {
+            LvResult::Warning((), LVWarning::new(5002, "Later warning"))
+        });
+        assert_eq!(status, MgErr::NO_ERROR);
+        assert!(cluster.is_warning());
+        assert_eq!(cluster.code(), MgErr::from(5001));
+    }
+
+    #[test]
+    fn test_lv_result_from_result() {
+        let ok: LvResult<u32> = Ok(1).into();
+        assert!(ok.is_ok() && !ok.is_warning());
+
+        let err: LvResult<u32> = Err(LVInteropError::InvalidHandle).into();
+        assert!(err.is_err() && !err.is_ok());
+    }
+
+    #[test]
+    fn test_lv_result_warning_is_success() {
+        let warning = LVWarning::new(5001, "Used defaults").with_source("config");
+        let result: LvResult<u32> = LvResult::Warning(1, warning.clone());
+        assert!(result.is_ok());
+        assert_eq!(result.warning(), Some(&warning));
+        assert!(!warning.is_error());
+        assert_eq!(warning.code(), MgErr::from(5001));
+
+        let result = result.map(|value| value * 2);
+        assert!(result.is_warning());
+        assert_eq!(result.into_result().unwrap(), 2);
+    }
 }
@@ -0,0 +1,229 @@
+//! LabVIEW maps and sets.
+//!
+//! Like variants, the structure behind a map or set is internal to LabVIEW
+//! and can't be read through the documented C interface. [`LVMap`] and
+//! [`LVSet`] let Rust hold one it was passed, for example in a cluster, but
+//! not read its keys and values in place.
+//!
+//! To read the contents, use Flatten To String on the diagram and pass the
+//! string to Rust. [`BTreeMap`] and [`BTreeSet`] implement
+//! [`LvData`] for the flattened form, which is the size
+//! followed by the keys and values in key order, so
+//! [`unflatten`](super::data::unflatten) gives a map whose entries can be
+//! looked up, iterated in the same order as LabVIEW and inserted.
+//! [`flatten`](super::data::flatten) writes it back for Unflatten From String.
+//! [`from_lv`](super::data::LvData::from_lv) and
+//! [`to_lv`](super::data::LvData::to_lv) on a wired [`LVMap`] or [`LVSet`]
+//! return [`OpaqueData`](crate::errors::LVInteropError::OpaqueData).
+//!
+//! NI doesn't document the type code of maps and sets so the type descriptor
+//! is that of a 1D array of key and value clusters, or of elements for a set.
+//! This flattens the same way, so a [`FlattenedVariant`](super::variant::FlattenedVariant)
+//! of a map unflattens on the diagram as the array, which a For Loop with
+//! Insert Into Map turns back into a map. The [`flattened`](super::flattened)
+//! serde format writes maps the same way.
+//!
+//! # Example
+//! ```
+//! use std::collections::BTreeMap;
+//! use labview_interop::types::data::{flatten, unflatten};
+//!
+//! let mut config = BTreeMap::new();
+//! config.insert("rate".to_string(), 1000.0f64);
+//! config.insert("gain".to_string(), 2.0);
+//!
+//! let flattened = flatten(&config).unwrap();
+//! let copy: BTreeMap<String, f64> = unflatten(&flattened).unwrap();
+//! assert_eq!(copy["rate"], 1000.0);
+//! ```
+
+#[cfg(target_pointer_width = "64")]
+use std::collections::{BTreeMap, BTreeSet};
+use std::ffi::c_void;
+
+#[cfg(target_pointer_width = "64")]
+use super::data::{array_descriptor, cluster_descriptor, flatten_length, unflatten_length, LvData};
+#[cfg(target_pointer_width = "64")]
+use crate::errors::{LVInteropError, Result};
+use crate::memory::UHandle;
+
+/// A LabVIEW map. The internal structure is undefined by NI.
+///
+/// This is available as a placeholder in clusters etc.
+#[repr(transparent)]
+pub struct LVMap(UHandle<c_void>);
+
+/// A LabVIEW set. The internal structure is undefined by NI.
+///
+/// This is available as a placeholder in clusters etc.
+#[repr(transparent)]
+pub struct LVSet(UHandle<c_void>);
+
+macro_rules! opaque_collection {
+    ($($name:ident),+) => {
+        $(
+            impl $name {
+                /// True if there is no collection.
+                pub fn is_null(&self) -> bool {
+                    self.0 .0.is_null()
+                }
+
+                /// The pointer LabVIEW uses for the collection.
+                pub fn as_raw(&self) -> *mut c_void {
+                    self.0 .0.cast()
+                }
+            }
+
+            impl std::fmt::Debug for $name {
+                fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                    f.debug_tuple(stringify!($name)).field(&self.as_raw()).finish()
+                }
+            }
+        )+
+    };
+}
+
+opaque_collection!(LVMap, LVSet);
+
+/// Maps are only exchanged in their flattened form.
+#[cfg(target_pointer_width = "64")]
+impl<K: LvData + Ord, V: LvData> LvData for BTreeMap<K, V> {
+    type Repr = LVMap;
+
+    fn type_descriptor() -> Vec<u8> {
+        array_descriptor(&cluster_descriptor(&[
+            K::type_descriptor(),
+            V::type_descriptor(),
+        ]))
+    }
+
+    /// Always an error as the map can't be read in place.
+    fn from_lv(_repr: &Self::Repr) -> Result<Self> {
+        Err(LVInteropError::OpaqueData("map"))
+    }
+
+    /// Always an error as the map can't be written in place.
+    fn to_lv(&self, _repr: &mut Self::Repr) -> Result<()> {
+        Err(LVInteropError::OpaqueData("map"))
+    }
+
+    fn flatten(&self, output: &mut Vec<u8>) -> Result<()> {
+        flatten_length(self.len(), output)?;
+        for (key, value) in self {
+            key.flatten(output)?;
+            value.flatten(output)?;
+        }
+        Ok(())
+    }
+
+    fn unflatten(input: &mut &[u8]) -> Result<Self> {
+        let count = unflatten_length(input)?;
+        let mut map = BTreeMap::new();
+        for _ in 0..count {
+            let key = K::unflatten(input)?;
+            map.insert(key, V::unflatten(input)?);
+        }
+        Ok(map)
+    }
+}
+
+/// Sets are only exchanged in their flattened form.
+#[cfg(target_pointer_width = "64")]
+impl<T: LvData + Ord> LvData for BTreeSet<T> {
+    type Repr = LVSet;
+
+    fn type_descriptor() -> Vec<u8> {
+        array_descriptor(&T::type_descriptor())
+    }
+
+    /// Always an error as the set can't be read in place.
+    fn from_lv(_repr: &Self::Repr) -> Result<Self> {
+        Err(LVInteropError::OpaqueData("set"))
+    }
+
+    /// Always an error as the set can't be written in place.
+    fn to_lv(&self, _repr: &mut Self::Repr) -> Result<()> {
+        Err(LVInteropError::OpaqueData("set"))
+    }
+
+    fn flatten(&self, output: &mut Vec<u8>) -> Result<()> {
+        flatten_length(self.len(), output)?;
+        for element in self {
+            element.flatten(output)?;
+        }
+        Ok(())
+    }
+
+    fn unflatten(input: &mut &[u8]) -> Result<Self> {
+        let count = unflatten_length(input)?;
+        let mut set = BTreeSet::new();
+        for _ in 0..count {
+            set.insert(T::unflatten(input)?);
+        }
+        Ok(set)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_null_collections() {
+        assert!(LVMap(UHandle(std::ptr::null_mut())).is_null());
+        assert!(LVSet(UHandle(std::ptr::null_mut())).as_raw().is_null());
+    }
+
+    #[cfg(target_pointer_width = "64")]
+    #[test]
+    fn test_flattened_collections() {
+        use crate::types::data::{flatten, unflatten};
+
+        let map = BTreeMap::from([(2u8, 20i16), (1, 10)]);
+        let flattened = flatten(&map).unwrap();
+        assert_eq!(flattened, [0, 0, 0, 2, 1, 0, 10, 2, 0, 20]);
+        assert_eq!(unflatten::<BTreeMap<u8, i16>>(&flattened).unwrap(), map);
+
+        let set = BTreeSet::from([3i32, -1]);
+        assert_eq!(
+            unflatten::<BTreeSet<i32>>(&flatten(&set).unwrap()).unwrap(),
+            set
+        );
+        assert!(unflatten::<BTreeSet<u8>>(&[0xFF, 0xFF, 0xFF, 0xFF]).is_err());
+        assert!(unflatten::<BTreeSet<u8>>(&[0, 0, 0, 1]).is_err());
+
+        let mut wired = LVMap(UHandle(std::ptr::null_mut()));
+        assert!(BTreeMap::<u8, i16>::from_lv(&wired).is_err());
+        assert!(map.to_lv(&mut wired).is_err());
+    }
+
+    #[cfg(target_pointer_width = "64")]
+    #[test]
+    fn test_map_in_variant() {
+        use crate::types::type_descriptor::{TypeDescriptor, TypeKind};
+        use crate::types::variant::FlattenedVariant;
+
+        let map = BTreeMap::from([(1u8, true)]);
+        let variant = FlattenedVariant::new(&map).unwrap();
+        assert_eq!(variant.get::<BTreeMap<u8, bool>>().unwrap(), map);
+        assert!(variant.get::<BTreeMap<u8, u8>>().is_err());
+
+        let descriptor = TypeDescriptor::parse(&BTreeMap::<u8, bool>::type_descriptor()).unwrap();
+        let TypeKind::Array { element, .. } = descriptor.kind else {
+            panic!("not an array");
+        };
+        assert!(matches!(element.kind, TypeKind::Cluster(ref fields) if fields.len() == 2));
+    }
+
+    #[cfg(all(feature = "serde", target_pointer_width = "64"))]
+    #[test]
+    fn test_matches_serde_format() {
+        use crate::types::flattened::to_flattened;
+
+        let map = BTreeMap::from([("a".to_string(), 1.5f64), ("b".to_string(), -2.0)]);
+        assert_eq!(
+            crate::types::data::flatten(&map).unwrap(),
+            to_flattened(&map).unwrap()
+        );
+    }
+}
@@ -0,0 +1,228 @@
+//! Support for the LabVIEW matrix types.
+//!
+//! LabVIEW's real and complex matrices have their own type descriptor so they
+//! stay matrices on the diagram, but in memory they are the same as a 2D array
+//! handle. [`LVMatrixHandle`] gives them a distinct type in function signatures
+//! and converts to the 2D array handle for everything else, including the
+//! ndarray views.
+//!
+//! With the `nalgebra` feature the matrix can also be viewed or copied as an
+//! nalgebra matrix. LabVIEW stores matrices in row major order so the views
+//! are strided rather than contiguous.
+
+use super::LVArrayHandle;
+
+/// A handle to a LabVIEW matrix of `T`.
+///
+/// From LabVIEW this is a matrix passed as "Handles by Value".
+///
+/// # Example
+/// ```
+/// use labview_interop::errors::MgErr;
+/// use labview_interop::types::LVMatrixHandle;
+///
+/// #[no_mangle]
+/// pub extern "C" fn matrix_size(matrix: LVMatrixHandle<f64>, rows: &mut i32, columns: &mut i32) -> MgErr {
+///     *rows = matrix.rows() as i32;
+///     *columns = matrix.columns() as i32;
+///     MgErr::NO_ERROR
+/// }
+/// ```
+#[repr(transparent)]
+pub struct LVMatrixHandle<T>(LVArrayHandle<2, T>);
+
+impl<T> LVMatrixHandle<T> {
+    /// Treat a 2D array handle as a matrix.
+    pub fn from_array(array: LVArrayHandle<2, T>) -> Self {
+        Self(array)
+    }
+
+    /// Access the matrix as a 2D array handle.
+    pub fn as_array(&self) -> &LVArrayHandle<2, T> {
+        &self.0
+    }
+
+    /// Access the matrix as a mutable 2D array handle, for example to resize it.
+    pub fn as_array_mut(&mut self) -> &mut LVArrayHandle<2, T> {
+        &mut self.0
+    }
+
+    /// Convert the matrix into the 2D array handle.
+    pub fn into_array(self) -> LVArrayHandle<2, T> {
+        self.0
+    }
+
+    /// The number of rows. A null handle has no rows.
+    pub fn rows(&self) -> usize {
+        let [rows, _]: [usize; 2] = self.0.dimensions_or_empty().into();
+        rows
+    }
+
+    /// The number of columns. A null handle has no columns.
+    pub fn columns(&self) -> usize {
+        let [_, columns]: [usize; 2] = self.0.dimensions_or_empty().into();
+        columns
+    }
+}
+
+impl<T> From<LVArrayHandle<2, T>> for LVMatrixHandle<T> {
+    fn from(array: LVArrayHandle<2, T>) -> Self {
+        Self::from_array(array)
+    }
+}
+
+#[cfg(all(feature = "nalgebra", target_pointer_width = "64"))]
+mod nalgebra_support {
+    use nalgebra::{DMatrix, Dim, Dyn, Matrix, MatrixView, MatrixViewMut, RawStorage, Scalar, U1};
+
+    use super::LVMatrixHandle;
+    use crate::errors::Result;
+    #[cfg(feature = "link")]
+    use crate::types::array::NumericArrayResizable;
+
+    /// A view of a row major LabVIEW matrix.
+    pub type LVMatrixView<'a, T> = MatrixView<'a, T, Dyn, Dyn, Dyn, U1>;
+    /// A mutable view of a row major LabVIEW matrix.
+    pub type LVMatrixViewMut<'a, T> = MatrixViewMut<'a, T, Dyn, Dyn, Dyn, U1>;
+
+    impl<T: Scalar> LVMatrixHandle<T> {
+        /// Get the LabVIEW matrix as an nalgebra view.
+        ///
+        /// # Errors
+        ///
+        /// * The handle is null.
+        pub fn nalgebra_view(&self) -> Result<LVMatrixView<'_, T>> {
+            self.0.validate()?;
+            let (rows, columns) = (self.rows(), self.columns());
+            let data = unsafe { self.0.as_ref()? }.data_as_slice();
+            Ok(LVMatrixView::from_slice_with_strides_generic(
+                data,
+                Dyn(rows),
+                Dyn(columns),
+                Dyn(columns),
+                U1,
+            ))
+        }
+
+        /// Get the LabVIEW matrix as a mutable nalgebra view.
+        ///
+        /// # Errors
+        ///
+        /// * The handle is null.
+        pub fn nalgebra_view_mut(&mut self) -> Result<LVMatrixViewMut<'_, T>> {
+            self.0.validate()?;
+            let (rows, columns) = (self.rows(), self.columns());
+            let data = unsafe { self.0.as_ref_mut()? }.data_as_slice_mut();
+            Ok(LVMatrixViewMut::from_slice_with_strides_generic(
+                data,
+                Dyn(rows),
+                Dyn(columns),
+                Dyn(columns),
+                U1,
+            ))
+        }
+
+        /// Copy the LabVIEW matrix into an owned nalgebra matrix.
+        ///
+        /// A null handle is an empty matrix.
+        pub fn to_dmatrix(&self) -> DMatrix<T> {
+            match unsafe { self.0.as_ref() } {
+                Ok(array) => {
+                    DMatrix::from_row_slice(self.rows(), self.columns(), array.data_as_slice())
+                }
+                Err(_) => DMatrix::from_vec(0, 0, Vec::new()),
+            }
+        }
+    }
+
+    #[cfg(feature = "link")]
+    impl<T: Scalar + Copy + NumericArrayResizable> LVMatrixHandle<T> {
+        /// Set the LabVIEW matrix from an nalgebra matrix.
+        ///
+        /// It will resize the matrix to match the dimensions if required.
+        pub fn copy_from_nalgebra<R: Dim, C: Dim, S: RawStorage<T, R, C>>(
+            &mut self,
+            matrix: &Matrix<T, R, C, S>,
+        ) -> Result<()> {
+            let (rows, columns) = matrix.shape();
+            self.0.resize_array((&[rows, columns]).try_into()?)?;
+            let data = unsafe { self.0.as_ref_mut()? }.data_as_slice_mut();
+            for (index, output) in data.iter_mut().enumerate() {
+                *output = matrix[(index / columns, index % columns)];
+            }
+            Ok(())
+        }
+    }
+}
+
+#[cfg(all(feature = "nalgebra", target_pointer_width = "64"))]
+pub use nalgebra_support::{LVMatrixView, LVMatrixViewMut};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::UHandle;
+
+    #[test]
+    fn test_layout_matches_array_handle() {
+        assert_eq!(
+            std::mem::size_of::<LVMatrixHandle<f64>>(),
+            std::mem::size_of::<LVArrayHandle<2, f64>>()
+        );
+        assert_eq!(
+            std::mem::align_of::<LVMatrixHandle<f64>>(),
+            std::mem::align_of::<LVArrayHandle<2, f64>>()
+        );
+    }
+
+    #[test]
+    fn test_null_matrix_is_empty() {
+        let matrix = LVMatrixHandle::<f64>::from_array(UHandle(std::ptr::null_mut()));
+        assert_eq!((matrix.rows(), matrix.columns()), (0, 0));
+    }
+
+    #[cfg(feature = "link")]
+    #[test]
+    fn test_rows_and_columns() {
+        use crate::types::LVArrayOwned;
+
+        let array =
+            LVArrayOwned::<2, i32>::from_shape_slice([2, 3].into(), &[1, 2, 3, 4, 5, 6]).unwrap();
+        let matrix = LVMatrixHandle::from_array(UHandle(array.0));
+        assert_eq!((matrix.rows(), matrix.columns()), (2, 3));
+        assert_eq!(matrix.as_array().as_slice(), &[1, 2, 3, 4, 5, 6]);
+    }
+
+    #[cfg(all(feature = "link", feature = "nalgebra", target_pointer_width = "64"))]
+    #[test]
+    fn test_nalgebra_indexing_is_row_major() {
+        use crate::types::LVArrayOwned;
+
+        let array = LVArrayOwned::<2, f64>::from_shape_slice(
+            [2, 3].into(),
+            &[1.0, 2.0, 3.0, 4.0, 5.0, 6.0],
+        )
+        .unwrap();
+        let mut matrix = LVMatrixHandle::from_array(UHandle(array.0));
+
+        let view = matrix.nalgebra_view().unwrap();
+        assert_eq!(view.shape(), (2, 3));
+        assert_eq!(view[(0, 2)], 3.0);
+        assert_eq!(view[(1, 0)], 4.0);
+        assert_eq!(
+            matrix.to_dmatrix(),
+            nalgebra::DMatrix::from_row_slice(2, 3, &[1.0, 2.0, 3.0, 4.0, 5.0, 6.0])
+        );
+
+        matrix.nalgebra_view_mut().unwrap()[(1, 2)] = 9.0;
+        assert_eq!(matrix.as_array().as_slice()[5], 9.0);
+
+        let transposed = matrix.to_dmatrix().transpose();
+        matrix.copy_from_nalgebra(&transposed).unwrap();
+        assert_eq!((matrix.rows(), matrix.columns()), (3, 2));
+        assert_eq!(
+            matrix.as_array().as_slice(),
+            &[1.0, 4.0, 2.0, 5.0, 3.0, 9.0]
+        );
+    }
+}
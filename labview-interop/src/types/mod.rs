@@ -1,24 +1,61 @@
 //! The types module provides some of the common structures
 //! and functions for handling types from LabVIEW.
 
+pub mod alignment;
 pub mod array;
-mod boolean;
+pub mod boolean;
+pub mod byte_order;
+pub mod complex;
 #[cfg(target_pointer_width = "64")]
-mod lv_errors;
+pub mod data;
+pub mod error_collector;
+#[cfg(feature = "serde")]
+pub mod flattened;
+#[cfg(all(feature = "json", target_pointer_width = "64"))]
+pub mod json;
+pub mod last_error;
+pub mod lv_errors;
+pub mod map;
+pub mod matrix;
+pub mod numeric;
+#[cfg(target_pointer_width = "64")]
+pub mod packed_strings;
+pub mod path;
+pub mod refnum;
 pub mod string;
 pub mod timestamp;
-
-use std::ffi::c_void;
-
-use crate::memory::UHandle;
+#[cfg(target_pointer_width = "64")]
+pub mod type_descriptor;
+pub mod variant;
 
 //surface some of the common types.
+#[cfg(target_pointer_width = "64")]
+pub use array::ArrayView;
+#[cfg(feature = "link")]
+pub use array::LVArrayOwned;
 pub use array::{LVArray, LVArrayHandle};
 pub use boolean::LVBool;
+pub use complex::{LVComplex, LVComplexDouble, LVComplexSingle};
 #[cfg(target_pointer_width = "64")]
-pub use lv_errors::{ErrorClusterPtr, ToLvError};
+pub use data::LvData;
+pub use error_collector::{ErrorCollector, ErrorEntry};
+#[cfg(feature = "macros")]
+pub use labview_interop_macros::ToLvError;
+pub use lv_errors::{
+    set_source_preallocation, source_preallocation, ErrorClusterPtr, LVWarning, LvResult,
+    LvResultExt, ToLvError,
+};
+pub use map::{LVMap, LVSet};
+pub use matrix::LVMatrixHandle;
+pub use path::LVPathHandle;
+#[cfg(feature = "link")]
+pub use path::LVPathOwned;
+pub use refnum::LVRefNum;
 pub use string::LStrHandle;
+#[cfg(feature = "link")]
+pub use string::LStrOwned;
 pub use timestamp::LVTime;
+pub use variant::LVVariant;
 
 /// Wrap a struct declaration to have the packing attributes
 /// set for exchanging the data with the LabVIEW cluster type.
@@ -87,13 +124,6 @@ macro_rules! labview_layout {
     };
 }
 
-/// Represents a LabVIEW Variant. The internal structure is undefined
-/// by NI and therefore unavailable.
-///
-/// This is available as a placeholder in clusters etc.
-#[repr(transparent)]
-pub struct LVVariant(UHandle<c_void>);
-
 labview_layout!(
     /// Represents the LabVIEW waveform type where:
     ///
@@ -125,3 +155,104 @@ labview_layout!(
         _pad2: u32,
     }
 );
+
+labview_layout!(
+    /// Represents the LabVIEW digital data type, also called a digital table.
+    ///
+    /// * transitions: The sample index at which each row of `data` starts.
+    /// * data: A 2D array of samples with a row per sample and a column per
+    ///   signal. Each value is a digital state where 0 and 1 are low and high.
+    pub struct DigitalTable {
+        /// The sample index of each row in the data.
+        pub transitions: LVArrayHandle<1, u32>,
+        /// The states with a row per sample and a column per signal.
+        pub data: LVArrayHandle<2, u8>,
+    }
+);
+
+labview_layout!(
+    /// Represents the LabVIEW digital waveform type where:
+    ///
+    /// * t0: The start time of the data.
+    /// * dt: The time delta between samples.
+    /// * data: The digital table of samples.
+    ///
+    /// ## Padding
+    ///
+    /// This follows the same reverse engineered padding as [`Waveform`], with
+    /// the digital table in place of the array handle. The unit tests check
+    /// it against [`Waveform`] but it hasn't yet been checked against a
+    /// digital waveform from LabVIEW.
+    pub struct DigitalWaveform {
+        /// The timestamp for the first sample.
+        pub t0: timestamp::LVTime,
+        /// The time in seconds between samples.
+        pub dt: f64,
+        /// The samples.
+        pub data: DigitalTable,
+        #[cfg(target_pointer_width = "64")]
+        _pad: u64,
+        #[cfg(target_pointer_width = "32")]
+        _pad: u32,
+        #[cfg(target_pointer_width = "32")]
+        _mini_pad: u8,
+        attributes: LVVariant,
+        #[cfg(target_pointer_width = "64")]
+        _pad2: u64,
+        #[cfg(target_pointer_width = "32")]
+        _pad2: u32,
+    }
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::mem::{offset_of, size_of};
+
+    /// The digital waveform matches the analog waveform with the digital table
+    /// of two handles in place of the array handle.
+    #[test]
+    fn test_digital_waveform_layout() {
+        let handle = size_of::<LVArrayHandle<1, u32>>();
+        assert_eq!(size_of::<DigitalTable>(), 2 * handle);
+        assert_eq!(offset_of!(DigitalTable, data), handle);
+
+        assert_eq!(
+            offset_of!(DigitalWaveform, t0),
+            offset_of!(Waveform<f64>, t0)
+        );
+        assert_eq!(
+            offset_of!(DigitalWaveform, dt),
+            offset_of!(Waveform<f64>, dt)
+        );
+        assert_eq!(
+            offset_of!(DigitalWaveform, data),
+            offset_of!(Waveform<f64>, data)
+        );
+        assert_eq!(
+            offset_of!(DigitalWaveform, attributes),
+            offset_of!(Waveform<f64>, attributes) + handle
+        );
+        assert_eq!(
+            size_of::<DigitalWaveform>(),
+            size_of::<Waveform<f64>>() + handle
+        );
+    }
+
+    #[cfg(target_pointer_width = "64")]
+    #[test]
+    fn test_digital_waveform_layout_64() {
+        assert_eq!(offset_of!(DigitalWaveform, dt), 16);
+        assert_eq!(offset_of!(DigitalWaveform, data), 24);
+        assert_eq!(offset_of!(DigitalWaveform, attributes), 48);
+        assert_eq!(size_of::<DigitalWaveform>(), 64);
+    }
+
+    #[cfg(target_pointer_width = "32")]
+    #[test]
+    fn test_digital_waveform_layout_32() {
+        assert_eq!(offset_of!(DigitalWaveform, data), 24);
+        assert_eq!(offset_of!(DigitalWaveform, attributes), 37);
+        assert_eq!(size_of::<DigitalWaveform>(), 45);
+    }
+}
@@ -0,0 +1,432 @@
+//! Numeric conversions with LabVIEW's coercion rules.
+//!
+//! LabVIEW converts floating point values to integers by rounding to the
+//! nearest value (ties to even) and saturating at the limits of the integer
+//! type. The functions here apply the same rules to whole slices and array
+//! handles, with a choice of rounding, for scaling raw ADC counts to engineering
+//! units and back at high throughput.
+//!
+//! NaN converts to zero.
+//!
+//! Where a silent saturation would hide a bug, [`try_coerce`] and
+//! [`try_coerce_slice`] check the conversion instead and return an error
+//! naming the value and its index.
+
+#[cfg(all(feature = "link", target_pointer_width = "64"))]
+use super::array::NumericArrayResizable;
+#[cfg(all(feature = "link", target_pointer_width = "64"))]
+use super::LVArrayHandle;
+use crate::errors::{LVInteropError, Result};
+
+/// How to round floating point values when converting to integers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Rounding {
+    /// Round to nearest with ties to even. This matches LabVIEW's coercion.
+    #[default]
+    NearestEven,
+    /// Round to nearest with ties away from zero.
+    NearestAway,
+    /// Round towards negative infinity.
+    Floor,
+    /// Round towards positive infinity.
+    Ceil,
+    /// Round towards zero.
+    Truncate,
+}
+
+impl Rounding {
+    fn apply(self, value: f64) -> f64 {
+        match self {
+            Rounding::NearestEven => value.round_ties_even(),
+            Rounding::NearestAway => value.round(),
+            Rounding::Floor => value.floor(),
+            Rounding::Ceil => value.ceil(),
+            Rounding::Truncate => value.trunc(),
+        }
+    }
+}
+
+/// Conversion from `F` with saturation at the limits of the output type.
+pub trait CoerceFrom<F>: Sized {
+    /// Convert `value`, rounding if it is a float going to an integer.
+    fn coerce_from(value: F, rounding: Rounding) -> Self;
+}
+
+macro_rules! coerce_float_to_int {
+    ($float:ty => $($int:ty),+) => {
+        $(
+            impl CoerceFrom<$float> for $int {
+                fn coerce_from(value: $float, rounding: Rounding) -> Self {
+                    // Float to integer `as` casts saturate and convert NaN to zero.
+                    rounding.apply(value as f64) as $int
+                }
+            }
+        )+
+    };
+}
+
+macro_rules! coerce_to_float {
+    ($($from:ty),+ => $float:ty) => {
+        $(
+            impl CoerceFrom<$from> for $float {
+                fn coerce_from(value: $from, _rounding: Rounding) -> Self {
+                    value as $float
+                }
+            }
+        )+
+    };
+}
+
+macro_rules! coerce_int_to_int {
+    ($from:ty => $($int:ty),+) => {
+        $(
+            impl CoerceFrom<$from> for $int {
+                fn coerce_from(value: $from, _rounding: Rounding) -> Self {
+                    // i128 holds every value of the 64 bit integers so this clamp is exact.
+                    (value as i128).clamp(<$int>::MIN as i128, <$int>::MAX as i128) as $int
+                }
+            }
+        )+
+    };
+}
+
+coerce_float_to_int!(f32 => i8, i16, i32, i64, u8, u16, u32, u64);
+coerce_float_to_int!(f64 => i8, i16, i32, i64, u8, u16, u32, u64);
+coerce_to_float!(i8, i16, i32, i64, u8, u16, u32, u64, f32, f64 => f64);
+coerce_to_float!(i8, i16, i32, i64, u8, u16, u32, u64, f32, f64 => f32);
+coerce_int_to_int!(i8 => i8, i16, i32, i64, u8, u16, u32, u64);
+coerce_int_to_int!(i16 => i8, i16, i32, i64, u8, u16, u32, u64);
+coerce_int_to_int!(i32 => i8, i16, i32, i64, u8, u16, u32, u64);
+coerce_int_to_int!(i64 => i8, i16, i32, i64, u8, u16, u32, u64);
+coerce_int_to_int!(u8 => i8, i16, i32, i64, u8, u16, u32, u64);
+coerce_int_to_int!(u16 => i8, i16, i32, i64, u8, u16, u32, u64);
+coerce_int_to_int!(u32 => i8, i16, i32, i64, u8, u16, u32, u64);
+coerce_int_to_int!(u64 => i8, i16, i32, i64, u8, u16, u32, u64);
+
+/// Why a checked conversion failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CoerceFailure {
+    /// The value is outside the range of the output type.
+    OutOfRange,
+    /// The value is NaN and the output type is an integer.
+    NaN,
+}
+
+/// Conversion from `F` which fails rather than saturating.
+pub trait TryCoerceFrom<F>: Sized {
+    /// Convert `value`, rounding to nearest with ties to even if it is a float
+    /// going to an integer.
+    fn try_coerce_from(value: F) -> std::result::Result<Self, CoerceFailure>;
+}
+
+macro_rules! try_coerce_float_to_int {
+    ($float:ty => $($int:ty),+) => {
+        $(
+            impl TryCoerceFrom<$float> for $int {
+                fn try_coerce_from(value: $float) -> std::result::Result<Self, CoerceFailure> {
+                    if value.is_nan() {
+                        return Err(CoerceFailure::NaN);
+                    }
+                    let value = (value as f64).round_ties_even();
+                    // MAX + 1 is a power of two so is exact as a float, unlike MAX for 64 bit types.
+                    if value >= <$int>::MIN as f64 && value < (<$int>::MAX as f64 + 1.0) {
+                        Ok(value as $int)
+                    } else {
+                        Err(CoerceFailure::OutOfRange)
+                    }
+                }
+            }
+        )+
+    };
+}
+
+macro_rules! try_coerce_to_float {
+    ($($from:ty),+ => $float:ty) => {
+        $(
+            impl TryCoerceFrom<$from> for $float {
+                fn try_coerce_from(value: $from) -> std::result::Result<Self, CoerceFailure> {
+                    // Integers only lose precision, they are always in range.
+                    Ok(value as $float)
+                }
+            }
+        )+
+    };
+}
+
+macro_rules! try_coerce_int_to_int {
+    ($from:ty => $($int:ty),+) => {
+        $(
+            impl TryCoerceFrom<$from> for $int {
+                fn try_coerce_from(value: $from) -> std::result::Result<Self, CoerceFailure> {
+                    <$int>::try_from(value).map_err(|_| CoerceFailure::OutOfRange)
+                }
+            }
+        )+
+    };
+}
+
+impl TryCoerceFrom<f64> for f32 {
+    fn try_coerce_from(value: f64) -> std::result::Result<Self, CoerceFailure> {
+        let output = value as f32;
+        // NaN and infinity are kept, only finite values can overflow.
+        if value.is_finite() && output.is_infinite() {
+            Err(CoerceFailure::OutOfRange)
+        } else {
+            Ok(output)
+        }
+    }
+}
+
+try_coerce_float_to_int!(f32 => i8, i16, i32, i64, u8, u16, u32, u64);
+try_coerce_float_to_int!(f64 => i8, i16, i32, i64, u8, u16, u32, u64);
+try_coerce_to_float!(i8, i16, i32, i64, u8, u16, u32, u64, f32, f64 => f64);
+try_coerce_to_float!(i8, i16, i32, i64, u8, u16, u32, u64, f32 => f32);
+try_coerce_int_to_int!(i8 => i8, i16, i32, i64, u8, u16, u32, u64);
+try_coerce_int_to_int!(i16 => i8, i16, i32, i64, u8, u16, u32, u64);
+try_coerce_int_to_int!(i32 => i8, i16, i32, i64, u8, u16, u32, u64);
+try_coerce_int_to_int!(i64 => i8, i16, i32, i64, u8, u16, u32, u64);
+try_coerce_int_to_int!(u8 => i8, i16, i32, i64, u8, u16, u32, u64);
+try_coerce_int_to_int!(u16 => i8, i16, i32, i64, u8, u16, u32, u64);
+try_coerce_int_to_int!(u32 => i8, i16, i32, i64, u8, u16, u32, u64);
+try_coerce_int_to_int!(u64 => i8, i16, i32, i64, u8, u16, u32, u64);
+
+fn coerce_error<F: std::fmt::Display, T>(
+    value: F,
+    index: Option<usize>,
+    failure: CoerceFailure,
+) -> LVInteropError {
+    let target = std::any::type_name::<T>();
+    match failure {
+        CoerceFailure::OutOfRange => LVInteropError::CoercionOutOfRange {
+            value: value.to_string(),
+            target,
+            index,
+        },
+        CoerceFailure::NaN => LVInteropError::CoercionNaN { target, index },
+    }
+}
+
+/// Convert a value, returning an error if it is out of range for `T` or is
+/// NaN going to an integer.
+///
+/// # Example
+/// ```
+/// use labview_interop::types::numeric::try_coerce;
+///
+/// assert_eq!(try_coerce::<f64, u8>(254.5).unwrap(), 254);
+/// assert!(try_coerce::<i32, u8>(-1).is_err());
+/// ```
+pub fn try_coerce<F, T>(value: F) -> Result<T>
+where
+    F: Copy + std::fmt::Display,
+    T: TryCoerceFrom<F>,
+{
+    T::try_coerce_from(value).map_err(|failure| coerce_error::<F, T>(value, None, failure))
+}
+
+/// Convert every element of `input` into `output`, stopping at the first
+/// element which can't be converted.
+///
+/// The error includes the index of that element. Only the overlapping length
+/// of the slices is converted.
+pub fn try_coerce_slice<F, T>(input: &[F], output: &mut [T]) -> Result<()>
+where
+    F: Copy + std::fmt::Display,
+    T: TryCoerceFrom<F>,
+{
+    for (index, (output, input)) in output.iter_mut().zip(input).enumerate() {
+        *output = T::try_coerce_from(*input)
+            .map_err(|failure| coerce_error::<F, T>(*input, Some(index), failure))?;
+    }
+    Ok(())
+}
+
+/// Convert every element of `input` into `output`.
+///
+/// Only the overlapping length of the slices is converted.
+pub fn coerce_slice<F: Copy, T: CoerceFrom<F>>(input: &[F], output: &mut [T], rounding: Rounding) {
+    for (output, input) in output.iter_mut().zip(input) {
+        *output = T::coerce_from(*input, rounding);
+    }
+}
+
+/// Convert raw values to engineering units as `raw * gain + offset`.
+///
+/// For example converting ADC counts to volts.
+pub fn scale_slice<F, T>(input: &[F], output: &mut [T], gain: f64, offset: f64)
+where
+    F: Copy,
+    f64: CoerceFrom<F>,
+    T: CoerceFrom<f64>,
+{
+    for (output, input) in output.iter_mut().zip(input) {
+        let value = f64::coerce_from(*input, Rounding::default()) * gain + offset;
+        *output = T::coerce_from(value, Rounding::default());
+    }
+}
+
+/// Convert engineering units back to raw values as `(value - offset) / gain`.
+///
+/// The result is rounded and saturated at the limits of the raw type, for
+/// example when converting volts to DAC codes.
+pub fn unscale_slice<F, T>(
+    input: &[F],
+    output: &mut [T],
+    gain: f64,
+    offset: f64,
+    rounding: Rounding,
+) where
+    F: Copy,
+    f64: CoerceFrom<F>,
+    T: CoerceFrom<f64>,
+{
+    for (output, input) in output.iter_mut().zip(input) {
+        let value = (f64::coerce_from(*input, rounding) - offset) / gain;
+        *output = T::coerce_from(value, rounding);
+    }
+}
+
+#[cfg(all(feature = "link", target_pointer_width = "64"))]
+impl<const D: usize, F: Copy> LVArrayHandle<D, F> {
+    /// Convert this array into `output` with LabVIEW's coercion rules.
+    ///
+    /// The output array is resized to match this array.
+    ///
+    /// # Example
+    /// ```
+    /// use labview_interop::errors::MgErr;
+    /// use labview_interop::types::numeric::Rounding;
+    /// use labview_interop::types::LVArrayHandle;
+    ///
+    /// #[no_mangle]
+    /// pub extern "C" fn to_counts(volts: LVArrayHandle<1, f64>, mut counts: LVArrayHandle<1, i16>) -> MgErr {
+    ///     volts.coerce_into(&mut counts, Rounding::NearestEven).into()
+    /// }
+    /// ```
+    pub fn coerce_into<T>(&self, output: &mut LVArrayHandle<D, T>, rounding: Rounding) -> Result<()>
+    where
+        T: CoerceFrom<F> + NumericArrayResizable,
+    {
+        self.validate()?;
+        let input = unsafe { self.as_ref()? };
+        output.resize_array(input.dimension_sizes())?;
+        let output = unsafe { output.as_ref_mut()? };
+        coerce_slice(input.data_as_slice(), output.data_as_slice_mut(), rounding);
+        Ok(())
+    }
+
+    /// Convert this array into `output`, failing on the first element which is
+    /// out of range or NaN going to an integer.
+    ///
+    /// The output array is resized to match this array.
+    pub fn try_coerce_into<T>(&self, output: &mut LVArrayHandle<D, T>) -> Result<()>
+    where
+        F: std::fmt::Display,
+        T: TryCoerceFrom<F> + NumericArrayResizable,
+    {
+        self.validate()?;
+        let input = unsafe { self.as_ref()? };
+        output.resize_array(input.dimension_sizes())?;
+        let output = unsafe { output.as_ref_mut()? };
+        try_coerce_slice(input.data_as_slice(), output.data_as_slice_mut())
+    }
+
+    /// Scale this array into `output` as `raw * gain + offset`.
+    ///
+    /// The output array is resized to match this array.
+    pub fn scale_into<T>(
+        &self,
+        output: &mut LVArrayHandle<D, T>,
+        gain: f64,
+        offset: f64,
+    ) -> Result<()>
+    where
+        f64: CoerceFrom<F>,
+        T: CoerceFrom<f64> + NumericArrayResizable,
+    {
+        self.validate()?;
+        let input = unsafe { self.as_ref()? };
+        output.resize_array(input.dimension_sizes())?;
+        let output = unsafe { output.as_ref_mut()? };
+        scale_slice(
+            input.data_as_slice(),
+            output.data_as_slice_mut(),
+            gain,
+            offset,
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_float_to_int_rounds_ties_to_even() {
+        let mut output = [0i32; 4];
+        coerce_slice(&[0.5, 1.5, 2.5, -2.5], &mut output, Rounding::NearestEven);
+        assert_eq!(output, [0, 2, 2, -2]);
+    }
+
+    #[test]
+    fn test_float_to_int_saturates() {
+        let mut output = [0i16; 4];
+        coerce_slice(
+            &[1e9, -1e9, f64::NAN, 12.7],
+            &mut output,
+            Rounding::Truncate,
+        );
+        assert_eq!(output, [i16::MAX, i16::MIN, 0, 12]);
+    }
+
+    #[test]
+    fn test_int_to_int_saturates() {
+        let mut output = [0u8; 3];
+        coerce_slice(&[-5i32, 300, 7], &mut output, Rounding::default());
+        assert_eq!(output, [0, u8::MAX, 7]);
+        assert_eq!(i64::coerce_from(u64::MAX, Rounding::default()), i64::MAX);
+    }
+
+    #[test]
+    fn test_try_coerce_limits() {
+        assert_eq!(try_coerce::<f64, i64>(i64::MIN as f64).unwrap(), i64::MIN);
+        assert!(try_coerce::<f64, i64>(-(i64::MIN as f64)).is_err());
+        assert_eq!(try_coerce::<f32, u8>(255.4).unwrap(), 255);
+        assert!(try_coerce::<f32, u8>(255.5).is_err());
+        assert!(try_coerce::<f64, f32>(1e300).is_err());
+        assert!(try_coerce::<f64, f32>(f64::INFINITY).unwrap().is_infinite());
+        assert_eq!(try_coerce::<u64, f32>(u64::MAX).unwrap(), u64::MAX as f32);
+    }
+
+    #[test]
+    fn test_try_coerce_slice_reports_index() {
+        let mut output = [0u16; 4];
+        let error = try_coerce_slice(&[1.0, 2.0, f64::NAN, 4.0], &mut output).unwrap_err();
+        assert!(matches!(
+            error,
+            LVInteropError::CoercionNaN {
+                target: "u16",
+                index: Some(2)
+            }
+        ));
+        assert_eq!(output[..2], [1, 2]);
+
+        let error = try_coerce_slice(&[1i32, 70000], &mut output).unwrap_err();
+        assert!(error.to_string().contains("70000"));
+        assert!(error.to_string().contains("index 1"));
+    }
+
+    #[test]
+    fn test_scale_and_unscale_round_trip() {
+        let counts = [-32768i16, 0, 16384, 32767];
+        let mut volts = [0f64; 4];
+        let gain = 10.0 / 32768.0;
+        scale_slice(&counts, &mut volts, gain, 0.0);
+        assert_eq!(volts[2], 5.0);
+
+        let mut round_trip = [0i16; 4];
+        unscale_slice(&volts, &mut round_trip, gain, 0.0, Rounding::NearestEven);
+        assert_eq!(round_trip, counts);
+    }
+}
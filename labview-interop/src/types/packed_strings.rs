@@ -0,0 +1,245 @@
+//! Transfer many strings as one byte array and an offsets array.
+//!
+//! Returning an array of strings needs a handle for every element, and for
+//! thousands of short strings those allocations dominate the cost of the call.
+//! The packed format instead puts every string end to end in a `u8` array with
+//! an `i32` array of offsets into it. The offsets array has one more element
+//! than there are strings so string `i` is the bytes from `offsets[i]` up to
+//! `offsets[i + 1]`.
+//!
+//! # LabVIEW Side
+//!
+//! Configure both parameters as "Array Handle" passed as "Handles by Value",
+//! with the data as a 1D U8 array and the offsets as a 1D I32 array.
+//!
+//! To decode, convert the data with Byte Array To String and wire the offsets
+//! into a For Loop with Delete From Array removing the first element to get the
+//! end offsets. Auto-index the offsets without their last element and the end
+//! offsets, and in the loop use String Subset with the offset and a length of
+//! the end offset minus the offset. Strings are in LabVIEW's encoding.
+//!
+//! # Example
+//! ```
+//! use labview_interop::errors::MgErr;
+//! use labview_interop::types::packed_strings::PackedStringsBuilder;
+//! use labview_interop::types::LVArrayHandle;
+//!
+//! #[no_mangle]
+//! pub extern "C" fn channel_names(
+//!     mut data: LVArrayHandle<1, u8>,
+//!     mut offsets: LVArrayHandle<1, i32>,
+//! ) -> MgErr {
+//!     let mut names = PackedStringsBuilder::new();
+//!     for channel in 0..1000 {
+//!         if let Err(error) = names.push_str(&format!("ai{channel}")) {
+//!             return error.into();
+//!         }
+//!     }
+//!     names.write_to(&mut data, &mut offsets).into()
+//! }
+//! ```
+
+#[cfg(feature = "link")]
+use super::array::LVArrayDims;
+use super::string::LV_ENCODING;
+use super::LVArrayHandle;
+use crate::errors::{LVInteropError, Result};
+
+/// Builds the packed format to return to LabVIEW.
+#[derive(Debug, Clone)]
+pub struct PackedStringsBuilder {
+    data: Vec<u8>,
+    offsets: Vec<i32>,
+}
+
+impl Default for PackedStringsBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PackedStringsBuilder {
+    pub fn new() -> Self {
+        Self::with_capacity(0, 0)
+    }
+
+    /// Create a builder with space for `strings` strings totalling `bytes` bytes.
+    pub fn with_capacity(strings: usize, bytes: usize) -> Self {
+        let mut offsets = Vec::with_capacity(strings + 1);
+        offsets.push(0);
+        Self {
+            data: Vec::with_capacity(bytes),
+            offsets,
+        }
+    }
+
+    /// Add a string as raw bytes.
+    ///
+    /// This fails if the total size would be beyond the `i32` range of the offsets.
+    pub fn push(&mut self, value: &[u8]) -> Result<()> {
+        let end = i32::try_from(self.data.len() + value.len())
+            .map_err(|_| LVInteropError::ArrayDimensionsOutOfRange)?;
+        self.data.extend_from_slice(value);
+        self.offsets.push(end);
+        Ok(())
+    }
+
+    /// Add a Rust string, encoding it in LabVIEW's encoding.
+    pub fn push_str(&mut self, value: &str) -> Result<()> {
+        let (buffer, _, _) = LV_ENCODING.encode(value);
+        self.push(&buffer)
+    }
+
+    /// The number of strings added.
+    pub fn len(&self) -> usize {
+        self.offsets.len() - 1
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The packed data and offsets.
+    pub fn as_parts(&self) -> (&[u8], &[i32]) {
+        (&self.data, &self.offsets)
+    }
+
+    /// Write the strings into the LabVIEW arrays, resizing them to fit.
+    #[cfg(feature = "link")]
+    pub fn write_to(
+        &self,
+        data: &mut LVArrayHandle<1, u8>,
+        offsets: &mut LVArrayHandle<1, i32>,
+    ) -> Result<()> {
+        data.resize_array(LVArrayDims::try_from(&[self.data.len()])?)?;
+        offsets.resize_array(LVArrayDims::try_from(&[self.offsets.len()])?)?;
+        unsafe { data.as_ref_mut()? }
+            .data_as_slice_mut()
+            .copy_from_slice(&self.data);
+        unsafe { offsets.as_ref_mut()? }
+            .data_as_slice_mut()
+            .copy_from_slice(&self.offsets);
+        Ok(())
+    }
+}
+
+impl<S: AsRef<str>> FromIterator<S> for PackedStringsBuilder {
+    /// Collect strings, skipping any that would overflow the offsets.
+    fn from_iter<I: IntoIterator<Item = S>>(iter: I) -> Self {
+        let mut builder = Self::new();
+        for value in iter {
+            let _ = builder.push_str(value.as_ref());
+        }
+        builder
+    }
+}
+
+/// Packed strings received from LabVIEW.
+#[derive(Debug, Clone, Copy)]
+pub struct PackedStrings<'a> {
+    data: &'a [u8],
+    offsets: &'a [i32],
+}
+
+impl<'a> PackedStrings<'a> {
+    /// Check the offsets are in order and inside the data.
+    ///
+    /// Empty offsets are accepted as no strings.
+    pub fn from_slices(data: &'a [u8], offsets: &'a [i32]) -> Result<Self> {
+        let in_range =
+            |offset: i32| usize::try_from(offset).is_ok_and(|offset| offset <= data.len());
+        if !offsets.iter().all(|offset| in_range(*offset))
+            || offsets.windows(2).any(|pair| pair[0] > pair[1])
+        {
+            return Err(LVInteropError::ValidationFailed(format!(
+                "packed string offsets must increase and be within the {} bytes of data",
+                data.len()
+            )));
+        }
+        Ok(Self { data, offsets })
+    }
+
+    /// Read the strings from LabVIEW arrays. Null handles are empty arrays.
+    pub fn from_handles(
+        data: &'a LVArrayHandle<1, u8>,
+        offsets: &'a LVArrayHandle<1, i32>,
+    ) -> Result<Self> {
+        let data = unsafe { data.as_ref() }
+            .map(|array| array.data_as_slice())
+            .unwrap_or_default();
+        let offsets = unsafe { offsets.as_ref() }
+            .map(|array| array.data_as_slice())
+            .unwrap_or_default();
+        Self::from_slices(data, offsets)
+    }
+
+    pub fn len(&self) -> usize {
+        self.offsets.len().saturating_sub(1)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Get the bytes of string `index`.
+    pub fn get(&self, index: usize) -> Option<&'a [u8]> {
+        let start = *self.offsets.get(index)? as usize;
+        let end = *self.offsets.get(index + 1)? as usize;
+        Some(&self.data[start..end])
+    }
+
+    /// Iterate over the bytes of each string.
+    pub fn iter(&self) -> impl Iterator<Item = &'a [u8]> + 'a {
+        let data = self.data;
+        self.offsets
+            .windows(2)
+            .map(move |pair| &data[pair[0] as usize..pair[1] as usize])
+    }
+
+    /// Iterate over the strings decoded from LabVIEW's encoding.
+    pub fn iter_str(&self) -> impl Iterator<Item = std::borrow::Cow<'a, str>> + 'a {
+        self.iter()
+            .map(|bytes| LV_ENCODING.decode_without_bom_handling(bytes).0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builder_round_trip() {
+        let builder: PackedStringsBuilder = ["ai0", "", "ai10"].into_iter().collect();
+        let (data, offsets) = builder.as_parts();
+        assert_eq!(data, b"ai0ai10");
+        assert_eq!(offsets, [0, 3, 3, 7]);
+
+        let strings = PackedStrings::from_slices(data, offsets).unwrap();
+        assert_eq!(strings.len(), 3);
+        assert_eq!(strings.get(2), Some(&b"ai10"[..]));
+        assert_eq!(strings.get(3), None);
+        assert_eq!(strings.iter_str().collect::<Vec<_>>(), ["ai0", "", "ai10"]);
+    }
+
+    #[test]
+    fn test_invalid_offsets() {
+        assert!(PackedStrings::from_slices(b"abc", &[0, 4]).is_err());
+        assert!(PackedStrings::from_slices(b"abc", &[0, 2, 1]).is_err());
+        assert!(PackedStrings::from_slices(b"abc", &[-1, 2]).is_err());
+        assert!(PackedStrings::from_slices(b"", &[]).unwrap().is_empty());
+    }
+
+    #[cfg(feature = "link")]
+    #[test]
+    fn test_write_to_arrays() {
+        use crate::types::LVArrayOwned;
+
+        let mut data = LVArrayOwned::<1, u8>::new([0].into()).unwrap();
+        let mut offsets = LVArrayOwned::<1, i32>::new([0].into()).unwrap();
+        let builder: PackedStringsBuilder = ["one", "two"].into_iter().collect();
+        builder.write_to(&mut data, &mut offsets).unwrap();
+
+        let strings = PackedStrings::from_handles(&data, &offsets).unwrap();
+        assert_eq!(strings.iter().collect::<Vec<_>>(), [b"one", b"two"]);
+    }
+}
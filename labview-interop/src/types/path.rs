@@ -0,0 +1,211 @@
+//! LabVIEW paths.
+//!
+//! A path is passed from a path terminal as a pointer to LabVIEW's own
+//! path structure, which isn't documented. Set the terminal to
+//! `adapt to type` and `handles by value` to receive an [`LVPathHandle`].
+//!
+//! With the `link` feature paths convert to and from [`PathBuf`] using
+//! LabVIEW's own conversion to the platform path text, so components and
+//! separators match what Path To String gives on the diagram.
+//!
+//! # Example
+//! ```
+//! use std::path::PathBuf;
+//! use labview_interop::errors::MgErr;
+//! use labview_interop::types::{LVPathHandle, LVPathOwned};
+//!
+//! #[no_mangle]
+//! pub extern "C" fn log_directory(file: LVPathHandle, directory: *mut LVPathHandle) -> MgErr {
+//!     let result = (|| {
+//!         let file = PathBuf::try_from(file)?;
+//!         let directory_path = LVPathOwned::try_from(file.with_file_name("logs"))?;
+//!         unsafe { directory_path.write_to(directory) }
+//!     })();
+//!     result.into()
+//! }
+//! ```
+
+use std::ffi::c_void;
+#[cfg(feature = "link")]
+use std::path::{Path, PathBuf};
+
+#[cfg(feature = "link")]
+use crate::errors::{LVInteropError, Result};
+#[cfg(feature = "link")]
+use crate::labview::file_api;
+#[cfg(feature = "link")]
+use crate::types::string::{LStrOwned, LV_ENCODING};
+
+/// A LabVIEW path as passed from a path terminal.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(transparent)]
+pub struct LVPathHandle(*mut c_void);
+
+impl LVPathHandle {
+    /// Wrap a path pointer from LabVIEW.
+    ///
+    /// # Safety
+    ///
+    /// The pointer must be null or a valid LabVIEW path.
+    pub unsafe fn from_raw(path: *mut c_void) -> Self {
+        Self(path)
+    }
+
+    pub fn as_raw(&self) -> *mut c_void {
+        self.0
+    }
+
+    pub fn is_null(&self) -> bool {
+        self.0.is_null()
+    }
+}
+
+#[cfg(feature = "link")]
+impl LVPathHandle {
+    /// Convert the path to a [`PathBuf`] in the platform format.
+    pub fn to_path_buf(&self) -> Result<PathBuf> {
+        let api = file_api()?;
+        if self.is_null() {
+            return Err(LVInteropError::InvalidHandle);
+        }
+        let mut text = LStrOwned::from_data(&[])?;
+        unsafe { api.path_to_string(self.0, text.handle_value_ptr()) }.to_result(())?;
+        let text = unsafe { text.as_ref()? }.to_rust_string();
+        Ok(PathBuf::from(text.into_owned()))
+    }
+}
+
+#[cfg(feature = "link")]
+impl TryFrom<LVPathHandle> for PathBuf {
+    type Error = LVInteropError;
+
+    fn try_from(path: LVPathHandle) -> Result<Self> {
+        path.to_path_buf()
+    }
+}
+
+/// A LabVIEW path allocated from Rust.
+///
+/// The path is disposed when this is dropped unless it is handed to LabVIEW
+/// with [`LVPathOwned::write_to`] or [`LVPathOwned::into_raw`].
+#[cfg(feature = "link")]
+#[derive(Debug)]
+pub struct LVPathOwned(LVPathHandle);
+
+#[cfg(feature = "link")]
+impl LVPathOwned {
+    /// Create a LabVIEW path from a path in the platform format.
+    ///
+    /// The path text must be representable in LabVIEW's encoding.
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let api = file_api()?;
+        let invalid = || LVInteropError::InvalidPath(path.to_path_buf());
+        let text = path.to_str().ok_or_else(invalid)?;
+        let (bytes, _, unmappable) = LV_ENCODING.encode(text);
+        if unmappable {
+            return Err(invalid());
+        }
+        let length = i32::try_from(bytes.len()).map_err(|_| invalid())?;
+        let mut raw = std::ptr::null_mut();
+        unsafe { api.text_to_path(bytes.as_ptr(), length, &mut raw) }.to_result(())?;
+        if raw.is_null() {
+            return Err(invalid());
+        }
+        #[cfg(feature = "leak-tracking")]
+        crate::leak_tracking::track(raw.cast(), "LVPath");
+        Ok(Self(LVPathHandle(raw)))
+    }
+
+    /// The path for passing to functions such as [`LVFile::open`](crate::file::LVFile::open).
+    pub fn handle(&self) -> LVPathHandle {
+        self.0
+    }
+
+    /// Give up ownership of the path. It must then be disposed by LabVIEW.
+    pub fn into_raw(self) -> LVPathHandle {
+        let path = self.0;
+        #[cfg(feature = "leak-tracking")]
+        crate::leak_tracking::untrack(path.0.cast());
+        std::mem::forget(self);
+        path
+    }
+
+    /// Replace the path in a `pointers to handles` output parameter.
+    ///
+    /// The path previously in `output` is disposed and LabVIEW takes
+    /// ownership of this one.
+    ///
+    /// # Safety
+    ///
+    /// `output` must be null or point to a path owned by LabVIEW.
+    pub unsafe fn write_to(self, output: *mut LVPathHandle) -> Result<()> {
+        let output = output.as_mut().ok_or(LVInteropError::InvalidHandle)?;
+        if !output.is_null() {
+            file_api()?.dispose_path(output.0).to_result(())?;
+        }
+        *output = self.into_raw();
+        Ok(())
+    }
+}
+
+#[cfg(feature = "link")]
+impl Drop for LVPathOwned {
+    fn drop(&mut self) {
+        #[cfg(feature = "leak-tracking")]
+        crate::leak_tracking::untrack(self.0 .0.cast());
+        if let Ok(api) = file_api() {
+            unsafe { api.dispose_path(self.0 .0) };
+        }
+    }
+}
+
+#[cfg(feature = "link")]
+impl TryFrom<&Path> for LVPathOwned {
+    type Error = LVInteropError;
+
+    fn try_from(path: &Path) -> Result<Self> {
+        Self::from_path(path)
+    }
+}
+
+#[cfg(feature = "link")]
+impl TryFrom<PathBuf> for LVPathOwned {
+    type Error = LVInteropError;
+
+    fn try_from(path: PathBuf) -> Result<Self> {
+        Self::from_path(&path)
+    }
+}
+
+#[cfg(feature = "link")]
+impl crate::memory::DeepDispose for LVPathHandle {
+    /// Dispose the path. A null path is empty so is skipped.
+    unsafe fn deep_dispose(self) -> Result<()> {
+        if self.is_null() {
+            return Ok(());
+        }
+        file_api()?.dispose_path(self.0).to_result(())
+    }
+}
+
+/// A null path is an empty path in arrays.
+#[cfg(feature = "link")]
+unsafe impl crate::types::array::ArrayElement for LVPathHandle {}
+
+#[cfg(all(test, feature = "link"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_conversions_without_labview() {
+        let path = unsafe { LVPathHandle::from_raw(std::ptr::null_mut()) };
+        assert!(matches!(
+            PathBuf::try_from(path),
+            Err(LVInteropError::NoLabviewApi)
+        ));
+        assert!(matches!(
+            LVPathOwned::try_from(Path::new("/tmp/data.tdms")),
+            Err(LVInteropError::NoLabviewApi)
+        ));
+    }
+}
@@ -0,0 +1,133 @@
+//! Typed LabVIEW refnums.
+//!
+//! LabVIEW passes refnums such as queues, user events and DAQmx tasks as a
+//! 32 bit magic cookie. [`LVRefNum`] wraps the cookie with a tag type so
+//! refnums of different kinds can't be mixed up in Rust code. The tag is only
+//! a marker so can be any type, such as an empty enum.
+//!
+//! From LabVIEW set the terminal to be `adapt to type` and `handles by value`
+//! for refnum controls, or an unsigned 32 bit integer for the raw value.
+//!
+//! # Example
+//! ```
+//! use labview_interop::types::LVRefNum;
+//!
+//! /// Tags a refnum as a DAQmx task.
+//! enum DaqmxTask {}
+//!
+//! fn start(task: LVRefNum<DaqmxTask>) -> bool {
+//!     !task.is_null()
+//! }
+//!
+//! assert!(!start(LVRefNum::null()));
+//! assert!(start(LVRefNum::from_raw(0x2000_0001)));
+//! ```
+
+use std::fmt::{Debug, Formatter};
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+
+use crate::memory::MagicCookie;
+
+/// A LabVIEW refnum of the kind identified by `Tag`.
+///
+/// This has the same layout as the magic cookie so can be used directly in
+/// parameters and clusters.
+#[repr(transparent)]
+pub struct LVRefNum<Tag> {
+    cookie: MagicCookie,
+    // The function pointer keeps the refnum `Send` and `Sync` whatever the tag is.
+    _tag: PhantomData<fn() -> Tag>,
+}
+
+impl<Tag> LVRefNum<Tag> {
+    /// The refnum LabVIEW uses for "not a refnum".
+    pub const fn null() -> Self {
+        Self::from_cookie(MagicCookie::NULL)
+    }
+
+    /// Wrap a raw refnum value.
+    pub const fn from_raw(value: u32) -> Self {
+        Self::from_cookie(MagicCookie::from_raw(value))
+    }
+
+    pub(crate) const fn from_cookie(cookie: MagicCookie) -> Self {
+        Self {
+            cookie,
+            _tag: PhantomData,
+        }
+    }
+
+    /// The raw refnum value.
+    pub const fn as_raw(&self) -> u32 {
+        self.cookie.as_raw()
+    }
+
+    #[cfg(feature = "sync")]
+    pub(crate) const fn cookie(&self) -> MagicCookie {
+        self.cookie
+    }
+
+    /// Whether this is "not a refnum", as LabVIEW's Not A Number/Path/Refnum checks.
+    pub const fn is_null(&self) -> bool {
+        self.as_raw() == 0
+    }
+}
+
+// These are implemented by hand so they don't require anything of the tag.
+
+impl<Tag> Clone for LVRefNum<Tag> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<Tag> Copy for LVRefNum<Tag> {}
+
+impl<Tag> PartialEq for LVRefNum<Tag> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cookie == other.cookie
+    }
+}
+
+impl<Tag> Eq for LVRefNum<Tag> {}
+
+impl<Tag> Hash for LVRefNum<Tag> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_raw().hash(state);
+    }
+}
+
+impl<Tag> Default for LVRefNum<Tag> {
+    fn default() -> Self {
+        Self::null()
+    }
+}
+
+impl<Tag> Debug for LVRefNum<Tag> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let tag = std::any::type_name::<Tag>();
+        let tag = tag.rsplit("::").next().unwrap_or(tag);
+        write!(f, "LVRefNum<{tag}>({:#010x})", self.as_raw())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    enum Queue {}
+
+    #[test]
+    fn test_null_refnum() {
+        let refnum: LVRefNum<Queue> = LVRefNum::default();
+        assert!(refnum.is_null());
+        assert!(!LVRefNum::<Queue>::from_raw(7).is_null());
+    }
+
+    #[test]
+    fn test_debug_names_tag() {
+        let refnum = LVRefNum::<Queue>::from_raw(0x1234);
+        assert_eq!(format!("{refnum:?}"), "LVRefNum<Queue>(0x00001234)");
+    }
+}
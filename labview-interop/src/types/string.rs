@@ -5,9 +5,12 @@
 use encoding_rs::Encoding;
 use std::borrow::Cow;
 
-use crate::errors::Result;
+use crate::errors::{LVInteropError, Result};
 use crate::labview_layout;
+#[cfg(feature = "link")]
+use crate::memory::OwnedUHandle;
 use crate::memory::{UHandle, UPtr};
+use crate::validation::{validation_enabled, ValidationLevel};
 
 #[cfg(target_os = "windows")]
 fn get_encoding() -> &'static Encoding {
@@ -19,10 +22,9 @@ fn get_encoding() -> &'static Encoding {
     //SAFETY: No real concerns with this call.
     let code_page = unsafe { GetACP() };
 
-    // Crap - ctor errors again. I think it is reasonably safe
-    // to assume LabVIEW isn't going to hit anything to unusual
-    // due to it's level of support.
-    codepage::to_encoding(code_page as u16).expect("Unknown code page.")
+    // This runs in a ctor so can't report an error. LabVIEW only supports
+    // the common code pages so fall back to Windows-1252 for anything else.
+    codepage::to_encoding(code_page as u16).unwrap_or(encoding_rs::WINDOWS_1252)
 }
 
 #[cfg(target_os = "linux")]
@@ -39,6 +41,26 @@ fn get_encoding() -> &'static Encoding {
 /// The encoding that LabVIEW uses on the current platform.
 pub(crate) static LV_ENCODING: &'static Encoding = get_encoding();
 
+/// The encoding LabVIEW uses for strings on the current platform.
+///
+/// This is the ANSI code page on Windows, Windows-1252 on Linux and UTF-8 on
+/// macOS. It is used by the methods without an explicit encoding such as
+/// [`LStr::to_rust_string`] and [`LStrHandle::set_str`]. Use the
+/// `_with_encoding` methods where the text is known to be in another encoding,
+/// for example UTF-8 from an instrument.
+///
+/// # Example
+/// ```
+/// use labview_interop::encoding_rs::UTF_8;
+/// use labview_interop::types::string::labview_encoding;
+///
+/// let (bytes, _, _) = labview_encoding().encode("plain ascii");
+/// assert_eq!(bytes, UTF_8.encode("plain ascii").0);
+/// ```
+pub fn labview_encoding() -> &'static Encoding {
+    &LV_ENCODING
+}
+
 labview_layout!(
     /// Internal LabVIEW string structure.
     ///
@@ -50,12 +72,51 @@ labview_layout!(
     }
 );
 
+/// The size of the length header in front of the string data.
+#[cfg(feature = "link")]
+const LSTR_HEADER_SIZE: usize = std::mem::size_of::<i32>();
+
 /// Definition of a handle to an LabVIEW String. Helper for FFI definition and
 /// required for any functions that need to resize the string.
 pub type LStrHandle = UHandle<LStr>;
 /// Definition of a pointer to an LabVIEW String. Helper for FFI definition.
 pub type LStrPtr = UPtr<LStr>;
 
+/// Strings hold no handles so only the string itself is disposed.
+#[cfg(feature = "link")]
+impl crate::memory::DisposeContents for LStr {
+    unsafe fn dispose_contents(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(feature = "link")]
+impl crate::memory::DeepCopy for LStrHandle {
+    unsafe fn deep_copy(&self) -> Result<Self> {
+        let mut copy: Self = UHandle(std::ptr::null_mut());
+        copy.deep_copy_from(self)?;
+        Ok(copy)
+    }
+
+    /// Copy the string into this handle, resizing it. A null handle is allocated.
+    unsafe fn deep_copy_from(&mut self, source: &Self) -> Result<()> {
+        if self.0 == source.0 {
+            return Ok(());
+        }
+        let data = if source.is_null() {
+            &[]
+        } else {
+            source.as_bytes()?
+        };
+        if self.is_null() {
+            *self = LStrOwned::from_data(data)?.into_raw();
+            Ok(())
+        } else {
+            self.set(data)
+        }
+    }
+}
+
 impl LStr {
     /// Access the data from the string as a binary slice.
     pub fn as_slice(&self) -> &[u8] {
@@ -72,36 +133,153 @@ impl LStr {
         unsafe { std::slice::from_raw_parts_mut(self.data.as_mut_ptr(), self.size as usize) }
     }
 
-    /// Uses a system appropriate decoder to return a rust compatible string.
+    /// Decode the string with the provided encoding.
     ///
     /// This returns a [`std::borrow::Cow`] to avoid any allocations if the
-    /// input is already valid UTF8.
-    pub fn to_rust_string_with_encoding(&self, encoding: &'static Encoding) -> Cow<str> {
+    /// input is already valid UTF8. Invalid bytes are replaced with U+FFFD.
+    pub fn to_rust_string_with_encoding(&self, encoding: &'static Encoding) -> Cow<'_, str> {
         let (result, _, _) = encoding.decode(self.as_slice());
         result
     }
 
+    /// Decode the string with the provided encoding, failing on invalid bytes.
+    ///
+    /// # Errors
+    ///
+    /// * [`LVInteropError::InvalidStringEncoding`] if the bytes aren't valid in the encoding.
+    pub fn try_to_rust_string_with_encoding(
+        &self,
+        encoding: &'static Encoding,
+    ) -> Result<Cow<'_, str>> {
+        encoding
+            .decode_without_bom_handling_and_without_replacement(self.as_slice())
+            .ok_or(LVInteropError::InvalidStringEncoding(encoding.name()))
+    }
+
+    /// Decode the string with the system encoding, failing on invalid bytes.
+    ///
+    /// Use this where binary data coming through a string must be rejected
+    /// rather than silently changed.
+    ///
+    /// # Errors
+    ///
+    /// * [`LVInteropError::InvalidStringEncoding`] if the bytes aren't valid in the encoding.
+    pub fn try_to_rust_string(&self) -> Result<Cow<'_, str>> {
+        self.try_to_rust_string_with_encoding(&LV_ENCODING)
+    }
+
+    /// Decode the string with the system encoding, replacing invalid bytes with U+FFFD.
+    ///
+    /// This is the same as [`LStr::to_rust_string`] but names the behaviour.
+    pub fn to_rust_string_lossy(&self) -> Cow<'_, str> {
+        self.to_rust_string()
+    }
+
     /// Uses a system appropriate decoder to return a rust compatible string.
     ///
     /// This returns a [`std::borrow::Cow`] to avoid any allocations if the
-    /// input is already valid UTF8.
+    /// input is already valid UTF8. Invalid bytes are replaced with U+FFFD, use
+    /// [`LStr::try_to_rust_string`] to reject them instead.
     ///
     /// # Example
     /// ```
     /// use labview_interop::types::LStrHandle;
     /// use labview_interop::errors::MgErr;
     /// #[no_mangle]
-    /// pub extern "C" fn string_check(mut string: LStrHandle) -> MgErr {
-    ///    let string_value = string.to_string();
-    ///    format!("Read value: {string_value}");
-    ///    MgErr::NO_ERROR
+    /// pub extern "C" fn string_check(string: LStrHandle) -> MgErr {
+    ///    match unsafe { string.as_ref() } {
+    ///        Ok(string) => {
+    ///            let string_value = string.to_rust_string();
+    ///            format!("Read value: {string_value}");
+    ///            MgErr::NO_ERROR
+    ///        }
+    ///        Err(error) => error.into(),
+    ///    }
     /// }
     //```
-    pub fn to_rust_string(&self) -> Cow<str> {
+    pub fn to_rust_string(&self) -> Cow<'_, str> {
         self.to_rust_string_with_encoding(&LV_ENCODING)
     }
 }
 
+impl LStrHandle {
+    /// The string data as raw bytes.
+    ///
+    /// LabVIEW strings often carry binary data, such as VISA reads or
+    /// flattened data, which this returns without any decoding.
+    ///
+    /// # Errors
+    ///
+    /// * This will error if the string handle is invalid (likely a null pointer).
+    ///
+    /// # Example
+    /// ```
+    /// use labview_interop::types::LStrHandle;
+    /// use labview_interop::errors::MgErr;
+    /// #[no_mangle]
+    /// pub extern "C" fn checksum(data: LStrHandle, sum: *mut u8) -> MgErr {
+    ///     let bytes = match data.as_bytes() {
+    ///         Ok(bytes) => bytes,
+    ///         Err(error) => return error.into(),
+    ///     };
+    ///     let total = bytes.iter().fold(0u8, |total, byte| total.wrapping_add(*byte));
+    ///     unsafe { *sum = total };
+    ///     MgErr::NO_ERROR
+    /// }
+    /// ```
+    pub fn as_bytes(&self) -> Result<&[u8]> {
+        Ok(unsafe { self.as_ref()? }.as_slice())
+    }
+
+    /// The string data as mutable raw bytes.
+    ///
+    /// This can change the bytes but not the length. Use [`LStrHandle::set_bytes`]
+    /// to change the length.
+    ///
+    /// # Errors
+    ///
+    /// * This will error if the string handle is invalid (likely a null pointer).
+    pub fn as_bytes_mut(&mut self) -> Result<&mut [u8]> {
+        Ok(unsafe { self.as_ref_mut()? }.as_mut_slice())
+    }
+
+    /// Check the string is consistent at the current [validation level](crate::validation).
+    ///
+    /// A null handle is a valid empty string.
+    pub fn validate(&self) -> Result<()> {
+        if !validation_enabled(ValidationLevel::Basic) {
+            return Ok(());
+        }
+        let Ok(string) = (unsafe { self.as_ref() }) else {
+            return Ok(());
+        };
+        let size = string.size;
+        if size < 0 {
+            return Err(LVInteropError::ValidationFailed(format!(
+                "string has negative size {size}"
+            )));
+        }
+
+        #[cfg(feature = "link")]
+        if validation_enabled(ValidationLevel::Paranoid) {
+            let capacity = self.capacity()?;
+            if capacity < size as usize {
+                return Err(LVInteropError::ValidationFailed(format!(
+                    "string handle holds {capacity} bytes but has size {size}"
+                )));
+            }
+            unsafe {
+                crate::validation::canary::check(
+                    self.handle_value(),
+                    LSTR_HEADER_SIZE + size as usize,
+                    self.handle_size()?,
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
 impl std::fmt::Display for LStr {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", self.to_rust_string())
@@ -120,6 +298,139 @@ impl PartialEq for LStr {
     }
 }
 
+labview_layout!(
+    /// A Pascal string, with a single byte length in front of the data.
+    ///
+    /// Some older LabVIEW and memory manager functions use these in place of
+    /// [`LStr`] so they hold at most 255 bytes. C passes them as a plain
+    /// pointer to the length byte so receive them as `*const u8` and use
+    /// [`PStr::from_ptr`].
+    pub struct PStr {
+        size: u8,
+        data: [u8],
+    }
+);
+
+/// The longest string a [`PStr`] can hold.
+pub const PSTR_MAX_LENGTH: usize = u8::MAX as usize;
+
+impl PStr {
+    /// Read a Pascal string from a pointer to its length byte.
+    ///
+    /// # Safety
+    ///
+    /// The pointer must be null or point to a Pascal string which lives and
+    /// isn't modified for the lifetime `'a`.
+    pub unsafe fn from_ptr<'a>(ptr: *const u8) -> Result<&'a PStr> {
+        if ptr.is_null() {
+            return Err(LVInteropError::InvalidHandle);
+        }
+        let length = *ptr as usize;
+        Ok(&*(std::ptr::slice_from_raw_parts(ptr, length) as *const PStr))
+    }
+
+    /// Access the data from the string as a binary slice.
+    pub fn as_slice(&self) -> &[u8] {
+        &self.data[..self.size as usize]
+    }
+
+    /// Decode the string with the provided encoding.
+    pub fn to_rust_string_with_encoding(&self, encoding: &'static Encoding) -> Cow<'_, str> {
+        let (result, _, _) = encoding.decode(self.as_slice());
+        result
+    }
+
+    /// Uses a system appropriate decoder to return a rust compatible string.
+    pub fn to_rust_string(&self) -> Cow<'_, str> {
+        self.to_rust_string_with_encoding(&LV_ENCODING)
+    }
+}
+
+impl std::fmt::Display for PStr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_rust_string())
+    }
+}
+
+impl std::fmt::Debug for PStr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "\"{}\"", self.to_rust_string())
+    }
+}
+
+impl PartialEq for PStr {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_slice() == other.as_slice()
+    }
+}
+
+/// A Pascal string built in Rust for passing to LabVIEW functions.
+///
+/// # Example
+/// ```
+/// use labview_interop::types::string::{PStr, PStrBuf};
+///
+/// let name = PStrBuf::try_from("data.bin").unwrap();
+/// let pstr = unsafe { PStr::from_ptr(name.as_ptr()) }.unwrap();
+/// assert_eq!(pstr.to_rust_string(), "data.bin");
+/// ```
+#[derive(Clone, PartialEq, Eq)]
+pub struct PStrBuf(Vec<u8>);
+
+impl PStrBuf {
+    /// Create a Pascal string holding the binary value.
+    ///
+    /// This fails if the value is longer than [`PSTR_MAX_LENGTH`].
+    pub fn from_data(value: &[u8]) -> Result<Self> {
+        let length = u8::try_from(value.len())
+            .map_err(|_| LVInteropError::StringTooLong(value.len(), PSTR_MAX_LENGTH))?;
+        let mut buffer = Vec::with_capacity(value.len() + 1);
+        buffer.push(length);
+        buffer.extend_from_slice(value);
+        Ok(Self(buffer))
+    }
+
+    /// Create a Pascal string from a Rust string encoded with the provided encoder.
+    pub fn from_str_with_encoding(encoder: &'static Encoding, value: &str) -> Result<Self> {
+        let (buffer, _, _) = encoder.encode(value);
+        Self::from_data(&buffer)
+    }
+
+    /// The pointer to the length byte to pass to C.
+    pub fn as_ptr(&self) -> *const u8 {
+        self.0.as_ptr()
+    }
+
+    /// The length byte followed by the data.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl std::ops::Deref for PStrBuf {
+    type Target = PStr;
+
+    fn deref(&self) -> &PStr {
+        // Safety: the buffer always starts with the length of the data which follows.
+        unsafe { PStr::from_ptr(self.as_ptr()) }.expect("buffer is never null")
+    }
+}
+
+impl std::fmt::Debug for PStrBuf {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(&**self, f)
+    }
+}
+
+/// Encodes the string in the LabVIEW encoding.
+impl TryFrom<&str> for PStrBuf {
+    type Error = LVInteropError;
+
+    fn try_from(value: &str) -> Result<Self> {
+        Self::from_str_with_encoding(&LV_ENCODING, value)
+    }
+}
+
 /// Implement features that require a full string handle rather than just the [`LStr`]
 /// type.
 ///
@@ -155,11 +466,83 @@ impl LStrHandle {
 
             let l_str = self.as_ref_mut()?;
             l_str.size = input_length as i32;
-            for (value, output) in value.iter().zip(l_str.data.iter_mut()) {
-                *output = *value;
-            }
+            crate::labview::memory_api()?.move_block(
+                value.as_ptr().cast(),
+                l_str.data.as_mut_ptr().cast(),
+                input_length,
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Copy the string into `output` with `MoveBlock`.
+    ///
+    /// The length of `output` must match the length of the string. Use
+    /// [`LStrHandle::set`] to copy a slice into the string.
+    ///
+    /// # Errors
+    ///
+    /// * [`LVInteropError::ArrayDimensionMismatch`] if the lengths don't match.
+    pub fn copy_to_slice(&self, output: &mut [u8]) -> Result<()> {
+        let data = self.as_bytes()?;
+        if data.len() != output.len() {
+            return Err(LVInteropError::ArrayDimensionMismatch);
+        }
+        unsafe {
+            crate::labview::memory_api()?.move_block(
+                data.as_ptr().cast(),
+                output.as_mut_ptr().cast(),
+                data.len(),
+            )
+        };
+        Ok(())
+    }
+
+    /// Set the string to the raw bytes, resizing the handle to fit.
+    ///
+    /// This is the same as [`LStrHandle::set`] and is named to pair with
+    /// [`LStrHandle::as_bytes`].
+    pub fn set_bytes(&mut self, value: &[u8]) -> Result<()> {
+        self.set(value)
+    }
+
+    /// The number of bytes the handle can hold without being resized.
+    ///
+    /// # Errors
+    ///
+    /// * This will error if the string handle is invalid (likely a null pointer).
+    pub fn capacity(&self) -> Result<usize> {
+        let handle_size = unsafe { self.handle_size()? };
+        let canary = crate::validation::canary::reserved(self.handle_value(), handle_size);
+        Ok(handle_size.saturating_sub(LSTR_HEADER_SIZE + canary))
+    }
+
+    /// Grow the handle so that it can hold at least `capacity` bytes.
+    ///
+    /// This does not change the string contents and does nothing if the
+    /// handle is already large enough.
+    pub fn reserve(&mut self, capacity: usize) -> Result<()> {
+        if self.capacity()? < capacity {
+            unsafe { self.resize(capacity + LSTR_HEADER_SIZE)? };
+        }
+        Ok(())
+    }
+
+    /// Set the string as a binary value, reusing the existing allocation.
+    ///
+    /// Unlike [`LStrHandle::set`] this will only resize the handle if the value
+    /// doesn't fit in the current capacity, so a handle prepared with
+    /// [`LStrHandle::reserve`] can be written repeatedly without any calls
+    /// to the memory manager beyond the capacity check.
+    pub fn set_in_place(&mut self, value: &[u8]) -> Result<()> {
+        if self.capacity()? < value.len() {
+            return self.set(value);
         }
 
+        let l_str = unsafe { self.as_ref_mut()? };
+        l_str.size = value.len() as i32;
+        l_str.as_mut_slice().copy_from_slice(value);
         Ok(())
     }
 
@@ -191,4 +574,164 @@ impl LStrHandle {
         let (buffer, _, _) = encoder.encode(value);
         self.set(&buffer)
     }
+
+    /// Set a Rust string into the LabVIEW string reusing the existing allocation.
+    ///
+    /// This is the [`LStrHandle::set_str`] equivalent of [`LStrHandle::set_in_place`].
+    pub fn set_str_in_place(&mut self, value: &str) -> Result<()> {
+        let (buffer, _, _) = LV_ENCODING.encode(value);
+        self.set_in_place(&buffer)
+    }
+}
+
+/// A string handle allocated by this library which is disposed when dropped.
+///
+/// This derefs to an [`LStrHandle`] so all of the handle methods are available.
+/// Use [`OwnedUHandle::into_raw`] to hand the string to LabVIEW, which then
+/// owns it.
+///
+/// # Example
+/// ```
+/// use labview_interop::types::LStrOwned;
+///
+/// let string = LStrOwned::try_from("Hello World").unwrap();
+/// let text = unsafe { string.as_ref() }.unwrap().to_rust_string();
+/// assert_eq!(text, "Hello World");
+/// ```
+#[cfg(feature = "link")]
+pub type LStrOwned = OwnedUHandle<LStr>;
+
+#[cfg(feature = "link")]
+impl LStrOwned {
+    /// Allocate a new string holding the binary value.
+    pub fn from_data(value: &[u8]) -> Result<Self> {
+        let mut handle = Self::allocate(LSTR_HEADER_SIZE + value.len())?;
+        handle.set(value)?;
+        Ok(handle)
+    }
+
+    /// Allocate a new string from a Rust string encoded with the provided encoder.
+    pub fn from_str_with_encoding(encoder: &'static Encoding, value: &str) -> Result<Self> {
+        let (buffer, _, _) = encoder.encode(value);
+        Self::from_data(&buffer)
+    }
+}
+
+/// Encodes the string in the LabVIEW encoding.
+#[cfg(feature = "link")]
+impl TryFrom<&str> for LStrOwned {
+    type Error = LVInteropError;
+
+    fn try_from(value: &str) -> Result<Self> {
+        Self::from_str_with_encoding(&LV_ENCODING, value)
+    }
+}
+
+/// Encodes the string in the LabVIEW encoding.
+#[cfg(feature = "link")]
+impl TryFrom<String> for LStrOwned {
+    type Error = LVInteropError;
+
+    fn try_from(value: String) -> Result<Self> {
+        Self::try_from(value.as_str())
+    }
+}
+
+#[cfg(feature = "link")]
+impl std::str::FromStr for LStrOwned {
+    type Err = LVInteropError;
+
+    fn from_str(value: &str) -> Result<Self> {
+        Self::try_from(value)
+    }
+}
+
+/// Strings serialize as text decoded from LabVIEW's encoding. A null handle is
+/// an empty string.
+#[cfg(feature = "serde")]
+impl serde::Serialize for LStrHandle {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        match unsafe { self.as_ref() } {
+            Ok(string) => serializer.serialize_str(&string.to_rust_string()),
+            Err(_) => serializer.serialize_str(""),
+        }
+    }
+}
+
+/// Deserializing allocates a new handle which the caller must dispose, such as
+/// with [`DeepDispose`](crate::memory::DeepDispose) on the containing cluster.
+/// The handle has no drop so it is leaked if a later field of the cluster
+/// fails. Deserialize [`LStrOwned`] fields to have it disposed.
+#[cfg(all(feature = "serde", feature = "link"))]
+impl<'de> serde::Deserialize<'de> for LStrHandle {
+    fn deserialize<D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Self, D::Error> {
+        let string = <String as serde::Deserialize>::deserialize(deserializer)?;
+        LStrOwned::try_from(string)
+            .map(LStrOwned::into_raw)
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pstr_round_trip() {
+        let string = PStrBuf::try_from("Hello").unwrap();
+        assert_eq!(string.as_bytes(), b"\x05Hello");
+        assert_eq!(string.as_slice(), b"Hello");
+        assert_eq!(string.to_rust_string(), "Hello");
+
+        assert!(PStrBuf::from_data(&[0; PSTR_MAX_LENGTH]).is_ok());
+        assert!(matches!(
+            PStrBuf::from_data(&[0; PSTR_MAX_LENGTH + 1]),
+            Err(LVInteropError::StringTooLong(256, 255))
+        ));
+        assert!(unsafe { PStr::from_ptr(std::ptr::null()) }.is_err());
+    }
+
+    #[cfg(feature = "link")]
+    #[test]
+    fn test_strict_and_lossy_decoding() {
+        let invalid = LStrOwned::from_data(b"ok\xff").unwrap();
+        let invalid = unsafe { invalid.as_ref() }.unwrap();
+        assert!(matches!(
+            invalid.try_to_rust_string_with_encoding(encoding_rs::UTF_8),
+            Err(LVInteropError::InvalidStringEncoding("UTF-8"))
+        ));
+        assert_eq!(
+            invalid.to_rust_string_with_encoding(encoding_rs::UTF_8),
+            "ok\u{FFFD}"
+        );
+
+        let valid = LStrOwned::from_data(b"fine").unwrap();
+        let valid = unsafe { valid.as_ref() }.unwrap();
+        assert_eq!(valid.try_to_rust_string().unwrap(), "fine");
+        assert_eq!(valid.to_rust_string_lossy(), "fine");
+    }
+
+    #[cfg(feature = "link")]
+    #[test]
+    fn test_owned_string_from_str() {
+        let mut string: LStrOwned = "Hello".parse().unwrap();
+        assert_eq!(string.as_bytes().unwrap(), b"Hello");
+
+        string.set_str("A longer string").unwrap();
+        let text = unsafe { string.as_ref() }.unwrap().to_rust_string();
+        assert_eq!(text, "A longer string");
+
+        string.set_bytes(&[0, 159, 255]).unwrap();
+        string.as_bytes_mut().unwrap()[0] = 1;
+        assert_eq!(string.as_bytes().unwrap(), [1, 159, 255]);
+
+        let raw = LStrOwned::from_data(b"\x00\xff").unwrap().into_raw();
+        assert_eq!(unsafe { raw.as_ref() }.unwrap().as_slice(), [0, 255]);
+        drop(unsafe { LStrOwned::from_raw(raw) });
+    }
 }
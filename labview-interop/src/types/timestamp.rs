@@ -1,26 +1,51 @@
 //! Support for exchanging date and times. with LabVIEWs timestamp format.
 //!
-//! This includes binary formats, to and from 1904 epoch, unix (1970) epoch
-//! and optionally chrono DateTime with the `chrono` feature.
+//! This includes binary formats, to and from 1904 epoch, unix (1970) epoch,
+//! [`SystemTime`] and optionally chrono DateTime with the `chrono` feature.
 //!
 
+use std::cmp::Ordering;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
 use thiserror::Error;
 
 #[derive(Debug, Error)]
 pub enum LVTimeError {
     #[error("Cannot generate a chrono time as it is out of range.")]
     ChronoOutOfRange,
+    #[error("Cannot generate a system time as it is out of range.")]
+    SystemTimeOutOfRange,
 }
 
-// The LV Type consists really of a (i64, u64) but
-// the effective storage type is u128 so I've kept
-// it there for now.
-
 /// Mirrors the internal LabVIEW timestamp structure so
 /// it can be passed back and forward.
-#[repr(transparent)]
-#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
-pub struct LVTime(u128);
+///
+/// The timestamp is an i64 of seconds since the 1904 epoch followed by a u64
+/// of fractional seconds, stored as a little endian 128 bit value. It is kept
+/// as the two halves rather than a `u128` so it has the 8 byte alignment
+/// LabVIEW uses in clusters. On 32 bit [`labview_layout!`](crate::labview_layout)
+/// packs it like any other field.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct LVTime {
+    fractions: u64,
+    seconds: i64,
+}
+
+/// Timestamps hold no handles so there is nothing to dispose.
+#[cfg(feature = "link")]
+impl crate::memory::DeepDispose for LVTime {
+    unsafe fn deep_dispose(self) -> crate::errors::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(feature = "link")]
+impl crate::memory::DeepCopy for LVTime {
+    unsafe fn deep_copy(&self) -> crate::errors::Result<Self> {
+        Ok(*self)
+    }
+}
 
 ///The Unix Epoch in LabVIEW epoch seconds for shifting timestamps between them.
 pub const UNIX_EPOCH_IN_LV_SECONDS: f64 = 2082844800.0;
@@ -34,7 +59,7 @@ impl LVTime {
 
     ///Extract the seconds component which is referenced to the LabVIEW epoc.
     pub const fn seconds(&self) -> i64 {
-        (self.0 >> 64) as i64
+        self.seconds
     }
 
     /// From a double precision number which is the seconds
@@ -65,36 +90,34 @@ impl LVTime {
 
     /// Build from the full seconds and fractional second parts.
     pub const fn from_parts(seconds: i64, fractions: u64) -> Self {
-        let time = (seconds as u128) << 64 | (fractions as u128);
-        Self(time)
+        Self { fractions, seconds }
     }
 
     /// Seperate out the u64 components.
     #[inline]
     pub const fn to_parts(&self) -> (i64, u64) {
-        let fractions = (self.0 & 0xFFFF_FFFF_FFFF_FFFF) as u64;
-        (self.seconds(), fractions)
+        (self.seconds, self.fractions)
     }
 
     /// Load from u128 which is the storage format
     #[inline]
     const fn from_u128(repr: u128) -> Self {
-        Self(repr)
+        Self::from_parts((repr >> 64) as i64, repr as u64)
     }
 
     #[inline]
-    const fn as_u128(&self) -> &u128 {
-        &self.0
+    const fn to_u128(self) -> u128 {
+        (self.seconds as u128) << 64 | (self.fractions as u128)
     }
 
     /// To little endian bytes.
     pub const fn to_le_bytes(&self) -> [u8; 16] {
-        self.as_u128().to_le_bytes()
+        self.to_u128().to_le_bytes()
     }
 
     /// To big endian bytes.
     pub const fn to_be_bytes(&self) -> [u8; 16] {
-        self.as_u128().to_be_bytes()
+        self.to_u128().to_be_bytes()
     }
 
     /// From little endian bytes.
@@ -108,6 +131,75 @@ impl LVTime {
     }
 }
 
+impl Ord for LVTime {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.to_parts().cmp(&other.to_parts())
+    }
+}
+
+impl PartialOrd for LVTime {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Convert nanoseconds to fractional seconds.
+///
+/// This rounds up so converting back with [`fractions_to_nanos`] is exact.
+fn nanos_to_fractions(nanos: u32) -> u64 {
+    (((nanos as u128) << 64).div_ceil(1_000_000_000)) as u64
+}
+
+/// Convert fractional seconds to whole nanoseconds, rounding down.
+fn fractions_to_nanos(fractions: u64) -> u32 {
+    ((fractions as u128 * 1_000_000_000) >> 64) as u32
+}
+
+/// Build from seconds since the unix epoch and nanoseconds.
+fn from_unix_parts(seconds: i64, nanos: u32) -> LVTime {
+    LVTime::from_parts(
+        seconds + UNIX_EPOCH_IN_LV_SECONDS as i64,
+        nanos_to_fractions(nanos),
+    )
+}
+
+impl From<SystemTime> for LVTime {
+    fn from(value: SystemTime) -> Self {
+        match value.duration_since(UNIX_EPOCH) {
+            Ok(after) => from_unix_parts(after.as_secs() as i64, after.subsec_nanos()),
+            Err(before) => {
+                // Count back whole seconds then forward the fraction.
+                let before = before.duration();
+                let mut seconds = -(before.as_secs() as i64);
+                let mut nanos = before.subsec_nanos();
+                if nanos > 0 {
+                    seconds -= 1;
+                    nanos = 1_000_000_000 - nanos;
+                }
+                from_unix_parts(seconds, nanos)
+            }
+        }
+    }
+}
+
+impl TryFrom<LVTime> for SystemTime {
+    type Error = LVTimeError;
+
+    fn try_from(value: LVTime) -> Result<Self, Self::Error> {
+        let (seconds, fractions) = value.to_parts();
+        let unix_seconds = seconds - UNIX_EPOCH_IN_LV_SECONDS as i64;
+        let fraction = Duration::from_nanos(fractions_to_nanos(fractions) as u64);
+        let whole = Duration::from_secs(unix_seconds.unsigned_abs());
+        let time = if unix_seconds >= 0 {
+            UNIX_EPOCH.checked_add(whole)
+        } else {
+            UNIX_EPOCH.checked_sub(whole)
+        };
+        time.and_then(|time| time.checked_add(fraction))
+            .ok_or(LVTimeError::SystemTimeOutOfRange)
+    }
+}
+
 #[cfg(feature = "chrono")]
 mod chrono {
 
@@ -123,11 +215,19 @@ mod chrono {
         }
     }
 
+    impl From<DateTime<Utc>> for LVTime {
+        fn from(value: DateTime<Utc>) -> Self {
+            // Leap seconds are reported as over a billion nanoseconds.
+            let nanos = value.timestamp_subsec_nanos().min(999_999_999);
+            from_unix_parts(value.timestamp(), nanos)
+        }
+    }
+
     impl TryFrom<LVTime> for NaiveDateTime {
         type Error = LVTimeError;
 
         fn try_from(value: LVTime) -> Result<Self, Self::Error> {
-            let seconds_for_time: i64 = value.seconds() as i64 - UNIX_EPOCH_IN_LV_SECONDS as i64;
+            let seconds_for_time = value.seconds() - UNIX_EPOCH_IN_LV_SECONDS as i64;
             let nanoseconds = value.sub_seconds() * 1_000_000_000f64;
             Self::from_timestamp_opt(seconds_for_time, nanoseconds as u32)
                 .ok_or(LVTimeError::ChronoOutOfRange)
@@ -141,7 +241,7 @@ mod tests {
     #[test]
     fn test_to_from_parts() {
         let time = LVTime::from_parts(20, 0x8000_0000_0000_0000);
-        assert_eq!(time.0, 0x14_8000_0000_0000_0000);
+        assert_eq!(time.to_u128(), 0x14_8000_0000_0000_0000);
         assert_eq!((20, 0x8000_0000_0000_0000), time.to_parts());
     }
 
@@ -159,10 +259,32 @@ mod tests {
         assert_eq!(time, LVTime::from_unix_epoch(1676129672.5f64));
     }
 
+    #[test]
+    fn test_layout_matches_labview() {
+        assert_eq!(std::mem::size_of::<LVTime>(), 16);
+        assert_eq!(std::mem::align_of::<LVTime>(), std::mem::align_of::<u64>());
+        let time = LVTime::from_parts(-1, 5);
+        assert!(time < LVTime::from_parts(0, 0));
+    }
+
+    #[test]
+    fn test_system_time_round_trip() {
+        let after = UNIX_EPOCH + Duration::new(1_676_129_672, 123_456_789);
+        let time = LVTime::from(after);
+        assert_eq!(time.seconds(), 3758974472);
+        assert_eq!(SystemTime::try_from(time).unwrap(), after);
+
+        let before = UNIX_EPOCH - Duration::new(10, 250_000_000);
+        let time = LVTime::from(before);
+        assert_eq!(time.seconds(), UNIX_EPOCH_IN_LV_SECONDS as i64 - 11);
+        assert_eq!(time.sub_seconds(), 0.75);
+        assert_eq!(SystemTime::try_from(time).unwrap(), before);
+    }
+
     #[test]
     fn test_to_from_le_bytes() {
         let time = LVTime::from_parts(20, 0x8000_0000_0000_0000);
-        assert_eq!(time.0, 0x14_8000_0000_0000_0000);
+        assert_eq!(time.to_u128(), 0x14_8000_0000_0000_0000);
         let bytes = time.to_le_bytes();
         assert_eq!(
             bytes,
@@ -174,7 +296,7 @@ mod tests {
     #[test]
     fn test_to_from_be_bytes() {
         let time = LVTime::from_parts(20, 0x8000_0000_0000_0000);
-        assert_eq!(time.0, 0x14_8000_0000_0000_0000);
+        assert_eq!(time.to_u128(), 0x14_8000_0000_0000_0000);
         let bytes = time.to_be_bytes();
         assert_eq!(
             bytes,
@@ -209,4 +331,11 @@ mod chrono_tests {
         assert_eq!(date_time, expected);
         assert_eq!(naive, expected_naive)
     }
+
+    #[test]
+    fn lv_time_from_datetime() {
+        let naive = NaiveDateTime::from_timestamp_opt(1676129672, 500_000_000).unwrap();
+        let time = LVTime::from(DateTime::<Utc>::from_utc(naive, Utc));
+        assert_eq!(time, LVTime::from_parts(3758974472, 0x8000_0000_0000_0000));
+    }
 }
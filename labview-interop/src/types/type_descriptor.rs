@@ -0,0 +1,430 @@
+//! Parsing of LabVIEW type descriptors.
+//!
+//! A type descriptor describes a LabVIEW type as bytes. It is the type string
+//! from Flatten To String and describes the data in variants, so reading it
+//! lets data whose type is only known at run time be handled. See
+//! [`LvData::type_descriptor`](super::data::LvData::type_descriptor) for
+//! building them for Rust types.
+//!
+//! Each descriptor starts with its size in bytes as a big endian `u16`, a
+//! flags byte and the type code, followed by data for that type such as the
+//! elements of a cluster. If the type has a label it is at the end as a
+//! Pascal string.
+//!
+//! # Example
+//! ```
+//! use labview_interop::types::data::LvData;
+//! use labview_interop::types::type_descriptor::{NumericType, TypeDescriptor, TypeKind};
+//!
+//! let descriptor = TypeDescriptor::parse(&Vec::<f64>::type_descriptor()).unwrap();
+//! let TypeKind::Array { dimensions, element } = descriptor.kind else {
+//!     panic!("not an array");
+//! };
+//! assert_eq!(dimensions, [-1]);
+//! assert_eq!(element.kind, TypeKind::Numeric(NumericType::F64));
+//! ```
+
+use super::data::{take, type_code, TIMESTAMP_SUBTYPE};
+use super::string::LV_ENCODING;
+use crate::errors::{LVInteropError, Result};
+
+/// The flag set when the type descriptor ends with a label.
+const HAS_LABEL: u8 = 0x40;
+
+/// The deepest nesting of arrays and clusters parsed. The descriptors come from
+/// outside the library so this stops a crafted one overflowing the stack.
+pub const MAX_DEPTH: usize = 64;
+
+/// The numeric representations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NumericType {
+    I8,
+    I16,
+    I32,
+    I64,
+    U8,
+    U16,
+    U32,
+    U64,
+    F32,
+    F64,
+    /// Extended precision, which is 16 bytes when flattened.
+    Extended,
+    ComplexF32,
+    ComplexF64,
+    ComplexExtended,
+}
+
+impl NumericType {
+    /// The numeric type for a type code.
+    pub fn from_code(code: u8) -> Option<Self> {
+        Some(match code {
+            type_code::I8 => Self::I8,
+            type_code::I16 => Self::I16,
+            type_code::I32 => Self::I32,
+            type_code::I64 => Self::I64,
+            type_code::U8 => Self::U8,
+            type_code::U16 => Self::U16,
+            type_code::U32 => Self::U32,
+            type_code::U64 => Self::U64,
+            type_code::F32 => Self::F32,
+            type_code::F64 => Self::F64,
+            type_code::EXTENDED => Self::Extended,
+            type_code::COMPLEX_F32 => Self::ComplexF32,
+            type_code::COMPLEX_F64 => Self::ComplexF64,
+            type_code::COMPLEX_EXTENDED => Self::ComplexExtended,
+            _ => return None,
+        })
+    }
+
+    /// The type code of the numeric.
+    pub fn code(self) -> u8 {
+        match self {
+            Self::I8 => type_code::I8,
+            Self::I16 => type_code::I16,
+            Self::I32 => type_code::I32,
+            Self::I64 => type_code::I64,
+            Self::U8 => type_code::U8,
+            Self::U16 => type_code::U16,
+            Self::U32 => type_code::U32,
+            Self::U64 => type_code::U64,
+            Self::F32 => type_code::F32,
+            Self::F64 => type_code::F64,
+            Self::Extended => type_code::EXTENDED,
+            Self::ComplexF32 => type_code::COMPLEX_F32,
+            Self::ComplexF64 => type_code::COMPLEX_F64,
+            Self::ComplexExtended => type_code::COMPLEX_EXTENDED,
+        }
+    }
+
+    /// The size of the flattened value in bytes.
+    pub fn size(self) -> usize {
+        match self {
+            Self::I8 | Self::U8 => 1,
+            Self::I16 | Self::U16 => 2,
+            Self::I32 | Self::U32 | Self::F32 => 4,
+            Self::I64 | Self::U64 | Self::F64 | Self::ComplexF32 => 8,
+            Self::Extended | Self::ComplexF64 => 16,
+            Self::ComplexExtended => 32,
+        }
+    }
+}
+
+/// The kind of type a [`TypeDescriptor`] describes.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TypeKind {
+    Numeric(NumericType),
+    /// An enum with the names of its items, stored as the unsigned integer `repr`.
+    Enum {
+        repr: NumericType,
+        items: Vec<String>,
+    },
+    Boolean,
+    String,
+    Path,
+    /// An array with the size of each dimension, where -1 is variable sized.
+    Array {
+        dimensions: Vec<i32>,
+        element: Box<TypeDescriptor>,
+    },
+    Cluster(Vec<TypeDescriptor>),
+    Timestamp,
+    Variant,
+    /// A refnum of the given refnum kind.
+    Refnum {
+        kind: u16,
+    },
+    /// A type this doesn't model, with the bytes following the type code.
+    Other {
+        code: u8,
+        data: Vec<u8>,
+    },
+}
+
+/// A parsed LabVIEW type descriptor.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeDescriptor {
+    /// The label of the control, such as a cluster element name, if it has one.
+    pub label: Option<String>,
+    pub kind: TypeKind,
+}
+
+impl TypeDescriptor {
+    /// Parse a type descriptor, requiring all of `bytes` to be used.
+    pub fn parse(mut bytes: &[u8]) -> Result<Self> {
+        let descriptor = Self::parse_from(&mut bytes)?;
+        if !bytes.is_empty() {
+            return Err(invalid(format!("{} bytes left over", bytes.len())));
+        }
+        Ok(descriptor)
+    }
+
+    /// Parse a type descriptor from the start of `input`, advancing past it.
+    ///
+    /// Arrays and clusters nested more than [`MAX_DEPTH`] deep are rejected.
+    pub fn parse_from(input: &mut &[u8]) -> Result<Self> {
+        Self::parse_nested(input, 0)
+    }
+
+    fn parse_nested(input: &mut &[u8], depth: usize) -> Result<Self> {
+        if depth > MAX_DEPTH {
+            return Err(invalid(format!("is nested more than {MAX_DEPTH} deep")));
+        }
+        let size = usize::from(read_u16(input)?);
+        if size < 4 {
+            return Err(invalid(format!("size {size} is too small")));
+        }
+        // The size includes the size itself.
+        let mut body = take(input, size - 2)?;
+        let [flags, code] = [body[0], body[1]];
+        body = &body[2..];
+
+        let (kind, complete) = parse_kind(code, &mut body, depth)?;
+        // The label can only be found after types whose data is fully read.
+        let label = if complete && flags & HAS_LABEL != 0 {
+            Some(read_pascal_string(&mut body)?)
+        } else {
+            None
+        };
+        Ok(Self { label, kind })
+    }
+
+    /// True if both describe the same type, ignoring labels.
+    pub fn same_type(&self, other: &Self) -> bool {
+        match (&self.kind, &other.kind) {
+            (
+                TypeKind::Array {
+                    dimensions,
+                    element,
+                },
+                TypeKind::Array {
+                    dimensions: other_dimensions,
+                    element: other_element,
+                },
+            ) => dimensions == other_dimensions && element.same_type(other_element),
+            (TypeKind::Cluster(elements), TypeKind::Cluster(other_elements)) => {
+                elements.len() == other_elements.len()
+                    && elements
+                        .iter()
+                        .zip(other_elements)
+                        .all(|(element, other)| element.same_type(other))
+            }
+            (kind, other_kind) => kind == other_kind,
+        }
+    }
+}
+
+/// Parse the data after the type code. Also returns whether all of the data
+/// for the type has been read, so the label follows.
+fn parse_kind(code: u8, body: &mut &[u8], depth: usize) -> Result<(TypeKind, bool)> {
+    if let Some(numeric) = NumericType::from_code(code) {
+        return Ok((TypeKind::Numeric(numeric), true));
+    }
+    let kind = match code {
+        type_code::ENUM_U8 | type_code::ENUM_U16 | type_code::ENUM_U32 => {
+            let repr = match code {
+                type_code::ENUM_U8 => NumericType::U8,
+                type_code::ENUM_U16 => NumericType::U16,
+                _ => NumericType::U32,
+            };
+            let count = read_u16(body)?;
+            let items = (0..count)
+                .map(|_| read_pascal_string(body))
+                .collect::<Result<_>>()?;
+            TypeKind::Enum { repr, items }
+        }
+        // Booleans from LabVIEW 4 are 0x20.
+        0x20 | type_code::BOOLEAN => TypeKind::Boolean,
+        type_code::STRING => {
+            read_i32(body)?;
+            TypeKind::String
+        }
+        type_code::PATH => {
+            read_i32(body)?;
+            TypeKind::Path
+        }
+        type_code::ARRAY => {
+            let count = read_u16(body)?;
+            let dimensions = (0..count).map(|_| read_i32(body)).collect::<Result<_>>()?;
+            let element = Box::new(TypeDescriptor::parse_nested(body, depth + 1)?);
+            TypeKind::Array {
+                dimensions,
+                element,
+            }
+        }
+        type_code::CLUSTER => {
+            let count = read_u16(body)?;
+            let elements = (0..count)
+                .map(|_| TypeDescriptor::parse_nested(body, depth + 1))
+                .collect::<Result<_>>()?;
+            TypeKind::Cluster(elements)
+        }
+        type_code::MEASURE_DATA if body.starts_with(&TIMESTAMP_SUBTYPE.to_be_bytes()) => {
+            read_u16(body)?;
+            TypeKind::Timestamp
+        }
+        type_code::VARIANT => return Ok((TypeKind::Variant, false)),
+        type_code::REFNUM => {
+            let kind = read_u16(body)?;
+            return Ok((TypeKind::Refnum { kind }, false));
+        }
+        code => {
+            let data = std::mem::take(body).to_vec();
+            return Ok((TypeKind::Other { code, data }, false));
+        }
+    };
+    Ok((kind, true))
+}
+
+fn invalid(message: String) -> LVInteropError {
+    LVInteropError::InvalidFlattenedData(format!("type descriptor {message}"))
+}
+
+fn read_u16(input: &mut &[u8]) -> Result<u16> {
+    let bytes = take(input, 2)?;
+    Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+}
+
+fn read_i32(input: &mut &[u8]) -> Result<i32> {
+    let mut bytes = [0; 4];
+    bytes.copy_from_slice(take(input, 4)?);
+    Ok(i32::from_be_bytes(bytes))
+}
+
+fn read_pascal_string(input: &mut &[u8]) -> Result<String> {
+    let length = take(input, 1)?[0];
+    let bytes = take(input, length.into())?;
+    Ok(LV_ENCODING
+        .decode_without_bom_handling(bytes)
+        .0
+        .into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::data::{cluster_descriptor, LvData};
+    use crate::types::LVTime;
+
+    fn kind(bytes: &[u8]) -> TypeKind {
+        TypeDescriptor::parse(bytes).unwrap().kind
+    }
+
+    #[test]
+    fn test_parses_built_descriptors() {
+        assert_eq!(
+            kind(&i16::type_descriptor()),
+            TypeKind::Numeric(NumericType::I16)
+        );
+        assert_eq!(kind(&bool::type_descriptor()), TypeKind::Boolean);
+        assert_eq!(kind(&LVTime::type_descriptor()), TypeKind::Timestamp);
+        assert_eq!(
+            kind(&cluster_descriptor(&[
+                u8::type_descriptor(),
+                bool::type_descriptor()
+            ])),
+            TypeKind::Cluster(vec![
+                TypeDescriptor {
+                    label: None,
+                    kind: TypeKind::Numeric(NumericType::U8)
+                },
+                TypeDescriptor {
+                    label: None,
+                    kind: TypeKind::Boolean
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn test_labelled_enum() {
+        let bytes = [
+            0x00, 0x12, 0x40, 0x16, // Size, flags with a label and a U16 enum.
+            0x00, 0x02, 3, b'O', b'f', b'f', 2, b'O', b'n', // Two items.
+            4, b'M', b'o', b'd', b'e', // The label.
+        ];
+        assert_eq!(
+            TypeDescriptor::parse(&bytes).unwrap(),
+            TypeDescriptor {
+                label: Some("Mode".to_string()),
+                kind: TypeKind::Enum {
+                    repr: NumericType::U16,
+                    items: vec!["Off".to_string(), "On".to_string()],
+                },
+            }
+        );
+    }
+
+    #[test]
+    fn test_labelled_array_of_clusters() {
+        let bytes = [
+            0x00, 0x29, 0x40, 0x40, // Size, flags with a label and an array.
+            0x00, 0x01, 0xFF, 0xFF, 0xFF, 0xFF, // One dimension of any size.
+            0x00, 0x18, 0x40, 0x50, 0x00, 0x02, // A labelled cluster of two elements.
+            0x00, 0x06, 0x40, 0x0A, 1, b'x', // A labelled F64.
+            0x00, 0x06, 0x40, 0x03, 1, b'y', // A labelled I32.
+            5, b'P', b'o', b'i', b'n', b't', // The cluster label.
+            6, b'P', b'o', b'i', b'n', b't', b's', // The array label.
+        ];
+        let labelled = |label: &str, kind| TypeDescriptor {
+            label: Some(label.to_string()),
+            kind,
+        };
+        assert_eq!(
+            TypeDescriptor::parse(&bytes).unwrap(),
+            labelled(
+                "Points",
+                TypeKind::Array {
+                    dimensions: vec![-1],
+                    element: Box::new(labelled(
+                        "Point",
+                        TypeKind::Cluster(vec![
+                            labelled("x", TypeKind::Numeric(NumericType::F64)),
+                            labelled("y", TypeKind::Numeric(NumericType::I32)),
+                        ])
+                    )),
+                }
+            )
+        );
+    }
+
+    /// An I32 inside `depth` 1D arrays.
+    fn nested_arrays(depth: usize) -> Vec<u8> {
+        let mut bytes = vec![0x00, 0x04, 0x00, 0x03];
+        for _ in 0..depth {
+            let size = u16::try_from(bytes.len() + 10).unwrap();
+            let mut array = size.to_be_bytes().to_vec();
+            array.extend([0x00, 0x40, 0x00, 0x01, 0xFF, 0xFF, 0xFF, 0xFF]);
+            array.extend(bytes);
+            bytes = array;
+        }
+        bytes
+    }
+
+    #[test]
+    fn test_nesting_is_limited() {
+        assert!(TypeDescriptor::parse(&nested_arrays(MAX_DEPTH)).is_ok());
+        assert!(matches!(
+            TypeDescriptor::parse(&nested_arrays(MAX_DEPTH + 1)),
+            Err(LVInteropError::InvalidFlattenedData(_))
+        ));
+        // Deep enough to overflow the stack without the limit.
+        assert!(TypeDescriptor::parse(&nested_arrays(6000)).is_err());
+    }
+
+    #[test]
+    fn test_invalid_descriptors() {
+        // Truncated, too small and left over bytes.
+        assert!(TypeDescriptor::parse(&[0x00, 0x08, 0x00, 0x30]).is_err());
+        assert!(TypeDescriptor::parse(&[0x00, 0x02]).is_err());
+        assert!(TypeDescriptor::parse(&[0x00, 0x04, 0x00, 0x0A, 0x00]).is_err());
+        // Unknown types keep their data.
+        assert_eq!(
+            kind(&[0x00, 0x06, 0x00, 0xF1, 0xAB, 0xCD]),
+            TypeKind::Other {
+                code: 0xF1,
+                data: vec![0xAB, 0xCD]
+            }
+        );
+    }
+}
@@ -0,0 +1,512 @@
+//! LabVIEW variants.
+//!
+//! The structure behind a variant is internal to LabVIEW and its contents
+//! can't be read through the documented C interface. Rust code can hold a
+//! variant it was passed, for example inside a cluster or waveform, and leave
+//! it in place for LabVIEW.
+//!
+//! Set the Call Library node parameter to `adapt to type` and `handles by value`
+//! to receive an [`LVVariant`] directly.
+//!
+//! # Typed Values
+//!
+//! A [`FlattenedVariant`] holds the type string and data of a variant flattened
+//! with Variant To Flattened String and converts them to and from Rust types
+//! with [`get`](FlattenedVariant::get) and [`set`](FlattenedVariant::set),
+//! checking the type so one exported function can accept data of several
+//! types. Flattened String To Variant turns the result back into a variant.
+//!
+//! [`LVVariant::get`] and [`LVVariant::set`] do the same on a variant passed
+//! to Rust, flattening it through the runtime exports LabVIEW uses for those
+//! functions. These aren't part of the documented interface and are only
+//! available when the library is loaded by LabVIEW, so elsewhere pass the
+//! type string and data flattened on the diagram instead.
+//!
+//! ```
+//! use labview_interop::types::variant::FlattenedVariant;
+//!
+//! let variant = FlattenedVariant::new(&vec![1.5f64, 2.5]).unwrap();
+//! assert_eq!(variant.get::<Vec<f64>>().unwrap(), [1.5, 2.5]);
+//! assert!(variant.get::<String>().is_err());
+//! ```
+//!
+//! # Attributes
+//!
+//! Variant attributes are also stored inside the variant so they are
+//! flattened on the diagram too. Get Variant Attribute without a name returns
+//! arrays of every name and value. In a For Loop, flatten each value with
+//! Variant To Flattened String and bundle the name, type string and data into
+//! a [`FlattenedAttribute`] cluster, then pass the array of clusters to
+//! [`FlattenedVariant::attributes_from_lv`]. The attributes are then read and
+//! written with [`attribute`](FlattenedVariant::attribute) and
+//! [`set_attribute`](FlattenedVariant::set_attribute).
+//! [`attributes_to_lv`](FlattenedVariant::attributes_to_lv) writes them back
+//! for a loop of Flattened String To Variant and Set Variant Attribute.
+//!
+//! ```
+//! use labview_interop::types::variant::FlattenedVariant;
+//!
+//! let mut variant = FlattenedVariant::new(&2.5f64).unwrap();
+//! variant.set_attribute("Units", &"V".to_string()).unwrap();
+//! assert_eq!(variant.attribute::<String>("Units").unwrap(), Some("V".to_string()));
+//! assert_eq!(variant.attribute::<String>("Gain").unwrap(), None);
+//! ```
+
+#[cfg(target_pointer_width = "64")]
+use std::collections::BTreeMap;
+use std::ffi::c_void;
+
+#[cfg(target_pointer_width = "64")]
+use super::data::LvData;
+#[cfg(target_pointer_width = "64")]
+use super::type_descriptor::TypeDescriptor;
+#[cfg(target_pointer_width = "64")]
+use super::{LStrHandle, LVArrayHandle};
+#[cfg(target_pointer_width = "64")]
+use crate::errors::{LVInteropError, Result};
+use crate::memory::UHandle;
+
+/// Represents a LabVIEW Variant. The internal structure is undefined
+/// by NI and therefore unavailable.
+///
+/// This is available as a placeholder in clusters etc.
+#[repr(transparent)]
+pub struct LVVariant(UHandle<c_void>);
+
+impl LVVariant {
+    /// True if there is no variant.
+    ///
+    /// LabVIEW always passes a variant for a variant terminal, even when it
+    /// holds no data, so this indicates a missing value rather than an empty
+    /// variant.
+    pub fn is_null(&self) -> bool {
+        self.0 .0.is_null()
+    }
+
+    /// The pointer LabVIEW uses for the variant.
+    pub fn as_raw(&self) -> *mut c_void {
+        self.0 .0.cast()
+    }
+}
+
+#[cfg(all(feature = "link", target_pointer_width = "64"))]
+impl LVVariant {
+    /// Flatten the variant as Variant To Flattened String does. Attributes
+    /// aren't part of the flattened value.
+    pub fn to_flattened(&self) -> Result<FlattenedVariant> {
+        if self.is_null() {
+            return Err(LVInteropError::InvalidHandle);
+        }
+        let api = crate::labview::variant_api()?;
+        let mut type_string = super::LVArrayOwned::<1, i16>::from_slice(&[])?;
+        let mut data = super::LStrOwned::from_data(&[])?;
+        unsafe {
+            api.flatten(
+                self.as_raw(),
+                type_string.handle_value_ptr(),
+                data.handle_value_ptr(),
+            )
+        }
+        .to_result(())?;
+        FlattenedVariant::from_lv(&type_string, &data)
+    }
+
+    /// Replace the value and type with a flattened variant, as Flattened
+    /// String To Variant does.
+    pub fn set_flattened(&mut self, variant: &FlattenedVariant) -> Result<()> {
+        if self.is_null() {
+            return Err(LVInteropError::InvalidHandle);
+        }
+        let api = crate::labview::variant_api()?;
+        let mut type_string = super::LVArrayOwned::<1, i16>::from_slice(&[])?;
+        let mut data = super::LStrOwned::from_data(&[])?;
+        variant.to_lv(&mut type_string, &mut data)?;
+        unsafe {
+            api.unflatten(
+                type_string.handle_value(),
+                data.handle_value(),
+                self.0.handle_value_ptr(),
+            )
+        }
+        .to_result(())
+    }
+
+    /// Read the value as `T` through [`FlattenedVariant::get`], failing if
+    /// the variant holds another type.
+    pub fn get<T: LvData>(&self) -> Result<T> {
+        self.to_flattened()?.get()
+    }
+
+    /// Replace the value, and its type, with `value`.
+    pub fn set<T: LvData>(&mut self, value: &T) -> Result<()> {
+        self.set_flattened(&FlattenedVariant::new(value)?)
+    }
+}
+
+impl std::fmt::Debug for LVVariant {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("LVVariant").field(&self.as_raw()).finish()
+    }
+}
+
+/// A variant flattened by Variant To Flattened String, as its type descriptor
+/// and flattened data, with any attributes flattened the same way.
+#[cfg(target_pointer_width = "64")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FlattenedVariant {
+    type_descriptor: Vec<u8>,
+    data: Vec<u8>,
+    /// Kept sorted by name, as LabVIEW returns them.
+    attributes: BTreeMap<String, FlattenedVariant>,
+}
+
+#[cfg(target_pointer_width = "64")]
+crate::labview_layout!(
+    /// A variant attribute with its value flattened by Variant To Flattened
+    /// String, as LabVIEW passes it in an array of clusters.
+    pub struct FlattenedAttribute {
+        pub name: LStrHandle,
+        pub type_string: LVArrayHandle<1, i16>,
+        pub data: LStrHandle,
+    }
+);
+
+#[cfg(all(feature = "link", target_pointer_width = "64"))]
+impl crate::memory::DeepDispose for FlattenedAttribute {
+    unsafe fn deep_dispose(self) -> Result<()> {
+        let FlattenedAttribute {
+            name,
+            type_string,
+            data,
+        } = self;
+        name.deep_dispose()?;
+        type_string.deep_dispose()?;
+        data.deep_dispose()
+    }
+}
+
+/// Null handles are empty so zeroed attributes are valid.
+#[cfg(all(feature = "link", target_pointer_width = "64"))]
+unsafe impl crate::types::array::ArrayElement for FlattenedAttribute {}
+
+#[cfg(target_pointer_width = "64")]
+impl FlattenedVariant {
+    /// A variant holding `value`.
+    pub fn new<T: LvData>(value: &T) -> Result<Self> {
+        Ok(Self {
+            type_descriptor: T::type_descriptor(),
+            data: super::data::flatten(value)?,
+            attributes: BTreeMap::new(),
+        })
+    }
+
+    /// A variant from a type descriptor and the data flattened with it.
+    pub fn from_parts(type_descriptor: Vec<u8>, data: Vec<u8>) -> Self {
+        Self {
+            type_descriptor,
+            data,
+            attributes: BTreeMap::new(),
+        }
+    }
+
+    /// Read the outputs of Variant To Flattened String.
+    ///
+    /// LabVIEW gives the type string as an array of `i16`, each holding two
+    /// bytes of the descriptor. Null handles are empty.
+    pub fn from_lv(type_string: &LVArrayHandle<1, i16>, data: &LStrHandle) -> Result<Self> {
+        type_string.validate()?;
+        data.validate()?;
+        let words = unsafe { type_string.as_ref() }
+            .map(|array| array.data_as_slice())
+            .unwrap_or(&[]);
+        let data = unsafe { data.as_ref() }
+            .map(|string| string.as_slice().to_vec())
+            .unwrap_or_default();
+        Ok(Self {
+            type_descriptor: words.iter().flat_map(|word| word.to_be_bytes()).collect(),
+            data,
+            attributes: BTreeMap::new(),
+        })
+    }
+
+    /// Write the type string and data for Flattened String To Variant.
+    #[cfg(feature = "link")]
+    pub fn to_lv(
+        &self,
+        type_string: &mut LVArrayHandle<1, i16>,
+        data: &mut LStrHandle,
+    ) -> Result<()> {
+        // Descriptors are padded to an even length so they fit the words.
+        let words: Vec<i16> = self
+            .type_descriptor
+            .chunks(2)
+            .map(|pair| i16::from_be_bytes([pair[0], pair.get(1).copied().unwrap_or(0)]))
+            .collect();
+        words.to_lv(type_string)?;
+        if data.is_null() {
+            *data = super::LStrOwned::from_data(&self.data)?.into_raw();
+            Ok(())
+        } else {
+            data.set(&self.data)
+        }
+    }
+
+    /// The type descriptor of the value.
+    pub fn type_descriptor(&self) -> &[u8] {
+        &self.type_descriptor
+    }
+
+    /// The flattened value.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Parse the type descriptor, to find the type of the value.
+    pub fn value_type(&self) -> Result<TypeDescriptor> {
+        TypeDescriptor::parse(&self.type_descriptor)
+    }
+
+    /// Read the value as `T`, failing if the variant holds another type.
+    ///
+    /// Labels don't have to match, so a cluster reads into any type with the
+    /// same elements.
+    pub fn get<T: LvData>(&self) -> Result<T> {
+        let actual = self.value_type()?;
+        let expected = TypeDescriptor::parse(&T::type_descriptor())?;
+        if !actual.same_type(&expected) {
+            return Err(LVInteropError::VariantTypeMismatch {
+                expected: format!("{:?}", expected.kind),
+                actual: format!("{:?}", actual.kind),
+            });
+        }
+        super::data::unflatten(&self.data)
+    }
+
+    /// Replace the value, and its type, with `value`. Attributes are kept.
+    pub fn set<T: LvData>(&mut self, value: &T) -> Result<()> {
+        self.data = super::data::flatten(value)?;
+        self.type_descriptor = T::type_descriptor();
+        Ok(())
+    }
+
+    /// Read an attribute as `T`, or `None` if the variant doesn't have it.
+    pub fn attribute<T: LvData>(&self, name: &str) -> Result<Option<T>> {
+        self.attributes
+            .get(name)
+            .map(FlattenedVariant::get)
+            .transpose()
+    }
+
+    /// Add or replace an attribute.
+    pub fn set_attribute<T: LvData>(&mut self, name: impl Into<String>, value: &T) -> Result<()> {
+        self.attributes.insert(name.into(), Self::new(value)?);
+        Ok(())
+    }
+
+    /// Remove an attribute, returning its value if there was one.
+    pub fn remove_attribute(&mut self, name: &str) -> Option<FlattenedVariant> {
+        self.attributes.remove(name)
+    }
+
+    /// The attributes by name, in name order.
+    pub fn attributes(&self) -> &BTreeMap<String, FlattenedVariant> {
+        &self.attributes
+    }
+
+    /// Replace the attributes with those flattened on the diagram. Null
+    /// handles are empty.
+    pub fn attributes_from_lv(
+        &mut self,
+        attributes: &LVArrayHandle<1, FlattenedAttribute>,
+    ) -> Result<()> {
+        attributes.validate()?;
+        let attributes = unsafe { attributes.as_ref() }
+            .map(|array| array.data_as_slice())
+            .unwrap_or(&[]);
+        self.attributes = attributes
+            .iter()
+            .map(|attribute| {
+                attribute.name.validate()?;
+                let name = unsafe { attribute.name.as_ref() }
+                    .map(|name| name.to_rust_string().into_owned())
+                    .unwrap_or_default();
+                let value = Self::from_lv(&attribute.type_string, &attribute.data)?;
+                Ok((name, value))
+            })
+            .collect::<Result<_>>()?;
+        Ok(())
+    }
+
+    /// Write the attributes for Flattened String To Variant and Set Variant
+    /// Attribute, resizing the array to fit.
+    #[cfg(feature = "link")]
+    pub fn attributes_to_lv(
+        &self,
+        attributes: &mut LVArrayHandle<1, FlattenedAttribute>,
+    ) -> Result<()> {
+        let dims = super::array::LVArrayDims::try_from(&[self.attributes.len()])?;
+        if attributes.is_null() {
+            *attributes = super::array::LVArrayOwned::new_zeroed(dims)?.into_raw();
+        } else {
+            attributes.resize_elements(dims)?;
+        }
+        for (output, (name, value)) in attributes.iter_mut().zip(&self.attributes) {
+            if output.name.is_null() {
+                output.name = super::LStrOwned::try_from(name.as_str())?.into_raw();
+            } else {
+                output.name.set_str(name)?;
+            }
+            value.to_lv(&mut output.type_string, &mut output.data)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_null_variant() {
+        let variant = LVVariant(UHandle(std::ptr::null_mut()));
+        assert!(variant.is_null());
+        assert!(variant.as_raw().is_null());
+    }
+
+    #[cfg(all(feature = "link", target_pointer_width = "64"))]
+    #[test]
+    fn test_variant_value_needs_labview() {
+        let mut variant = LVVariant(UHandle(std::ptr::null_mut()));
+        assert!(matches!(
+            variant.get::<f64>(),
+            Err(LVInteropError::InvalidHandle)
+        ));
+        assert!(matches!(
+            variant.set(&1.5f64),
+            Err(LVInteropError::InvalidHandle)
+        ));
+
+        // The runtime exports are only found inside LabVIEW, so this fails
+        // before the placeholder pointer is used.
+        let mut slot = std::ptr::null_mut();
+        let mut variant = LVVariant(UHandle(&mut slot));
+        assert!(matches!(
+            variant.get::<f64>(),
+            Err(LVInteropError::NoLabviewApi)
+        ));
+        assert!(matches!(
+            variant.set(&1.5f64),
+            Err(LVInteropError::NoLabviewApi)
+        ));
+    }
+
+    #[cfg(target_pointer_width = "64")]
+    #[test]
+    fn test_flattened_variant_types() {
+        use crate::types::data::{cluster_descriptor, flatten};
+
+        let mut variant = FlattenedVariant::new(&7i32).unwrap();
+        assert_eq!(variant.get::<i32>().unwrap(), 7);
+        assert!(matches!(
+            variant.get::<f64>(),
+            Err(LVInteropError::VariantTypeMismatch { .. })
+        ));
+
+        variant.set(&2.5f64).unwrap();
+        assert_eq!(variant.get::<f64>().unwrap(), 2.5);
+
+        // A labelled numeric, as LabVIEW writes for a named control.
+        let labelled = FlattenedVariant::from_parts(
+            vec![0x00, 0x08, 0x40, 0x03, 3, b'N', b'u', b'm'],
+            flatten(&3i32).unwrap(),
+        );
+        assert_eq!(labelled.get::<i32>().unwrap(), 3);
+
+        let cluster = FlattenedVariant::from_parts(
+            cluster_descriptor(&[i32::type_descriptor()]),
+            flatten(&3i32).unwrap(),
+        );
+        assert!(cluster.get::<i32>().is_err());
+    }
+
+    #[cfg(target_pointer_width = "64")]
+    #[test]
+    fn test_attributes() {
+        let mut variant = FlattenedVariant::new(&1u8).unwrap();
+        variant.set_attribute("Gain", &2.0f64).unwrap();
+        variant.set_attribute("Enabled", &true).unwrap();
+        variant.set(&3u8).unwrap();
+
+        assert_eq!(variant.get::<u8>().unwrap(), 3);
+        assert_eq!(variant.attribute::<f64>("Gain").unwrap(), Some(2.0));
+        assert!(variant.attribute::<i32>("Gain").is_err());
+        assert_eq!(
+            variant.attributes().keys().collect::<Vec<_>>(),
+            ["Enabled", "Gain"]
+        );
+        assert!(variant.remove_attribute("Enabled").is_some());
+        assert_eq!(variant.attribute::<bool>("Enabled").unwrap(), None);
+    }
+
+    #[cfg(all(feature = "link", target_pointer_width = "64"))]
+    #[test]
+    fn test_attributes_round_trip_through_lv() {
+        use crate::memory::DeepDispose;
+
+        let mut variant = FlattenedVariant::new(&0i32).unwrap();
+        variant.set_attribute("Units", &"mV".to_string()).unwrap();
+        variant
+            .set_attribute("Scale", &[1.5f64, 2.0].to_vec())
+            .unwrap();
+
+        let mut attributes: LVArrayHandle<1, FlattenedAttribute> = UHandle(std::ptr::null_mut());
+        variant.attributes_to_lv(&mut attributes).unwrap();
+        assert_eq!(attributes.as_slice().len(), 2);
+
+        let mut read = FlattenedVariant::new(&0i32).unwrap();
+        read.attributes_from_lv(&attributes).unwrap();
+        assert_eq!(read, variant);
+        assert_eq!(
+            read.attribute::<String>("Units").unwrap().as_deref(),
+            Some("mV")
+        );
+
+        // Writing fewer attributes disposes the extra elements.
+        variant.remove_attribute("Units");
+        variant.attributes_to_lv(&mut attributes).unwrap();
+        assert_eq!(attributes.as_slice().len(), 1);
+        unsafe { attributes.deep_dispose() }.unwrap();
+    }
+
+    #[cfg(all(feature = "link", target_pointer_width = "64"))]
+    #[test]
+    fn test_type_string_words() {
+        use crate::memory::DeepDispose;
+
+        let variant = FlattenedVariant::new(&1.5f64).unwrap();
+        let mut type_string: LVArrayHandle<1, i16> = UHandle(std::ptr::null_mut());
+        let mut data: LStrHandle = UHandle(std::ptr::null_mut());
+        variant.to_lv(&mut type_string, &mut data).unwrap();
+        // Each word holds two bytes of the descriptor, the first in the high byte.
+        assert_eq!(type_string.as_slice(), [0x0004, 0x000A]);
+        assert_eq!(data.as_bytes().unwrap(), 1.5f64.to_be_bytes());
+        assert_eq!(
+            FlattenedVariant::from_lv(&type_string, &data).unwrap(),
+            variant
+        );
+
+        // An odd length descriptor is padded with a zero byte.
+        let odd = FlattenedVariant::from_parts(vec![0x12, 0x34, 0xAB], vec![]);
+        odd.to_lv(&mut type_string, &mut data).unwrap();
+        assert_eq!(type_string.as_slice(), [0x1234, 0xAB00u16 as i16]);
+        assert_eq!(
+            FlattenedVariant::from_lv(&type_string, &data)
+                .unwrap()
+                .type_descriptor(),
+            [0x12, 0x34, 0xAB, 0x00]
+        );
+
+        unsafe { type_string.deep_dispose() }.unwrap();
+        unsafe { data.deep_dispose() }.unwrap();
+    }
+}
@@ -0,0 +1,230 @@
+//! Runtime control over the amount of validation the crate performs.
+//!
+//! Extra checks are useful when diagnosing a problem but cost time on every
+//! call. The level can be changed at runtime, including from LabVIEW with the
+//! `interop_set_validation_level` export, so one build of a library can be
+//! switched into a diagnostic mode at a customer site. This is why the checks
+//! are a runtime level rather than a `checked` cargo feature: a feature would
+//! need a separate diagnostic build to be shipped and swapped in.
+//!
+//! Null pointer checks are needed for memory safety so are made at every level.
+//! The level controls the additional checks on data received from LabVIEW:
+//!
+//! * [`ValidationLevel::Off`]: No additional checks.
+//! * [`ValidationLevel::Basic`]: Cheap consistency checks such as negative sizes and
+//!   dimensions. This is the default.
+//! * [`ValidationLevel::Paranoid`]: Also checks each handle with `DSCheckHandle`
+//!   before it is dereferenced and that the allocation behind it is large enough
+//!   for the size it claims to hold, and checks the index of unchecked element
+//!   access. Handles the crate sizes get a canary after their data which
+//!   validation checks, catching code which writes past the end of the data.
+//!   This requires the `link` feature and calls into the memory manager so is
+//!   comparatively slow.
+//!
+//! At every level a null string or array handle is valid and empty, as LabVIEW
+//! passes empty strings and arrays that way.
+
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use crate::errors::{LVInteropError, MgErr, Result};
+
+static VALIDATION_LEVEL: AtomicU8 = AtomicU8::new(ValidationLevel::Basic as u8);
+
+/// The amount of validation to perform.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[repr(u8)]
+pub enum ValidationLevel {
+    Off = 0,
+    Basic = 1,
+    Paranoid = 2,
+}
+
+impl TryFrom<i32> for ValidationLevel {
+    type Error = LVInteropError;
+
+    fn try_from(value: i32) -> Result<Self> {
+        match value {
+            0 => Ok(Self::Off),
+            1 => Ok(Self::Basic),
+            2 => Ok(Self::Paranoid),
+            _ => Err(LVInteropError::InvalidValidationLevel(value)),
+        }
+    }
+}
+
+/// Set the validation level for the whole library.
+pub fn set_validation_level(level: ValidationLevel) {
+    VALIDATION_LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+/// The current validation level.
+pub fn validation_level() -> ValidationLevel {
+    match VALIDATION_LEVEL.load(Ordering::Relaxed) {
+        0 => ValidationLevel::Off,
+        1 => ValidationLevel::Basic,
+        _ => ValidationLevel::Paranoid,
+    }
+}
+
+/// True if checks for `level` should be made.
+pub fn validation_enabled(level: ValidationLevel) -> bool {
+    validation_level() >= level
+}
+
+/// Set the validation level from LabVIEW.
+///
+/// The level is 0 for off, 1 for basic and 2 for paranoid.
+#[no_mangle]
+pub extern "C" fn interop_set_validation_level(level: i32) -> MgErr {
+    match ValidationLevel::try_from(level) {
+        Ok(level) => {
+            set_validation_level(level);
+            MgErr::NO_ERROR
+        }
+        Err(error) => error.into(),
+    }
+}
+
+/// Guard bytes written after the data of the handles the crate sizes at the
+/// paranoid level.
+///
+/// A handle which LabVIEW resizes after the canary was written is forgotten
+/// when it is next checked, as its data may now cover the canary.
+#[cfg(feature = "link")]
+pub(crate) mod canary {
+    use std::collections::BTreeMap;
+    use std::sync::{Mutex, MutexGuard};
+
+    use super::{validation_enabled, ValidationLevel};
+    use crate::errors::{LVInteropError, Result};
+    use crate::memory::HandleValue;
+
+    const CANARY: [u8; 8] = *b"LVCANARY";
+
+    /// The size of the data before the canary, by the address of the handle.
+    static CANARIES: Mutex<BTreeMap<usize, usize>> = Mutex::new(BTreeMap::new());
+
+    fn lock() -> MutexGuard<'static, BTreeMap<usize, usize>> {
+        CANARIES
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// The size to allocate for `size` bytes of data, leaving room for the
+    /// canary at the paranoid level.
+    pub fn allocation_size(size: usize) -> usize {
+        if validation_enabled(ValidationLevel::Paranoid) {
+            size.saturating_add(CANARY.len())
+        } else {
+            size
+        }
+    }
+
+    /// Write the canary after `size` bytes of data if `allocated` has room for
+    /// it, or forget any earlier canary if not.
+    ///
+    /// # Safety
+    ///
+    /// * The handle must be valid with `allocated` bytes.
+    pub unsafe fn write(handle: HandleValue, size: usize, allocated: usize) {
+        let address = handle.as_ptr().addr();
+        if allocated < size.saturating_add(CANARY.len()) {
+            lock().remove(&address);
+            return;
+        }
+        let data = *handle.as_ptr().cast::<*mut u8>();
+        data.add(size)
+            .copy_from_nonoverlapping(CANARY.as_ptr(), CANARY.len());
+        lock().insert(address, size);
+    }
+
+    /// Forget the canary of a handle which is being disposed.
+    pub fn forget(handle: HandleValue) {
+        lock().remove(&handle.as_ptr().addr());
+    }
+
+    /// The number of bytes at the end of the handle taken by its canary.
+    pub fn reserved(handle: HandleValue, allocated: usize) -> usize {
+        match lock().get(&handle.as_ptr().addr()) {
+            Some(size) if size + CANARY.len() == allocated => CANARY.len(),
+            _ => 0,
+        }
+    }
+
+    /// Check the canary of a handle whose data uses `used` of its `allocated` bytes.
+    ///
+    /// # Safety
+    ///
+    /// * The handle must be valid with `allocated` bytes.
+    pub unsafe fn check(handle: HandleValue, used: usize, allocated: usize) -> Result<()> {
+        let address = handle.as_ptr().addr();
+        let mut canaries = lock();
+        let Some(&size) = canaries.get(&address) else {
+            return Ok(());
+        };
+        if used > size || allocated != size + CANARY.len() {
+            // Resized since the canary was written, or a new handle at the same address.
+            canaries.remove(&address);
+            return Ok(());
+        }
+        let data = *handle.as_ptr().cast::<*const u8>();
+        if std::slice::from_raw_parts(data.add(size), CANARY.len()) != CANARY {
+            return Err(LVInteropError::ValidationFailed(format!(
+                "data was written past the first {size} bytes of the handle"
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_levels_are_ordered() {
+        assert!(ValidationLevel::Paranoid > ValidationLevel::Basic);
+        assert!(ValidationLevel::Basic > ValidationLevel::Off);
+    }
+
+    #[test]
+    fn test_level_from_code() {
+        assert_eq!(
+            ValidationLevel::try_from(2).unwrap(),
+            ValidationLevel::Paranoid
+        );
+        assert!(matches!(
+            ValidationLevel::try_from(3),
+            Err(LVInteropError::InvalidValidationLevel(3))
+        ));
+    }
+
+    #[cfg(feature = "link")]
+    #[test]
+    fn test_paranoid_checks() {
+        use crate::memory::UHandle;
+        use crate::types::{LStrHandle, LStrOwned, LVArrayOwned};
+        use std::panic::{catch_unwind, AssertUnwindSafe};
+
+        let null: LStrHandle = UHandle(std::ptr::null_mut());
+        assert!(null.validate().is_ok());
+
+        set_validation_level(ValidationLevel::Paranoid);
+        let mut string = LStrOwned::from_data(b"hello").unwrap();
+        string.set(b"hello world").unwrap();
+        assert!(string.validate().is_ok());
+        assert_eq!(string.capacity().unwrap(), 11);
+        // Write past the end of the data, over the canary.
+        unsafe { (*string.0).cast::<u8>().add(4 + 11).write(0) };
+        assert!(string.validate().is_err());
+
+        let mut array = LVArrayOwned::<1, f64>::from_slice(&[1.0, 2.0]).unwrap();
+        array.resize_array([3].into()).unwrap();
+        assert!(array.validate().is_ok());
+        let array = unsafe { array.as_ref() }.unwrap();
+        let out_of_bounds =
+            catch_unwind(AssertUnwindSafe(|| unsafe { array.get_value_unchecked(3) }));
+        assert!(out_of_bounds.is_err());
+        set_validation_level(ValidationLevel::Basic);
+    }
+}
@@ -1,10 +1,18 @@
+//! Exports used by the LabVIEW integration test project.
+//!
+//! These are called through the Call Library node so raw pointers from LabVIEW
+//! are dereferenced in safe extern functions by design.
+#![allow(clippy::not_unsafe_ptr_arg_deref)]
+
 use labview_interop::errors::MgErr;
 use labview_interop::labview_layout;
-use labview_interop::sync::{LVUserEvent, Occurence};
-use labview_interop::types::string::LStrHandle;
-#[cfg(target_pointer_width = "64")]
+use labview_interop::memory::UPtr;
+use labview_interop::sync::{LVOccurrence, LVUserEvent};
+use labview_interop::types::string::{LStrHandle, LStrOwned};
+use labview_interop::types::{
+    DigitalWaveform, LVArrayHandle, LVArrayOwned, LVBool, LVTime, LVVariant, Waveform,
+};
 use labview_interop::types::{ErrorClusterPtr, ToLvError};
-use labview_interop::types::{LVArrayHandle, LVBool, LVTime, LVVariant, Waveform};
 
 use std::ffi::{c_char, CStr};
 use std::ptr::{addr_of, read_unaligned};
@@ -67,8 +75,8 @@ pub extern "C" fn extract_from_array_ndarray(
     let rows = array.nrows();
     let columns = array.ncols();
     unsafe {
-        *end_of_first_row = array.get([0, columns - 1]).unwrap().clone();
-        *start_of_last_row = array.get([rows - 1, 0]).unwrap().clone();
+        *end_of_first_row = *array.get([0, columns - 1]).unwrap();
+        *start_of_last_row = *array.get([rows - 1, 0]).unwrap();
     }
 }
 
@@ -97,6 +105,25 @@ pub extern "C" fn resize_array(mut array_handle: LVArrayHandle<2, f64>) -> MgErr
     }
 }
 
+/// Fill an output array passed as a pointer to a handle, which may be null.
+#[no_mangle]
+pub extern "C" fn fill_output_array(array_handle: UPtr<LVArrayHandle<1, f64>>) -> MgErr {
+    let Ok(array_handle) = (unsafe { array_handle.as_ref_mut() }) else {
+        return MgErr::ARGUMENT_ERROR;
+    };
+    match array_handle.resize_array([4].into()) {
+        Ok(()) => {
+            for index in 0..4 {
+                unsafe {
+                    array_handle.set_value_unchecked(index, index as f64);
+                }
+            }
+            MgErr::NO_ERROR
+        }
+        Err(e) => e.into(),
+    }
+}
+
 #[no_mangle]
 pub extern "C" fn is_array_empty(array_handle: LVArrayHandle<1, f64>, empty: *mut LVBool) -> MgErr {
     let size = array_handle.element_count();
@@ -160,6 +187,25 @@ pub extern "C" fn extract_test_struct_with_waveform(
     });
 }
 
+/// Count the samples in a digital waveform.
+#[cfg(target_pointer_width = "64")]
+#[no_mangle]
+pub extern "C" fn digital_waveform_samples(
+    waveform: *const DigitalWaveform,
+    samples: *mut i32,
+    signals: *mut i32,
+) -> MgErr {
+    let Some(waveform) = (unsafe { waveform.as_ref() }) else {
+        return MgErr::ARGUMENT_ERROR;
+    };
+    let [rows, columns] = <[usize; 2]>::from(waveform.data.data.dimensions_or_empty());
+    unsafe {
+        *samples = rows as i32;
+        *signals = columns as i32;
+    }
+    MgErr::NO_ERROR
+}
+
 labview_layout!(
     pub struct ClusterHandles {
         array1: LVArrayHandle<1, u8>,
@@ -212,12 +258,37 @@ pub extern "C" fn extract_cluster_variant(
 #[no_mangle]
 pub extern "C" fn generate_event_3(lv_user_event: *mut LVUserEvent<i32>) -> MgErr {
     let event = unsafe { *lv_user_event };
-    let result = event.post(&mut 3);
+    let result = event.post(&3);
     result.into()
 }
 
+labview_layout!(
+    #[derive(labview_interop::memory::DeepDispose)]
+    pub struct EventCluster {
+        name: LStrHandle,
+        values: LVArrayHandle<1, f64>,
+        count: i32,
+    }
+);
+
+/// Post a cluster with a string and an array built in Rust.
 #[no_mangle]
-pub extern "C" fn generate_occurence(occurence: *mut Occurence) -> MgErr {
+pub extern "C" fn generate_cluster_event(lv_user_event: *mut LVUserEvent<EventCluster>) -> MgErr {
+    let event = unsafe { *lv_user_event };
+    let result = (|| {
+        let data = EventCluster {
+            name: LStrOwned::from_data(b"Hello")?.into_raw(),
+            values: LVArrayOwned::<1, f64>::from_shape_slice([3].into(), &[1.0, 2.0, 3.0])?
+                .into_raw(),
+            count: 3,
+        };
+        unsafe { event.post_and_dispose(data) }
+    })();
+    result.into()
+}
+
+#[no_mangle]
+pub extern "C" fn generate_occurence(occurence: *mut LVOccurrence) -> MgErr {
     let result = unsafe { (*occurence).set() };
     result.into()
 }
@@ -252,7 +323,6 @@ pub extern "C" fn count_words_c_string(string: *const c_char, count: &mut i32) -
 /// A simple type for testing the error integration.
 struct ErrorText(&'static str);
 
-#[cfg(target_pointer_width = "64")]
 impl ToLvError for ErrorText {
     fn source(&self) -> std::borrow::Cow<'_, str> {
         "Rust".into()
@@ -263,13 +333,63 @@ impl ToLvError for ErrorText {
     }
 }
 
-#[cfg(target_pointer_width = "64")]
 #[no_mangle]
 pub extern "C" fn set_error_cluster(error_cluster: ErrorClusterPtr) -> MgErr {
     let error = ErrorText("This is a test");
     error.write_error(error_cluster).into()
 }
 
+#[cfg(target_pointer_width = "64")]
+labview_interop::lv_export! {
+    pub fn mean_of_array(values: &[f64]) -> Result<f64, labview_interop::errors::LVInteropError> {
+        if values.is_empty() {
+            return Err(labview_interop::errors::LVInteropError::ArrayDimensionMismatch);
+        }
+        Ok(values.iter().sum::<f64>() / values.len() as f64)
+    }
+}
+
+#[cfg(target_pointer_width = "64")]
+labview_interop::lv_export! {
+    pub fn greet(name: &str, shout: bool) -> String {
+        let greeting = format!("Hello {name}");
+        if shout {
+            greeting.to_uppercase()
+        } else {
+            greeting
+        }
+    }
+}
+
+#[cfg(target_pointer_width = "64")]
+#[labview_interop::labview_export]
+pub fn clamp_values(values: &[f64], limit: f64) -> Vec<f64> {
+    values
+        .iter()
+        .map(|value| value.clamp(-limit, limit))
+        .collect()
+}
+
+/// A cluster using the generated accessors, which work on 32 and 64 bit.
+#[labview_interop::labview_cluster(debug)]
+pub struct Setpoint {
+    channel: u16,
+    value: f64,
+    enabled: LVBool,
+}
+
+/// Double the value of an enabled setpoint.
+#[no_mangle]
+pub extern "C" fn double_setpoint(setpoint: *mut Setpoint) -> MgErr {
+    let Some(setpoint) = (unsafe { setpoint.as_mut() }) else {
+        return MgErr::ARGUMENT_ERROR;
+    };
+    if setpoint.enabled().into() {
+        setpoint.set_value(setpoint.value() * 2.0);
+    }
+    MgErr::NO_ERROR
+}
+
 pub fn test() {
     labview_layout!(
         pub struct TestStruct {